@@ -0,0 +1,43 @@
+use std::env;
+use std::process::Command;
+
+/// Records build information for `--version`: the commit the binary was built from, the target, the profile, the
+/// compiler, and the optional features built in. Values that can't be found, such as the commit when building from a source archive, are `unknown`.
+fn main() {
+    let commit = output_of("git", &["log", "-1", "--format=%h (%cs)"]);
+    let rustc = output_of(&env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()), &["--version"]);
+    println!("cargo:rustc-env=BUILD_COMMIT={}", commit);
+    println!("cargo:rustc-env=BUILD_TARGET={}", env::var("TARGET").unwrap_or_default());
+    println!("cargo:rustc-env=BUILD_PROFILE={}", env::var("PROFILE").unwrap_or_default());
+    println!("cargo:rustc-env=BUILD_RUSTC={}", rustc);
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features());
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
+
+/// The enabled features other than `default`, as Cargo.toml names them (e.g. `hook-scripts, keyring`), or `none`.
+fn features() -> String {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(|feature| feature.to_lowercase().replace('_', "-")))
+        .filter(|feature| feature != "default")
+        .collect();
+    features.sort();
+    if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(", ")
+    }
+}
+
+/// The first line a command prints, or `unknown` if it can't be run or fails.
+fn output_of(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|stdout| stdout.lines().next().map(str::to_string))
+        .filter(|line| !line.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,198 @@
+# English messages, which are also used for any message a translation leaves out.
+# Variables such as { $path } are filled in by the tool; keep them in translations.
+
+## Errors
+
+error-prefix = Error: { $error }
+error-nothing-detected = No context found (shell history, project files, or environment keys); set INCLUDE_SHELL_HISTORY, INCLUDE_REPOSITORY_FILES, or INCLUDE_ENV_FILE_KEYS to true
+
+## Gathering context
+
+project-directory = Project directory: { $path }
+no-dotenv = No .env file found; using environment variables and configuration files.
+retention-purged = Deleted what is older than RETENTION_DAYS={ $days }: { $runs } saved runs, { $artifacts } artifacts, { $entries } audit log entries.
+relevant-project-files = Relevant project files: { $files }
+replaying-request = Sending the saved request { $path } to { $model } instead of gathering context
+running-external = Handing the context to { $name } ({ $program })
+history-cutoff = Cutoff time for shell history: { $time }
+history-path = History path is: { $path } ({ $shell })
+context-block = Gathered from { $source }: { $label }, ~{ $tokens } tokens
+history-blocked = Dropped { $count ->
+    [one] one history entry
+   *[other] { $count } history entries
+} on COMMAND_BLOCKLIST
+credential-file-skipped = Skipped { $path }, which { $reason }
+history-credentials = Removed { $count ->
+    [one] one credential
+   *[other] { $count } credentials
+} typed on the command line from shell history
+redacted = Redacted { $count ->
+    [one] one match
+   *[other] { $count } matches
+} of REDACT_PATTERNS from the { $doc_type } request
+env-keys-filtered = Left out { $count ->
+    [one] one env key
+   *[other] { $count } env keys
+} on ENV_KEY_DENYLIST or not on ENV_KEY_ALLOWLIST
+secrets-redacted = Redacted { $count ->
+    [one] a likely secret
+   *[other] { $count } likely secrets
+} from file contents (set SECRET_SCAN=abort to stop instead):
+secrets-abort = Found { $count ->
+    [one] a likely secret
+   *[other] { $count } likely secrets
+} in file contents, so nothing was sent. Remove them, keep the files out with IGNORE_GLOBS, or set SECRET_SCAN=redact:
+injections-stripped = Removed { $count ->
+    [one] a line
+   *[other] { $count } lines
+} of instruction-like text from file contents (set INJECTION_SCAN=flag to send them as they are):
+injections-flagged = Found { $count ->
+    [one] a line
+   *[other] { $count } lines
+} of instruction-like text in file contents, which will be sent as data (INJECTION_SCAN=flag):
+pii-masked = Masked { $summary } (SCRUB_PII)
+pii-emails = { $count ->
+    [one] one email address
+   *[other] { $count } email addresses
+}
+pii-ip-addresses = { $count ->
+    [one] one IP address
+   *[other] { $count } IP addresses
+}
+pii-hostnames = { $count ->
+    [one] one internal hostname
+   *[other] { $count } internal hostnames
+}
+pii-ticket-ids = { $count ->
+    [one] one ticket ID
+   *[other] { $count } ticket IDs
+}
+pseudonymized = Replaced { $summary } with pseudonyms (PSEUDONYMIZE)
+pseudonym-buckets = { $count ->
+    [one] one bucket
+   *[other] { $count } buckets
+}
+pseudonym-usernames = { $count ->
+    [one] one username
+   *[other] { $count } usernames
+}
+
+## Reviewing context
+
+review-empty = No context to review.
+review-prompt = Toggle items by number (e.g. `1 4 7-9`, `a` for all), or press Enter to continue:
+review-item-tokens = { $tokens } tokens
+review-total = Estimated tokens included: { $tokens }
+review-invalid-selection = Ignoring invalid selection: { $selection }
+
+## Dry runs and the send summary
+
+dry-run-request = --- Request for { $doc_type } ---
+dry-run-estimate = --- Estimate ({ $model }) ---
+dry-run-document = ~{ $input } input tokens, ~{ $output } output tokens, { $cost }
+dry-run-total = ~{ $input } input tokens, { $cost }
+dry-run-total-label = total
+dry-run-nothing-sent = Dry run: nothing was sent.
+dry-run-no-env-example = Dry run: not writing .env.example
+summary-title = --- About to send ---
+summary-endpoint = Endpoint:          { $host }, model { $model }
+summary-documents = Documents:         { $documents }
+summary-history = Shell history:     { $count } entries
+summary-files = Project files:     { $count } files, { $with_contents } with contents
+summary-dependencies = Dependencies:      { $count } from lockfiles
+summary-env-keys = Environment keys:  { $count } (names only, never values)
+summary-exemplars = Exemplars:         { $count } documents
+summary-estimate = Estimate:          ~{ $input } input tokens, ~{ $output } output tokens, { $cost }
+report-title = --- Redaction report ---
+report-ignored-files = Files left out by IGNORE_GLOBS or the policy: { $count }
+report-excluded-files = Files left out by a magic-quickstart: exclude comment: { $count }
+report-credential-files = Files left out because they look like credential stores: { $count }
+report-blocked-commands = History entries dropped by COMMAND_BLOCKLIST: { $count }
+report-history-credentials = Credentials typed on the command line, removed from shell history: { $count }
+report-env-keys = Env keys left out by ENV_KEY_ALLOWLIST or ENV_KEY_DENYLIST: { $count }
+report-secrets-redact = Likely secrets, redacted (SECRET_SCAN=redact): { $count }
+report-secrets-abort = Likely secrets, which stop the run (SECRET_SCAN=abort): { $count }
+report-secrets-off = Likely secrets, sent as they are (SECRET_SCAN=off): { $count }
+report-injections-strip = Instruction-like text, removed (INJECTION_SCAN=strip): { $count }
+report-injections-flag = Instruction-like text, sent as data (INJECTION_SCAN=flag): { $count }
+report-injections-off = Instruction-like text, sent as it is (INJECTION_SCAN=off): { $count }
+report-pii-none = Personal details: none found
+report-pii-masked = Personal details, masked (SCRUB_PII): { $summary }
+report-pii-unmasked = Personal details, sent as they are (set SCRUB_PII=true to mask them): { $summary }
+report-pseudonyms = Identifiers, replaced with pseudonyms (PSEUDONYMIZE): { $summary }
+report-pseudonyms-none = Identifiers, replaced with pseudonyms (PSEUDONYMIZE): none found
+report-pseudonyms-off = Identifiers: sent as they are (set PSEUDONYMIZE=true to replace them with pseudonyms)
+report-patterns = REDACT_PATTERNS in the { $doc_type } request:
+report-no-patterns = REDACT_PATTERNS in the { $doc_type } request: none set
+report-pattern-matches = { $pattern }: { $count ->
+    [0] no matches
+    [one] one match
+   *[other] { $count } matches
+}
+report-nothing-sent = Report only: nothing was generated or sent.
+openai-disabled = ENABLE_OPENAI is not set to true. Exiting early.
+confirm-send = Send this to OpenAI?
+consent-remembered = Sending the same kinds of data you agreed to before for this project (REMEMBER_CONSENT).
+consent-new-categories = New since you last agreed: { $categories }
+consent-not-saved = Couldn't remember this answer, so you'll be asked again next time: { $error }
+nothing-sent = Nothing was sent.
+
+## Answers to yes/no questions, separated by spaces. English answers are always accepted too.
+
+confirm-answers = y yes
+
+## Writing documents
+
+wrote = Wrote { $path }
+wrote-tasks = Wrote { $path }. Review recipes that deploy or publish before running them.
+wrote-workflow = Wrote { $path }. Review it before committing.
+wrote-script = Wrote { $path }. Review it before running, especially lines marked SAFETY.
+wrote-man-page = Wrote man page to { $path } (view with `man { $path }`)
+merged = Merged generated content into { $path }
+regenerated-section = Regenerated the "{ $heading }" section of { $path }
+copied = Copied { $name } to the clipboard
+sink-stdout = --- { $path } ---
+sink-posted = Posted { $path } to { $host }
+sink-committed = Wrote and committed { $path }
+up-to-date = { $path } is already up to date
+confirm-overwrite = Overwrite { $path }?
+left-unchanged = Left { $path } unchanged
+backed-up = Backed up previous version to { $path }
+editor-failed = Editor exited with { $status }; discarding the draft
+confirm-finalize = Finalize the reviewed document to { $path }?
+draft-discarded = Discarded the draft for { $path }
+risky-commands = Found { $count ->
+    [one] a risky command
+   *[other] { $count } risky commands
+} in the { $doc_type } document; check before running:
+risky-commands-annotated = Marked { $count ->
+    [one] a risky command
+   *[other] { $count } risky commands
+} in the { $doc_type } document with a warning; check before running:
+response-cut = The { $doc_type } response was cut off because { $reason }; writing what arrived, marked as truncated
+response-cut-unusable = The { $doc_type } response was cut off because { $reason }, before anything usable arrived, so nothing is written for it
+truncation-note = Truncated: { $reason } before this document was finished.
+cut-interrupted = the run was interrupted
+cut-idle = the API sent nothing for { $seconds } seconds
+cut-deadline = the request took longer than { $seconds } seconds
+cut-broken = the connection broke
+manifest-saved = Recorded what the documents were generated from in { $path }
+run-saved = Saved run { $id }
+run-not-saved = Couldn't save this run: { $error }
+
+## Verifying the run manifest
+
+manifest-current = { $doc_type }: up to date
+manifest-drift-missing = { $doc_type }: not recorded in { $path }; generate it to add it
+manifest-drift-model = { $doc_type }: generated with { $was }, now set to { $now }
+manifest-drift-seed = { $doc_type }: generated with seed { $was }, now set to { $now }
+manifest-drift-tool-version = { $doc_type }: generated by magic_quickstart { $was }, and { $now } builds a different request
+manifest-drift-context = { $doc_type }: the project changed since it was generated
+
+## Watching and interrupts
+
+watching = Watching { $paths } for changes (Ctrl-C to stop)
+watch-changed = { $path } changed; regenerating
+watch-changed-more = { $path } changed (and { $count } more); regenerating
+interrupted = Interrupted; cancelling and cleaning up
+interrupt-keeping-partial = Interrupted; stopping the requests and keeping what has arrived (Ctrl-C again to quit now)
@@ -0,0 +1,65 @@
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::io::{self, BufRead};
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+/// The OpenAI API key. It is only ever shown to the HTTP client, never printed (`Debug` masks it), and our copy is
+/// overwritten with zeros when it is dropped, so it doesn't linger in memory once the requests are sent.
+#[derive(Clone)]
+pub struct ApiKey(Zeroizing<String>);
+
+impl ApiKey {
+    /// Wraps a key read from the environment or a configuration file, or `None` if it is blank.
+    pub fn new(key: String) -> Option<Self> {
+        let key = Zeroizing::new(key);
+        let trimmed = key.trim();
+        (!trimmed.is_empty()).then(|| ApiKey(Zeroizing::new(trimmed.to_string())))
+    }
+
+    /// The key, for the `Authorization` header.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Reads the key from the first line of `reader`; `source` names it in errors.
+    fn read(mut reader: impl BufRead, source: &str) -> Result<Self> {
+        let mut line = Zeroizing::new(String::new());
+        reader
+            .read_line(&mut line)
+            .with_context(|| Error::Config(format!("Failed to read the API key from {}", source)))?;
+        match ApiKey::new(line.to_string()) {
+            Some(key) => Ok(key),
+            None => bail!(Error::Config(format!("No API key on the first line of {}", source))),
+        }
+    }
+
+    /// Reads the key from standard input (`--api-key-stdin`).
+    pub fn from_stdin() -> Result<Self> {
+        ApiKey::read(io::stdin().lock(), "standard input")
+    }
+
+    /// Reads the key from an open file descriptor (`--api-key-fd`), as secret-injection systems pass it, e.g.
+    /// `magic_quickstart --api-key-fd 3 3< <(vault kv get -field=key secret/openai)`.
+    #[cfg(unix)]
+    pub fn from_fd(fd: u32) -> Result<Self> {
+        let source = format!("file descriptor {}", fd);
+        let file =
+            std::fs::File::open(format!("/dev/fd/{}", fd)).with_context(|| Error::Config(format!("Can't read {}; is it open?", source)))?;
+        ApiKey::read(io::BufReader::new(file), &source)
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_fd(_fd: u32) -> Result<Self> {
+        bail!(Error::Config(
+            "--api-key-fd only works on Unix; use --api-key-stdin instead".to_string()
+        ))
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ApiKey(****)")
+    }
+}
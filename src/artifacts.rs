@@ -0,0 +1,265 @@
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::console::status;
+use crate::encryption::{self, Sealer};
+use crate::errors::Error;
+use crate::interrupt::{forget_on_interrupt, remove_on_interrupt};
+use crate::models::{AssetDirectory, DependencySummary, EnvKey, Exemplar, FileContext, GenerationRequest, HistoryEntry};
+use crate::rules::REDACTED;
+use crate::secrets::scan_secrets;
+
+/// Name of the directory intermediate artifacts are written to.
+pub const ARTIFACTS_DIR: &str = ".magic_quickstart";
+
+/// Version of the artifact format, written into every artifact as `schema_version`. It goes up whenever a field is
+/// removed, renamed, or changes meaning; adding a field doesn't change it, so readers should ignore fields they
+/// don't know.
+pub const ARTIFACT_SCHEMA_VERSION: u64 = 1;
+
+/// Field names, compared without case, that hold credentials and are never written to an artifact.
+const CREDENTIAL_FIELDS: [&str; 7] = [
+    "authorization",
+    "proxy-authorization",
+    "api_key",
+    "api-key",
+    "x-api-key",
+    "openai_api_key",
+    "token",
+];
+
+/// Removes credential fields from `value`, and redacts strings that carry a bearer token, at any depth.
+fn strip_credentials(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|key, _| !CREDENTIAL_FIELDS.contains(&key.to_lowercase().as_str()));
+            map.values_mut().for_each(strip_credentials);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_credentials),
+        serde_json::Value::String(text) if text.trim_start().to_lowercase().starts_with("bearer ") => {
+            *text = format!("Bearer {}", REDACTED);
+        }
+        _ => {}
+    }
+}
+
+/// Writes intermediate JSON artifacts (history, file contents, request payload) into a dedicated directory
+/// instead of the project root. Files written during the run are removed when the store is dropped
+/// unless `keep` is set; a disabled store ignores all writes. Each file is written under a temporary name and then
+/// renamed, so an interrupted run never leaves half-written JSON, and an interrupted run removes its artifacts too.
+/// With ENCRYPT_ARTIFACTS, each file is encrypted and gets an `.enc` suffix.
+pub struct ArtifactStore {
+    directory: Option<PathBuf>,
+    keep: bool,
+    sealer: Option<&'static Sealer>,
+    written: Vec<PathBuf>,
+    /// Whether this run created the directory, so it can be removed whole on interrupt.
+    created: bool,
+}
+
+impl ArtifactStore {
+    /// Creates a store writing into `base_dir/.magic_quickstart`, or a disabled store if `enabled` is false. With
+    /// `encrypt`, the key is loaded up front, so a missing keyring stops the run before anything is gathered.
+    pub fn new(base_dir: &Path, enabled: bool, keep: bool, encrypt: bool) -> Result<Self> {
+        let sealer = if enabled && encrypt { Some(Sealer::get()?) } else { None };
+        let mut created = false;
+        let directory = if enabled {
+            let directory = base_dir.join(ARTIFACTS_DIR);
+            created = !directory.exists();
+            if created && !keep {
+                remove_on_interrupt(&directory);
+            }
+            fs::create_dir_all(&directory).with_context(|| format!("Failed to create {}", directory.display()))?;
+            // Ignore the directory's contents so artifacts never show up in git status.
+            let gitignore = directory.join(".gitignore");
+            if !gitignore.exists() {
+                fs::write(&gitignore, "*\n").with_context(|| format!("Failed to write {}", gitignore.display()))?;
+            }
+            Some(directory)
+        } else {
+            None
+        };
+
+        Ok(ArtifactStore {
+            directory,
+            keep,
+            sealer,
+            written: Vec::new(),
+            created,
+        })
+    }
+
+    /// Writes JSON data to the named artifact file, if artifacts are enabled, as
+    /// `{"schema_version": 1, "artifact": "<kind>", "data": ...}`. Credentials never reach the file, whatever the
+    /// data grows to include: fields named like credentials (such as an `Authorization` header) are left out, and
+    /// anything that looks like a key or token is redacted.
+    pub fn write_json(&mut self, file_name: &str, data: &serde_json::Value) -> Result<()> {
+        let Some(directory) = &self.directory else {
+            return Ok(());
+        };
+        let mut data = data.clone();
+        strip_credentials(&mut data);
+        scan_secrets(&mut data, file_name, true);
+        let data = json!({
+            "schema_version": ARTIFACT_SCHEMA_VERSION,
+            "artifact": artifact_kind(file_name),
+            "data": data,
+        });
+        let (file_name, content) = match self.sealer {
+            Some(sealer) => (format!("{}.enc", file_name), sealer.seal(data.to_string().as_bytes())),
+            None => (file_name.to_string(), data.to_string().into_bytes()),
+        };
+        let file_path = directory.join(&file_name);
+        let partial_path = directory.join(format!("{}.partial", file_name));
+        if !self.keep && !self.created {
+            remove_on_interrupt(&file_path);
+        }
+        remove_on_interrupt(&partial_path);
+        fs::write(&partial_path, content).with_context(|| format!("Failed to write to {}", partial_path.display()))?;
+        fs::rename(&partial_path, &file_path).with_context(|| format!("Failed to write to {}", file_path.display()))?;
+        forget_on_interrupt(&partial_path);
+        self.written.push(file_path);
+        Ok(())
+    }
+}
+
+impl Drop for ArtifactStore {
+    fn drop(&mut self) {
+        let Some(directory) = &self.directory else {
+            return;
+        };
+        forget_on_interrupt(directory);
+        for file_path in &self.written {
+            forget_on_interrupt(file_path);
+        }
+        if self.keep {
+            status!("Artifacts kept in {}", directory.display());
+            return;
+        }
+
+        for file_path in &self.written {
+            let _ = fs::remove_file(file_path);
+        }
+        // Only remove the directory if nothing from earlier kept runs is left in it.
+        let only_gitignore_left = fs::read_dir(directory)
+            .map(|entries| entries.flatten().all(|entry| entry.file_name() == ".gitignore"))
+            .unwrap_or(false);
+        if only_gitignore_left {
+            let _ = fs::remove_dir_all(directory);
+        }
+    }
+}
+
+/// The kind of artifact a file holds, from its name: `request_contributing.json` holds a `request` like
+/// `request.json` does.
+fn artifact_kind(file_name: &str) -> &str {
+    let stem = file_name.strip_suffix(".json").unwrap_or(file_name);
+    if stem.starts_with("request_") {
+        "request"
+    } else {
+        stem
+    }
+}
+
+/// Checks that `data` has the shape artifacts of `kind` have in this schema version.
+fn check_data(kind: &str, data: Value) -> Result<(), String> {
+    fn shape<T: DeserializeOwned>(data: Value) -> Result<(), String> {
+        serde_json::from_value::<T>(data).map(drop).map_err(|error| error.to_string())
+    }
+    match kind {
+        "command_history" => shape::<Vec<HistoryEntry>>(data),
+        "project_files_content" | "ci_config_files" => shape::<Vec<FileContext>>(data),
+        "request" => shape::<GenerationRequest>(data),
+        "dependency_summary" => shape::<Vec<DependencySummary>>(data),
+        "asset_inventory" => shape::<Vec<AssetDirectory>>(data),
+        "env_file_keys" => shape::<Vec<EnvKey>>(data),
+        "exemplars" => shape::<Vec<Exemplar>>(data),
+        "git_metadata" | "module_structure" | "toolchain_versions" => shape::<serde_json::Map<String, Value>>(data),
+        _ => Err(format!("unknown artifact \"{}\"", kind)),
+    }
+}
+
+/// Reads an artifact file, decrypting it if it was written with ENCRYPT_ARTIFACTS, and returns its kind and data.
+/// The error says what is wrong with it.
+fn read_artifact(path: &Path) -> Result<(String, Value), String> {
+    let content = encryption::read_to_string(path).map_err(|error| format!("{:#}", error))?;
+    let mut artifact: Value = serde_json::from_str(&content).map_err(|error| format!("not JSON: {}", error))?;
+    let Some(version) = artifact.get("schema_version") else {
+        return Err("no schema_version; it was written before artifacts were versioned".to_string());
+    };
+    match version.as_u64() {
+        Some(ARTIFACT_SCHEMA_VERSION) => {}
+        Some(version) if version > ARTIFACT_SCHEMA_VERSION => {
+            return Err(format!(
+                "schema_version {} is newer than this version of magic_quickstart reads ({})",
+                version, ARTIFACT_SCHEMA_VERSION
+            ))
+        }
+        _ => return Err(format!("unsupported schema_version {}", version)),
+    }
+    let Some(kind) = artifact["artifact"].as_str().map(str::to_string) else {
+        return Err("no artifact kind".to_string());
+    };
+    Ok((kind, artifact["data"].take()))
+}
+
+/// Checks one artifact file. Returns what is wrong with it.
+fn check_artifact(path: &Path) -> Result<(), String> {
+    let (kind, data) = read_artifact(path)?;
+    check_data(&kind, data).map_err(|problem| format!("{}: {}", kind, problem))
+}
+
+/// Reads a request artifact (`request.json` or `request_<doc type>.json`, encrypted or not) to send it again. A
+/// file that isn't one, or that an older or newer version wrote, is a configuration error.
+pub fn read_request(path: &Path) -> Result<GenerationRequest> {
+    let request = read_artifact(path).and_then(|(kind, data)| match kind.as_str() {
+        "request" => serde_json::from_value(data).map_err(|error| format!("request: {}", error)),
+        _ => Err(format!("it holds {}, not a request", kind)),
+    });
+    request.map_err(|problem| Error::Config(format!("{} can't be sent: {}", path.display(), problem)).into())
+}
+
+/// Checks artifact files against the schema this version writes, printing `ok` or what is wrong for each. Without
+/// `paths`, checks every artifact kept in `project_dir/.magic_quickstart`. Fails if any doesn't match.
+pub fn validate_artifacts(project_dir: &Path, paths: &[PathBuf]) -> Result<()> {
+    let paths = if paths.is_empty() {
+        let directory = project_dir.join(ARTIFACTS_DIR);
+        let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        paths.retain(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.ends_with(".json") || name.ends_with(".json.enc")
+        });
+        paths.sort();
+        if paths.is_empty() {
+            println!("No artifacts in {}", directory.display());
+            return Ok(());
+        }
+        paths
+    } else {
+        paths.to_vec()
+    };
+    let mut invalid = 0;
+    for path in &paths {
+        match check_artifact(path) {
+            Ok(()) => println!("ok       {}", path.display()),
+            Err(problem) => {
+                invalid += 1;
+                println!("invalid  {}: {}", path.display(), problem);
+            }
+        }
+    }
+    if invalid > 0 {
+        bail!(
+            "{} of {} artifacts don't match artifact schema version {}",
+            invalid,
+            paths.len(),
+            ARTIFACT_SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::models::AssetDirectory;
+use crate::parsers::walk_source_files;
+
+/// Directories that usually hold assets or data rather than code, summarized instead of read.
+const ASSET_DIRS: [&str; 8] = [
+    "assets",
+    "static",
+    "public",
+    "data",
+    "fixtures",
+    "seeds",
+    "migrations",
+    "db/migrations",
+];
+
+/// Upper bound on files counted per directory, to keep huge data directories fast.
+const MAX_INVENTORY_FILES: usize = 10_000;
+
+/// How many file names are listed from each end of a directory's sorted listing.
+const NOTABLE_FILES_PER_END: usize = 5;
+
+/// Summarizes asset and data directories as an inventory: file counts by extension, subdirectories,
+/// and a few notable file names (the first and last by name, which for migrations are the oldest and newest).
+pub fn summarize_asset_directories(root: &Path, follow_symlinks: bool) -> Vec<AssetDirectory> {
+    present_asset_directories(root)
+        .into_iter()
+        .map(|dir| summarize_directory(root, dir, follow_symlinks))
+        .collect()
+}
+
+/// The asset and data directories in the project that would be summarized, without walking them.
+pub fn present_asset_directories(root: &Path) -> Vec<&'static str> {
+    ASSET_DIRS.into_iter().filter(|dir| root.join(dir).is_dir()).collect()
+}
+
+fn summarize_directory(root: &Path, dir: &str, follow_symlinks: bool) -> AssetDirectory {
+    let directory = root.join(dir);
+    let walk = walk_source_files(&directory, &[], MAX_INVENTORY_FILES, follow_symlinks);
+    let mut files: Vec<String> = walk
+        .files
+        .iter()
+        .map(|path| path.strip_prefix(&directory).unwrap_or(path).display().to_string())
+        .collect();
+    files.sort();
+
+    let mut counts_by_extension: BTreeMap<String, usize> = BTreeMap::new();
+    for file in &files {
+        let extension = Path::new(file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_else(|| "(none)".to_string());
+        *counts_by_extension.entry(extension).or_default() += 1;
+    }
+
+    let mut subdirectories: Vec<String> = files
+        .iter()
+        .filter_map(|file| file.split_once('/').map(|(subdirectory, _)| subdirectory.to_string()))
+        .collect();
+    subdirectories.dedup();
+
+    let notable_files: Vec<String> = if files.len() <= NOTABLE_FILES_PER_END * 2 {
+        files.clone()
+    } else {
+        files
+            .iter()
+            .take(NOTABLE_FILES_PER_END)
+            .chain(files.iter().skip(files.len() - NOTABLE_FILES_PER_END))
+            .cloned()
+            .collect()
+    };
+
+    AssetDirectory {
+        directory: dir.to_string(),
+        file_count: files.len(),
+        truncated: walk.sampled || files.len() >= MAX_INVENTORY_FILES,
+        counts_by_extension,
+        subdirectories,
+        notable_files,
+    }
+}
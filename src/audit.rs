@@ -0,0 +1,91 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::errors::Error;
+use crate::file_lock::FileLock;
+use crate::settings::data_home;
+
+/// Where the audit log is kept unless AUDIT_LOG says otherwise: `$XDG_DATA_HOME/magic_quickstart/audit.jsonl`,
+/// defaulting to `~/.local/share/magic_quickstart/audit.jsonl`.
+pub fn default_audit_log_path() -> Result<PathBuf> {
+    match data_home() {
+        Some(dir) => Ok(dir.join("magic_quickstart").join("audit.jsonl")),
+        None => bail!(Error::Config(
+            "Can't find the directory for the audit log; set HOME, XDG_DATA_HOME, or AUDIT_LOG".to_string()
+        )),
+    }
+}
+
+/// An append-only record of every API call, one JSON object per line: when it was made, the provider and endpoint,
+/// the payload exactly as sent (after redaction), the HTTP status, and a SHA-256 of the response body. Compliance
+/// reviews can check the payloads for anything that shouldn't have left the machine, and match the hashes against
+/// responses kept elsewhere. Lines are only ever appended; the only rewrite is RETENTION_DAYS or `purge` dropping old ones.
+/// Requests are sent in parallel, and other instances may share the log, so each record is appended whole while
+/// holding `<log>.lock`, which a purge holds too while it replaces the log.
+pub struct AuditLog {
+    /// `None` for an in-memory run without AUDIT_LOG, which records nothing.
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) the log before anything is sent, so a log that can't be written stops the run instead of
+    /// letting calls go unrecorded. On Unix a new log is only readable by the user, as payloads include project files.
+    pub fn open(path: &Path) -> Result<Self> {
+        let error = || {
+            Error::Config(format!(
+                "Can't write the audit log {}; fix its permissions or set AUDIT_LOG",
+                path.display()
+            ))
+        };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(error)?;
+        }
+        // The lock beside the log is tried too, so a directory where it can't be created stops the run here as well.
+        append_options().open(path).with_context(error)?;
+        FileLock::beside(path).with_context(error)?;
+        Ok(AuditLog {
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// A log that records nothing, for in-memory runs that weren't given an AUDIT_LOG to write to.
+    pub fn disabled() -> Self {
+        AuditLog { path: None }
+    }
+
+    /// Appends the record of one call. `response` is the HTTP status and raw body, or `None` if the endpoint
+    /// couldn't be reached.
+    pub fn record(&self, provider: &str, endpoint: &str, payload: &impl Serialize, response: Option<(u16, &[u8])>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let record = json!({
+            "timestamp": Local::now().to_rfc3339(),
+            "provider": provider,
+            "endpoint": endpoint,
+            "payload": payload,
+            "status": response.map(|(status, _)| status),
+            "response_sha256": response.map(|(_, body)| format!("{:x}", Sha256::digest(body))),
+        });
+        let error = || format!("Failed to append to the audit log {}", path.display());
+        // The log is opened again each time, since a purge may have replaced it since the last record.
+        let _lock = FileLock::beside(path).with_context(error)?;
+        let mut file = append_options().open(path).with_context(error)?;
+        file.write_all(format!("{}\n", record).as_bytes()).with_context(error)
+    }
+}
+
+/// How the log is opened: for appending, created if need be, and on Unix only readable by the user.
+fn append_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use crate::console::status;
+use crate::models::{DependencySummary, FileContext, HistoryEntry};
+use crate::tokens::estimate_tokens;
+
+/// Manifest file names whose contents count toward the `manifests` budget.
+const MANIFEST_FILES: [&str; 4] = ["Cargo.toml", "pyproject.toml", "package.json", "go.mod"];
+
+/// Extensions whose contents count toward the `docs` budget.
+const DOC_EXTENSIONS: [&str; 5] = ["md", "rst", "txt", "adoc", "org"];
+
+/// Marker appended to content cut short by the budget.
+const TRUNCATION_MARKER: &str = "\n... [truncated to fit token budget]";
+
+/// A category of context that receives a share of the token budget.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BudgetCategory {
+    History,
+    Files,
+    Manifests,
+    Docs,
+}
+
+impl BudgetCategory {
+    const ALL: [BudgetCategory; 4] = [
+        BudgetCategory::History,
+        BudgetCategory::Files,
+        BudgetCategory::Manifests,
+        BudgetCategory::Docs,
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim() {
+            "history" => Some(BudgetCategory::History),
+            "files" => Some(BudgetCategory::Files),
+            "manifests" => Some(BudgetCategory::Manifests),
+            "docs" => Some(BudgetCategory::Docs),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Which category a file's content counts toward.
+    fn for_file(path: &str) -> Self {
+        let path = Path::new(path);
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        if MANIFEST_FILES.contains(&file_name) {
+            BudgetCategory::Manifests
+        } else if DOC_EXTENSIONS.contains(&extension) {
+            BudgetCategory::Docs
+        } else {
+            BudgetCategory::Files
+        }
+    }
+}
+
+/// Relative shares of the token budget per category, indexed by `BudgetCategory`.
+#[derive(Clone, Copy, Debug)]
+pub struct BudgetSplit([u32; 4]);
+
+impl BudgetSplit {
+    /// Parses a split such as `history:20,files:60,manifests:20`. Categories that are not listed get no share,
+    /// but can still receive budget left unused by others. Returns `None` if no category gets a positive share.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut shares = [0; 4];
+        for part in spec.split(',').filter(|part| !part.trim().is_empty()) {
+            let parsed = part
+                .split_once(':')
+                .and_then(|(name, share)| Some((BudgetCategory::from_name(name)?, share.trim().parse::<u32>().ok()?)));
+            match parsed {
+                Some((category, share)) => shares[category.index()] = share,
+                None => status!("Ignoring invalid BUDGET_SPLIT entry: {}", part),
+            }
+        }
+        shares.iter().any(|&share| share > 0).then_some(BudgetSplit(shares))
+    }
+}
+
+/// Splits `total` tokens between categories by share, handing budget a category doesn't need
+/// to the categories that still want more, in proportion to their shares.
+pub fn allocate_budget(total: usize, split: BudgetSplit, demands: [usize; 4]) -> [usize; 4] {
+    let mut allocation = [0usize; 4];
+    let mut remaining = total;
+
+    loop {
+        let wanting: Vec<usize> = (0..4).filter(|&i| allocation[i] < demands[i]).collect();
+        if remaining == 0 || wanting.is_empty() {
+            break;
+        }
+        // Categories without a share only receive budget once every shared category is satisfied.
+        let shared: Vec<usize> = wanting.iter().copied().filter(|&i| split.0[i] > 0).collect();
+        let (recipients, weights): (Vec<usize>, Vec<u64>) = if shared.is_empty() {
+            (wanting.clone(), vec![1; wanting.len()])
+        } else {
+            (shared.clone(), shared.iter().map(|&i| split.0[i] as u64).collect())
+        };
+        let total_weight: u64 = weights.iter().sum();
+
+        let mut handed_out = 0;
+        for (&i, &weight) in recipients.iter().zip(&weights) {
+            let share = ((remaining as u64 * weight) / total_weight) as usize;
+            let grant = share.min(demands[i] - allocation[i]);
+            allocation[i] += grant;
+            handed_out += grant;
+        }
+        if handed_out == 0 {
+            // Rounding left less than one token per recipient; give the remainder to the first one.
+            let i = recipients[0];
+            let grant = remaining.min(demands[i] - allocation[i]);
+            allocation[i] += grant;
+            handed_out = grant;
+        }
+        remaining -= handed_out;
+    }
+
+    allocation
+}
+
+/// Trims history, file contents, and the dependency summary so each category fits its share of `total` tokens.
+/// The most recent history entries and the earliest (highest priority) files are kept first.
+pub fn apply_token_budget(
+    total: usize,
+    split: BudgetSplit,
+    command_history: &mut Vec<HistoryEntry>,
+    project_files_content: &mut Vec<FileContext>,
+    dependency_summary: &mut Vec<DependencySummary>,
+) {
+    let mut demands = [0usize; 4];
+    demands[BudgetCategory::History.index()] = command_history.iter().map(|entry| estimate_tokens(&entry.to_json())).sum();
+    for entry in project_files_content.iter() {
+        let category = BudgetCategory::for_file(&entry.file_path);
+        demands[category.index()] += estimate_tokens(&entry.content);
+    }
+    demands[BudgetCategory::Manifests.index()] += dependency_summary
+        .iter()
+        .map(|entry| estimate_tokens(&entry.to_json()))
+        .sum::<usize>();
+
+    let mut allocation = allocate_budget(total, split, demands);
+    for category in BudgetCategory::ALL {
+        status!(
+            "Token budget for {:?}: {} of {} requested",
+            category,
+            allocation[category.index()],
+            demands[category.index()]
+        );
+    }
+
+    // History is newest first, so cutting the tail drops the oldest commands.
+    let history_budget = &mut allocation[BudgetCategory::History.index()];
+    command_history.retain(|entry| take_tokens(history_budget, estimate_tokens(&entry.to_json())));
+
+    // Dependency summaries are small and high value, so they are charged before manifest contents.
+    let manifest_budget = &mut allocation[BudgetCategory::Manifests.index()];
+    dependency_summary.retain(|entry| take_tokens(manifest_budget, estimate_tokens(&entry.to_json())));
+
+    project_files_content.retain_mut(|entry| {
+        let category = BudgetCategory::for_file(&entry.file_path);
+        let budget = &mut allocation[category.index()];
+        let tokens = estimate_tokens(&entry.content);
+        if take_tokens(budget, tokens) {
+            return true;
+        }
+        if *budget == 0 {
+            return false;
+        }
+        // Keep as much of the file as still fits.
+        let kept: String = entry.content.chars().take(*budget * 4).collect();
+        entry.content = format!("{}{}", kept, TRUNCATION_MARKER);
+        *budget = 0;
+        true
+    });
+}
+
+/// Deducts `tokens` from `budget` if it fits, returning whether it did.
+fn take_tokens(budget: &mut usize, tokens: usize) -> bool {
+    if tokens <= *budget {
+        *budget -= tokens;
+        true
+    } else {
+        false
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::api_key::ApiKey;
+use crate::cli::{Args, Target};
+use crate::doc_types::DocType;
+use crate::errors::Error;
+use crate::formats::OutputFormat;
+use crate::generate::{generate, Outcome};
+use crate::sinks::OutputSink;
+use crate::sources::ContextSource;
+
+/// A kind of context a run gathers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    /// Recent shell history (INCLUDE_SHELL_HISTORY).
+    ShellHistory,
+    /// Project files, lockfiles, and asset directories (INCLUDE_REPOSITORY_FILES).
+    RepositoryFiles,
+    /// Key names from the project's env files (INCLUDE_ENV_FILE_KEYS).
+    EnvFileKeys,
+}
+
+/// What writes the documents.
+pub enum Provider {
+    /// An OpenAI-compatible API. Fields left `None` come from the settings: OPENAI_API_KEY, OPENAI_MODEL, and
+    /// OPENAI_BASE_URL.
+    OpenAi {
+        api_key: Option<String>,
+        model: Option<String>,
+        base_url: Option<String>,
+    },
+    /// The built-in quickstart template, filled in on this machine (LOCAL_ONLY); nothing is sent.
+    Local,
+}
+
+/// Configures generation for tools that embed it, such as editor extensions and bots. Settings are read from the
+/// project's `.env`, the environment, and the configuration files as on the command line, and what is set here
+/// takes precedence, like a flag. The organization policy applies all the same.
+///
+/// ```no_run
+/// use magic_quickstart::{DocType, Provider, QuickstartBuilder, Source};
+///
+/// let outcome = QuickstartBuilder::new("path/to/project")
+///     .sources(&[Source::RepositoryFiles, Source::EnvFileKeys])
+///     .doc_types([DocType::Quickstart, DocType::Contributing])
+///     .provider(Provider::OpenAi {
+///         api_key: Some("sk-...".to_string()),
+///         model: Some("gpt-4o-mini".to_string()),
+///         base_url: None,
+///     })
+///     .output_dir("generated")
+///     .build()?
+///     .generate()?;
+/// println!("{}", outcome.name());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct QuickstartBuilder {
+    project_dir: PathBuf,
+    output_dir: Option<PathBuf>,
+    args: Args,
+}
+
+impl QuickstartBuilder {
+    /// Starts configuring generation for the project in `project_dir`.
+    pub fn new(project_dir: impl Into<PathBuf>) -> Self {
+        QuickstartBuilder {
+            project_dir: project_dir.into(),
+            output_dir: None,
+            args: Args::default(),
+        }
+    }
+
+    /// Uses a named profile from the configuration files.
+    pub fn profile(mut self, name: &str) -> Self {
+        self.args.profile = Some(name.to_string());
+        self
+    }
+
+    /// Gathers exactly these kinds of context, whatever the INCLUDE_* settings say.
+    pub fn sources(mut self, sources: &[Source]) -> Self {
+        self.args.include_shell_history = Some(sources.contains(&Source::ShellHistory));
+        self.args.include_repository_files = Some(sources.contains(&Source::RepositoryFiles));
+        self.args.include_env_file_keys = Some(sources.contains(&Source::EnvFileKeys));
+        self
+    }
+
+    /// Reads this history file instead of the shell's own; call it again to merge several. Each file's format is
+    /// detected.
+    pub fn history_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.args.history.files.push(path.into());
+        self
+    }
+
+    /// How many hours of shell history to gather.
+    pub fn hours(mut self, hours: i64) -> Self {
+        self.args.hours = Some(hours);
+        self
+    }
+
+    /// How many project files the detectors may pick.
+    pub fn max_files(mut self, count: usize) -> Self {
+        self.args.max_files = Some(count);
+        self
+    }
+
+    /// Adds a source of context of the tool's own, such as a ticket or a design document. Its blocks go through the
+    /// same filtering, scanning, and redaction as the built-in context, and INCLUDE_* settings don't turn it off.
+    pub fn context_source(mut self, source: impl ContextSource + 'static) -> Self {
+        self.args.context_sources.push(Arc::new(source));
+        self
+    }
+
+    /// Hands the generated files to `sink` instead of the one OUTPUT_SINK names, e.g. to show them in an editor
+    /// without writing them.
+    pub fn output_sink(mut self, sink: impl OutputSink + 'static) -> Self {
+        self.args.output_sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Writes nothing but the generated files, and those only if the sink does: no intermediate artifacts, saved
+    /// run, remembered consent, retention purge, or audit log unless AUDIT_LOG names one. With a [`MemorySink`],
+    /// nothing is written at all, so a server can run any number of generations side by side.
+    ///
+    /// [`MemorySink`]: crate::MemorySink
+    pub fn in_memory(mut self) -> Self {
+        self.args.in_memory = true;
+        self
+    }
+
+    /// Keeps files matching `glob` out of what the detectors pick, on top of IGNORE_GLOBS.
+    pub fn ignore(mut self, glob: &str) -> Self {
+        self.args.ignore.push(glob.to_string());
+        self
+    }
+
+    /// Generates these documents, each from its own request.
+    pub fn doc_types(mut self, doc_types: impl IntoIterator<Item = DocType>) -> Self {
+        self.args.batch = Some(doc_types.into_iter().collect());
+        self
+    }
+
+    /// Writes documents in this format.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.args.format = Some(format);
+        self
+    }
+
+    /// Sends nothing if the estimated cost is above `dollars`.
+    pub fn max_cost(mut self, dollars: f64) -> Self {
+        self.args.max_cost = Some(dollars);
+        self
+    }
+
+    /// Chooses what writes the documents.
+    pub fn provider(mut self, provider: Provider) -> Self {
+        match provider {
+            Provider::OpenAi { api_key, model, base_url } => {
+                self.args.enable_openai = Some(true);
+                self.args.local_only = Some(false);
+                self.args.api_key = api_key.and_then(ApiKey::new);
+                self.args.model = model;
+                self.args.base_url = base_url;
+            }
+            Provider::Local => self.args.local_only = Some(true),
+        }
+        self
+    }
+
+    /// Writes the documents into `dir` instead of the project directory. It is created if needed.
+    pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(dir.into());
+        self
+    }
+
+    /// Checks the project and output directories. The settings are read and checked when generating, since they
+    /// may change in between.
+    pub fn build(self) -> Result<Quickstart> {
+        let project_dir = self
+            .project_dir
+            .canonicalize()
+            .with_context(|| Error::Config(format!("Project directory {} does not exist", self.project_dir.display())))?;
+        let mut args = self.args;
+        args.set_target(Target::Local(project_dir));
+        // Nothing can be asked of an embedding tool's user; configuring the run is the consent.
+        args.yes = true;
+        if let Some(dir) = self.output_dir {
+            fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            args.output_dir = Some(dir);
+        }
+        Ok(Quickstart { args })
+    }
+}
+
+/// Generation configured by a [`QuickstartBuilder`], which can be run any number of times.
+pub struct Quickstart {
+    args: Args,
+}
+
+impl Quickstart {
+    /// Gathers context, sends the requests, and writes the documents, as `magic_quickstart generate --yes` would.
+    /// Progress goes to stderr. Returns how the run ended and, for a generated run, the files written. [`Error::of`]
+    /// tells what kind of failure an error is.
+    pub fn generate(&self) -> Result<Outcome> {
+        generate(&self.args)
+    }
+}
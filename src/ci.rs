@@ -0,0 +1,112 @@
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::models::HistoryEntry;
+
+/// CI, lint, and formatter configuration files read for contributor-facing documents.
+const CI_CONFIG_FILES: [&str; 18] = [
+    ".gitlab-ci.yml",
+    ".circleci/config.yml",
+    "azure-pipelines.yml",
+    ".travis.yml",
+    "Jenkinsfile",
+    ".pre-commit-config.yaml",
+    "rustfmt.toml",
+    ".rustfmt.toml",
+    "clippy.toml",
+    ".eslintrc.json",
+    ".eslintrc.js",
+    ".prettierrc",
+    "ruff.toml",
+    ".flake8",
+    ".golangci.yml",
+    "Makefile",
+    "justfile",
+    "tox.ini",
+];
+
+/// Directory holding GitHub Actions workflows, all of which are read.
+const GITHUB_WORKFLOWS_DIR: &str = ".github/workflows";
+
+/// Largest configuration file read, in bytes; bigger files are noted but not included.
+const MAX_CI_FILE_BYTES: u64 = 32 * 1024;
+
+/// Command prefixes that indicate building, testing, linting, or formatting.
+const WORKFLOW_COMMAND_PREFIXES: [&str; 24] = [
+    "cargo test",
+    "cargo clippy",
+    "cargo fmt",
+    "cargo build",
+    "cargo check",
+    "pytest",
+    "python -m pytest",
+    "ruff",
+    "black",
+    "flake8",
+    "mypy",
+    "tox",
+    "npm test",
+    "npm run",
+    "yarn",
+    "pnpm",
+    "npx eslint",
+    "npx prettier",
+    "go test",
+    "go vet",
+    "golangci-lint",
+    "make",
+    "just",
+    "pre-commit",
+];
+
+/// Reads CI and lint configuration files from the project, returning `{file_path, content}` objects.
+pub fn read_ci_config_files(root: &Path) -> Vec<serde_json::Value> {
+    ci_config_paths(root)
+        .into_iter()
+        .filter_map(|path| {
+            let full_path = root.join(&path);
+            let metadata = fs::metadata(&full_path).ok()?;
+            let content = if metadata.len() > MAX_CI_FILE_BYTES {
+                format!("(omitted: {} bytes)", metadata.len())
+            } else {
+                fs::read_to_string(&full_path).unwrap_or_default()
+            };
+            Some(json!({
+                "file_path": path,
+                "content": content
+            }))
+        })
+        .collect()
+}
+
+/// The CI configuration files in the project, relative to `root`: GitHub Actions workflows first, then the others.
+pub fn ci_config_paths(root: &Path) -> Vec<String> {
+    let mut paths: Vec<String> = CI_CONFIG_FILES.iter().map(|file| file.to_string()).collect();
+    if let Ok(entries) = fs::read_dir(root.join(GITHUB_WORKFLOWS_DIR)) {
+        let mut workflows: Vec<String> = entries
+            .flatten()
+            .map(|entry| format!("{}/{}", GITHUB_WORKFLOWS_DIR, entry.file_name().to_string_lossy()))
+            .filter(|path| path.ends_with(".yml") || path.ends_with(".yaml"))
+            .collect();
+        workflows.sort();
+        paths.splice(0..0, workflows);
+    }
+    paths.retain(|path| root.join(path).is_file());
+    paths
+}
+
+/// Picks the build, test, lint, and format commands out of the shell history, most recent first and deduplicated.
+pub fn workflow_commands(command_history: &[HistoryEntry]) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    for entry in command_history {
+        let command = entry.command.trim();
+        let is_workflow = WORKFLOW_COMMAND_PREFIXES
+            .iter()
+            .any(|prefix| command == *prefix || command.starts_with(&format!("{} ", prefix)));
+        if is_workflow && !commands.iter().any(|seen| seen == command) {
+            commands.push(command.to_string());
+        }
+    }
+    commands
+}
@@ -0,0 +1,669 @@
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::api_key::ApiKey;
+use crate::doc_types::{DocType, ScriptShell, TaskRunner};
+use crate::endpoints::parse_base_url;
+use crate::errors::Error;
+use crate::external::{find_external_subcommand, ExternalSubcommand};
+use crate::formats::OutputFormat;
+use crate::front_matter::FrontMatter;
+use crate::history::{HistoryShell, HistoryTimeZone};
+use crate::injection::InjectionScan;
+use crate::remote::is_git_url;
+use crate::safety::CommandSafety;
+use crate::secrets::SecretScan;
+use crate::settings::ConfigFile;
+use crate::sinks::{OutputSink, SinkKind};
+use crate::sources::ContextSource;
+use crate::style::{Audience, Tone};
+
+/// The project a quickstart is generated for.
+#[derive(Clone)]
+pub enum Target {
+    /// A directory on disk.
+    Local(PathBuf),
+    /// A git URL that is shallow-cloned into a temporary directory.
+    Remote(String),
+}
+
+/// `--version` output: the version followed by what the binary was built from (recorded by build.rs).
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ",
+    env!("BUILD_COMMIT"),
+    "\ntarget: ",
+    env!("BUILD_TARGET"),
+    "\nprofile: ",
+    env!("BUILD_PROFILE"),
+    "\nrustc: ",
+    env!("BUILD_RUSTC"),
+    "\nfeatures: ",
+    env!("BUILD_FEATURES"),
+);
+
+/// Generates a project quickstart guide from your shell history, repository files, and env keys.
+///
+/// Configuration is read from PROJECT_DIR/.env or the current directory's .env. Every setting can also be given
+/// as a flag, which takes precedence over the environment.
+#[derive(Parser)]
+#[command(name = "magic_quickstart", version, long_version = LONG_VERSION, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Check whether a newer release is available and print how to upgrade, then exit.
+    #[arg(long, exclusive = true)]
+    check_update: bool,
+    /// Options for `generate`, which runs when no subcommand is given.
+    #[command(flatten)]
+    generate: Args,
+}
+
+/// What the invocation should do.
+// Parsed once per run, so the size of the generate arguments doesn't matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+pub enum Command {
+    /// Gather context and generate a document (the default when no subcommand is given).
+    Generate(Args),
+    /// Render a Markdown file to HTML and serve it locally, reloading the page whenever the file changes.
+    Preview {
+        /// File to preview (default: the newest README_GENERATED_*.md, else README.md).
+        file: Option<PathBuf>,
+        /// Port for the preview server.
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+    },
+    /// Show or change settings. Without an action, shows the effective value of every setting and where it comes from.
+    #[command(args_conflicts_with_subcommands = true)]
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+    /// Check the configuration, shell history, project, API key, and network for common setup problems.
+    Doctor {
+        #[command(flatten)]
+        project: ProjectArgs,
+        /// Skip the checks that go over the network.
+        #[arg(long)]
+        offline: bool,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+    /// Print the shell history entries that would be sent as context.
+    History {
+        #[command(flatten)]
+        project: ProjectArgs,
+        /// Hours of shell history to show, overriding HOURS_OF_SHELL_HISTORY.
+        #[arg(long, value_name = "N")]
+        hours: Option<i64>,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+    /// List every kind of data this tool would collect here and on this machine, and where it comes from, without
+    /// collecting any of it, for privacy reviews.
+    Inventory {
+        #[command(flatten)]
+        project: ProjectArgs,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+    /// Gather context as generating would and print the request payloads after redaction, without sending or writing
+    /// anything.
+    Redact {
+        /// Instead of the payloads, print what each redaction and secret rule finds, to tune them before a first run.
+        #[arg(long)]
+        report: bool,
+        #[command(flatten)]
+        args: Args,
+    },
+    /// List the keys in the project's env files and whether the code requires, optionally reads, or never reads them.
+    Usage(ProjectArgs),
+    /// Print an artifact written with ENCRYPT_ARTIFACTS, decrypted with the key from the system keyring.
+    Decrypt {
+        /// The encrypted file, e.g. .magic_quickstart/request.json.enc.
+        file: PathBuf,
+    },
+    /// Check the intermediate artifacts (request.json and the rest) against the schema this version writes, so
+    /// tooling that reads them can tell when their format changed.
+    ValidateArtifacts {
+        /// Artifact to check; repeat for several (default: every artifact in the project's .magic_quickstart).
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+    /// Delete saved runs, kept artifacts in the project, and audit log entries older than RETENTION_DAYS or
+    /// --older-than.
+    Purge {
+        /// Age in days beyond which data is deleted, overriding RETENTION_DAYS; 0 deletes everything.
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+        /// List what would be deleted without deleting it.
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+    /// Look back at saved runs: every generation is kept with its context manifest, model, cost, and output.
+    Runs {
+        #[command(subcommand)]
+        action: RunsAction,
+    },
+    /// `--check-update`.
+    #[command(skip)]
+    CheckUpdate,
+}
+
+/// Reading saved runs, which are identified by the local time they were saved, e.g. `20250301-141502`.
+#[derive(Subcommand)]
+pub enum RunsAction {
+    /// List saved runs, oldest first, with their project, documents, model, and cost.
+    List,
+    /// Print a saved run's details, context manifest, and the files it wrote.
+    Show { id: String },
+    /// Compare two saved runs: what changed in the model, cost, and context, and a diff of each output file.
+    Diff { a: String, b: String },
+    /// Write the files of a saved run back, e.g. to undo a regeneration that made the README worse.
+    Restore {
+        id: String,
+        /// Overwrite changed files without asking.
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Reading and changing settings, for scripts that adjust them without templating TOML.
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the effective value of a setting, or its value in one configuration file.
+    Get {
+        /// Setting name, e.g. OPENAI_MODEL or openai_model.
+        key: String,
+        #[command(flatten)]
+        file: ConfigFileArgs,
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+    /// Set a setting in the project's .magic_quickstart.toml, or with --user in the user config.toml.
+    Set {
+        /// Setting name, e.g. OPENAI_MODEL or openai_model.
+        key: String,
+        value: String,
+        #[command(flatten)]
+        file: ConfigFileArgs,
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+    /// Print every setting with its effective value and source, or only what one configuration file sets.
+    List {
+        #[command(flatten)]
+        file: ConfigFileArgs,
+        #[command(flatten)]
+        project: ProjectArgs,
+    },
+    /// Print the merged configuration as TOML, with where each value and list entry comes from.
+    Effective(ProjectArgs),
+}
+
+/// Which configuration file a `config` action works on. With `--profile`, that is the file's profile table.
+#[derive(Default, clap::Args)]
+pub struct ConfigFileArgs {
+    /// Use the user's ~/.config/magic_quickstart/config.toml.
+    #[arg(long, conflicts_with = "project_file")]
+    user: bool,
+    /// Use the project's .magic_quickstart.toml.
+    #[arg(long = "project")]
+    project_file: bool,
+}
+
+impl ConfigFileArgs {
+    /// The selected file, or `None` for the effective settings.
+    pub fn file(&self) -> Option<ConfigFile> {
+        if self.user {
+            Some(ConfigFile::User)
+        } else if self.project_file {
+            Some(ConfigFile::Project)
+        } else {
+            None
+        }
+    }
+}
+
+/// Which shell history files to read, for commands that read them.
+#[derive(Clone, Default, clap::Args)]
+pub struct HistoryArgs {
+    /// History file to read instead of the shell's own, e.g. one copied from another machine. Repeat to merge
+    /// several files. Overrides HISTORY_FILES.
+    #[arg(long = "history", value_name = "PATH")]
+    pub files: Vec<PathBuf>,
+    /// Format of the history files (zsh, bash, fish, atuin, or auto to detect each file's), overriding
+    /// HISTORY_SHELL. Without --history, that shell's own history file is read.
+    #[arg(long, value_name = "SHELL", value_parser = parse_with(HistoryShell::parse, "shell"))]
+    pub shell: Option<HistoryShell>,
+    /// Time zone history timestamps are shown in (local, or an IANA name such as Europe/Berlin), overriding
+    /// TIMEZONE. Defaults to the local time zone, which honors TZ.
+    #[arg(long, value_name = "ZONE", value_parser = parse_with(HistoryTimeZone::parse, "time zone"))]
+    pub timezone: Option<HistoryTimeZone>,
+}
+
+/// The project directory for subcommands that only inspect a local project.
+#[derive(clap::Args)]
+pub struct ProjectArgs {
+    /// Project directory (defaults to the current directory).
+    #[arg(value_name = "PROJECT_DIR", value_parser = parse_project_dir)]
+    project_dir: Option<PathBuf>,
+    /// Named profile from the configuration files, overriding MAGIC_QUICKSTART_PROFILE.
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+}
+
+impl ProjectArgs {
+    /// Returns the project directory, defaulting to the current directory.
+    pub fn dir(&self) -> Result<PathBuf> {
+        match &self.project_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => current_dir(),
+        }
+    }
+}
+
+/// Command line arguments for generating documents. Flags mirror the environment variables they override.
+#[derive(Clone, Default, clap::Args)]
+pub struct Args {
+    /// Project to generate the quickstart for (defaults to the current directory).
+    #[arg(value_name = "PROJECT_DIR | GIT_URL", value_parser = parse_target)]
+    target: Option<Target>,
+    /// List the context about to be sent and let the user toggle items before the request is built.
+    #[arg(long)]
+    pub review_context: bool,
+    /// Keep intermediate artifacts in .magic_quickstart/ after the run (and write them even without DEBUG_REQUEST).
+    #[arg(long, conflicts_with_all = ["no_artifacts", "in_memory"])]
+    pub keep_artifacts: bool,
+    /// Never write intermediate artifacts, even with DEBUG_REQUEST=true.
+    #[arg(long)]
+    pub no_artifacts: bool,
+    /// Write nothing but the generated files: no intermediate artifacts, saved run, remembered consent, retention
+    /// purge, or audit log (unless AUDIT_LOG is set), so runs can share a machine without sharing files.
+    #[arg(long)]
+    pub in_memory: bool,
+    /// Merge between the magic-quickstart markers in README.md (or CONTRIBUTING.md) instead of writing a new file,
+    /// overriding MERGE_INTO_README.
+    #[arg(long)]
+    pub merge: bool,
+    /// Gather context and print the request payloads with estimated tokens and cost, without calling the API or
+    /// writing any documents.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Gather context and build the requests as for a run, then check them against the quickstart.lock in the output
+    /// directory instead of sending them. Exits with status 6 if a document's inputs changed since it was generated.
+    #[arg(long, conflicts_with_all = ["dry_run", "watch", "from_request", "section", "edit"])]
+    pub verify_manifest: bool,
+    /// Send a request.json kept from an earlier run as it is, instead of gathering context; with --model, send it to
+    /// that model. Pass the --doc-type and --format it was built for, so the response is written the same way.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["review_context", "watch", "env_example", "batch_items", "with_setup_script", "section"]
+    )]
+    pub from_request: Option<PathBuf>,
+    /// Print what the redaction and secret rules find instead of the payloads; set by `redact --report`.
+    #[arg(skip)]
+    pub redaction_report: bool,
+    /// Send the request without showing a summary and asking for confirmation, and overwrite existing output
+    /// files without showing a diff and asking.
+    #[arg(short, long)]
+    pub yes: bool,
+    /// Print a JSON record of the run (output paths, model, token usage, cost, and what context was sent) to
+    /// stdout; progress messages go to stderr.
+    #[arg(long)]
+    pub json: bool,
+    /// Keep running and regenerate whenever a project file or the shell history changes.
+    #[arg(long)]
+    pub watch: bool,
+    /// Run unattended in a pipeline: never prompt (implies --yes), print only errors and the --json record,
+    /// without colors, and refuse to send without a cost cap (--max-cost or MAX_COST).
+    #[arg(long, conflicts_with_all = ["review_context", "edit", "watch"])]
+    pub ci: bool,
+    /// Output format (markdown, asciidoc, rst, man, txt, or org), overriding OUTPUT_FORMAT.
+    #[arg(long, value_parser = parse_with(OutputFormat::parse, "format"))]
+    pub format: Option<OutputFormat>,
+    /// Kind of document to generate (quickstart, contributing, architecture, onboarding, setup-script, tasks,
+    /// devcontainer, or ci), overriding DOC_TYPE.
+    #[arg(long, value_name = "TYPE", value_parser = parse_with(DocType::parse, "doc type"))]
+    pub doc_type: Option<DocType>,
+    /// Shell the setup script is written for (sh or powershell), overriding SETUP_SCRIPT_SHELL.
+    #[arg(long, value_name = "SHELL", value_parser = parse_with(ScriptShell::parse, "script shell"))]
+    pub script_shell: Option<ScriptShell>,
+    /// Also generate a setup script next to the document.
+    #[arg(long)]
+    pub with_setup_script: bool,
+    /// Task runner the tasks file is written for (just or make), overriding TASK_RUNNER.
+    #[arg(long, value_name = "RUNNER", value_parser = parse_with(TaskRunner::parse, "task runner"))]
+    pub task_runner: Option<TaskRunner>,
+    /// Write a .env.example with placeholders and usage comments for the env keys.
+    #[arg(long)]
+    pub env_example: bool,
+    /// Ask for a Dockerfile alongside devcontainer.json, overriding DEVCONTAINER_DOCKERFILE.
+    #[arg(long)]
+    pub dockerfile: bool,
+    /// Natural language to write the document in (e.g. ja or German), overriding OUTPUT_LANGUAGE.
+    #[arg(long, value_name = "LANG")]
+    pub language: Option<String>,
+    /// Reader the document is written for (beginner or expert), overriding AUDIENCE.
+    #[arg(long, value_name = "WHO", value_parser = parse_with(Audience::parse, "audience"))]
+    pub audience: Option<Audience>,
+    /// Tone of the document (terse or friendly), overriding TONE.
+    #[arg(long, value_parser = parse_with(Tone::parse, "tone"))]
+    pub tone: Option<Tone>,
+    /// Directory of example documents to match in style, overriding EXEMPLARS_DIR.
+    #[arg(long, value_name = "DIR")]
+    pub exemplars: Option<PathBuf>,
+    /// Documents to generate in one run from the same context, e.g. quickstart,contributing,env-example.
+    #[arg(long = "batch", value_name = "TYPES", value_delimiter = ',', value_parser = parse_batch_item)]
+    batch_items: Vec<BatchItem>,
+    /// Documents to generate in one run, resolved from --batch.
+    #[arg(skip)]
+    pub batch: Option<Vec<DocType>>,
+    /// Skip the table of contents in Markdown output, overriding INSERT_TOC.
+    #[arg(long)]
+    pub no_toc: bool,
+    /// Append a provenance comment to generated files, overriding PROVENANCE_FOOTER.
+    #[arg(long)]
+    pub provenance: bool,
+    /// Copy the generated document to the clipboard instead of writing it, like `--sink clipboard`.
+    #[arg(long, conflicts_with = "sink")]
+    pub copy: bool,
+    /// Where generated files go (file, stdout, clipboard, http, or git), overriding OUTPUT_SINK.
+    #[arg(long, value_parser = parse_with(SinkKind::parse, "output sink"))]
+    pub sink: Option<SinkKind>,
+    /// URL the http sink posts each file to, overriding OUTPUT_SINK_URL.
+    #[arg(long, value_name = "URL", value_parser = parse_with(parse_base_url, "URL"))]
+    pub sink_url: Option<String>,
+    /// Open the generated document in $EDITOR before it is written.
+    #[arg(long)]
+    pub edit: bool,
+    /// Static site front matter to prepend to Markdown output (jekyll, hugo, or docusaurus), overriding FRONT_MATTER.
+    #[arg(long, value_name = "SSG", value_parser = parse_with(FrontMatter::parse, "front matter"))]
+    pub front_matter: Option<FrontMatter>,
+    /// Write each section of the document to its own file under docs/<doc-type>/ with an index.
+    #[arg(long)]
+    pub split: bool,
+    /// Regenerate only this section (e.g. troubleshooting) of the marker-managed document.
+    #[arg(long, value_name = "NAME")]
+    pub section: Option<String>,
+    /// Named profile from the configuration files, overriding MAGIC_QUICKSTART_PROFILE.
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Model to use, overriding OPENAI_MODEL.
+    #[arg(long)]
+    pub model: Option<String>,
+    /// Seed the model samples with, for more repeatable answers, overriding SEED.
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u32>,
+    /// OpenAI-compatible API to send requests to, such as an AI gateway, overriding OPENAI_BASE_URL.
+    #[arg(long, value_name = "URL", value_parser = parse_with(parse_base_url, "base URL"))]
+    pub base_url: Option<String>,
+    /// Hours of shell history to include, overriding HOURS_OF_SHELL_HISTORY.
+    #[arg(long, value_name = "N")]
+    pub hours: Option<i64>,
+    #[command(flatten)]
+    pub history: HistoryArgs,
+    /// Maximum number of project files to include, overriding MAX_FILE_COUNT_FOR_CONTEXT.
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+    /// Estimated token cap for the context, overriding TOKEN_BUDGET.
+    #[arg(long, value_name = "TOKENS")]
+    pub token_budget: Option<usize>,
+    /// Largest request, serialized, that is sent, overriding MAX_PAYLOAD_BYTES; 0 turns the cap off.
+    #[arg(long, value_name = "BYTES")]
+    pub max_payload_bytes: Option<usize>,
+    /// Refuse to send if the estimated cost of the requests exceeds this many US dollars, overriding MAX_COST.
+    #[arg(long, value_name = "USD", value_parser = parse_with(parse_cost, "cost"))]
+    pub max_cost: Option<f64>,
+    /// Shares of the token budget per category (e.g. history:20,files:55,manifests:15,docs:10), overriding BUDGET_SPLIT.
+    #[arg(long, value_name = "SPLIT")]
+    pub budget_split: Option<String>,
+    /// Redact matches of this regular expression from the request, on top of REDACT_PATTERNS (repeatable).
+    #[arg(long, value_name = "REGEX")]
+    pub redact: Vec<String>,
+    /// Never send project files matching this glob, on top of IGNORE_GLOBS (repeatable).
+    #[arg(long, value_name = "GLOB")]
+    pub ignore: Vec<String>,
+    /// Drop shell history entries that run this command, on top of COMMAND_BLOCKLIST (repeatable).
+    #[arg(long, value_name = "COMMAND")]
+    pub block_command: Vec<String>,
+    /// Only send env keys matching this glob (e.g. APP_*), on top of ENV_KEY_ALLOWLIST (repeatable).
+    #[arg(long, value_name = "GLOB")]
+    pub allow_env_key: Vec<String>,
+    /// Never send env keys matching this glob (e.g. *SECRET*), on top of ENV_KEY_DENYLIST (repeatable).
+    #[arg(long, value_name = "GLOB")]
+    pub deny_env_key: Vec<String>,
+    /// Send no env keys at all, overriding INCLUDE_ENV_FILE_KEYS.
+    #[arg(long, conflicts_with = "include_env_file_keys")]
+    pub no_env_keys: bool,
+    /// What to do about likely secrets in file contents (redact, abort, or off), overriding SECRET_SCAN.
+    #[arg(long, value_name = "MODE", value_parser = parse_with(SecretScan::parse, "secret scan mode"))]
+    pub secret_scan: Option<SecretScan>,
+    /// What to do about instruction-like text in file contents, such as "ignore previous instructions" (strip, flag,
+    /// or off), overriding INJECTION_SCAN.
+    #[arg(long, value_name = "MODE", value_parser = parse_with(InjectionScan::parse, "injection scan mode"))]
+    pub injection_scan: Option<InjectionScan>,
+    /// What to do about risky commands such as `curl | sh` in the generated document (annotate, warn, or off),
+    /// overriding COMMAND_SAFETY.
+    #[arg(long, value_name = "MODE", value_parser = parse_with(CommandSafety::parse, "command safety mode"))]
+    pub command_safety: Option<CommandSafety>,
+    /// Mask emails, IP addresses, internal hostnames, and ticket IDs in history and file contents, overriding SCRUB_PII.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub scrub_pii: Option<bool>,
+    /// Replace internal hostnames, bucket names, and usernames with stable placeholders, overriding PSEUDONYMIZE.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub pseudonymize: Option<bool>,
+    /// Treat hostnames under this domain (e.g. corp.example.com) as internal, on top of INTERNAL_DOMAINS (repeatable).
+    #[arg(long, value_name = "DOMAIN")]
+    pub internal_domain: Vec<String>,
+    /// Load this WebAssembly plugin, on top of WASM_PLUGINS (repeatable).
+    #[arg(long = "plugin", value_name = "PATH")]
+    pub plugins: Vec<PathBuf>,
+    /// Allow sending requests to this host (e.g. gateway.example.com or *.example.com), on top of ALLOWED_HOSTS
+    /// (repeatable).
+    #[arg(long, value_name = "HOST")]
+    pub allow_host: Vec<String>,
+    /// Seconds each phase of an API request may take (e.g. response:300,total:900), overriding REQUEST_TIMEOUTS.
+    #[arg(long, value_name = "TIMEOUTS")]
+    pub request_timeouts: Option<String>,
+    /// Remember which kinds of data you agreed to send from this project and only ask again for new ones, overriding
+    /// REMEMBER_CONSENT.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub remember_consent: Option<bool>,
+    /// Encrypt saved artifacts and runs with a key from the system keyring, overriding ENCRYPT_ARTIFACTS.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub encrypt_artifacts: Option<bool>,
+    /// Append a record of every API call to this file, overriding AUDIT_LOG.
+    #[arg(long, value_name = "PATH")]
+    pub audit_log: Option<PathBuf>,
+    /// Delete saved runs, kept artifacts, and audit log entries older than this many days, overriding RETENTION_DAYS.
+    #[arg(long, value_name = "DAYS")]
+    pub retention_days: Option<u64>,
+    /// Read the OpenAI API key from the first line of standard input instead of OPENAI_API_KEY.
+    #[arg(long, conflicts_with_all = ["api_key_fd", "watch"])]
+    pub api_key_stdin: bool,
+    /// Read the OpenAI API key from this open file descriptor instead of OPENAI_API_KEY (Unix only).
+    #[arg(long, value_name = "FD", conflicts_with = "watch")]
+    pub api_key_fd: Option<u32>,
+    /// Per-extension content transforms (e.g. rs:strip-comments;*:drop-license-header), overriding CONTENT_TRANSFORMS.
+    #[arg(long, value_name = "SPEC")]
+    pub content_transforms: Option<String>,
+    /// Tera template the document is rendered with, overriding OUTPUT_TEMPLATE.
+    #[arg(long, value_name = "PATH")]
+    pub output_template: Option<PathBuf>,
+    /// Tera template for the prompt messages, overriding PROMPT_TEMPLATE.
+    #[arg(long, value_name = "PATH")]
+    pub prompt_template: Option<PathBuf>,
+    /// Rhai script with `pre_send` and `post_generate` hooks, overriding HOOK_SCRIPT.
+    #[arg(long, value_name = "PATH")]
+    pub hook_script: Option<PathBuf>,
+    /// Send the request to OpenAI, overriding ENABLE_OPENAI.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub enable_openai: Option<bool>,
+    /// Write intermediate artifacts to .magic_quickstart/, overriding DEBUG_REQUEST.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub debug_request: Option<bool>,
+    /// Write the quickstart from manifests, scripts, and history with templates, never sending anything, overriding LOCAL_ONLY.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub local_only: Option<bool>,
+    /// Include recent shell history, overriding INCLUDE_SHELL_HISTORY.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub include_shell_history: Option<bool>,
+    /// Include project files, overriding INCLUDE_REPOSITORY_FILES.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub include_repository_files: Option<bool>,
+    /// Include keys (never values) from the project's env files, overriding INCLUDE_ENV_FILE_KEYS.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub include_env_file_keys: Option<bool>,
+    /// Follow symlinks during file discovery, overriding FOLLOW_SYMLINKS.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub follow_symlinks: Option<bool>,
+    /// API key to send with instead of OPENAI_API_KEY, set through the library; there is no flag, so keys stay out
+    /// of shell history.
+    #[arg(skip)]
+    pub api_key: Option<ApiKey>,
+    /// Directory to write documents to instead of the project's, set through the library.
+    #[arg(skip)]
+    pub output_dir: Option<PathBuf>,
+    /// Context sources added by an embedding tool, collected after the built-in ones.
+    #[arg(skip)]
+    pub context_sources: Vec<Arc<dyn ContextSource>>,
+    /// Where generated files go, set through the library in place of OUTPUT_SINK.
+    #[arg(skip)]
+    pub output_sink: Option<Arc<dyn OutputSink>>,
+    /// The external subcommand the gathered context is handed to instead of generating, for `magic_quickstart NAME`.
+    #[arg(skip)]
+    pub external: Option<ExternalSubcommand>,
+}
+
+impl Args {
+    /// Sets the project to generate for, as the positional argument does, for the library.
+    pub fn set_target(&mut self, target: Target) {
+        self.target = Some(target);
+    }
+
+    /// The arguments `redact` generates with: a dry run that shows what the rules did to the payloads, or with
+    /// `report`, what they found.
+    pub fn redacting(mut self, report: bool) -> Args {
+        self.dry_run = true;
+        self.redaction_report = report;
+        self
+    }
+
+    /// Returns the project to generate for, defaulting to the current directory.
+    pub fn target(&self) -> Result<Target> {
+        match &self.target {
+            Some(target) => Ok(target.clone()),
+            None => current_dir().map(Target::Local),
+        }
+    }
+}
+
+/// An entry of `--batch`: a document type, or `env-example` for the locally rendered `.env.example`.
+#[derive(Clone, PartialEq)]
+enum BatchItem {
+    Document(DocType),
+    EnvExample,
+}
+
+/// Parses the command line, printing usage and exiting on `--help` or invalid arguments.
+/// Without a subcommand, the arguments are those of `generate`.
+pub fn parse_command() -> Command {
+    // Like cargo, an unknown subcommand runs `magic-quickstart-NAME` from PATH, unless a project by that name is here.
+    let name = env::args_os().nth(1).and_then(|name| name.into_string().ok()).unwrap_or_default();
+    if name != "help" && Cli::command().find_subcommand(&name).is_none() && !is_git_url(&name) {
+        if let Some(external) = find_external_subcommand(env::args_os().skip(1)) {
+            return Command::Generate(Args {
+                external: Some(external),
+                ..Args::default()
+            });
+        }
+    }
+    let cli = Cli::parse();
+    if cli.check_update {
+        return Command::CheckUpdate;
+    }
+    let command = match cli.command.unwrap_or(Command::Generate(cli.generate)) {
+        Command::Redact { report, args } => Command::Generate(args.redacting(report)),
+        command => command,
+    };
+    match command {
+        Command::Generate(mut args) => {
+            // .env.example is rendered locally rather than by the model, so it is a flag rather than a document.
+            if !args.batch_items.is_empty() {
+                args.env_example |= args.batch_items.contains(&BatchItem::EnvExample);
+                let mut doc_types = Vec::new();
+                for item in &args.batch_items {
+                    if let BatchItem::Document(doc_type) = item {
+                        if !doc_types.contains(doc_type) {
+                            doc_types.push(*doc_type);
+                        }
+                    }
+                }
+                args.batch = Some(doc_types);
+            }
+            // CI runs never wait for an answer and always leave a record to parse.
+            if args.ci {
+                args.yes = true;
+                args.json = true;
+            }
+            Command::Generate(args)
+        }
+        command => command,
+    }
+}
+
+/// Parses an amount in US dollars, as accepted by `--max-cost` and MAX_COST.
+pub fn parse_cost(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .trim_start_matches('$')
+        .parse()
+        .ok()
+        .filter(|cost: &f64| cost.is_finite() && *cost >= 0.0)
+}
+
+/// Builds a clap value parser from one of the `parse` functions that return `None` for unknown names.
+fn parse_with<T>(parse: fn(&str) -> Option<T>, what: &'static str) -> impl Fn(&str) -> Result<T, String> + Clone {
+    move |value| parse(value).ok_or_else(|| format!("invalid {}: {}", what, value))
+}
+
+fn parse_batch_item(value: &str) -> Result<BatchItem, String> {
+    match value.trim() {
+        "env-example" => Ok(BatchItem::EnvExample),
+        name => DocType::parse(name)
+            .map(BatchItem::Document)
+            .ok_or_else(|| format!("invalid doc type in --batch: {}", name)),
+    }
+}
+
+/// Git URLs are cloned later; local paths must exist and are made absolute.
+fn parse_target(value: &str) -> Result<Target, String> {
+    if is_git_url(value) {
+        Ok(Target::Remote(value.to_string()))
+    } else {
+        parse_project_dir(value).map(Target::Local)
+    }
+}
+
+fn parse_project_dir(value: &str) -> Result<PathBuf, String> {
+    PathBuf::from(value)
+        .canonicalize()
+        .map_err(|_| format!("project directory {} does not exist", value))
+}
+
+/// The current directory; one that was removed or can't be read is a configuration error.
+fn current_dir() -> Result<PathBuf> {
+    env::current_dir().context(Error::Config("Failed to get current working directory".to_string()))
+}
@@ -0,0 +1,23 @@
+#[cfg(feature = "clipboard")]
+use arboard::Clipboard;
+
+use crate::console::status;
+
+/// Places `text` on the system clipboard, printing why if no clipboard is available (e.g. over SSH).
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> bool {
+    match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => true,
+        Err(error) => {
+            status!("Could not copy to the clipboard: {}", error);
+            false
+        }
+    }
+}
+
+/// Without the `clipboard` feature there is no clipboard to copy to, so this only says so.
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_text: &str) -> bool {
+    status!("Could not copy to the clipboard: this build was made without the `clipboard` feature");
+    false
+}
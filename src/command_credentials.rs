@@ -0,0 +1,95 @@
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::models::HistoryEntry;
+use crate::pseudonyms::replace_group;
+use crate::rules::REDACTED;
+
+/// A way credentials get typed on a command line. Each alternative of the pattern captures the credential in its
+/// own group, inside any quotes, so only the credential is cut out.
+struct CredentialRule {
+    name: &'static str,
+    pattern: &'static str,
+}
+
+const CREDENTIAL_RULES: &[CredentialRule] = &[
+    CredentialRule {
+        name: "MySQL password",
+        pattern: r#"\b(?:mysql|mysqldump|mysqladmin|mysqlimport|mysqlsh|mariadb|mariadb-dump)\b[^|;&\n]*?\s(?:-p|--password=)(?:'([^']*)'|"([^"]*)"|([^\s'"]\S*))"#,
+    },
+    CredentialRule {
+        name: "sshpass password",
+        pattern: r#"\bsshpass\s+(?:-[a-oq-zA-Z]\S*\s+)*-p\s*(?:'([^']*)'|"([^"]*)"|(\S+))"#,
+    },
+    CredentialRule {
+        name: "password in URL",
+        pattern: r"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^\s:/@]+:([^\s/@]+)@",
+    },
+    CredentialRule {
+        name: "secret in an environment variable",
+        pattern: r#"(?i)(?:^|[\s;&|(])(?:export\s+)?(?:\w*(?:secret|password|passwd|token|api_?key|access_key|private_key)\w*|mysql_pwd|sshpass)=(?:'([^']*)'|"([^"]*)"|([^\s'"]\S*))"#,
+    },
+    CredentialRule {
+        name: "password or token option",
+        pattern: r#"(?i)\s--(?:password|passwd|token|api-key|secret|client-secret)(?:=|\s+)(?:'([^']*)'|"([^"]*)"|([^\s'"-]\S*))"#,
+    },
+    CredentialRule {
+        name: "curl credentials",
+        pattern: r#"\bcurl\b[^|;&\n]*?\s(?:-u|--user)\s*(?:'[^':]*:([^']*)'|"[^":]*:([^"]*)"|[^\s'":]+:(\S+))"#,
+    },
+    CredentialRule {
+        name: "authorization header",
+        pattern: r#"(?i)\bauthorization:\s*(?:bearer|basic|token)\s+([^\s'"]+)"#,
+    },
+];
+
+fn compiled_rules() -> &'static [(Regex, &'static str)] {
+    static COMPILED: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        CREDENTIAL_RULES
+            .iter()
+            .map(|rule| (Regex::new(rule.pattern).expect("credential patterns are valid regexes"), rule.name))
+            .collect()
+    })
+}
+
+/// Replaces credentials typed on a command line with `[REDACTED]`, keeping the rest of the command, e.g.
+/// `mysql -u root -p'[REDACTED]' app` or `AWS_SECRET_ACCESS_KEY=[REDACTED] aws s3 ls`. References such as
+/// `$DB_PASSWORD` are kept, since they aren't the credential itself. Returns the command and the rules that found
+/// something, once per credential.
+pub fn strip_command_credentials(command: &str) -> (String, Vec<&'static str>) {
+    let mut found = Vec::new();
+    let mut command = command.to_string();
+    for (regex, name) in compiled_rules() {
+        command = regex
+            .replace_all(&command, |captures: &Captures| {
+                replace_group(captures, |credential| {
+                    if credential.is_empty() || credential.starts_with('$') || credential == REDACTED {
+                        credential.to_string()
+                    } else {
+                        found.push(*name);
+                        REDACTED.to_string()
+                    }
+                })
+            })
+            .into_owned();
+    }
+    (command, found)
+}
+
+/// Strips credentials from the command of every history entry. Returns how many each rule found.
+pub fn strip_history_credentials(history: &mut [HistoryEntry]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for entry in history {
+        let (command, found) = strip_command_credentials(&entry.command);
+        if found.is_empty() {
+            continue;
+        }
+        for name in found {
+            *counts.entry(name).or_default() += 1;
+        }
+        entry.command = command;
+    }
+    counts
+}
@@ -0,0 +1,129 @@
+use anyhow::{bail, Result};
+use chrono::{Duration, Utc};
+use std::path::Path;
+
+use crate::cli::ConfigAction;
+use crate::command_credentials::strip_command_credentials;
+use crate::env_usage::classify_env_keys;
+use crate::errors::Error;
+use crate::history::{read_history, HistorySource, HistoryTimeZone};
+use crate::parsers::{collect_env_file_keys, ENV_FILES};
+use crate::pii::{PiiCounts, PiiScrubber};
+use crate::policy::Policy;
+use crate::pseudonyms::Pseudonymizer;
+use crate::rules::ContextRules;
+use crate::settings::{
+    file_settings, find_setting, mask, parse_setting, print_effective_settings, print_file_settings, print_settings, set_setting,
+    ConfigFile, ConfigFiles,
+};
+
+/// Runs a `config` action. Without `--user` or `--project`, `get` and `list` show the effective settings, as
+/// generating would read them.
+pub fn config(action: ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Get { key, file, project } => {
+            let project_dir = project.dir()?;
+            let setting = find_setting(&key)?;
+            let value = match file.file() {
+                Some(file) => {
+                    let path = file.path(&project_dir)?;
+                    let settings = file_settings(&path, project.profile.as_deref())?;
+                    match settings.into_iter().find(|(found, _)| found.name == setting.name) {
+                        Some((_, value)) => value,
+                        None => bail!(Error::Config(format!("{} is not set in {}", setting.name, path.display()))),
+                    }
+                }
+                None => {
+                    let files = ConfigFiles::load(&project_dir, project.profile.as_deref())?;
+                    files.get(setting.name).unwrap_or_else(|| setting.default.to_string())
+                }
+            };
+            println!("{}", if setting.secret { mask(&value) } else { value });
+        }
+        ConfigAction::Set { key, value, file, project } => {
+            let setting = find_setting(&key)?;
+            let file = file.file().unwrap_or(ConfigFile::Project);
+            let path = set_setting(file, &project.dir()?, project.profile.as_deref(), setting, &value)?;
+            println!("Set {} in {}", setting.name, path.display());
+        }
+        ConfigAction::List { file, project } => {
+            let project_dir = project.dir()?;
+            match file.file() {
+                Some(file) => print_file_settings(&file_settings(&file.path(&project_dir)?, project.profile.as_deref())?),
+                None => {
+                    print_settings(&ConfigFiles::load(&project_dir, project.profile.as_deref())?);
+                }
+            }
+        }
+        ConfigAction::Effective(project) => {
+            let project_dir = project.dir()?;
+            print_effective_settings(&ConfigFiles::load(&project_dir, project.profile.as_deref())?);
+            Policy::load()?.print();
+        }
+    }
+    Ok(())
+}
+
+/// Prints the shell history entries from the last `hours` (default: HOURS_OF_SHELL_HISTORY), newest first, as they
+/// would be sent: without blocked commands, and with redaction patterns, PSEUDONYMIZE, and SCRUB_PII applied.
+pub fn print_history(files: &ConfigFiles, sources: &[HistorySource], time_zone: HistoryTimeZone, hours: Option<i64>) -> Result<()> {
+    let hours = match hours {
+        Some(hours) => hours,
+        None => parse_setting(files, "HOURS_OF_SHELL_HISTORY", "a whole number", |hours| hours.parse().ok())?.unwrap_or(5),
+    };
+    let (sources, missing): (Vec<HistorySource>, Vec<HistorySource>) = sources.iter().cloned().partition(|source| source.path.is_file());
+    for source in &missing {
+        println!("No shell history at {}", source.path.display());
+    }
+    if sources.is_empty() {
+        return Ok(());
+    }
+    let rules = ContextRules::load(files, &Policy::load()?)?;
+    let cutoff_time = Utc::now() - Duration::hours(hours);
+    let mut history = read_history(&sources, cutoff_time.timestamp(), time_zone)?;
+    history.retain(|entry| !rules.blocks(&entry.command));
+    let domains: Vec<String> = files
+        .list_entries("INTERNAL_DOMAINS")
+        .into_iter()
+        .map(|(domain, _)| domain)
+        .collect();
+    let mut pseudonymizer = if files.get("PSEUDONYMIZE").unwrap_or_default().to_lowercase() == "true" {
+        Some(Pseudonymizer::new(&domains)?)
+    } else {
+        None
+    };
+    let scrubber = (files.get("SCRUB_PII").unwrap_or_default().to_lowercase() == "true").then(|| PiiScrubber::new(&domains));
+    for entry in &history {
+        let (command, _) = strip_command_credentials(&entry.command);
+        let mut command = rules.redact_text(&command).0;
+        if let Some(pseudonymizer) = &mut pseudonymizer {
+            command = pseudonymizer.pseudonymize(&command);
+        }
+        if let Some(scrubber) = &scrubber {
+            command = scrubber.scrub(&command, &mut PiiCounts::default());
+        }
+        println!("{}  [{:?}]  {}", entry.timestamp, entry.exit_code, command);
+    }
+    let paths: Vec<String> = sources.iter().map(|source| source.path.display().to_string()).collect();
+    println!("{} entries from the last {} hours in {}", history.len(), hours, paths.join(", "));
+    Ok(())
+}
+
+/// Prints each key from the project's env files with how the code reads it and where.
+pub fn print_env_usage(project_dir: &Path, files: &ConfigFiles) {
+    let follow_symlinks = files.get("FOLLOW_SYMLINKS").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+    let keys = classify_env_keys(project_dir, &collect_env_file_keys(project_dir, &ENV_FILES), follow_symlinks);
+    if keys.is_empty() {
+        println!("No keys found in {}", ENV_FILES.join(", "));
+        return;
+    }
+    for entry in &keys {
+        println!(
+            "{:30} {:8} {:14} {}",
+            entry.key,
+            entry.usage.name(),
+            entry.file,
+            entry.referenced_in.join(", ")
+        );
+    }
+}
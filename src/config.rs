@@ -0,0 +1,406 @@
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+use crate::api_key::ApiKey;
+use crate::budget::BudgetSplit;
+use crate::cli::{parse_cost, Args};
+use crate::doc_types::{DocType, ScriptShell, TaskRunner};
+use crate::endpoints::{parse_base_url, HostAllowlist, BASE_URL_EXPECTED, DEFAULT_BASE_URL};
+use crate::errors::Error;
+use crate::formats::OutputFormat;
+use crate::front_matter::FrontMatter;
+use crate::hooks::HookScript;
+use crate::injection::{InjectionScan, INJECTION_SCAN_EXPECTED};
+use crate::payload_size::DEFAULT_MAX_PAYLOAD_BYTES;
+use crate::plugins::Plugins;
+use crate::policy::Policy;
+use crate::retention::retention_days;
+use crate::rules::ContextRules;
+use crate::safety::{CommandSafety, COMMAND_SAFETY_EXPECTED};
+use crate::secrets::{SecretScan, SECRET_SCAN_EXPECTED};
+use crate::sections::SectionPresets;
+use crate::settings::{parse_setting, ConfigFiles};
+use crate::sinks::{SinkKind, OUTPUT_SINK_EXPECTED};
+use crate::streaming::{RequestTimeouts, REQUEST_TIMEOUTS_EXPECTED};
+use crate::style::{Audience, Tone};
+use crate::transforms::{parse_transform_config, TransformConfig};
+
+/// Holds configuration values loaded from environment variables and configuration files.
+pub struct Config {
+    pub openai_api_key: Option<ApiKey>,
+    pub max_file_context: usize,
+    pub time_back_hours: i64,
+    pub openai_model: String,
+    pub seed: Option<u32>,
+    pub openai_base_url: String,
+    pub allowed_hosts: HostAllowlist,
+    pub request_timeouts: RequestTimeouts,
+    pub enable_openai: bool,
+    pub debug_request: bool,
+    pub include_shell_history: bool,
+    pub include_repository_files: bool,
+    pub include_env_file_keys: bool,
+    pub follow_symlinks: bool,
+    pub content_transforms: TransformConfig,
+    pub rules: ContextRules,
+    pub secret_scan: SecretScan,
+    pub injection_scan: InjectionScan,
+    pub command_safety: CommandSafety,
+    pub scrub_pii: bool,
+    pub pseudonymize: bool,
+    pub remember_consent: bool,
+    pub encrypt_artifacts: bool,
+    pub internal_domains: Vec<String>,
+    pub plugins: Plugins,
+    pub audit_log: Option<PathBuf>,
+    pub retention_days: Option<u64>,
+    pub local_only: bool,
+    pub token_budget: Option<usize>,
+    pub max_cost: Option<f64>,
+    pub max_payload_bytes: usize,
+    pub budget_split: Option<BudgetSplit>,
+    pub merge_into_readme: bool,
+    pub output_sink: SinkKind,
+    pub output_sink_url: Option<String>,
+    pub output_template: Option<PathBuf>,
+    pub output_format: OutputFormat,
+    pub doc_type: DocType,
+    pub script_shell: ScriptShell,
+    pub task_runner: TaskRunner,
+    pub devcontainer_dockerfile: bool,
+    pub output_language: Option<String>,
+    pub audience: Option<Audience>,
+    pub tone: Option<Tone>,
+    pub prompt_template: Option<PathBuf>,
+    pub hook_script: Option<HookScript>,
+    pub exemplars_dir: Option<PathBuf>,
+    pub insert_toc: bool,
+    pub provenance_footer: bool,
+    pub section_presets: Option<SectionPresets>,
+    pub front_matter: Option<FrontMatter>,
+    pub regenerate_section: Option<String>,
+}
+
+impl Config {
+    /// Loads the configuration from environment variables, falling back to the configuration files.
+    /// Fails with a configuration error naming the setting and where it was set if a value is invalid.
+    pub fn load(files: &ConfigFiles, policy: &Policy) -> Result<Self> {
+        let openai_api_key = files.get("OPENAI_API_KEY").and_then(ApiKey::new);
+        let max_file_context =
+            parse_setting(files, "MAX_FILE_COUNT_FOR_CONTEXT", "a whole number", |count| count.parse().ok())?.unwrap_or(5);
+        let time_back_hours = parse_setting(files, "HOURS_OF_SHELL_HISTORY", "a whole number", |hours| hours.parse().ok())?.unwrap_or(5);
+        let openai_model = files.get("OPENAI_MODEL").unwrap_or_else(|| "gpt-4o".to_string());
+        let seed = parse_setting(files, "SEED", "a whole number up to 4294967295", |seed| seed.parse().ok())?;
+        let openai_base_url =
+            parse_setting(files, "OPENAI_BASE_URL", BASE_URL_EXPECTED, parse_base_url)?.unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        let allowed_hosts = HostAllowlist::load(files)?;
+        let request_timeouts =
+            parse_setting(files, "REQUEST_TIMEOUTS", REQUEST_TIMEOUTS_EXPECTED, RequestTimeouts::parse)?.unwrap_or_default();
+        let enable_openai = files.get("ENABLE_OPENAI").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let debug_request = files.get("DEBUG_REQUEST").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let include_shell_history = files
+            .get("INCLUDE_SHELL_HISTORY")
+            .unwrap_or_else(|| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let include_repository_files = files
+            .get("INCLUDE_REPOSITORY_FILES")
+            .unwrap_or_else(|| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let include_env_file_keys = files
+            .get("INCLUDE_ENV_FILE_KEYS")
+            .unwrap_or_else(|| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let follow_symlinks = files.get("FOLLOW_SYMLINKS").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let content_transforms = parse_transform_config(&files.get("CONTENT_TRANSFORMS").unwrap_or_default());
+        let rules = ContextRules::load(files, policy)?;
+        let secret_scan = parse_setting(files, "SECRET_SCAN", SECRET_SCAN_EXPECTED, SecretScan::parse)?.unwrap_or(SecretScan::Redact);
+        let injection_scan =
+            parse_setting(files, "INJECTION_SCAN", INJECTION_SCAN_EXPECTED, InjectionScan::parse)?.unwrap_or(InjectionScan::Strip);
+        let command_safety =
+            parse_setting(files, "COMMAND_SAFETY", COMMAND_SAFETY_EXPECTED, CommandSafety::parse)?.unwrap_or(CommandSafety::Annotate);
+        let scrub_pii = files.get("SCRUB_PII").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let pseudonymize = files.get("PSEUDONYMIZE").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let remember_consent = files.get("REMEMBER_CONSENT").unwrap_or_else(|| "true".to_string()).to_lowercase() == "true";
+        let encrypt_artifacts = files.get("ENCRYPT_ARTIFACTS").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let internal_domains = files
+            .list_entries("INTERNAL_DOMAINS")
+            .into_iter()
+            .map(|(domain, _)| domain)
+            .collect();
+        let mut plugins = Plugins::default();
+        for (path, _) in files.list_entries("WASM_PLUGINS") {
+            plugins.add(Path::new(&path))?;
+        }
+        let audit_log = files.get("AUDIT_LOG").filter(|path| !path.trim().is_empty()).map(PathBuf::from);
+        let retention_days = retention_days(files)?;
+        let local_only = files.get("LOCAL_ONLY").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let token_budget = parse_setting(files, "TOKEN_BUDGET", "a whole number", |budget| budget.parse().ok())?;
+        let max_cost = parse_setting(files, "MAX_COST", "an amount in US dollars", parse_cost)?;
+        let max_payload_bytes =
+            parse_setting(files, "MAX_PAYLOAD_BYTES", "a whole number", |bytes| bytes.parse().ok())?.unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES);
+        let budget_split = parse_setting(files, "BUDGET_SPLIT", BUDGET_SPLIT_EXPECTED, BudgetSplit::parse)?;
+        let merge_into_readme = files.get("MERGE_INTO_README").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let output_sink = parse_setting(files, "OUTPUT_SINK", OUTPUT_SINK_EXPECTED, SinkKind::parse)?.unwrap_or(SinkKind::File);
+        let output_sink_url = parse_setting(files, "OUTPUT_SINK_URL", "an http:// or https:// URL", parse_base_url)?;
+        let output_template = files.get("OUTPUT_TEMPLATE").map(PathBuf::from);
+        let output_format = parse_setting(
+            files,
+            "OUTPUT_FORMAT",
+            "markdown, asciidoc, rst, man, txt, or org",
+            OutputFormat::parse,
+        )?
+        .unwrap_or(OutputFormat::Markdown);
+        let doc_type = parse_setting(
+            files,
+            "DOC_TYPE",
+            "quickstart, contributing, architecture, onboarding, setup-script, tasks, devcontainer, or ci",
+            DocType::parse,
+        )?
+        .unwrap_or(DocType::Quickstart);
+        let script_shell = parse_setting(files, "SETUP_SCRIPT_SHELL", "sh or powershell", ScriptShell::parse)?.unwrap_or(ScriptShell::Sh);
+        let task_runner = parse_setting(files, "TASK_RUNNER", "just or make", TaskRunner::parse)?.unwrap_or(TaskRunner::Just);
+        let output_language = files.get("OUTPUT_LANGUAGE").filter(|language| !language.trim().is_empty());
+        let audience = parse_setting(files, "AUDIENCE", "beginner or expert", Audience::parse)?;
+        let tone = parse_setting(files, "TONE", "terse or friendly", Tone::parse)?;
+        let prompt_template = files.get("PROMPT_TEMPLATE").map(PathBuf::from);
+        let hook_script = files
+            .get("HOOK_SCRIPT")
+            .map(|path| HookScript::load(Path::new(&path)))
+            .transpose()?;
+        let exemplars_dir = files.get("EXEMPLARS_DIR").map(PathBuf::from);
+        let provenance_footer = files.get("PROVENANCE_FOOTER").unwrap_or_else(|| "false".to_string()).to_lowercase() == "true";
+        let front_matter = parse_setting(files, "FRONT_MATTER", "jekyll, hugo, or docusaurus", FrontMatter::parse)?;
+        let insert_toc = files.get("INSERT_TOC").unwrap_or_else(|| "true".to_string()).to_lowercase() == "true";
+        let devcontainer_dockerfile = files
+            .get("DEVCONTAINER_DOCKERFILE")
+            .unwrap_or_else(|| "false".to_string())
+            .to_lowercase()
+            == "true";
+
+        Ok(Config {
+            openai_api_key,
+            max_file_context,
+            time_back_hours,
+            openai_model,
+            seed,
+            openai_base_url,
+            allowed_hosts,
+            request_timeouts,
+            enable_openai,
+            debug_request,
+            include_shell_history,
+            include_repository_files,
+            include_env_file_keys,
+            follow_symlinks,
+            content_transforms,
+            rules,
+            secret_scan,
+            injection_scan,
+            command_safety,
+            scrub_pii,
+            pseudonymize,
+            remember_consent,
+            encrypt_artifacts,
+            internal_domains,
+            plugins,
+            audit_log,
+            retention_days,
+            local_only,
+            token_budget,
+            max_cost,
+            max_payload_bytes,
+            budget_split,
+            merge_into_readme,
+            output_sink,
+            output_sink_url,
+            output_template,
+            output_format,
+            doc_type,
+            script_shell,
+            task_runner,
+            devcontainer_dockerfile,
+            output_language,
+            audience,
+            tone,
+            prompt_template,
+            hook_script,
+            exemplars_dir,
+            insert_toc,
+            provenance_footer,
+            section_presets: None,
+            front_matter,
+            regenerate_section: None,
+        })
+    }
+
+    /// Applies the command line flags, which take precedence over the environment.
+    pub fn apply_flags(&mut self, args: &Args) -> Result<()> {
+        if let Some(model) = &args.model {
+            self.openai_model = model.clone();
+        }
+        if let Some(seed) = args.seed {
+            self.seed = Some(seed);
+        }
+        if let Some(base_url) = &args.base_url {
+            self.openai_base_url = base_url.clone();
+        }
+        for host in &args.allow_host {
+            self.allowed_hosts.allow(host, "--allow-host")?;
+        }
+        if let Some(timeouts) = &args.request_timeouts {
+            let Some(timeouts) = RequestTimeouts::parse(timeouts) else {
+                bail!(Error::Config(format!(
+                    "Invalid --request-timeouts \"{}\" (expected {})",
+                    timeouts, REQUEST_TIMEOUTS_EXPECTED
+                )));
+            };
+            self.request_timeouts = timeouts;
+        }
+        if let Some(hours) = args.hours {
+            self.time_back_hours = hours;
+        }
+        if let Some(max_files) = args.max_files {
+            self.max_file_context = max_files;
+        }
+        if let Some(enable_openai) = args.enable_openai {
+            self.enable_openai = enable_openai;
+        }
+        if let Some(debug_request) = args.debug_request {
+            self.debug_request = debug_request;
+        }
+        if let Some(include_shell_history) = args.include_shell_history {
+            self.include_shell_history = include_shell_history;
+        }
+        if let Some(include_repository_files) = args.include_repository_files {
+            self.include_repository_files = include_repository_files;
+        }
+        if let Some(include_env_file_keys) = args.include_env_file_keys {
+            self.include_env_file_keys = include_env_file_keys;
+        }
+        self.include_env_file_keys &= !args.no_env_keys;
+        if let Some(follow_symlinks) = args.follow_symlinks {
+            self.follow_symlinks = follow_symlinks;
+        }
+        if let Some(spec) = &args.content_transforms {
+            self.content_transforms = parse_transform_config(spec);
+        }
+        // Rules from flags extend the configured ones rather than replacing them.
+        for pattern in &args.redact {
+            self.rules.redact(pattern, "--redact")?;
+        }
+        for glob in &args.ignore {
+            self.rules.ignore(glob, "--ignore")?;
+        }
+        for command in &args.block_command {
+            self.rules.block_command(command);
+        }
+        for pattern in &args.allow_env_key {
+            self.rules.allow_env_key(pattern, "--allow-env-key")?;
+        }
+        for pattern in &args.deny_env_key {
+            self.rules.deny_env_key(pattern, "--deny-env-key")?;
+        }
+        if let Some(secret_scan) = args.secret_scan {
+            self.secret_scan = secret_scan;
+        }
+        if let Some(injection_scan) = args.injection_scan {
+            self.injection_scan = injection_scan;
+        }
+        if let Some(command_safety) = args.command_safety {
+            self.command_safety = command_safety;
+        }
+        if let Some(scrub_pii) = args.scrub_pii {
+            self.scrub_pii = scrub_pii;
+        }
+        if let Some(pseudonymize) = args.pseudonymize {
+            self.pseudonymize = pseudonymize;
+        }
+        if let Some(remember_consent) = args.remember_consent {
+            self.remember_consent = remember_consent;
+        }
+        if let Some(encrypt_artifacts) = args.encrypt_artifacts {
+            self.encrypt_artifacts = encrypt_artifacts;
+        }
+        self.internal_domains.extend(args.internal_domain.iter().cloned());
+        for path in &args.plugins {
+            self.plugins.add(path)?;
+        }
+        if let Some(path) = &args.audit_log {
+            self.audit_log = Some(path.clone());
+        }
+        if let Some(retention_days) = args.retention_days {
+            self.retention_days = Some(retention_days);
+        }
+        if let Some(local_only) = args.local_only {
+            self.local_only = local_only;
+        }
+        if let Some(token_budget) = args.token_budget {
+            self.token_budget = Some(token_budget);
+        }
+        if let Some(max_cost) = args.max_cost {
+            self.max_cost = Some(max_cost);
+        }
+        if let Some(max_payload_bytes) = args.max_payload_bytes {
+            self.max_payload_bytes = max_payload_bytes;
+        }
+        if let Some(split) = &args.budget_split {
+            let Some(split) = BudgetSplit::parse(split) else {
+                bail!(Error::Config(format!(
+                    "Invalid --budget-split \"{}\" (expected {})",
+                    split, BUDGET_SPLIT_EXPECTED
+                )));
+            };
+            self.budget_split = Some(split);
+        }
+        if let Some(sink) = args.sink {
+            self.output_sink = sink;
+        }
+        if args.copy {
+            self.output_sink = SinkKind::Clipboard;
+        }
+        if let Some(url) = &args.sink_url {
+            self.output_sink_url = Some(url.clone());
+        }
+        if let Some(path) = &args.output_template {
+            self.output_template = Some(path.clone());
+        }
+        if let Some(path) = &args.prompt_template {
+            self.prompt_template = Some(path.clone());
+        }
+        if let Some(path) = &args.hook_script {
+            self.hook_script = Some(HookScript::load(path)?);
+        }
+        if let Some(format) = args.format {
+            self.output_format = format;
+        }
+        if let Some(doc_type) = args.doc_type {
+            self.doc_type = doc_type;
+        }
+        if let Some(script_shell) = args.script_shell {
+            self.script_shell = script_shell;
+        }
+        if let Some(task_runner) = args.task_runner {
+            self.task_runner = task_runner;
+        }
+        if let Some(language) = &args.language {
+            self.output_language = Some(language.clone());
+        }
+        if let Some(dir) = &args.exemplars {
+            self.exemplars_dir = Some(dir.clone());
+        }
+        self.audience = args.audience.or(self.audience);
+        self.tone = args.tone.or(self.tone);
+        self.front_matter = args.front_matter.or(self.front_matter);
+        self.merge_into_readme |= args.merge;
+        self.devcontainer_dockerfile |= args.dockerfile;
+        self.provenance_footer |= args.provenance;
+        self.insert_toc &= !args.no_toc;
+        Ok(())
+    }
+}
+
+/// Describes a valid BUDGET_SPLIT in configuration errors.
+const BUDGET_SPLIT_EXPECTED: &str = "shares such as history:20,files:60,manifests:20 with at least one above zero";
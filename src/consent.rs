@@ -0,0 +1,79 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+use crate::models::ContextBundle;
+use crate::state::StateStore;
+
+/// The kinds of data a run sends, e.g. `shell history`, `project files (*.rs)`, or the label of a document type's
+/// extra context. Project files are told apart by extension (or name, for files without one), so a detector that
+/// starts picking up a new kind of file counts as a new kind of data.
+pub fn data_categories(context: &ContextBundle, extra_labels: &[String]) -> BTreeSet<String> {
+    let mut categories = BTreeSet::new();
+    if !context.command_history.is_empty() {
+        categories.insert("shell history".to_string());
+    }
+    for path in &context.project_files {
+        let kind = match path.extension() {
+            Some(extension) => format!("*.{}", extension.to_string_lossy()),
+            None => path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        };
+        categories.insert(format!("project files ({})", kind));
+    }
+    if !context.dependency_summary.is_empty() {
+        categories.insert("dependencies from lockfiles".to_string());
+    }
+    if !context.asset_inventory.is_empty() {
+        categories.insert("asset directories".to_string());
+    }
+    if !context.env_file_keys.is_empty() {
+        categories.insert("environment keys".to_string());
+    }
+    if !context.exemplars.is_empty() {
+        categories.insert("exemplars".to_string());
+    }
+    categories.extend(extra_labels.iter().cloned());
+    categories
+}
+
+/// The kinds of data the user has agreed to send from one project to one host (REMEMBER_CONSENT).
+pub struct Consent {
+    project: String,
+    host: String,
+    /// What was agreed to, or `None` if nothing has been yet. Agreeing to send to a different host doesn't count.
+    granted: Option<BTreeSet<String>>,
+}
+
+impl Consent {
+    /// Reads what was agreed to for `project` (a directory or git URL) and `host`. A state database that can't be
+    /// read counts as no consent, so the worst it does is ask again.
+    pub fn load(project: &str, host: &str) -> Result<Self> {
+        let decision = StateStore::open().and_then(|store| store.consent(project)).ok().flatten();
+        let granted = decision.and_then(|(agreed_host, categories)| (agreed_host == host).then_some(categories));
+        Ok(Consent {
+            project: project.to_string(),
+            host: host.to_string(),
+            granted,
+        })
+    }
+
+    /// Whether the user has agreed to send anything from this project to this host before.
+    pub fn is_recorded(&self) -> bool {
+        self.granted.is_some()
+    }
+
+    /// The categories that haven't been agreed to; all of them if nothing has been yet.
+    pub fn new_categories(&self, categories: &BTreeSet<String>) -> Vec<String> {
+        categories
+            .iter()
+            .filter(|category| !self.granted.as_ref().is_some_and(|granted| granted.contains(*category)))
+            .cloned()
+            .collect()
+    }
+
+    /// Records that the user agreed to send `categories`, on top of what they agreed to before.
+    pub fn record(&mut self, categories: &BTreeSet<String>) -> Result<()> {
+        let granted = self.granted.get_or_insert_with(BTreeSet::new);
+        granted.extend(categories.iter().cloned());
+        StateStore::open()?.record_consent(&self.project, &self.host, granted)
+    }
+}
@@ -0,0 +1,62 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--json`, which reserves stdout for the result record.
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--ci`, which drops progress messages and colors.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Sends progress messages, prompts, and diffs to stderr from now on, so stdout only carries the `--json` record or
+/// the files printed by the stdout sink.
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Whether stdout is reserved for the `--json` record or the stdout sink.
+pub fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+/// Drops progress messages, diffs, and colors from now on; errors and the `--json` record are still printed.
+pub fn quiet() {
+    QUIET.store(true, Ordering::Relaxed);
+}
+
+/// Whether progress messages are dropped.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Wraps `text` in an ANSI color `code`, unless colors are off because of `--ci` or NO_COLOR.
+pub fn paint(code: &str, text: &str) -> String {
+    if is_quiet() || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+}
+
+/// Where progress messages, prompts, and diffs are written: stdout, stderr with `--json`, or nowhere when quiet.
+pub fn console() -> Box<dyn Write> {
+    if is_quiet() {
+        Box::new(io::sink())
+    } else if stdout_reserved() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// Like `println!`, but prints to stderr when stdout is reserved for the `--json` record, and nothing when quiet.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::console::is_quiet() {
+        } else if $crate::console::stdout_reserved() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use status;
@@ -0,0 +1,149 @@
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Parser, Tag, TagEnd};
+
+/// Target of a local Markdown conversion.
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    Org,
+    Text,
+}
+
+/// Converts Markdown to Org mode: `*` headings, `-`/`1.` lists, `#+begin_src` blocks, and Org inline markup.
+pub fn markdown_to_org(markdown: &str) -> String {
+    convert(markdown, Target::Org)
+}
+
+/// Converts Markdown to plain text: underlined top-level headings, `-`/`1.` lists, and code indented by four spaces.
+pub fn markdown_to_text(markdown: &str) -> String {
+    convert(markdown, Target::Text)
+}
+
+fn convert(markdown: &str, target: Target) -> String {
+    let mut output = String::new();
+    let mut line = String::new();
+    // Each open list holds its next item number (`None` for bullet lists).
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut heading: Option<HeadingLevel> = None;
+    let mut code_block: Option<String> = None;
+    let mut link_urls: Vec<String> = Vec::new();
+    let mut starts_item = false;
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => heading = Some(level),
+            Event::End(TagEnd::Heading(_)) => {
+                let level = heading.take().map(|level| level as usize).unwrap_or(1);
+                let text = line.trim().to_string();
+                line.clear();
+                match target {
+                    Target::Org => output.push_str(&format!("{} {}\n\n", "*".repeat(level), text)),
+                    Target::Text if level <= 2 => {
+                        let underline = if level == 1 { "=" } else { "-" };
+                        output.push_str(&format!("{}\n{}\n\n", text, underline.repeat(text.chars().count())));
+                    }
+                    Target::Text => output.push_str(&format!("{}\n\n", text)),
+                }
+            }
+            Event::Start(Tag::List(start)) => {
+                flush_line(&mut output, &mut line, &lists, &mut starts_item);
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                flush_line(&mut output, &mut line, &lists, &mut starts_item);
+                lists.pop();
+                if lists.is_empty() {
+                    output.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                flush_line(&mut output, &mut line, &lists, &mut starts_item);
+                let depth = lists.len().saturating_sub(1);
+                let marker = match lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "-".to_string(),
+                };
+                line.push_str(&format!("{}{} ", "  ".repeat(depth), marker));
+                starts_item = true;
+            }
+            Event::End(TagEnd::Item) => flush_line(&mut output, &mut line, &lists, &mut starts_item),
+            Event::End(TagEnd::Paragraph) => {
+                flush_line(&mut output, &mut line, &lists, &mut starts_item);
+                if lists.is_empty() {
+                    output.push('\n');
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                flush_line(&mut output, &mut line, &lists, &mut starts_item);
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or_default().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                if target == Target::Org {
+                    output.push_str(&if language.is_empty() {
+                        "#+begin_example\n".to_string()
+                    } else {
+                        format!("#+begin_src {}\n", language)
+                    });
+                }
+                code_block = Some(language);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if target == Target::Org {
+                    let language = code_block.as_deref().unwrap_or_default();
+                    output.push_str(if language.is_empty() { "#+end_example\n" } else { "#+end_src\n" });
+                }
+                output.push('\n');
+                code_block = None;
+            }
+            Event::Text(text) if code_block.is_some() => {
+                let indent = if target == Target::Text { "    " } else { "" };
+                for code_line in text.lines() {
+                    output.push_str(&format!("{}{}\n", indent, code_line));
+                }
+            }
+            Event::Text(text) => line.push_str(&text),
+            Event::Code(code) => match target {
+                Target::Org => line.push_str(&format!("~{}~", code)),
+                Target::Text => line.push_str(&code),
+            },
+            Event::Start(Tag::Strong) | Event::End(TagEnd::Strong) if target == Target::Org => line.push('*'),
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis) if target == Target::Org => line.push('/'),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                if target == Target::Org {
+                    line.push_str(&format!("[[{}][", dest_url));
+                }
+                link_urls.push(dest_url.to_string());
+            }
+            Event::End(TagEnd::Link) => {
+                let url = link_urls.pop().unwrap_or_default();
+                match target {
+                    Target::Org => line.push_str("]]"),
+                    Target::Text => line.push_str(&format!(" ({})", url)),
+                }
+            }
+            Event::SoftBreak => line.push(' '),
+            Event::HardBreak => flush_line(&mut output, &mut line, &lists, &mut starts_item),
+            Event::Rule => output.push_str(if target == Target::Org { "-----\n\n" } else { "----\n\n" }),
+            _ => {}
+        }
+    }
+    flush_line(&mut output, &mut line, &lists, &mut starts_item);
+
+    format!("{}\n", output.trim_end())
+}
+
+/// Writes the pending line. Lines that don't start a list item are continuation text inside the open lists
+/// and are indented to line up with the item text.
+fn flush_line(output: &mut String, line: &mut String, lists: &[Option<u64>], starts_item: &mut bool) {
+    if !line.trim().is_empty() {
+        if !*starts_item {
+            output.push_str(&"  ".repeat(lists.len()));
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    line.clear();
+    *starts_item = false;
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::secrets::entropy;
+
+/// Extensions of files that hold keys, certificates with their keys, or password databases.
+const CREDENTIAL_EXTENSIONS: [&str; 11] = ["pem", "key", "p12", "pfx", "jks", "keystore", "kdbx", "kdb", "ppk", "gpg", "age"];
+
+/// Names of files that hold SSH keys or passwords, compared without case.
+const CREDENTIAL_FILE_NAMES: [&str; 11] = [
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "id_ecdsa_sk",
+    "id_ed25519_sk",
+    ".netrc",
+    "_netrc",
+    ".pgpass",
+    ".git-credentials",
+    ".htpasswd",
+];
+
+/// The first bytes of a KeePass database, which may have any name.
+const KEEPASS_SIGNATURE: [u8; 4] = [0x03, 0xd9, 0xa2, 0x9a];
+
+/// How much of a file is read to judge its content; credential stores give themselves away early.
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+/// Binary content at least this long is judged by its entropy; shorter binary files are left to the other checks.
+const MIN_BINARY_BYTES: usize = 512;
+
+/// Bits per byte above which binary content is taken to be encrypted or key material (compressed data comes close).
+const MIN_BINARY_ENTROPY: f64 = 7.0;
+
+/// A run of base64 characters at least this long is judged as a blob, e.g. an encoded key or keystore.
+const MIN_BLOB_CHARS: usize = 256;
+
+/// Bits per character above which a blob is taken to be random (base64 of random bytes is close to 6; prose and
+/// identifiers stay well below 5).
+const MIN_BLOB_ENTROPY: f64 = 5.0;
+
+/// Why the file at `path` looks like a credential store and must not be sent, whatever found it and whatever its
+/// extension: its name, a private key or KeePass header in its content, binary content with the entropy of
+/// encrypted data, or a long high-entropy blob. Files that can't be read are left to the reader to report.
+pub fn credential_store_reason(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    if extension.is_some_and(|extension| CREDENTIAL_EXTENSIONS.contains(&extension.as_str())) {
+        return Some("has the extension of a key or keystore file");
+    }
+    if CREDENTIAL_FILE_NAMES.contains(&name.as_str()) {
+        return Some("is named like a file of SSH keys or passwords");
+    }
+    let mut head = Vec::new();
+    File::open(path).ok()?.take(SNIFF_BYTES).read_to_end(&mut head).ok()?;
+    content_reason(&head)
+}
+
+/// Why `content` looks like a credential store, for content that was read already (such as CI files).
+pub fn content_reason(content: &[u8]) -> Option<&'static str> {
+    if content.starts_with(&KEEPASS_SIGNATURE) {
+        return Some("is a KeePass database");
+    }
+    let text = String::from_utf8_lossy(content);
+    if text.contains("-----BEGIN") && text.contains("PRIVATE KEY") {
+        return Some("contains a private key");
+    }
+    if content.contains(&0) {
+        let bytes: String = content.iter().map(|&byte| byte as char).collect();
+        return (content.len() >= MIN_BINARY_BYTES && entropy(&bytes) >= MIN_BINARY_ENTROPY)
+            .then_some("is binary data with the entropy of encrypted content");
+    }
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '_' | '-')))
+        .any(|run| run.len() >= MIN_BLOB_CHARS && entropy(run) >= MIN_BLOB_ENTROPY)
+        .then_some("contains a long high-entropy blob, such as an encoded key")
+}
@@ -0,0 +1,206 @@
+/// Kind of document to generate from the gathered context.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DocType {
+    /// A README quickstart (the default).
+    Quickstart,
+    /// A CONTRIBUTING guide focused on CI, lint and test commands, and git conventions.
+    Contributing,
+    /// An ARCHITECTURE document with Mermaid diagrams inferred from the module structure.
+    Architecture,
+    /// A step-by-step day-one onboarding runbook following the shell history actually used.
+    Onboarding,
+    /// An executable bootstrap script reconstructing the minimal setup sequence.
+    SetupScript,
+    /// A justfile or Makefile with named recipes distilled from repeated command sequences.
+    Tasks,
+    /// A `.devcontainer/devcontainer.json` (and optionally a Dockerfile) matching the detected toolchain.
+    Devcontainer,
+    /// A GitHub Actions workflow building, testing, and linting the project.
+    CiWorkflow,
+}
+
+/// Shell the setup script is written for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptShell {
+    Sh,
+    PowerShell,
+}
+
+impl ScriptShell {
+    /// Parses a shell name as accepted by `--script-shell` and `SETUP_SCRIPT_SHELL`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "sh" | "bash" => Some(ScriptShell::Sh),
+            "powershell" | "pwsh" | "ps1" => Some(ScriptShell::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// Name of the generated script file.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ScriptShell::Sh => "setup.sh",
+            ScriptShell::PowerShell => "setup.ps1",
+        }
+    }
+
+    /// Language name used in the prompt.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ScriptShell::Sh => "bash",
+            ScriptShell::PowerShell => "PowerShell",
+        }
+    }
+}
+
+/// Task runner the tasks file is written for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskRunner {
+    Just,
+    Make,
+}
+
+impl TaskRunner {
+    /// Parses a task runner name as accepted by `--task-runner` and `TASK_RUNNER`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "just" | "justfile" => Some(TaskRunner::Just),
+            "make" | "makefile" => Some(TaskRunner::Make),
+            _ => None,
+        }
+    }
+
+    /// Name of the generated tasks file.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TaskRunner::Just => "justfile",
+            TaskRunner::Make => "Makefile",
+        }
+    }
+
+    /// File kind used in the prompt.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            TaskRunner::Just => "justfile",
+            TaskRunner::Make => "GNU Makefile",
+        }
+    }
+}
+
+impl DocType {
+    /// Parses a document type as accepted by `--doc-type` and `DOC_TYPE`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "quickstart" | "readme" => Some(DocType::Quickstart),
+            "contributing" => Some(DocType::Contributing),
+            "architecture" => Some(DocType::Architecture),
+            "onboarding" => Some(DocType::Onboarding),
+            "setup-script" | "setup" => Some(DocType::SetupScript),
+            "tasks" | "justfile" | "makefile" => Some(DocType::Tasks),
+            "devcontainer" => Some(DocType::Devcontainer),
+            "ci" | "ci-workflow" => Some(DocType::CiWorkflow),
+            _ => None,
+        }
+    }
+
+    /// Canonical name of the document type, as accepted by `--doc-type`.
+    pub fn name(self) -> &'static str {
+        match self {
+            DocType::Quickstart => "quickstart",
+            DocType::Contributing => "contributing",
+            DocType::Architecture => "architecture",
+            DocType::Onboarding => "onboarding",
+            DocType::SetupScript => "setup-script",
+            DocType::Tasks => "tasks",
+            DocType::Devcontainer => "devcontainer",
+            DocType::CiWorkflow => "ci",
+        }
+    }
+
+    /// File name stem for the generated document, e.g. `README` or `CONTRIBUTING`.
+    pub fn file_stem(self) -> &'static str {
+        match self {
+            DocType::Quickstart => "README",
+            DocType::Contributing => "CONTRIBUTING",
+            DocType::Architecture => "ARCHITECTURE",
+            DocType::Onboarding => "ONBOARDING",
+            DocType::SetupScript => "setup",
+            DocType::Tasks => "tasks",
+            DocType::Devcontainer => "devcontainer",
+            DocType::CiWorkflow => "ci",
+        }
+    }
+
+    /// Whether CI configuration, lint and test commands, and git history are gathered as extra context.
+    pub fn wants_contributor_context(self) -> bool {
+        matches!(self, DocType::Contributing | DocType::Tasks | DocType::CiWorkflow)
+    }
+
+    /// Whether the module structure and import graph are gathered as extra context.
+    pub fn wants_structure_context(self) -> bool {
+        self == DocType::Architecture
+    }
+
+    /// Whether the output is a project file (script, task runner, container or CI config) written to a fixed path
+    /// rather than a text document.
+    pub fn writes_project_file(self) -> bool {
+        matches!(
+            self,
+            DocType::SetupScript | DocType::Tasks | DocType::Devcontainer | DocType::CiWorkflow
+        )
+    }
+
+    /// Whether the chronological setup sequence, toolchain versions, and clone URL are gathered as extra context.
+    pub fn wants_onboarding_context(self) -> bool {
+        matches!(
+            self,
+            DocType::Onboarding | DocType::SetupScript | DocType::Devcontainer | DocType::CiWorkflow
+        )
+    }
+
+    /// Default BUDGET_SPLIT, weighting the context that matters most for this document.
+    pub fn default_budget_split(self) -> &'static str {
+        match self {
+            DocType::Quickstart => "history:20,files:55,manifests:15,docs:10",
+            DocType::Contributing => "history:30,files:25,manifests:25,docs:20",
+            DocType::Architecture => "history:5,files:65,manifests:15,docs:15",
+            DocType::Onboarding => "history:50,files:20,manifests:20,docs:10",
+            DocType::SetupScript => "history:45,files:15,manifests:35,docs:5",
+            DocType::Tasks => "history:55,files:15,manifests:25,docs:5",
+            DocType::Devcontainer => "history:20,files:20,manifests:55,docs:5",
+            DocType::CiWorkflow => "history:35,files:20,manifests:40,docs:5",
+        }
+    }
+
+    /// Opening request sent as the first user message.
+    pub fn user_request(self) -> &'static str {
+        match self {
+            DocType::Quickstart => {
+                "Generate a quickstart guide for my project based on the following data. Note that some commands may be irrelevant."
+            }
+            DocType::Contributing => {
+                "Generate a contributing guide for my project based on the following data. Note that some commands may be irrelevant."
+            }
+            DocType::Architecture => "Generate an architecture document for my project based on the following data.",
+            DocType::Onboarding => "Generate a day-one onboarding runbook for my project based on the following data. The shell history shows the setup sequence I actually used; some commands may be irrelevant.",
+            DocType::SetupScript => "Generate a bootstrap setup script for my project based on the following data. The shell history shows the setup sequence I actually used; some commands may be irrelevant.",
+            DocType::Tasks => "Generate a task runner file for my project based on the following data. The shell history and CI commands show the workflows I repeat; some commands may be irrelevant.",
+            DocType::Devcontainer => "Generate a development container configuration for my project based on the following data.",
+            DocType::CiWorkflow => "Generate a CI workflow for my project based on the following data. The shell history shows the build, test, and lint commands I run; some commands may be irrelevant.",
+        }
+    }
+
+    /// System prompt describing the document, for output written in `format_name` (the script language for scripts).
+    pub fn system_prompt(self, format_name: &str) -> String {
+        match self {
+            DocType::Quickstart => format!("You are a helpful assistant specialized in creating concise project quickstart guides. Use the provided context to generate a {format_name} README that lists only the essential commands to get started. Ensure the guide is strictly relevant to the detected project type (for example, if it is a Rust project, do not include Node.js instructions, and vice versa). Output only {format_name} content without any extra explanation, preamble, or code fences.  If you see any descriptions of the project in the TOML files, be sure to include this under the project name.  If any output is generated, be sure to call this out in the generated readme.  Dependencies can be called out, but don't merely include what's in the toml file as this is redundant.  In the configuration section, list environment keys marked required first, mention optional keys separately, and leave out unused keys."),
+            DocType::Contributing => format!("You are a helpful assistant specialized in writing CONTRIBUTING guides for open source projects. Use the provided context to generate a {format_name} CONTRIBUTING document covering: setting up a development environment, building, running tests, linters and formatters with the exact commands used in CI configuration and shell history, branch naming and commit message conventions inferred from the git history, and how to open a pull request. Ensure the guide is strictly relevant to the detected project type. Only describe conventions the context supports; do not invent policies. Output only {format_name} content without any extra explanation, preamble, or code fences."),
+            DocType::Architecture => format!("You are a helpful assistant specialized in documenting software architecture. Use the provided context, especially the module structure and the dependencies between modules, to generate a {format_name} ARCHITECTURE document with: a short overview of what the system does, a component diagram as a Mermaid `flowchart` grouping modules into components and showing their dependencies, a description of each component's responsibility, at least one Mermaid `sequenceDiagram` showing the main runtime flow from the entry point, and notes on external services, data stores, and configuration. Base diagrams only on relationships present in the context. Put each diagram in a fenced code block with the `mermaid` language tag; apart from diagrams and short code references, output only {format_name} content without any extra explanation or preamble."),
+            DocType::Onboarding => format!("You are a helpful assistant specialized in onboarding new engineers. Use the provided context to generate a {format_name} ONBOARDING runbook with numbered steps a new team member follows on day one: clone the repository, install prerequisites with specific versions (prefer versions pinned by the project, then the installed versions provided), set up environment variables and configuration, achieve a first successful run, and run the tests for the first time. Follow the order of the chronological setup sequence from the shell history closely, since it reflects what actually worked; skip commands that were unrelated, failed, or were later undone. Give every step the exact command and what success looks like. Ensure the runbook is strictly relevant to the detected project type. Output only {format_name} content without any extra explanation, preamble, or code fences."),
+            DocType::SetupScript => format!("You are a helpful assistant specialized in developer environment automation. Use the provided context to write a single {format_name} script that reconstructs the minimal setup sequence for this project: check that prerequisites are installed (printing the expected versions), install dependencies, prepare configuration files (for example by copying an example env file if one exists, never writing secret values), build, and run the tests once. Follow the order of the setup sequence from the shell history and the manifests; leave out commands that were unrelated, exploratory, or failed. Make the script stop on the first error, make steps safe to re-run, and precede every step with a short comment explaining it. Any command that needs elevated privileges, installs system-wide packages, deletes files, or changes global configuration must be commented out with a `SAFETY:` comment explaining why it needs review. Output only the script, starting with the shebang line if the language uses one, without any extra explanation, preamble, or code fences."),
+            DocType::Tasks => format!("You are a helpful assistant specialized in build automation. Use the provided context to write a {format_name} that turns the command sequences repeated in the shell history and CI configuration into named recipes, such as `build`, `test`, `lint`, `fmt`, `run`, and `deploy`, only for workflows the context actually shows. Make the first recipe a default that lists or runs the most common task, let recipes depend on each other instead of repeating commands, and add a one-line comment above every recipe describing it. Never hard-code secret values; read them from the environment instead. Recipes that deploy, publish, or delete files must be clearly commented as such. In a Makefile, indent recipe lines with tabs and declare every recipe as `.PHONY`. Output only the file content without any extra explanation, preamble, or code fences."),
+            DocType::Devcontainer => format!("You are a helpful assistant specialized in reproducible development environments. Use the provided context, especially the toolchain versions (prefer versions pinned by the project, then the installed versions provided) and the dependency manifests, to write a Dev Container configuration that lets a new contributor open the project and build it without any local setup. Respond with a {format_name} object with two string fields: `devcontainer_json`, the complete contents of `.devcontainer/devcontainer.json` (with a `name`, an `image` or `build` section, Dev Container Features pinned to the project's toolchain versions, editor extensions for the detected languages, forwarded ports the project listens on, and a `postCreateCommand` that installs dependencies), and `dockerfile`, the contents of `.devcontainer/Dockerfile` or an empty string if none is needed. Never include secret values; list required environment variables under `remoteEnv` with `${{localEnv:NAME}}` references instead."),
+            DocType::CiWorkflow => format!("You are a helpful assistant specialized in continuous integration. Use the provided context to write a GitHub Actions workflow in {format_name} for `.github/workflows/ci.yml` that runs on pushes and pull requests to the default branch and builds the project, runs its tests, and runs its linters and format checks. Use the exact commands from the existing CI configuration and the shell history where they exist, otherwise the standard commands for the detected ecosystem; only add steps for tools the project actually uses. Set up the toolchain with the official setup actions at the versions pinned by the project, cache dependencies, and give every step a `name`. Do not add deployment or publishing jobs, and never inline secret values; reference `secrets.NAME` where a step needs one. Output only the workflow without any extra explanation, preamble, or code fences."),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use chrono::{Duration, Utc};
+use reqwest::blocking::Client;
+use std::path::Path;
+
+use crate::api_key::ApiKey;
+use crate::console::paint;
+use crate::doc_types::DocType;
+use crate::endpoints::{parse_base_url, url_host, HostAllowlist, DEFAULT_BASE_URL};
+use crate::formats::OutputFormat;
+use crate::history::{format_problem, read_history, HistorySource, HistoryTimeZone};
+use crate::markdown::detect_fence_languages;
+use crate::parsers::find_project_files;
+use crate::policy::Policy;
+use crate::settings::{value_problem, ConfigFiles, SETTINGS};
+use crate::update::{latest_release, CURRENT_VERSION};
+
+/// How long the network check waits for the API.
+const NETWORK_TIMEOUT_SECS: u64 = 10;
+
+/// Settings that point at a file or directory.
+const PATH_SETTINGS: [&str; 4] = ["OUTPUT_TEMPLATE", "PROMPT_TEMPLATE", "HOOK_SCRIPT", "EXEMPLARS_DIR"];
+
+/// Outcome of a single check.
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Checks the configuration, shell history, project, API key, network, and whether a newer release is out, and
+/// prints a fix for each problem. `offline` skips the checks that go over the network. Returns whether no check failed.
+pub fn run_doctor(project_dir: &Path, files: &ConfigFiles, history: &[HistorySource], offline: bool) -> bool {
+    let mut checks: Vec<(Status, String)> = Vec::new();
+    check_configuration(files, &mut checks);
+    check_shell_history(files, history, &mut checks);
+    check_project(project_dir, files, &mut checks);
+    // An invalid ALLOWED_HOSTS is reported with the other settings, and then nothing is contacted.
+    let allowed_hosts = HostAllowlist::load(files);
+    match &allowed_hosts {
+        Ok(allowed_hosts) => {
+            check_api(files, allowed_hosts, offline, &mut checks);
+            if !offline {
+                check_update(allowed_hosts, &mut checks);
+            }
+        }
+        Err(_) => check_api(files, &HostAllowlist::default(), true, &mut checks),
+    }
+
+    let mut healthy = true;
+    for (status, message) in &checks {
+        let label = match status {
+            Status::Ok => paint("32", "  ok"),
+            Status::Warn => paint("33", "warn"),
+            Status::Fail => {
+                healthy = false;
+                paint("31", "fail")
+            }
+        };
+        println!("[{}] {}", label, message);
+    }
+    healthy
+}
+
+/// Checks that every setting has a valid value and that the settings don't contradict each other.
+fn check_configuration(files: &ConfigFiles, checks: &mut Vec<(Status, String)>) {
+    checks.push(if let Some(path) = files.dotenv_path() {
+        (Status::Ok, format!(".env file found: {}", path.display()))
+    } else {
+        (
+            Status::Ok,
+            "No .env file; using environment variables and configuration files".to_string(),
+        )
+    });
+
+    let mut problems = Vec::new();
+    for setting in SETTINGS {
+        if let Some((value, source)) = files.lookup(setting.name) {
+            if let Some(hint) = value_problem(setting.name, &value) {
+                problems.push((Status::Fail, format!("{} is \"{}\" in {}; {}", setting.name, value, source, hint)));
+            }
+        }
+    }
+    for name in PATH_SETTINGS {
+        if let Some((value, source)) = files.lookup(name) {
+            if !Path::new(&value).exists() {
+                problems.push((
+                    Status::Fail,
+                    format!("{} points at {}, which doesn't exist (set in {})", name, value, source),
+                ));
+            }
+        }
+    }
+
+    // Combinations the generate command refuses or silently ignores.
+    let is_true = |name: &str| files.get(name).is_some_and(|value| value.to_lowercase() == "true");
+    let format = files.get("OUTPUT_FORMAT").and_then(|format| OutputFormat::parse(&format));
+    let doc_type = files.get("DOC_TYPE").and_then(|doc_type| DocType::parse(&doc_type));
+    let merge = is_true("MERGE_INTO_README");
+    let front_matter = files.get("FRONT_MATTER").is_some();
+    let not_markdown = format.is_some_and(|format| format != OutputFormat::Markdown);
+    let conflicts = [
+        (
+            merge && not_markdown,
+            "MERGE_INTO_README only works with Markdown output; unset it or OUTPUT_FORMAT",
+        ),
+        (
+            merge && doc_type.is_some_and(|doc_type| doc_type.writes_project_file()),
+            "MERGE_INTO_README has no effect for doc types that write project files (scripts, tasks, CI)",
+        ),
+        (
+            front_matter && not_markdown,
+            "FRONT_MATTER is only added to Markdown output; unset it or OUTPUT_FORMAT",
+        ),
+        (
+            front_matter && merge,
+            "FRONT_MATTER is not added when merging into an existing README; unset one of them",
+        ),
+        (
+            files.get("BUDGET_SPLIT").is_some() && files.get("TOKEN_BUDGET").is_none(),
+            "BUDGET_SPLIT has no effect without TOKEN_BUDGET",
+        ),
+        (
+            !is_true("INCLUDE_SHELL_HISTORY") && !is_true("INCLUDE_REPOSITORY_FILES") && !is_true("INCLUDE_ENV_FILE_KEYS"),
+            "No context source is enabled; set INCLUDE_SHELL_HISTORY, INCLUDE_REPOSITORY_FILES, or INCLUDE_ENV_FILE_KEYS",
+        ),
+    ];
+    for (conflicting, message) in conflicts {
+        if conflicting {
+            problems.push((Status::Warn, message.to_string()));
+        }
+    }
+
+    if problems.is_empty() {
+        checks.push((Status::Ok, "Settings are valid and consistent".to_string()));
+    }
+    checks.extend(problems);
+
+    match Policy::load() {
+        Ok(policy) if policy.sources.is_empty() => {}
+        Ok(policy) => {
+            let sources: Vec<String> = policy.sources.iter().map(|path| path.display().to_string()).collect();
+            checks.push((Status::Ok, format!("Policy applied from {}", sources.join(" and "))));
+        }
+        Err(error) => checks.push((
+            Status::Fail,
+            format!("{:#}; fix the policy file or ask whoever installed it", error),
+        )),
+    }
+}
+
+/// Checks that the history file exists, is in zsh's extended format, and has entries in the configured window.
+fn check_shell_history(files: &ConfigFiles, sources: &[HistorySource], checks: &mut Vec<(Status, String)>) {
+    // Problems only fail the check when history is actually used.
+    let enabled = files
+        .get("INCLUDE_SHELL_HISTORY")
+        .is_some_and(|value| value.to_lowercase() == "true");
+    let problem = || if enabled { Status::Fail } else { Status::Warn };
+    let mut usable = true;
+    for source in sources {
+        let message = if source.path.is_file() {
+            format_problem(source)
+        } else {
+            Some(format!(
+                "{} not found; pass --history PATH (or set HISTORY_FILES) to read another file, or --shell to pick the shell",
+                source.path.display()
+            ))
+        };
+        if let Some(message) = message {
+            checks.push((problem(), message));
+            usable = false;
+        }
+    }
+    if !usable {
+        return;
+    }
+
+    let hours = files
+        .get("HOURS_OF_SHELL_HISTORY")
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .unwrap_or(5);
+    let cutoff_time = Utc::now() - Duration::hours(hours);
+    checks.push(
+        match read_history(sources, cutoff_time.timestamp(), HistoryTimeZone::Local)
+            .unwrap_or_default()
+            .len()
+        {
+            0 => (
+                Status::Warn,
+                format!(
+                    "No entries in {} from the last {} hours; raise HOURS_OF_SHELL_HISTORY",
+                    sources
+                        .iter()
+                        .map(|source| source.path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    hours
+                ),
+            ),
+            count => (Status::Ok, format!("{} history entries from the last {} hours", count, hours)),
+        },
+    );
+}
+
+/// Checks that the project's ecosystem is recognized and that there are files to send.
+fn check_project(project_dir: &Path, files: &ConfigFiles, checks: &mut Vec<(Status, String)>) {
+    let languages = detect_fence_languages(project_dir);
+    checks.push(if languages.is_empty() {
+        (
+            Status::Warn,
+            format!(
+                "No Cargo.toml, package.json, pyproject.toml, or go.mod in {}; is this the project root?",
+                project_dir.display()
+            ),
+        )
+    } else {
+        (Status::Ok, format!("Detected a {} project", languages.join("/")))
+    });
+
+    let follow_symlinks = files.get("FOLLOW_SYMLINKS").is_some_and(|value| value.to_lowercase() == "true");
+    checks.push(match find_project_files(project_dir, usize::MAX, follow_symlinks).len() {
+        0 => (Status::Warn, format!("No project files detected in {}", project_dir.display())),
+        count => (Status::Ok, format!("{} project files detected", count)),
+    });
+}
+
+/// Checks that the API key is present and accepted, which also shows whether the API is reachable.
+fn check_api(files: &ConfigFiles, allowed_hosts: &HostAllowlist, offline: bool, checks: &mut Vec<(Status, String)>) {
+    let enable_openai = files.get("ENABLE_OPENAI").is_some_and(|value| value.to_lowercase() == "true");
+    if !enable_openai {
+        checks.push((
+            Status::Warn,
+            "ENABLE_OPENAI is not true, so no request is sent; set it or pass --enable-openai".to_string(),
+        ));
+    }
+
+    // The key is only required when requests are sent.
+    let Some(api_key) = files.get("OPENAI_API_KEY").and_then(ApiKey::new) else {
+        checks.push((
+            if enable_openai { Status::Fail } else { Status::Warn },
+            "OPENAI_API_KEY is not set; add it to .env, the environment, or the user config.toml".to_string(),
+        ));
+        return;
+    };
+    if offline {
+        checks.push((Status::Ok, "OPENAI_API_KEY is set (not verified with --offline)".to_string()));
+        return;
+    }
+
+    // An invalid OPENAI_BASE_URL is reported with the other settings; the key is checked against the default.
+    let base_url = files
+        .get("OPENAI_BASE_URL")
+        .and_then(|url| parse_base_url(&url))
+        .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    let models_url = format!("{}/models", base_url);
+    if let Err(error) = allowed_hosts.check(&models_url) {
+        checks.push((Status::Fail, format!("Can't check OPENAI_API_KEY: {:#}", error)));
+        return;
+    }
+    let client = match Client::builder()
+        .timeout(std::time::Duration::from_secs(NETWORK_TIMEOUT_SECS))
+        .redirect(allowed_hosts.redirect_policy())
+        .build()
+    {
+        Ok(client) => client,
+        Err(error) => {
+            checks.push((
+                Status::Fail,
+                format!("Can't check OPENAI_API_KEY: failed to build HTTP client: {}", error),
+            ));
+            return;
+        }
+    };
+    checks.push(match client.get(&models_url).bearer_auth(api_key.expose()).send() {
+        Ok(response) if response.status().is_success() => (Status::Ok, "OPENAI_API_KEY is valid".to_string()),
+        Ok(response) if response.status().as_u16() == 401 => (
+            Status::Fail,
+            "OPENAI_API_KEY was rejected; create a new key at https://platform.openai.com/api-keys".to_string(),
+        ),
+        Ok(response) => (
+            Status::Warn,
+            format!("The API answered {} when checking the key; try again later", response.status()),
+        ),
+        Err(error) => (
+            Status::Fail,
+            format!(
+                "Can't reach {} ({}); check your network connection or HTTPS_PROXY",
+                url_host(&models_url),
+                error
+            ),
+        ),
+    });
+}
+
+/// Checks for a newer release. Being out of date, or not being able to tell, never fails the diagnosis.
+fn check_update(allowed_hosts: &HostAllowlist, checks: &mut Vec<(Status, String)>) {
+    checks.push(match latest_release(allowed_hosts) {
+        Ok(release) if release.is_newer() => (
+            Status::Warn,
+            format!(
+                "magic_quickstart {} is available (you have {}); upgrade with `{}`",
+                release.version,
+                CURRENT_VERSION,
+                release.upgrade_command()
+            ),
+        ),
+        Ok(_) => (Status::Ok, format!("magic_quickstart {} is the latest release", CURRENT_VERSION)),
+        Err(error) => (Status::Warn, format!("Couldn't check for a newer release: {:#}", error)),
+    });
+}
@@ -0,0 +1,362 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::console::status;
+use crate::convert::{markdown_to_org, markdown_to_text};
+use crate::doc_types::DocType;
+use crate::errors::Error;
+use crate::formats::OutputFormat;
+use crate::front_matter::prepend_front_matter;
+use crate::i18n::t;
+use crate::manpage::render_man_page;
+use crate::markdown::{append_truncation_note, heading_anchor, postprocess_markdown, promote_headings, split_sections};
+use crate::output::{generated_section, make_executable, merge_generated_section, review_in_editor, strip_code_fences};
+use crate::provenance::{append_footer, provenance_line};
+use crate::safety::{review_commands, review_script_commands, CommandSafety, RiskyCommand};
+use crate::sections::{clear_disabled_slots, filter_markdown_sections, replace_markdown_section};
+use crate::sinks::OutputSink;
+use crate::streaming::Cut;
+use crate::templates::render_output_template;
+
+/// Hands `content`, the file meant for `path`, to the sink after review in `$EDITOR` with `--edit`. Returns whether
+/// the file was written.
+pub fn deliver(args: &Args, sink: &dyn OutputSink, path: &Path, content: &str) -> Result<bool> {
+    let content = if args.edit {
+        match review_in_editor(content, path, args.yes)? {
+            Some(edited) => edited,
+            None => return Ok(false),
+        }
+    } else {
+        content.to_string()
+    };
+    sink.write(path, &content)
+}
+
+/// Replaces one section of the generated part of a marker-managed document with the regenerated `section`,
+/// leaving the other sections and everything outside the markers untouched. Returns the path if it was written.
+fn write_regenerated_section(
+    args: &Args,
+    sink: &dyn OutputSink,
+    output_dir: &Path,
+    doc_type: DocType,
+    heading: &str,
+    section: &str,
+) -> Result<Option<PathBuf>> {
+    let path = output_dir.join(format!("{}.md", doc_type.file_stem()));
+    let existing = fs::read_to_string(&path).with_context(|| Error::Output(format!("Failed to read {}", path.display())))?;
+    let Some(generated) = generated_section(&existing) else {
+        bail!(Error::Output(format!(
+            "{} lost its markers while the section was generated",
+            path.display()
+        )));
+    };
+
+    // The model is asked to start with the heading; add it back if it didn't.
+    let mut section = strip_code_fences(section);
+    if !section.trim_start().starts_with('#') {
+        section = format!("## {}\n\n{}", heading, section);
+    }
+    let section = if args.edit {
+        match review_in_editor(&section, &path, args.yes)? {
+            Some(edited) => edited,
+            None => return Ok(None),
+        }
+    } else {
+        section
+    };
+
+    let merged = merge_generated_section(Some(&existing), &replace_markdown_section(generated, heading, &section))?;
+    if !sink.write(&path, &merged)? {
+        return Ok(None);
+    }
+    status!(
+        "{}",
+        t!("regenerated-section", heading = heading, path = path.display().to_string())
+    );
+    Ok(Some(path))
+}
+
+/// Writes each second-level section of a Markdown document to its own file next to `index_path`, named after its
+/// heading (e.g. `installation.md`), and an index page with the introduction and links to the sections.
+/// Returns the paths that were written.
+fn write_split_document(sink: &dyn OutputSink, index_path: &Path, content: &str, provenance_comment: Option<&str>) -> Result<Vec<PathBuf>> {
+    let directory = index_path.parent().unwrap_or(Path::new("."));
+    let (intro, sections) = split_sections(content);
+    let mut written = Vec::new();
+
+    let mut index = format!("{}\n\n## Contents\n\n", intro.trim_end());
+    let mut used_names = Vec::new();
+    for (heading, body) in &sections {
+        // Sections whose headings share a slug get numbered file names.
+        let base = heading_anchor(heading);
+        let base = if base.is_empty() { "section".to_string() } else { base };
+        let mut name = format!("{}.md", base);
+        let mut suffix = 1;
+        while used_names.contains(&name) || name == "index.md" {
+            name = format!("{}-{}.md", base, suffix);
+            suffix += 1;
+        }
+        used_names.push(name.clone());
+
+        let page = format!("# {}\n{}\n", heading, promote_headings(body).trim_end());
+        let page_path = directory.join(&name);
+        if sink.write(&page_path, &page)? {
+            status!("{}", t!("wrote", path = page_path.display().to_string()));
+            written.push(page_path);
+        }
+        index.push_str(&format!("- [{}]({})\n", heading, name));
+    }
+    if let Some(comment) = provenance_comment {
+        index = append_footer(index, comment);
+    }
+    if sink.write(index_path, &index)? {
+        status!("{}", t!("wrote", path = index_path.display().to_string()));
+        written.push(index_path.to_path_buf());
+    }
+    Ok(written)
+}
+
+/// A model response for one document, with the hash of the request it answers.
+pub struct GeneratedDocument {
+    pub doc_type: DocType,
+    pub response: String,
+    pub context_hash: String,
+    /// Why the response stops before the model finished it, if it does.
+    pub cut: Option<Cut>,
+}
+
+/// Writes a generated document according to its type and the output settings: scripts and task files to their fixed file name,
+/// man pages and templates rendered locally, and Markdown either merged between markers or timestamped. Every file
+/// goes to `sink`, after review in `$EDITOR` with `--edit`. Returns the paths that were written.
+pub fn write_document(
+    config: &Config,
+    args: &Args,
+    output_dir: &Path,
+    project_name: &str,
+    fence_languages: &[&str],
+    document: GeneratedDocument,
+    sink: &dyn OutputSink,
+) -> Result<Vec<PathBuf>> {
+    // PROVENANCE_FOOTER records which inputs produced the document in a comment at its end.
+    let provenance = config
+        .provenance_footer
+        .then(|| provenance_line(&config.openai_model, &document.context_hash));
+    let with_provenance = |content: String, comment: fn(&str) -> String| match &provenance {
+        Some(line) => append_footer(content, &comment(line)),
+        None => content,
+    };
+    let shell_comment: fn(&str) -> String = |line| format!("# {}", line);
+
+    let GeneratedDocument {
+        doc_type, response, cut, ..
+    } = document;
+    // Post-processor plugins get the response as the model wrote it, before it is formatted for its file.
+    let response = config.plugins.postprocess(doc_type, config.output_format, response)?;
+    let response = match &config.hook_script {
+        Some(hook_script) => hook_script.post_generate(doc_type, response)?,
+        None => response,
+    };
+    // A document that was cut off says so where it stops: in a comment in scripts and task files, and in a
+    // paragraph of its own in documents.
+    let truncation_note = cut.map(|cut| t!("truncation-note", reason = cut.reason()));
+    let with_provenance = |content: String, comment: fn(&str) -> String| match &truncation_note {
+        Some(note) => with_provenance(append_footer(content, &comment(note)), comment),
+        None => with_provenance(content, comment),
+    };
+    let response = match &truncation_note {
+        Some(_) if matches!(doc_type, DocType::Tasks | DocType::CiWorkflow | DocType::SetupScript) => response,
+        Some(note) if config.output_format.written_as_markdown() => append_truncation_note(&response, note),
+        Some(note) => format!("{}\n\n{}\n", response.trim_end(), note),
+        None => response,
+    };
+    if doc_type == DocType::Tasks {
+        let tasks_path = output_dir.join(config.task_runner.file_name());
+        let tasks = review_document_commands(config, doc_type, strip_code_fences(&response), review_script_commands);
+        if !deliver(args, sink, &tasks_path, &with_provenance(tasks, shell_comment))? {
+            return Ok(vec![]);
+        }
+        status!("{}", t!("wrote-tasks", path = tasks_path.display().to_string()));
+        return Ok(vec![tasks_path]);
+    }
+    if doc_type == DocType::Devcontainer {
+        // devcontainer.json is written as plain JSON, which has no comments, so it gets no provenance footer.
+        return write_devcontainer(args, config, sink, output_dir, &response);
+    }
+    if doc_type == DocType::CiWorkflow {
+        let workflow_path = output_dir.join(".github").join("workflows").join("ci.yml");
+        let workflow = review_document_commands(config, doc_type, strip_code_fences(&response), review_script_commands);
+        if !deliver(args, sink, &workflow_path, &with_provenance(workflow, shell_comment))? {
+            return Ok(vec![]);
+        }
+        status!("{}", t!("wrote-workflow", path = workflow_path.display().to_string()));
+        return Ok(vec![workflow_path]);
+    }
+    if doc_type == DocType::SetupScript {
+        let script_path = output_dir.join(config.script_shell.file_name());
+        let script = review_document_commands(config, doc_type, strip_code_fences(&response), review_script_commands);
+        if !deliver(args, sink, &script_path, &with_provenance(script, shell_comment))? {
+            return Ok(vec![]);
+        }
+        make_executable(&script_path);
+        status!("{}", t!("wrote-script", path = script_path.display().to_string()));
+        return Ok(vec![script_path]);
+    }
+
+    // Quickstart section presets are enforced again after generation, in case the model ignored them.
+    let sections = config.section_presets.as_ref().filter(|_| doc_type == DocType::Quickstart);
+    let mut content = match sections {
+        Some(sections) if config.output_format.needs_structured_output() || config.output_template.is_some() => {
+            clear_disabled_slots(&response, sections)
+        }
+        _ => response,
+    };
+    if config.output_format == OutputFormat::Man {
+        // Man pages are rendered from the structured sections and named after the project.
+        let man_page = render_man_page(&content, project_name);
+        let man_page = match &provenance {
+            Some(line) => append_footer(man_page, &OutputFormat::Man.comment(line)),
+            None => man_page,
+        };
+        let man_path = output_dir.join(format!("{}.1", project_name.to_lowercase()));
+        if !deliver(args, sink, &man_path, &man_page)? {
+            return Ok(vec![]);
+        }
+        status!("{}", t!("wrote-man-page", path = man_path.display().to_string()));
+        return Ok(vec![man_path]);
+    }
+    if let Some(template_path) = &config.output_template {
+        content = render_output_template(template_path, &content, project_name, &config.openai_model)?;
+    }
+    // A made-up destructive command shouldn't land in a README unnoticed.
+    if config.output_format.written_as_markdown() {
+        content = review_document_commands(config, doc_type, content, review_commands);
+    }
+    if let Some(heading) = &config.regenerate_section {
+        return Ok(write_regenerated_section(args, sink, output_dir, doc_type, heading, &content)?
+            .into_iter()
+            .collect());
+    }
+    if let Some(sections) = sections.filter(|_| config.output_format.written_as_markdown()) {
+        content = filter_markdown_sections(&content, sections);
+    }
+    if config.output_format.written_as_markdown() {
+        // A split document gets an index page instead of a table of contents, and links only work in Markdown.
+        let insert_toc = config.insert_toc && !args.split && config.output_format == OutputFormat::Markdown;
+        content = postprocess_markdown(&content, fence_languages, insert_toc);
+    }
+    match config.output_format {
+        OutputFormat::Org => content = markdown_to_org(&content),
+        OutputFormat::Text => content = markdown_to_text(&content),
+        _ => {}
+    }
+    if let Some(line) = provenance.as_ref().filter(|_| !args.split) {
+        content = append_footer(content, &config.output_format.comment(line));
+    }
+    // Merge between the magic-quickstart markers in README.md (or CONTRIBUTING.md), keeping hand-written content;
+    // otherwise write a new file with a timestamp at the end of its name, or one file per section with --split.
+    let merge = config.merge_into_readme;
+    let target = if args.split {
+        output_dir.join("docs").join(doc_type.name()).join("index.md")
+    } else if merge {
+        output_dir.join(format!("{}.md", doc_type.file_stem()))
+    } else {
+        output_dir.join(format!(
+            "{}_GENERATED_{}.{}",
+            doc_type.file_stem(),
+            Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+            config.output_format.extension()
+        ))
+    };
+    if args.edit {
+        // Only the generated content is edited; when merging, the reviewed version then replaces the section.
+        match review_in_editor(&content, &target, args.yes)? {
+            Some(edited) => content = edited,
+            None => return Ok(vec![]),
+        }
+    }
+
+    if let Some(front_matter) = config.front_matter {
+        // Front matter has to open the file, so it is only added to new files, not to sections merged into one.
+        if config.output_format == OutputFormat::Markdown && !merge && !args.split {
+            content = prepend_front_matter(&content, front_matter, project_name, doc_type.file_stem(), fence_languages);
+        }
+    }
+
+    if args.split {
+        let provenance_comment = provenance.map(|line| config.output_format.comment(&line));
+        return write_split_document(sink, &target, &content, provenance_comment.as_deref());
+    }
+    if merge {
+        let existing = fs::read_to_string(&target).ok();
+        let merged = merge_generated_section(existing.as_deref(), &content)?;
+        if !sink.write(&target, &merged)? {
+            return Ok(vec![]);
+        }
+        status!("{}", t!("merged", path = target.display().to_string()));
+    } else if !sink.write(&target, &content)? {
+        return Ok(vec![]);
+    }
+    Ok(vec![target])
+}
+
+/// Checks the commands in `content` with `review` unless COMMAND_SAFETY is off, lists the risky ones, and returns the
+/// content, with warnings added when COMMAND_SAFETY is `annotate`.
+fn review_document_commands(
+    config: &Config,
+    doc_type: DocType,
+    content: String,
+    review: fn(&str, bool) -> (String, Vec<RiskyCommand>),
+) -> String {
+    if config.command_safety == CommandSafety::Off {
+        return content;
+    }
+    let annotate = config.command_safety == CommandSafety::Annotate;
+    let (reviewed, risky) = review(&content, annotate);
+    if !risky.is_empty() {
+        let message = if annotate { "risky-commands-annotated" } else { "risky-commands" };
+        status!("{}", t!(message, count = risky.len(), doc_type = doc_type.name()));
+        for command in &risky {
+            status!("  {}", command);
+        }
+    }
+    reviewed
+}
+
+/// Writes `.devcontainer/devcontainer.json` and, if the model produced one, `.devcontainer/Dockerfile` from the
+/// model's JSON response, with the Dockerfile's commands reviewed like a script's. Returns the paths that were written.
+fn write_devcontainer(args: &Args, config: &Config, sink: &dyn OutputSink, output_dir: &Path, response: &str) -> Result<Vec<PathBuf>> {
+    let files: serde_json::Value =
+        serde_json::from_str(response.trim()).context(Error::provider("The model's devcontainer response was not valid JSON"))?;
+    let devcontainer_dir = output_dir.join(".devcontainer");
+    let mut written = Vec::new();
+
+    // Re-serialize the configuration so it is consistently formatted; keep it verbatim if it isn't plain JSON.
+    let devcontainer_json = files["devcontainer_json"].as_str().unwrap_or_default();
+    let devcontainer_json = match serde_json::from_str::<serde_json::Value>(devcontainer_json) {
+        Ok(value) => format!(
+            "{}\n",
+            serde_json::to_string_pretty(&value).context(Error::Output("Failed to format devcontainer.json".to_string()))?
+        ),
+        Err(_) => strip_code_fences(devcontainer_json),
+    };
+    let devcontainer_path = devcontainer_dir.join("devcontainer.json");
+    if deliver(args, sink, &devcontainer_path, &devcontainer_json)? {
+        status!("{}", t!("wrote", path = devcontainer_path.display().to_string()));
+        written.push(devcontainer_path);
+    }
+
+    let dockerfile = files["dockerfile"].as_str().unwrap_or_default();
+    if !dockerfile.trim().is_empty() {
+        let dockerfile_path = devcontainer_dir.join("Dockerfile");
+        let dockerfile = review_document_commands(config, DocType::Devcontainer, strip_code_fences(dockerfile), review_script_commands);
+        if deliver(args, sink, &dockerfile_path, &dockerfile)? {
+            status!("{}", t!("wrote", path = dockerfile_path.display().to_string()));
+            written.push(dockerfile_path);
+        }
+    }
+    Ok(written)
+}
@@ -0,0 +1,170 @@
+use anyhow::{bail, Context, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+#[cfg(feature = "keyring")]
+use crate::file_lock::FileLock;
+#[cfg(feature = "keyring")]
+use crate::settings::data_home;
+
+/// Marks a file written by ENCRYPT_ARTIFACTS; the nonce and the ciphertext follow it.
+const MAGIC: &[u8] = b"magic_quickstart encrypted v1\n";
+
+/// Length of an XChaCha20-Poly1305 nonce. It is random per file, which 24 bytes make safe.
+const NONCE_LEN: usize = 24;
+
+/// Where the key is kept in the system keyring (macOS Keychain, Windows Credential Manager, or the Secret Service).
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "magic_quickstart";
+#[cfg(feature = "keyring")]
+const KEYRING_USER: &str = "artifact-key";
+
+/// Names a key to use instead of the keyring's, as 64 hex characters, for machines without one (e.g. CI).
+pub const KEY_VARIABLE: &str = "MAGIC_QUICKSTART_ARTIFACT_KEY";
+
+/// Encrypts and decrypts artifacts and saved runs at rest (ENCRYPT_ARTIFACTS) with XChaCha20-Poly1305, which also
+/// detects a file that was tampered with.
+pub struct Sealer {
+    cipher: XChaCha20Poly1305,
+}
+
+#[cfg(feature = "keyring")]
+fn to_hex(bytes: &[u8]) -> Zeroizing<String> {
+    Zeroizing::new(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn from_hex(hex: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()
+        .map(Zeroizing::new)
+}
+
+/// The key from MAGIC_QUICKSTART_ARTIFACT_KEY, or else from the system keyring, where a random one is stored the
+/// first time.
+fn load_key() -> Result<Zeroizing<Vec<u8>>> {
+    if let Ok(hex) = env::var(KEY_VARIABLE) {
+        let hex = Zeroizing::new(hex);
+        match from_hex(&hex) {
+            Some(key) => return Ok(key),
+            None => bail!(Error::Config(format!("{} must be 64 hex characters", KEY_VARIABLE))),
+        }
+    }
+    keyring_key()
+}
+
+/// Without the `keyring` feature the key can only come from MAGIC_QUICKSTART_ARTIFACT_KEY.
+#[cfg(not(feature = "keyring"))]
+fn keyring_key() -> Result<Zeroizing<Vec<u8>>> {
+    bail!(Error::Config(format!(
+        "This build of magic_quickstart was made without the `keyring` feature, so ENCRYPT_ARTIFACTS needs {} set to 64 hex characters",
+        KEY_VARIABLE
+    )))
+}
+
+/// The key from the system keyring, storing a random one the first time.
+#[cfg(feature = "keyring")]
+fn keyring_key() -> Result<Zeroizing<Vec<u8>>> {
+    let unavailable = |error: keyring::Error| {
+        Error::Config(format!(
+            "ENCRYPT_ARTIFACTS keeps its key in the system keyring, which isn't available ({}); set {} to 64 hex characters instead",
+            error, KEY_VARIABLE
+        ))
+    };
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(unavailable)?;
+    match entry.get_password() {
+        Ok(hex) => match from_hex(&Zeroizing::new(hex)) {
+            Some(key) => Ok(key),
+            None => bail!(Error::Config(format!(
+                "The artifact key in the system keyring ({} / {}) is damaged; delete it to start over, which makes existing encrypted files unreadable",
+                KEYRING_SERVICE, KEYRING_USER
+            ))),
+        },
+        Err(keyring::Error::NoEntry) => {
+            // Instances starting at the same time take turns storing the key, so none encrypts with one that another
+            // then replaces. Without a data directory to lock in, there is no turn to take.
+            let _lock = data_home().and_then(|dir| {
+                let dir = dir.join("magic_quickstart");
+                fs::create_dir_all(&dir).ok()?;
+                FileLock::open(&dir.join("artifact-key.lock")).ok()
+            });
+            if let Some(key) = entry.get_password().ok().and_then(|hex| from_hex(&Zeroizing::new(hex))) {
+                return Ok(key);
+            }
+            let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+            entry.set_password(&to_hex(&key)).map_err(unavailable)?;
+            Ok(Zeroizing::new(key.to_vec()))
+        }
+        Err(error) => Err(unavailable(error).into()),
+    }
+}
+
+impl Sealer {
+    /// The sealer for this user, loaded once per run. Fails with a configuration error if there is no key and none
+    /// can be stored.
+    pub fn get() -> Result<&'static Sealer> {
+        static SEALER: OnceLock<Sealer> = OnceLock::new();
+        if let Some(sealer) = SEALER.get() {
+            return Ok(sealer);
+        }
+        let key = load_key()?;
+        let cipher = XChaCha20Poly1305::new_from_slice(&key).expect("artifact keys are 32 bytes");
+        Ok(SEALER.get_or_init(|| Sealer { cipher }))
+    }
+
+    /// Encrypts `plaintext` into the file format: the marker, a random nonce, and the ciphertext.
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, plaintext).expect("encryption in memory doesn't fail");
+        [MAGIC, nonce.as_slice(), &ciphertext].concat()
+    }
+
+    /// Decrypts a sealed file's contents; `source` names it in errors.
+    fn open(&self, sealed: &[u8], source: &str) -> Result<Vec<u8>> {
+        let body = &sealed[MAGIC.len()..];
+        if body.len() < NONCE_LEN {
+            bail!(Error::Config(format!("{} is truncated", source)));
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        match self.cipher.decrypt(XNonce::from_slice(nonce), ciphertext) {
+            Ok(plaintext) => Ok(plaintext),
+            Err(_) => bail!(Error::Config(format!(
+                "{} can't be decrypted: it was encrypted with another key or has been modified",
+                source
+            ))),
+        }
+    }
+}
+
+/// Reads a file that may have been written with ENCRYPT_ARTIFACTS, decrypting it if it was.
+pub fn read_to_string(path: &Path) -> Result<String> {
+    let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    decrypt_to_string(content, &path.display().to_string())
+}
+
+/// Whether `content` was sealed with ENCRYPT_ARTIFACTS.
+pub fn is_sealed(content: &[u8]) -> bool {
+    content.starts_with(MAGIC)
+}
+
+/// Reads content that may have been sealed with ENCRYPT_ARTIFACTS, decrypting it if it was; `source` names it in
+/// errors, e.g. `run 20250301-141502`.
+pub fn decrypt_to_string(content: Vec<u8>, source: &str) -> Result<String> {
+    let content = if is_sealed(&content) {
+        Sealer::get()?.open(&content, source)?
+    } else {
+        content
+    };
+    String::from_utf8(content).with_context(|| format!("{} is not UTF-8 text", source))
+}
@@ -0,0 +1,110 @@
+use anyhow::{bail, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use reqwest::redirect;
+use reqwest::Url;
+
+use crate::errors::Error;
+use crate::settings::ConfigFiles;
+
+/// Where OpenAI-compatible requests go unless OPENAI_BASE_URL points at a gateway.
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Describes a valid OPENAI_BASE_URL in configuration errors.
+pub const BASE_URL_EXPECTED: &str = "an http:// or https:// URL such as https://api.openai.com/v1";
+
+/// Parses OPENAI_BASE_URL, without a trailing slash so endpoint paths can be appended.
+pub fn parse_base_url(value: &str) -> Option<String> {
+    let value = value.trim().trim_end_matches('/');
+    Url::parse(value)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+        .map(|_| value.to_string())
+}
+
+/// The host of a URL, e.g. `api.openai.com`, for messages.
+pub fn url_host(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Hosts whose provider has a name of its own in the policy's `allowed_providers`.
+const PROVIDER_HOSTS: [(&str, &str); 1] = [("api.openai.com", "openai")];
+
+/// The provider a URL sends to, as the policy's `allowed_providers` names it: `openai` for api.openai.com, and the
+/// host itself, such as `gateway.example.com`, for anywhere else OPENAI_BASE_URL points.
+pub fn endpoint_provider(url: &str) -> String {
+    let host = url_host(url).to_lowercase();
+    PROVIDER_HOSTS
+        .iter()
+        .find(|(provider_host, _)| *provider_host == host)
+        .map_or(host, |(_, provider)| provider.to_string())
+}
+
+/// ALLOWED_HOSTS: if any are set, the only hosts this tool sends HTTP requests to, as exact names or globs such as
+/// `*.gateway.example.com`. With none set, every host is allowed.
+#[derive(Clone, Default)]
+pub struct HostAllowlist {
+    hosts: Vec<GlobMatcher>,
+}
+
+impl HostAllowlist {
+    /// Reads the merged ALLOWED_HOSTS, failing with a configuration error naming where an invalid entry was set.
+    pub fn load(files: &ConfigFiles) -> Result<Self> {
+        let mut allowlist = HostAllowlist::default();
+        for (host, source) in files.list_entries("ALLOWED_HOSTS") {
+            allowlist.allow(&host, &source)?;
+        }
+        Ok(allowlist)
+    }
+
+    /// Adds a host or host glob; `source` says where it was set, for the error if it isn't a valid glob.
+    pub fn allow(&mut self, host: &str, source: &str) -> Result<()> {
+        match GlobBuilder::new(host.trim()).case_insensitive(true).build() {
+            Ok(glob) => self.hosts.push(glob.compile_matcher()),
+            Err(_) => bail!(Error::Config(format!(
+                "Invalid ALLOWED_HOSTS entry \"{}\" in {} (expected a hostname such as api.openai.com or *.example.com)",
+                host, source
+            ))),
+        }
+        Ok(())
+    }
+
+    /// Whether no hosts are listed, which allows every host.
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    fn allows(&self, url: &Url) -> bool {
+        self.hosts.is_empty() || url.host_str().is_some_and(|host| self.hosts.iter().any(|glob| glob.is_match(host)))
+    }
+
+    /// Fails with a configuration error unless requests may be sent to `url`.
+    pub fn check(&self, url: &str) -> Result<()> {
+        match Url::parse(url) {
+            Ok(parsed) if self.allows(&parsed) => Ok(()),
+            _ => bail!(Error::Config(format!(
+                "{} is not on ALLOWED_HOSTS, so nothing was sent to {}; add it to ALLOWED_HOSTS",
+                url_host(url),
+                url
+            ))),
+        }
+    }
+
+    /// A redirect policy that only follows redirects to allowed hosts, so an allowed gateway can't hand a request
+    /// on to one that isn't.
+    pub fn redirect_policy(&self) -> redirect::Policy {
+        let allowlist = self.clone();
+        redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 10 {
+                attempt.error("too many redirects")
+            } else if allowlist.allows(attempt.url()) {
+                attempt.follow()
+            } else {
+                let message = format!("refused a redirect to {}, which is not on ALLOWED_HOSTS", attempt.url());
+                attempt.error(message)
+            }
+        })
+    }
+}
@@ -0,0 +1,158 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::models::{EnvKey, EnvKeyUsage};
+use crate::parsers::walk_source_files;
+
+/// Source file extensions scanned for environment variable lookups.
+const SCANNED_EXTENSIONS: [&str; 7] = ["rs", "py", "js", "ts", "jsx", "tsx", "go"];
+
+/// Upper bound on the number of source files scanned, to keep large repositories fast.
+const MAX_SCANNED_FILES: usize = 2000;
+
+/// How many characters after a lookup are inspected for a fallback (e.g. `.unwrap_or(...)`).
+const FALLBACK_WINDOW: usize = 160;
+
+/// Key name segments (between underscores) that mark a value as a credential, given a `changeme` placeholder.
+const SECRET_FRAGMENTS: [&str; 6] = ["KEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL"];
+
+/// How a key is referenced in the codebase.
+#[derive(Default)]
+struct FoundUsage {
+    required: bool,
+    referenced_in: Vec<String>,
+}
+
+/// Cross-references env keys against the codebase under `root` and labels each one as
+/// `required` (read without a fallback), `optional` (read with a default), or `unused`.
+pub fn classify_env_keys(root: &Path, env_file_keys: &[EnvKey], follow_symlinks: bool) -> Vec<EnvKey> {
+    let usages = scan_env_usages(root, follow_symlinks);
+
+    env_file_keys
+        .iter()
+        .map(|entry| {
+            let (usage, referenced_in) = match usages.get(&entry.key) {
+                Some(found) if found.required => (EnvKeyUsage::Required, found.referenced_in.clone()),
+                Some(found) => (EnvKeyUsage::Optional, found.referenced_in.clone()),
+                None => (EnvKeyUsage::Unused, vec![]),
+            };
+            EnvKey {
+                usage,
+                referenced_in,
+                ..entry.clone()
+            }
+        })
+        .collect()
+}
+
+/// Scans source files for environment variable lookups in Rust, Python, JavaScript/TypeScript, and Go.
+fn scan_env_usages(root: &Path, follow_symlinks: bool) -> HashMap<String, FoundUsage> {
+    // Each pattern captures the key name; the bool says whether the lookup is required by its nature.
+    // `None` means required-ness is decided by looking for a fallback after the match.
+    let patterns: Vec<(Regex, Option<bool>)> = vec![
+        (Regex::new(r#"env::var(?:_os)?\(\s*"([A-Za-z0-9_]+)"\s*\)"#).unwrap(), None),
+        (Regex::new(r#"os\.environ\[\s*["']([A-Za-z0-9_]+)["']\s*\]"#).unwrap(), Some(true)),
+        (
+            Regex::new(r#"os\.(?:environ\.get|getenv)\(\s*["']([A-Za-z0-9_]+)["']"#).unwrap(),
+            Some(false),
+        ),
+        (Regex::new(r#"process\.env\.([A-Za-z0-9_]+)"#).unwrap(), None),
+        (Regex::new(r#"process\.env\[\s*["']([A-Za-z0-9_]+)["']\s*\]"#).unwrap(), None),
+        (Regex::new(r#"os\.Getenv\(\s*"([A-Za-z0-9_]+)"\s*\)"#).unwrap(), Some(true)),
+        (Regex::new(r#"os\.LookupEnv\(\s*"([A-Za-z0-9_]+)"\s*\)"#).unwrap(), Some(false)),
+    ];
+    let fallback = Regex::new(r"unwrap_or|\.ok\(\)|is_ok\(\)|is_err\(\)|\|\||\?\?").unwrap();
+
+    let mut usages: HashMap<String, FoundUsage> = HashMap::new();
+    for file_path in walk_source_files(root, &SCANNED_EXTENSIONS, MAX_SCANNED_FILES, follow_symlinks).files {
+        let Ok(content) = fs::read_to_string(&file_path) else {
+            continue;
+        };
+        let display_path = file_path.strip_prefix(root).unwrap_or(&file_path).display().to_string();
+
+        for (pattern, fixed_required) in &patterns {
+            for captures in pattern.captures_iter(&content) {
+                let whole = captures.get(0).unwrap();
+                let required = fixed_required.unwrap_or_else(|| {
+                    let tail: String = content[whole.end()..].chars().take(FALLBACK_WINDOW).collect();
+                    let statement = tail.split(';').next().unwrap_or_default();
+                    !fallback.is_match(statement)
+                });
+
+                let usage = usages.entry(captures[1].to_string()).or_default();
+                usage.required |= required;
+                if !usage.referenced_in.contains(&display_path) {
+                    usage.referenced_in.push(display_path.clone());
+                }
+            }
+        }
+    }
+
+    usages
+}
+
+/// Renders a `.env.example` from classified env keys: required keys first, then optional ones, then unused ones
+/// commented out. Values are never copied from the env files; each key gets a placeholder guessed from its name
+/// and a comment saying where it is used.
+pub fn render_env_example(classified_keys: &[EnvKey]) -> String {
+    // The same key often appears in several env files; keep the first entry and note every file it came from.
+    let mut keys: Vec<(&str, EnvKeyUsage, Vec<&str>, &[String])> = Vec::new();
+    for entry in classified_keys {
+        let file = entry.file.as_str();
+        match keys.iter_mut().find(|(existing, ..)| *existing == entry.key) {
+            Some((_, _, files, _)) => {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+            None => keys.push((&entry.key, entry.usage, vec![file], &entry.referenced_in)),
+        }
+    }
+
+    let mut example = String::from("# Copy to .env and fill in the values.\n");
+    for (usage, heading) in [
+        (EnvKeyUsage::Required, "Required"),
+        (EnvKeyUsage::Optional, "Optional"),
+        (EnvKeyUsage::Unused, "Not referenced in the code"),
+    ] {
+        let group: Vec<_> = keys.iter().filter(|(_, key_usage, ..)| *key_usage == usage).collect();
+        if group.is_empty() {
+            continue;
+        }
+        example.push_str(&format!("\n# --- {} ---\n", heading));
+        for (key, _, files, referenced_in) in group {
+            if referenced_in.is_empty() {
+                example.push_str(&format!("# Defined in {}.\n", files.join(", ")));
+            } else {
+                example.push_str(&format!("# Used in {}.\n", referenced_in.join(", ")));
+            }
+            let prefix = if usage == EnvKeyUsage::Unused { "# " } else { "" };
+            example.push_str(&format!("{}{}={}\n", prefix, key, placeholder_value(key)));
+        }
+    }
+
+    example
+}
+
+/// Guesses a placeholder from the key name: `false` for flags, `changeme` for credentials, otherwise empty.
+fn placeholder_value(key: &str) -> &'static str {
+    let upper = key.to_uppercase();
+    if ["ENABLE_", "INCLUDE_", "DISABLE_", "USE_"]
+        .iter()
+        .any(|prefix| upper.starts_with(prefix))
+        || upper == "DEBUG"
+        || upper.ends_with("_DEBUG")
+    {
+        "false"
+    } else if upper.split('_').any(|segment| SECRET_FRAGMENTS.contains(&segment)) {
+        "changeme"
+    } else if upper == "PORT" || upper.ends_with("_PORT") {
+        "8080"
+    } else if upper.ends_with("_URL") || upper.ends_with("_URI") {
+        "https://example.com"
+    } else {
+        ""
+    }
+}
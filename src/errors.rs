@@ -0,0 +1,100 @@
+use thiserror::Error;
+
+/// Exit code for failures without a more specific code, such as files that can't be written.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit code for invalid or missing settings, flags, configuration files, and templates, and for shell history that
+/// can't be read.
+pub const EXIT_CONFIG: i32 = 2;
+
+/// Exit code when the API or a remote repository can't be reached.
+pub const EXIT_NETWORK: i32 = 3;
+
+/// Exit code when the API answers with an error or a response that can't be used.
+pub const EXIT_PROVIDER: i32 = 4;
+
+/// Exit code when no context was found to generate a document from.
+pub const EXIT_NOTHING_DETECTED: i32 = 5;
+
+/// Exit code when `--verify-manifest` finds documents whose inputs changed since they were generated.
+pub const EXIT_DRIFT: i32 = 6;
+
+/// Exit code after Ctrl-C or SIGTERM, following the shell convention of 128 plus SIGINT.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Kinds of failure that scripts wrapping the tool can tell apart by exit code, and tools embedding it by matching on
+/// [`Error::of`]. They are usually attached as context to the underlying error, so the message explains what went
+/// wrong and the cause follows it.
+///
+/// ```no_run
+/// use magic_quickstart::{Error, QuickstartBuilder};
+///
+/// match QuickstartBuilder::new("path/to/project").build().and_then(|quickstart| quickstart.generate()) {
+///     Ok(outcome) => println!("{}", outcome.name()),
+///     Err(error) => match Error::of(&error) {
+///         Some(Error::Provider { status: Some(429), .. }) => eprintln!("Rate limited; try again later"),
+///         Some(Error::NothingDetected) => eprintln!("Nothing to write about yet"),
+///         _ => eprintln!("{:#}", error),
+///     },
+/// }
+/// ```
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Invalid or missing settings, flags, configuration files, or templates, or an API key that was rejected.
+    #[error("{0}")]
+    Config(String),
+    /// A shell history file that can't be read.
+    #[error("{0}")]
+    History(String),
+    /// No shell history, project files, or environment keys to generate from.
+    #[error("{}", crate::i18n::message("error-nothing-detected", None))]
+    NothingDetected,
+    /// The API, a remote repository, or an HTTP sink can't be reached, or stopped answering.
+    #[error("{0}")]
+    Network(String),
+    /// The API answered with an error or with a response that can't be used. `status` and `body` are the HTTP status
+    /// and body of the answer, if there was one.
+    #[error("{message}")]
+    Provider {
+        message: String,
+        status: Option<u16>,
+        body: Option<String>,
+    },
+    /// A generated document that can't be written or delivered.
+    #[error("{0}")]
+    Output(String),
+}
+
+impl Error {
+    /// A provider error that isn't about a particular HTTP answer.
+    pub fn provider(message: impl Into<String>) -> Self {
+        Error::Provider {
+            message: message.into(),
+            status: None,
+            body: None,
+        }
+    }
+
+    /// The kind of failure `error` is: the outermost `Error` attached to it, if any. Failures without one, such as a
+    /// bug or a file that can't be read, are none of these kinds.
+    pub fn of(error: &anyhow::Error) -> Option<&Error> {
+        error
+            .downcast_ref::<Error>()
+            .or_else(|| error.chain().find_map(|cause| cause.downcast_ref::<Error>()))
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) | Error::History(_) => EXIT_CONFIG,
+            Error::NothingDetected => EXIT_NOTHING_DETECTED,
+            Error::Network(_) => EXIT_NETWORK,
+            Error::Provider { .. } => EXIT_PROVIDER,
+            Error::Output(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+/// The exit code for an error: that of the outermost `Error` attached to it, or `EXIT_FAILURE`.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    Error::of(error).map_or(EXIT_FAILURE, Error::exit_code)
+}
@@ -0,0 +1,52 @@
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Files larger than this are read through a memory map, keeping only their head and tail.
+pub const LARGE_FILE_BYTES: u64 = 256 * 1024;
+
+/// How much of the start of a large file is kept.
+const HEAD_BYTES: usize = 192 * 1024;
+
+/// How much of the end of a large file is kept.
+const TAIL_BYTES: usize = 64 * 1024;
+
+/// Reads a project file as text. A file up to `LARGE_FILE_BYTES` is read whole; a larger one (a log, a generated
+/// source file, a data dump) is mapped into memory and only its first and last parts are copied out, joined by a line
+/// saying how much was left out, so only the pages holding them are ever read. Returns `None` for a file that can't
+/// be read or isn't UTF-8 text.
+pub fn read_text(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let length = file.metadata().ok()?.len();
+    if length <= LARGE_FILE_BYTES {
+        let mut content = String::new();
+        file.read_to_string(&mut content).ok()?;
+        return Some(content);
+    }
+    // SAFETY: the map is only read while `file` stays open, and only copied out of after UTF-8 validation. A file
+    // truncated by another process while it is read is the usual caveat of memory maps; project files aren't
+    // written to while the tool runs.
+    let map = unsafe { Mmap::map(&file) }.ok()?;
+    let head = utf8_prefix(&map[..HEAD_BYTES])?;
+    let tail = utf8_suffix(&map[map.len() - TAIL_BYTES..])?;
+    let omitted = map.len() - head.len() - tail.len();
+    Some(format!("{}\n... [{} bytes left out] ...\n{}", head, omitted, tail))
+}
+
+/// `bytes` as text, dropping a character cut in half at the end. Returns `None` if `bytes` isn't UTF-8 otherwise.
+fn utf8_prefix(bytes: &[u8]) -> Option<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // Validation stops where the slice ends partway through a character; everything before it is valid.
+        Err(error) if error.error_len().is_none() => Some(std::str::from_utf8(&bytes[..error.valid_up_to()]).ok()?),
+        Err(_) => None,
+    }
+}
+
+/// `bytes` as text, dropping a character cut in half at the start. Returns `None` if `bytes` isn't UTF-8 otherwise.
+fn utf8_suffix(bytes: &[u8]) -> Option<&str> {
+    // A character is at most four bytes, so at most three continuation bytes (0b10xxxxxx) start a cut one.
+    let start = bytes.iter().take(3).take_while(|&&byte| byte & 0xC0 == 0x80).count();
+    std::str::from_utf8(&bytes[start..]).ok()
+}
@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::console::status;
+use crate::errors::Error;
+use crate::models::Exemplar;
+use crate::tokens::estimate_tokens;
+
+/// How many exemplar documents are included at most.
+const MAX_EXEMPLARS: usize = 2;
+
+/// Exemplars may use at most this fraction (1/n) of TOKEN_BUDGET; the rest is left for the project context.
+const EXEMPLAR_BUDGET_DIVISOR: usize = 4;
+
+/// Extensions of files in the exemplar directory that are considered documents.
+const EXEMPLAR_EXTENSIONS: [&str; 4] = ["md", "rst", "adoc", "txt"];
+
+/// Loads up to two "gold standard" documents from `dir`, in file name order.
+/// With a token budget, documents that would take the exemplars over a quarter of it are skipped, so a short
+/// exemplar can still be used when a longer one doesn't fit.
+pub fn load_exemplars(dir: &Path, token_budget: Option<usize>) -> Result<Vec<Exemplar>> {
+    let max_tokens = token_budget.map(|budget| budget / EXEMPLAR_BUDGET_DIVISOR);
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .with_context(|| Error::Config(format!("Failed to read exemplar directory {}", dir.display())))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| EXEMPLAR_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    paths.sort();
+
+    let mut exemplars = Vec::new();
+    let mut used_tokens = 0;
+    for path in paths {
+        if exemplars.len() == MAX_EXEMPLARS {
+            break;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let tokens = estimate_tokens(&content);
+        if max_tokens.is_some_and(|max| used_tokens + tokens > max) {
+            status!(
+                "Skipping exemplar {} ({} tokens) to stay within the token budget",
+                path.display(),
+                tokens
+            );
+            continue;
+        }
+        used_tokens += tokens;
+        exemplars.push(Exemplar {
+            file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            content,
+        });
+    }
+
+    Ok(exemplars)
+}
+
+/// Estimated tokens used by loaded exemplars.
+pub fn exemplar_tokens(exemplars: &[Exemplar]) -> usize {
+    exemplars.iter().map(|exemplar| estimate_tokens(&exemplar.content)).sum()
+}
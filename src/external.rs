@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::env;
+use std::ffi::OsString;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::errors::EXIT_FAILURE;
+
+/// External subcommands are executables named this followed by the subcommand, e.g. `magic-quickstart-slides`.
+const EXTERNAL_PREFIX: &str = "magic-quickstart-";
+
+/// An executable on PATH that `magic_quickstart NAME` runs, cargo-style, with the gathered context as JSON on stdin.
+#[derive(Clone)]
+pub struct ExternalSubcommand {
+    pub name: String,
+    pub program: PathBuf,
+    /// Everything after the subcommand's name, passed on as it is.
+    pub args: Vec<OsString>,
+}
+
+impl ExternalSubcommand {
+    /// Runs the subcommand with `input` on stdin, its stdout and stderr going to ours. Returns its exit code.
+    pub fn run(&self, input: &Value) -> Result<i32> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env("MAGIC_QUICKSTART", env::current_exe().unwrap_or_default())
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.program.display()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A subcommand that doesn't need the context may exit without reading it.
+            match stdin.write_all(input.to_string().as_bytes()) {
+                Err(error) if error.kind() != ErrorKind::BrokenPipe => {
+                    return Err(error).with_context(|| format!("Failed to send the context to {}", self.program.display()))
+                }
+                _ => {}
+            }
+        }
+        let status = child
+            .wait()
+            .with_context(|| format!("Failed to wait for {}", self.program.display()))?;
+        Ok(status.code().unwrap_or(EXIT_FAILURE))
+    }
+}
+
+/// The external subcommand the first argument names, if it is neither a flag nor a project and
+/// `magic-quickstart-NAME` is an executable on PATH. Built-in subcommands are checked before this.
+pub fn find_external_subcommand(mut args: impl Iterator<Item = OsString>) -> Option<ExternalSubcommand> {
+    let name = args.next()?.into_string().ok()?;
+    if name.is_empty() || name.starts_with('-') || name.contains(['/', '\\', '.']) || Path::new(&name).exists() {
+        return None;
+    }
+    let file_name = format!("{}{}{}", EXTERNAL_PREFIX, name, env::consts::EXE_SUFFIX);
+    let program = env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| is_executable(path))?;
+    Some(ExternalSubcommand {
+        name,
+        program,
+        args: args.collect(),
+    })
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another instance to finish with shared state before giving up, as the state database does.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a lock held by another instance is tried again.
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// An exclusive lock on a file, released when it is dropped or the process ends, however it ends. Instances of the
+/// tool running at the same time, such as a batch over many repositories, take turns with the state they share this
+/// way, so none of them reads what another is halfway through rewriting or overwrites what another just added.
+/// Threads of one instance take turns too. File systems without locks, such as some network mounts, get none.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Locks `path` itself, creating it if need be, for state that is read and rewritten in place. On Unix a new
+    /// file is only readable by the user.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        Self::wait(file, path)
+    }
+
+    /// Locks `<path>.lock` beside `path`, for state that is replaced by renaming a new version over it, or that is
+    /// kept somewhere without locks of its own, such as the system keyring.
+    pub fn beside(path: &Path) -> Result<Self> {
+        let mut lock_path = OsString::from(path.as_os_str());
+        lock_path.push(".lock");
+        Self::open(&PathBuf::from(lock_path))
+    }
+
+    /// Waits up to `LOCK_TIMEOUT` for the lock on `file`.
+    fn wait(file: File, path: &Path) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(FileLock { file }),
+                Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => thread::sleep(RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => bail!(
+                    "Another magic_quickstart has been using {} for over {} seconds; try again once it has finished",
+                    path.display(),
+                    LOCK_TIMEOUT.as_secs()
+                ),
+                Err(TryLockError::Error(error)) if error.kind() == ErrorKind::Unsupported => return Ok(FileLock { file }),
+                Err(TryLockError::Error(error)) => return Err(error).with_context(|| format!("Failed to lock {}", path.display())),
+            }
+        }
+    }
+
+    /// The locked file's content, for a lock taken with [`FileLock::open`].
+    pub fn read_to_string(&mut self) -> Result<String> {
+        let mut content = String::new();
+        self.file.rewind()?;
+        self.file.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    /// Replaces the locked file's content, for a lock taken with [`FileLock::open`].
+    pub fn replace(&mut self, content: &str) -> Result<()> {
+        self.file.rewind()?;
+        self.file.set_len(0)?;
+        self.file.write_all(content.as_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
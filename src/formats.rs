@@ -0,0 +1,87 @@
+/// Document format of the generated guide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Markdown,
+    AsciiDoc,
+    Rst,
+    /// A roff man page rendered locally from structured sections.
+    Man,
+    /// Plain text converted locally from Markdown.
+    Text,
+    /// An Org mode document converted locally from Markdown.
+    Org,
+}
+
+impl OutputFormat {
+    /// Parses a format name as accepted by `--format` and `OUTPUT_FORMAT`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Some(OutputFormat::Markdown),
+            "asciidoc" | "adoc" => Some(OutputFormat::AsciiDoc),
+            "rst" | "restructuredtext" => Some(OutputFormat::Rst),
+            "man" | "roff" => Some(OutputFormat::Man),
+            "txt" | "text" | "plain" => Some(OutputFormat::Text),
+            "org" | "org-mode" => Some(OutputFormat::Org),
+            _ => None,
+        }
+    }
+
+    /// File extension used for generated files.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+            OutputFormat::AsciiDoc => "adoc",
+            OutputFormat::Rst => "rst",
+            OutputFormat::Man => "1",
+            OutputFormat::Text => "txt",
+            OutputFormat::Org => "org",
+        }
+    }
+
+    /// Human-readable name used in the prompt.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "Markdown",
+            OutputFormat::AsciiDoc => "AsciiDoc",
+            OutputFormat::Rst => "reStructuredText",
+            // Man pages are assembled locally from Markdown slots, and text and Org mode converted from Markdown.
+            OutputFormat::Man | OutputFormat::Text | OutputFormat::Org => "Markdown",
+        }
+    }
+
+    /// Whether the format is rendered locally from structured slots rather than written by the model.
+    pub fn needs_structured_output(self) -> bool {
+        self == OutputFormat::Man
+    }
+
+    /// Whether the model writes Markdown for this format, which is then post-processed (and converted if needed).
+    pub fn written_as_markdown(self) -> bool {
+        matches!(self, OutputFormat::Markdown | OutputFormat::Text | OutputFormat::Org)
+    }
+
+    /// Extra formatting rules given to the model for formats other than Markdown.
+    pub fn instructions(self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Markdown | OutputFormat::Man | OutputFormat::Text | OutputFormat::Org => None,
+            OutputFormat::AsciiDoc => Some(
+                "Write valid AsciiDoc: use `=` for the document title and `==` for sections, `*` for bullet lists, and `[source,<language>]` blocks delimited by `----` for commands.",
+            ),
+            OutputFormat::Rst => Some(
+                "Write valid reStructuredText: underline the title with `=` and sections with `-` (underlines at least as long as the heading), use `-` for bullet lists, and `.. code-block:: <language>` directives with indented bodies for commands.",
+            ),
+        }
+    }
+
+    /// Wraps `text` in a comment that is not rendered in this format.
+    pub fn comment(self, text: &str) -> String {
+        match self {
+            OutputFormat::Markdown => format!("<!-- {} -->", text),
+            OutputFormat::AsciiDoc => format!("// {}", text),
+            OutputFormat::Rst => format!(".. {}", text),
+            OutputFormat::Man => format!(".\\\" {}", text),
+            OutputFormat::Org => format!("# {}", text),
+            // Plain text has no comments; the line is kept as it is.
+            OutputFormat::Text => text.to_string(),
+        }
+    }
+}
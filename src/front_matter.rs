@@ -0,0 +1,63 @@
+use crate::markdown::heading_anchor;
+
+/// Static site generator whose front matter is prepended to Markdown output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrontMatter {
+    Jekyll,
+    Hugo,
+    Docusaurus,
+}
+
+impl FrontMatter {
+    /// Parses a generator name as accepted by `--front-matter` and `FRONT_MATTER`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "jekyll" => Some(FrontMatter::Jekyll),
+            "hugo" => Some(FrontMatter::Hugo),
+            "docusaurus" => Some(FrontMatter::Docusaurus),
+            _ => None,
+        }
+    }
+
+    /// Renders the front matter block: YAML for Jekyll and Docusaurus, TOML for Hugo.
+    pub fn render(self, title: &str, slug: &str, tags: &[String]) -> String {
+        let quoted = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+        let tag_list = tags.iter().map(|tag| quoted(tag)).collect::<Vec<_>>().join(", ");
+        match self {
+            FrontMatter::Jekyll => format!(
+                "---\nlayout: page\ntitle: {}\npermalink: /{}/\ntags: [{}]\n---\n\n",
+                quoted(title),
+                slug,
+                tag_list
+            ),
+            FrontMatter::Hugo => format!(
+                "+++\ntitle = {}\nslug = {}\ntags = [{}]\n+++\n\n",
+                quoted(title),
+                quoted(slug),
+                tag_list
+            ),
+            FrontMatter::Docusaurus => format!("---\ntitle: {}\nslug: /{}\ntags: [{}]\n---\n\n", quoted(title), slug, tag_list),
+        }
+    }
+}
+
+/// Prepends front matter to a Markdown document. The title comes from the first `#` heading (or the project
+/// name), the slug from the project name and document kind, and the tags from the document kind and languages.
+pub fn prepend_front_matter(
+    markdown: &str,
+    front_matter: FrontMatter,
+    project_name: &str,
+    document_kind: &str,
+    languages: &[&str],
+) -> String {
+    let title = markdown
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .unwrap_or(project_name);
+    let slug = heading_anchor(&format!("{} {}", project_name, document_kind)).replace('_', "-");
+    let mut tags = vec![document_kind.to_lowercase()];
+    tags.extend(languages.iter().map(|language| language.to_string()));
+
+    format!("{}{}", front_matter.render(title, &slug, &tags), markdown)
+}
@@ -0,0 +1,910 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::artifacts::{read_request, ArtifactStore};
+use crate::audit::default_audit_log_path;
+use crate::budget::{apply_token_budget, BudgetSplit};
+use crate::ci::{read_ci_config_files, workflow_commands};
+use crate::cli::{Args, Target};
+use crate::command_credentials::strip_history_credentials;
+use crate::config::Config;
+use crate::console::{quiet, reserve_stdout, status};
+use crate::credential_files::{content_reason, credential_store_reason};
+use crate::doc_types::DocType;
+use crate::documents::{deliver, write_document, GeneratedDocument};
+use crate::env_usage::render_env_example;
+use crate::errors::{Error, EXIT_DRIFT, EXIT_INTERRUPTED};
+use crate::exemplars::{exemplar_tokens, load_exemplars};
+use crate::formats::OutputFormat;
+use crate::git::origin_url;
+use crate::history::{history_sources, history_time_zone};
+use crate::i18n::t;
+use crate::injection::{scan_injections, scan_text_injections, InjectionScan};
+use crate::interrupt::reset_interrupted;
+use crate::markdown::detect_fence_languages;
+use crate::models::{ContextBundle, GenerationRequest, HistoryEntry};
+use crate::offline::{local_quickstart_slots, LOCAL_GENERATOR, LOCAL_TEMPLATE};
+use crate::output::generated_section;
+use crate::parsers::{find_project_files, has_exclude_marker, is_excluded};
+use crate::pii::{PiiCounts, PiiScrubber};
+use crate::policy::Policy;
+use crate::prompt::{build_request_payload, PromptOptions};
+use crate::provenance::context_hash;
+use crate::pseudonyms::Pseudonymizer;
+use crate::redaction_report::RedactionReport;
+use crate::remote::clone_repository;
+use crate::retention::enforce_retention;
+use crate::review::review_context;
+use crate::secrets::{scan_secrets, scan_text_secrets, SecretScan};
+use crate::sections::{find_section_heading, load_section_presets};
+use crate::send::{send_requests, PreparedRequests};
+use crate::settings::ConfigFiles;
+use crate::sinks::{configured_sink, SinkKind};
+use crate::sources::{
+    collect_context, Collected, ContextSource, EnvKeySource, GitSource, ManifestSource, ProjectFileSource, ShellHistorySource,
+};
+use crate::structure::module_structure;
+use crate::templates::render_slots;
+use crate::toolchain::detect_toolchain_versions;
+use crate::watch::watch_and_regenerate;
+
+/// Regenerates whenever the project or shell history changes. Once the first run is sent, later runs send and
+/// overwrite without asking again; declining the first run stops watching.
+pub fn watch(mut args: Args) -> Result<()> {
+    let Target::Local(project_dir) = args.target()? else {
+        bail!(Error::Config("--watch only works with a local project directory".to_string()));
+    };
+    let files = ConfigFiles::load(&project_dir, args.profile.as_deref())?;
+    let history_paths: Vec<PathBuf> = history_sources(&files, &args.history)?
+        .into_iter()
+        .map(|source| source.path)
+        .collect();
+    watch_and_regenerate(&project_dir, &history_paths, || match generate(&args)? {
+        Outcome::Generated(written) => {
+            args.yes = true;
+            Ok(Some(written))
+        }
+        Outcome::Declined | Outcome::Interrupted(_) => Ok(None),
+        Outcome::DryRun | Outcome::Disabled | Outcome::Delegated(_) | Outcome::Verified | Outcome::Drifted(_) => Ok(Some(vec![])),
+    })
+}
+
+/// How a generate run ended.
+pub enum Outcome {
+    /// The documents were generated; holds the files that were written.
+    Generated(Vec<PathBuf>),
+    DryRun,
+    /// ENABLE_OPENAI is off, so nothing was sent.
+    Disabled,
+    /// The user declined to send the request.
+    Declined,
+    /// The context was handed to an external subcommand instead; holds its exit code.
+    Delegated(i32),
+    /// Ctrl-C stopped the documents while they were being received; holds the files written with what had arrived.
+    Interrupted(Vec<PathBuf>),
+    /// `--verify-manifest` found every document's inputs as quickstart.lock records them.
+    Verified,
+    /// `--verify-manifest` found documents whose inputs changed since they were generated; holds their types.
+    Drifted(Vec<DocType>),
+}
+
+impl Outcome {
+    /// The outcome as the `--json` record names it, e.g. `generated`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Generated(_) => "generated",
+            Outcome::DryRun => "dry_run",
+            Outcome::Disabled => "disabled",
+            Outcome::Declined => "declined",
+            Outcome::Delegated(_) => "delegated",
+            Outcome::Interrupted(_) => "interrupted",
+            Outcome::Verified => "verified",
+            Outcome::Drifted(_) => "drifted",
+        }
+    }
+
+    /// The exit code for the outcome: an external subcommand's own, `EXIT_INTERRUPTED`, `EXIT_DRIFT`, or 0.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Delegated(code) => *code,
+            Outcome::Interrupted(_) => EXIT_INTERRUPTED,
+            Outcome::Drifted(_) => EXIT_DRIFT,
+            _ => 0,
+        }
+    }
+}
+
+/// Gathers context, builds one request per document, and writes the generated documents. A Ctrl-C during an earlier
+/// run doesn't carry over to this one.
+/// Fails with a configuration error for contradictory settings and with `Error::NothingDetected` if there is no context.
+pub fn generate(args: &Args) -> Result<Outcome> {
+    reset_interrupted();
+    // Progress goes to stderr when stdout carries the run record, generated files, or an external subcommand's output.
+    if args.json || args.sink == Some(SinkKind::Stdout) || args.external.is_some() {
+        reserve_stdout();
+    }
+    if args.ci {
+        quiet();
+    }
+    // Remote repositories are cloned into a temporary directory that is removed when `_checkout` is dropped,
+    // so their guide is written to the current directory instead. Their `.env` and project file are someone else's,
+    // so neither is read.
+    let remote = matches!(args.target()?, Target::Remote(_));
+    let (project_dir, output_dir, _checkout) = match args.target()? {
+        Target::Local(dir) => (dir.clone(), dir, None),
+        Target::Remote(url) => {
+            let checkout = clone_repository(&url)?;
+            let output_dir = env::current_dir().context("Failed to get current working directory")?;
+            (checkout.path().to_path_buf(), output_dir, Some(checkout))
+        }
+    };
+    let output_dir = args.output_dir.clone().unwrap_or(output_dir);
+    status!("{}", t!("project-directory", path = project_dir.display().to_string()));
+
+    // Load configuration from environment variables, .env, and configuration files, letting command line flags take
+    // precedence. A .env file is optional; without one, settings come from the process environment and configuration files.
+    let files = if remote {
+        ConfigFiles::load_user(args.profile.as_deref())?
+    } else {
+        ConfigFiles::load(&project_dir, args.profile.as_deref())?
+    };
+    if files.dotenv_path().is_none() {
+        status!("{}", t!("no-dotenv"));
+    }
+    let policy = Policy::load()?;
+    let mut config = Config::load(&files, &policy)?;
+    config.apply_flags(args)?;
+    // The policy comes last, so neither settings nor flags can raise its cost cap.
+    config.max_cost = policy.cap_cost(config.max_cost);
+    // An in-memory run leaves files it doesn't generate alone, including remembered consent and the audit log,
+    // which other runs on the machine may be using.
+    if args.in_memory {
+        config.remember_consent = false;
+        config.retention_days = None;
+    }
+    if let Some(days) = config.retention_days {
+        let audit_log = match &config.audit_log {
+            Some(path) => path.clone(),
+            None => default_audit_log_path()?,
+        };
+        enforce_retention(&project_dir, &audit_log, days)?;
+    }
+    let history_sources = history_sources(&files, &args.history)?;
+    let time_zone = history_time_zone(&files, &args.history)?;
+    if args.ci && config.max_cost.is_none() && !args.dry_run && !args.verify_manifest && !config.local_only {
+        bail!(Error::Config(
+            "--ci needs a cost cap so an unattended run can't overspend; pass --max-cost or set MAX_COST".to_string()
+        ));
+    }
+    if !remote {
+        config.section_presets = load_section_presets(&project_dir)?;
+    }
+
+    // Documents to generate: every type listed with --batch, otherwise the selected type plus an optional setup script.
+    let doc_types = match &args.batch {
+        Some(batch) => batch.clone(),
+        None if args.with_setup_script && !config.doc_type.writes_project_file() => vec![config.doc_type, DocType::SetupScript],
+        None => vec![config.doc_type],
+    };
+    if let Some(&first) = doc_types.first() {
+        config.doc_type = first;
+    }
+    if config.local_only
+        && (doc_types != [DocType::Quickstart]
+            || args.section.is_some()
+            || !(config.output_format.written_as_markdown() || config.output_format == OutputFormat::Man))
+    {
+        bail!(Error::Config(
+            "LOCAL_ONLY writes a single quickstart as Markdown, text, Org, or a man page (not with --batch or --section)".to_string()
+        ));
+    }
+    if args.split && (config.output_format != OutputFormat::Markdown || config.merge_into_readme || !config.output_sink.writes_files()) {
+        bail!(Error::Config(
+            "--split only works for Markdown output written to new files (not with --merge, or OUTPUT_SINK other than file or git)"
+                .to_string()
+        ));
+    }
+    if args.verify_manifest && config.local_only {
+        bail!(Error::Config(
+            "--verify-manifest checks documents generated by a model, so it can't be combined with LOCAL_ONLY".to_string()
+        ));
+    }
+    if config.output_sink == SinkKind::Clipboard && (doc_types.len() != 1 || doc_types[0] == DocType::Devcontainer) {
+        bail!(Error::Config(
+            "--copy (OUTPUT_SINK=clipboard) works with a single document that is written to one file".to_string()
+        ));
+    }
+    if config.output_sink == SinkKind::Stdout && args.json {
+        bail!(Error::Config(
+            "OUTPUT_SINK=stdout can't be combined with --json, which prints the run record to stdout".to_string()
+        ));
+    }
+    if config.output_sink == SinkKind::Stdout {
+        reserve_stdout();
+    }
+    if (config.output_format != OutputFormat::Markdown || doc_types.iter().all(|doc_type| doc_type.writes_project_file()))
+        && config.merge_into_readme
+    {
+        bail!(Error::Config("Merging is only supported for Markdown documents".to_string()));
+    }
+
+    // Generated files go to the embedding tool's sink, or else to the one OUTPUT_SINK names. Only files in the
+    // output directory can be merged into; other sinks get the generated content itself.
+    let sink = match &args.output_sink {
+        Some(sink) => sink.clone(),
+        None => configured_sink(
+            config.output_sink,
+            config.output_sink_url.as_deref(),
+            &config.allowed_hosts,
+            &output_dir,
+            args.yes,
+        )?,
+    };
+    config.merge_into_readme &= config.output_sink.writes_files();
+
+    // --from-request sends a request kept from an earlier run instead of gathering context, so two models can be
+    // compared on exactly the same payload. It goes through the same checks, confirmation, and audit log.
+    if let Some(path) = &args.from_request {
+        if config.local_only || args.redaction_report {
+            bail!(Error::Config(
+                "--from-request sends a saved request, so it can't be combined with LOCAL_ONLY or redact".to_string()
+            ));
+        }
+        let mut request = read_request(path)?;
+        // The saved request's model and seed are kept unless --model or --seed names another.
+        if args.model.is_some() {
+            request.model = config.openai_model.clone();
+        } else {
+            config.openai_model = request.model.clone();
+        }
+        if args.seed.is_some() {
+            request.seed = config.seed;
+        }
+        status!(
+            "{}",
+            t!(
+                "replaying-request",
+                path = path.display().to_string(),
+                model = request.model.as_str()
+            )
+        );
+        let prepared = PreparedRequests {
+            context: ContextBundle::default(),
+            requests: vec![(config.doc_type, request)],
+            extra_labels: vec!["replayed requests".to_string()],
+            pseudonymizer: None,
+        };
+        return send_requests(args, &mut config, &policy, &project_dir, &output_dir, sink.as_ref(), prepared);
+    }
+
+    // --section regenerates one section of the marker-managed document, so the current one is read up front.
+    let current_document = match &args.section {
+        Some(name) => {
+            if config.output_format != OutputFormat::Markdown || doc_types.len() != 1 || config.doc_type.writes_project_file() || args.split
+            {
+                bail!(Error::Config(
+                    "--section works with a single Markdown document (not with --batch or --split)".to_string()
+                ));
+            }
+            let path = output_dir.join(format!("{}.md", config.doc_type.file_stem()));
+            let existing = fs::read_to_string(&path).unwrap_or_default();
+            let Some(generated) = generated_section(&existing) else {
+                bail!(Error::Config(format!(
+                    "{} has no magic-quickstart markers; generate it with --merge first",
+                    path.display()
+                )));
+            };
+            let Some(heading) = find_section_heading(generated, name) else {
+                bail!(Error::Config(format!(
+                    "No section matching \"{}\" in the generated part of {}",
+                    name,
+                    path.display()
+                )));
+            };
+            config.regenerate_section = Some(heading);
+            Some(generated.to_string())
+        }
+        None => None,
+    };
+
+    // Intermediate artifacts go to .magic_quickstart/ and are removed on exit unless --keep-artifacts is passed.
+    let mut artifacts = ArtifactStore::new(
+        &output_dir,
+        !args.no_artifacts && !args.in_memory && (config.debug_request || args.keep_artifacts),
+        args.keep_artifacts,
+        config.encrypt_artifacts,
+    )?;
+
+    // What each rule keeps out of the requests is collected as it is applied, for `redact --report`.
+    let mut report = RedactionReport::default();
+
+    // Identify project files to be used for context.
+    let mut project_files = if config.include_repository_files {
+        find_project_files(&project_dir, config.max_file_context, config.follow_symlinks)
+    } else {
+        vec![]
+    };
+    // Detector plugins add the files they pick after the built-in detectors', and the same filters apply to them.
+    if config.include_repository_files {
+        for path in config.plugins.detect(&project_dir, config.follow_symlinks)? {
+            if !project_files.contains(&path) {
+                project_files.push(path);
+            }
+        }
+    }
+    // A file whose top comment has the exclude marker, or that looks like a credential store, stays out however it
+    // was found.
+    project_files.retain(|path| {
+        if config.rules.ignores(path) {
+            report.ignored_files.push(path.display().to_string());
+            false
+        } else if is_excluded(&project_dir.join(path)) {
+            report.excluded_files.push(path.display().to_string());
+            false
+        } else if let Some(reason) = credential_store_reason(&project_dir.join(path)) {
+            status!(
+                "{}",
+                t!("credential-file-skipped", path = path.display().to_string(), reason = reason)
+            );
+            report.credential_files.push((path.display().to_string(), reason));
+            false
+        } else {
+            true
+        }
+    });
+    status!("{}", t!("relevant-project-files", files = format!("{:?}", project_files)));
+
+    // Calculate the cutoff time for shell history.
+    let cutoff_time = Utc::now() - Duration::hours(config.time_back_hours);
+    let cutoff = time_zone
+        .at(cutoff_time.timestamp())
+        .map_or(cutoff_time.to_string(), |time| time.to_string());
+    status!("{}", t!("history-cutoff", time = cutoff));
+
+    // Each kind of context comes from its own source, followed by any the embedding tool added. What they gather is
+    // filtered and checked below, whichever source it came from.
+    let history_source = ShellHistorySource {
+        sources: &history_sources,
+        cutoff_timestamp: cutoff_time.timestamp(),
+        time_zone,
+    };
+    let file_source = ProjectFileSource {
+        files: &project_files,
+        transforms: &config.content_transforms,
+    };
+    let manifest_source = ManifestSource {
+        follow_symlinks: config.follow_symlinks,
+    };
+    let env_source = EnvKeySource {
+        follow_symlinks: config.follow_symlinks,
+    };
+    let mut sources: Vec<&dyn ContextSource> = Vec::new();
+    if config.include_shell_history {
+        for source in &history_sources {
+            status!(
+                "{}",
+                t!(
+                    "history-path",
+                    path = source.path.display().to_string(),
+                    shell = source.shell.name()
+                )
+            );
+        }
+        sources.push(&history_source);
+    }
+    if config.include_repository_files {
+        sources.push(&file_source);
+        sources.push(&manifest_source);
+    }
+    // Keys are read for --env-example even when they aren't sent.
+    if config.include_env_file_keys || args.env_example {
+        sources.push(&env_source);
+    }
+    if doc_types.iter().any(|doc_type| doc_type.wants_contributor_context()) {
+        sources.push(&GitSource);
+    }
+    sources.extend(args.context_sources.iter().map(|source| source.as_ref() as &dyn ContextSource));
+    let plugin_sources = config.plugins.context_sources(&project_files);
+    sources.extend(plugin_sources.iter().map(|source| source as &dyn ContextSource));
+    let Collected {
+        mut command_history,
+        mut project_files_content,
+        mut dependency_summary,
+        asset_inventory,
+        mut env_file_keys,
+        git_metadata,
+        labeled,
+    } = collect_context(&sources, &project_dir)?;
+
+    // Blocked commands are dropped, and passwords and tokens typed on the command line are cut out, leaving the
+    // shape of the command.
+    let entries = command_history.len();
+    command_history.retain(|entry| match config.rules.blocked_by(&entry.command) {
+        Some(blocked) => {
+            *report.blocked_commands.entry(blocked.to_string()).or_default() += 1;
+            false
+        }
+        None => true,
+    });
+    if command_history.len() < entries {
+        status!("{}", t!("history-blocked", count = entries - command_history.len()));
+    }
+    report.history_credentials = strip_history_credentials(&mut command_history);
+    let stripped: usize = report.history_credentials.values().sum();
+    if stripped > 0 {
+        status!("{}", t!("history-credentials", count = stripped));
+    }
+
+    // Write a .env.example with placeholders and usage comments when --env-example is passed.
+    if args.env_example && args.dry_run {
+        status!("{}", t!("dry-run-no-env-example"));
+    } else if args.env_example && !args.verify_manifest {
+        let example_path = output_dir.join(".env.example");
+        if deliver(args, sink.as_ref(), &example_path, &render_env_example(&env_file_keys))? {
+            status!("{}", t!("wrote", path = example_path.display().to_string()));
+        }
+    }
+    if !config.include_env_file_keys {
+        env_file_keys.clear();
+    }
+    // .env.example is written locally from every key, but only allowed keys are sent.
+    let key_count = env_file_keys.len();
+    env_file_keys.retain(|entry| {
+        let included = config.rules.includes_env_key(&entry.key);
+        if !included {
+            report.filtered_env_keys.push(entry.key.clone());
+        }
+        included
+    });
+    if env_file_keys.len() < key_count {
+        status!("{}", t!("env-keys-filtered", count = key_count - env_file_keys.len()));
+    }
+
+    // Let the user drop individual items before anything is built when --review-context is passed.
+    if args.review_context {
+        review_context(
+            &mut command_history,
+            &mut project_files,
+            &mut project_files_content,
+            &mut env_file_keys,
+        );
+    }
+
+    // Each document gets the extra context its type asks for, gathered before the budget trims the history.
+    let mut extra_contexts: Vec<_> = doc_types
+        .iter()
+        .map(|&doc_type| {
+            let mut extra_context = gather_extra_context(
+                doc_type,
+                &project_dir,
+                &command_history,
+                git_metadata.as_ref(),
+                config.follow_symlinks,
+            );
+            extra_context.extend(labeled.iter().cloned());
+            extra_context
+        })
+        .collect();
+    // CI configuration files are project files too, so ignore globs (and the policy's forbidden globs), exclude
+    // markers, and the credential store checks apply to them.
+    for (_, value) in extra_contexts.iter_mut().flatten() {
+        if let Some(files) = value.as_array_mut() {
+            files.retain(|file| match file["file_path"].as_str() {
+                Some(path) if config.rules.ignores(Path::new(path)) => {
+                    report.ignored_files.push(path.to_string());
+                    false
+                }
+                Some(path) if has_exclude_marker(file["content"].as_str().unwrap_or_default()) => {
+                    report.excluded_files.push(path.to_string());
+                    false
+                }
+                Some(path) => match content_reason(file["content"].as_str().unwrap_or_default().as_bytes()) {
+                    Some(reason) => {
+                        status!("{}", t!("credential-file-skipped", path = path, reason = reason));
+                        report.credential_files.push((path.to_string(), reason));
+                        false
+                    }
+                    None => true,
+                },
+                None => true,
+            });
+        }
+    }
+    if let Some(document) = current_document {
+        extra_contexts[0].push((
+            "Current document (regenerate only the requested section)".to_string(),
+            json!(document),
+        ));
+    }
+
+    // Files are scanned for credentials once everything that is read from them has been gathered. A report lists
+    // what the scan finds whatever SECRET_SCAN is set to, and says what a run would do about it.
+    if config.secret_scan != SecretScan::Off || args.redaction_report {
+        let redact = config.secret_scan == SecretScan::Redact;
+        let mut findings = Vec::new();
+        for entry in &mut project_files_content {
+            findings.extend(scan_text_secrets(&mut entry.content, &entry.file_path, redact));
+        }
+        for (label, value) in extra_contexts.iter_mut().flatten() {
+            findings.extend(scan_secrets(value, label, redact));
+        }
+        let listing: Vec<String> = findings.iter().map(|finding| format!("  {}", finding)).collect();
+        if !findings.is_empty() && config.secret_scan == SecretScan::Abort && !args.redaction_report {
+            bail!(Error::Config(format!(
+                "{}\n{}",
+                t!("secrets-abort", count = findings.len()),
+                listing.join("\n")
+            )));
+        }
+        if !findings.is_empty() && redact {
+            status!("{}", t!("secrets-redacted", count = findings.len()));
+            status!("{}", listing.join("\n"));
+        }
+        report.secrets = findings;
+    }
+
+    // Text in files that reads like instructions to the model is stripped (or only listed) before it can be sent.
+    // Everything gathered is also sent inside delimited blocks the model is told are data, which covers what the
+    // patterns miss.
+    if config.injection_scan != InjectionScan::Off || args.redaction_report {
+        let strip = config.injection_scan == InjectionScan::Strip;
+        let mut findings = Vec::new();
+        for entry in &mut project_files_content {
+            findings.extend(scan_text_injections(&mut entry.content, &entry.file_path, strip));
+        }
+        for (label, value) in extra_contexts.iter_mut().flatten() {
+            findings.extend(scan_injections(value, label, strip));
+        }
+        if !findings.is_empty() && config.injection_scan != InjectionScan::Off {
+            let id = if strip { "injections-stripped" } else { "injections-flagged" };
+            status!("{}", t!(id, count = findings.len()));
+            let listing: Vec<String> = findings.iter().map(|finding| format!("  {}", finding)).collect();
+            status!("{}", listing.join("\n"));
+        }
+        report.injections = findings;
+    }
+
+    // Few-shot exemplars from EXEMPLARS_DIR are loaded with the rest of the context, so PSEUDONYMIZE and SCRUB_PII
+    // apply to them too. Only requests to a model use them.
+    let to_model = args.external.is_none() && (!config.local_only || args.redaction_report);
+    let mut exemplars = match &config.exemplars_dir {
+        Some(dir) if to_model && doc_types.iter().any(|doc_type| !doc_type.writes_project_file()) => {
+            load_exemplars(dir, config.token_budget)?
+        }
+        _ => vec![],
+    };
+
+    // With PSEUDONYMIZE, identifiers get stable placeholders first, so SCRUB_PII doesn't reduce them to `<host>`.
+    // The placeholders are mapped back in each response before it is written.
+    let mut pseudonymizer = if config.pseudonymize {
+        Some(Pseudonymizer::new(&config.internal_domains)?)
+    } else {
+        None
+    };
+    if let Some(pseudonymizer) = &mut pseudonymizer {
+        for entry in &mut command_history {
+            entry.command = pseudonymizer.pseudonymize(&entry.command);
+        }
+        for entry in &mut project_files_content {
+            entry.content = pseudonymizer.pseudonymize(&entry.content);
+        }
+        for (_, value) in extra_contexts.iter_mut().flatten() {
+            pseudonymizer.pseudonymize_json(value);
+        }
+        for exemplar in &mut exemplars {
+            exemplar.content = pseudonymizer.pseudonymize(&exemplar.content);
+        }
+        if pseudonymizer.count() > 0 {
+            status!("{}", t!("pseudonymized", summary = pseudonymizer.summary()));
+        }
+        report.pseudonyms = Some(pseudonymizer.summary());
+    }
+
+    // With SCRUB_PII, people, machines, and tickets are masked before anything is built from the context. A report
+    // counts what would be masked without it, on copies, so the payloads stay as a run would send them.
+    if config.scrub_pii || args.redaction_report {
+        let scrubber = PiiScrubber::new(&config.internal_domains);
+        let mut counts = PiiCounts::default();
+        for entry in &mut command_history {
+            let scrubbed = scrubber.scrub(&entry.command, &mut counts);
+            if config.scrub_pii {
+                entry.command = scrubbed;
+            }
+        }
+        for entry in &mut project_files_content {
+            let scrubbed = scrubber.scrub(&entry.content, &mut counts);
+            if config.scrub_pii {
+                entry.content = scrubbed;
+            }
+        }
+        for (_, value) in extra_contexts.iter_mut().flatten() {
+            if config.scrub_pii {
+                scrubber.scrub_json(value, &mut counts);
+            } else {
+                scrubber.scrub_json(&mut value.clone(), &mut counts);
+            }
+        }
+        for exemplar in &mut exemplars {
+            let scrubbed = scrubber.scrub(&exemplar.content, &mut counts);
+            if config.scrub_pii {
+                exemplar.content = scrubbed;
+            }
+        }
+        if config.scrub_pii && counts.total() > 0 {
+            status!("{}", t!("pii-masked", summary = counts.summary()));
+        }
+        report.pii = counts;
+    }
+
+    // Artifacts are written only now, so none of them holds what the scans, PSEUDONYMIZE, and SCRUB_PII took out.
+    if config.include_shell_history {
+        artifacts.write_json("command_history.json", &json!(command_history))?;
+    }
+    // Only the keys ENV_KEY_ALLOWLIST and ENV_KEY_DENYLIST let through, which are all that is sent.
+    if config.include_env_file_keys {
+        artifacts.write_json("env_file_keys.json", &json!(env_file_keys))?;
+    }
+    if config.include_repository_files {
+        artifacts.write_json("project_files_content.json", &json!(project_files_content))?;
+        artifacts.write_json("dependency_summary.json", &json!(dependency_summary))?;
+        artifacts.write_json("asset_inventory.json", &json!(asset_inventory))?;
+    }
+    for (label, value) in extra_contexts.iter().flatten() {
+        if let Some((_, file_name)) = EXTRA_CONTEXT_ARTIFACTS.iter().find(|(artifact_label, _)| artifact_label == label) {
+            artifacts.write_json(file_name, value)?;
+        }
+    }
+    if !exemplars.is_empty() {
+        artifacts.write_json("exemplars.json", &json!(exemplars))?;
+    }
+
+    // An external subcommand gets the context as it would be sent, redacted, instead of a model. It runs on this
+    // machine, so there is nothing to agree to; what it does with the context is up to it.
+    if let Some(external) = &args.external {
+        if command_history.is_empty() && project_files.is_empty() && project_files_content.is_empty() && env_file_keys.is_empty() {
+            bail!(Error::NothingDetected);
+        }
+        let mut context = ContextBundle {
+            command_history,
+            project_files,
+            project_files_content,
+            dependency_summary,
+            asset_inventory,
+            env_file_keys,
+            extra_context: extra_contexts.swap_remove(0),
+            exemplars: vec![],
+        };
+        if let Some(hook_script) = &config.hook_script {
+            hook_script.pre_send(config.doc_type, &mut context)?;
+        }
+        let mut input = json!({
+            "project_dir": project_dir,
+            "output_dir": output_dir,
+            "doc_type": config.doc_type.name(),
+            "extension": config.output_format.extension(),
+            "context": context,
+        });
+        let redactions = config.rules.redact_json(&mut input["context"]);
+        if redactions > 0 {
+            status!("{}", t!("redacted", count = redactions, doc_type = config.doc_type.name()));
+        }
+        status!(
+            "{}",
+            t!(
+                "running-external",
+                name = external.name.as_str(),
+                program = external.program.display().to_string()
+            )
+        );
+        return Ok(Outcome::Delegated(external.run(&input)?));
+    }
+
+    // LOCAL_ONLY fills the quickstart template from what was gathered, on this machine; nothing is sent. A report
+    // is about what would be sent, so it skips this.
+    if config.local_only && !args.redaction_report {
+        let project_name = project_dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let slots = local_quickstart_slots(&project_dir, &command_history, &env_file_keys);
+        config.openai_model = LOCAL_GENERATOR.to_string();
+        // An output template or man page is rendered from the slots later, like a model's structured response.
+        let response = if config.output_template.is_some() || config.output_format.needs_structured_output() {
+            slots.to_string()
+        } else {
+            render_slots(LOCAL_TEMPLATE, &slots, &project_name, LOCAL_GENERATOR)
+                .context("Failed to render the built-in quickstart template")?
+        };
+        let response = match &pseudonymizer {
+            Some(pseudonymizer) => pseudonymizer.restore(&response),
+            None => response,
+        };
+        if args.dry_run {
+            println!("{}", response);
+            return Ok(Outcome::DryRun);
+        }
+        let document = GeneratedDocument {
+            doc_type: DocType::Quickstart,
+            context_hash: context_hash(&slots),
+            response,
+            cut: None,
+        };
+        let written = write_document(
+            &config,
+            args,
+            &output_dir,
+            &project_name,
+            &detect_fence_languages(&project_dir),
+            document,
+            sink.as_ref(),
+        )?;
+        return Ok(Outcome::Generated(written));
+    }
+
+    // Split TOKEN_BUDGET between history, files, manifests, and docs according to BUDGET_SPLIT. Exemplars may use up
+    // to a quarter of it; the rest goes to the context.
+    if let Some(token_budget) = config.token_budget {
+        let budget_split = match config.budget_split {
+            Some(budget_split) => budget_split,
+            None => BudgetSplit::parse(config.doc_type.default_budget_split())
+                .with_context(|| Error::Config(format!("The default BUDGET_SPLIT for {} is invalid", config.doc_type.name())))?,
+        };
+        apply_token_budget(
+            token_budget.saturating_sub(exemplar_tokens(&exemplars)),
+            budget_split,
+            &mut command_history,
+            &mut project_files_content,
+            &mut dependency_summary,
+        );
+    }
+
+    // Without any context there is nothing to base a document on, so nothing is sent.
+    if command_history.is_empty() && project_files.is_empty() && project_files_content.is_empty() && env_file_keys.is_empty() {
+        bail!(Error::NothingDetected);
+    }
+
+    // Build one request payload per document from the same gathered context.
+    let context = ContextBundle {
+        command_history,
+        project_files,
+        project_files_content,
+        dependency_summary,
+        asset_inventory,
+        env_file_keys,
+        extra_context: vec![],
+        exemplars,
+    };
+    let prompt_options = PromptOptions {
+        model: config.openai_model.clone(),
+        seed: config.seed,
+        time_back_hours: config.time_back_hours,
+        format: config.output_format,
+        doc_type: config.doc_type,
+        script_shell: config.script_shell,
+        task_runner: config.task_runner,
+        devcontainer_dockerfile: config.devcontainer_dockerfile,
+        language: config.output_language.clone(),
+        audience: config.audience,
+        tone: config.tone,
+        section: config.regenerate_section.clone(),
+        sections: config.section_presets.clone(),
+        prompt_template: config.prompt_template.clone(),
+        structured_output: false,
+    };
+    // Labels of the extra context, which are kinds of data the user agrees to send, like the context itself.
+    let extra_labels: Vec<String> = extra_contexts.iter().flatten().map(|(label, _)| label.clone()).collect();
+    let requests: Vec<(DocType, GenerationRequest)> = doc_types
+        .iter()
+        .zip(extra_contexts)
+        .enumerate()
+        .map(|(index, (&doc_type, extra_context))| {
+            let mut document_context = ContextBundle {
+                extra_context,
+                ..context.clone()
+            };
+            // The hook script sees what the built-in rules left; REDACT_PATTERNS still apply to what it returns.
+            if let Some(hook_script) = &config.hook_script {
+                hook_script.pre_send(doc_type, &mut document_context)?;
+            }
+            let options = PromptOptions {
+                doc_type,
+                structured_output: !doc_type.writes_project_file()
+                    && (config.output_template.is_some() || config.output_format.needs_structured_output()),
+                ..prompt_options.clone()
+            };
+            let mut request_body = build_request_payload(&document_context, &options)?;
+            if args.redaction_report {
+                let matches = config.rules.pattern_matches(&request_body.messages);
+                report.redactions.push((
+                    doc_type,
+                    matches.into_iter().map(|(pattern, count)| (pattern.to_string(), count)).collect(),
+                ));
+            }
+            let redactions = config.rules.redact_messages(&mut request_body.messages);
+            if redactions > 0 {
+                status!("{}", t!("redacted", count = redactions, doc_type = doc_type.name()));
+            }
+            // The first document keeps the request.json name; others are named after their document.
+            let artifact_name = if index == 0 {
+                "request.json".to_string()
+            } else {
+                format!("request_{}.json", doc_type.file_stem().to_lowercase())
+            };
+            artifacts.write_json(&artifact_name, &json!(request_body))?;
+            Ok((doc_type, request_body))
+        })
+        .collect::<Result<_>>()?;
+
+    // `redact --report` prints what the rules found in place of the payloads.
+    if args.redaction_report {
+        report.print(config.secret_scan, config.injection_scan, config.scrub_pii);
+        return Ok(Outcome::DryRun);
+    }
+
+    let prepared = PreparedRequests {
+        context,
+        requests,
+        extra_labels,
+        pseudonymizer,
+    };
+    send_requests(args, &mut config, &policy, &project_dir, &output_dir, sink.as_ref(), prepared)
+}
+
+const CI_CONFIG_LABEL: &str = "CI, lint, and formatter configuration files";
+const GIT_METADATA_LABEL: &str = "Git branches and recent commit subjects (for naming conventions)";
+const MODULE_STRUCTURE_LABEL: &str = "Module structure and local dependencies between modules";
+const TOOLCHAIN_LABEL: &str = "Toolchain versions (pinned by the project and installed locally)";
+
+/// The extra context that is also written as an artifact, by label, with the artifact's file name.
+const EXTRA_CONTEXT_ARTIFACTS: [(&str, &str); 4] = [
+    (CI_CONFIG_LABEL, "ci_config_files.json"),
+    (GIT_METADATA_LABEL, "git_metadata.json"),
+    (MODULE_STRUCTURE_LABEL, "module_structure.json"),
+    (TOOLCHAIN_LABEL, "toolchain_versions.json"),
+];
+
+/// Gathers the extra context a document type asks for: CI configuration and git conventions for contributor
+/// documents, the module graph for architecture documents, and the setup sequence and toolchain for onboarding.
+/// `git_metadata` is what the git source collected, if anything.
+fn gather_extra_context(
+    doc_type: DocType,
+    project_dir: &Path,
+    command_history: &[HistoryEntry],
+    git_metadata: Option<&serde_json::Value>,
+    follow_symlinks: bool,
+) -> Vec<(String, serde_json::Value)> {
+    let mut extra_context = Vec::new();
+
+    // Contributor-facing documents also get CI configuration, lint and test commands, and git conventions.
+    if doc_type.wants_contributor_context() {
+        extra_context.push((CI_CONFIG_LABEL.to_string(), json!(read_ci_config_files(project_dir))));
+        extra_context.push((
+            "Build, test, lint, and format commands observed in shell history (most recent first)".to_string(),
+            json!(workflow_commands(command_history)),
+        ));
+        if let Some(metadata) = git_metadata {
+            extra_context.push((GIT_METADATA_LABEL.to_string(), metadata.clone()));
+        }
+    }
+
+    // Architecture documents also get the module structure and the imports between modules.
+    if doc_type.wants_structure_context() {
+        extra_context.push((MODULE_STRUCTURE_LABEL.to_string(), module_structure(project_dir, follow_symlinks)));
+    }
+
+    // Onboarding runbooks follow the setup sequence in the order it happened, with exact toolchain versions.
+    if doc_type.wants_onboarding_context() {
+        let setup_sequence: Vec<&str> = command_history.iter().rev().map(|entry| entry.command.as_str()).collect();
+        extra_context.push((
+            "Setup sequence from shell history (oldest first)".to_string(),
+            json!(setup_sequence),
+        ));
+        extra_context.push((TOOLCHAIN_LABEL.to_string(), detect_toolchain_versions(project_dir)));
+        if let Some(url) = origin_url(project_dir) {
+            extra_context.push(("Repository clone URL".to_string(), json!(url)));
+        }
+    }
+
+    extra_context
+}
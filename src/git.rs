@@ -0,0 +1,35 @@
+use serde_json::json;
+use std::path::Path;
+use std::process::Command;
+
+/// Number of recent commit subjects included to show message conventions.
+const RECENT_COMMITS: usize = 30;
+
+/// Number of branch names included to show naming conventions.
+const MAX_BRANCHES: usize = 40;
+
+/// Collects branch names and recent commit subjects so conventions can be inferred. Returns `None`
+/// outside a git repository or when git is unavailable.
+pub fn git_metadata(root: &Path) -> Option<serde_json::Value> {
+    let branches = run_git(root, &["branch", "--all", "--format=%(refname:short)"])?;
+    let commits = run_git(root, &["log", "--no-merges", &format!("-{}", RECENT_COMMITS), "--format=%s"])?;
+
+    let mut branch_names: Vec<&str> = branches.lines().filter(|name| !name.ends_with("/HEAD")).collect();
+    branch_names.truncate(MAX_BRANCHES);
+
+    Some(json!({
+        "branches": branch_names,
+        "recent_commit_subjects": commits.lines().collect::<Vec<_>>()
+    }))
+}
+
+/// Returns the URL of the `origin` remote, used for clone instructions.
+pub fn origin_url(root: &Path) -> Option<String> {
+    run_git(root, &["remote", "get-url", "origin"]).map(|url| url.trim().to_string())
+}
+
+/// Runs a git command in `root` and returns its stdout if it succeeded.
+pub fn run_git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(root).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -0,0 +1,561 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+use chrono_tz::Tz;
+use rev_lines::RevLines;
+use rusqlite::{Connection, OpenFlags};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::cli::HistoryArgs;
+use crate::console::status;
+use crate::errors::Error;
+use crate::models::HistoryEntry;
+use crate::settings::{data_home, parse_setting, ConfigFiles};
+
+/// Format of a shell history file, as accepted by `--shell` and HISTORY_SHELL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryShell {
+    /// Detect the format of each file; without files, read the user's shell's history.
+    Auto,
+    /// `: <timestamp>:<duration>;<command>` lines (`setopt EXTENDED_HISTORY`).
+    Zsh,
+    /// Commands preceded by `#<timestamp>` lines (HISTTIMEFORMAT set).
+    Bash,
+    /// `- cmd:` entries with `when:` timestamps.
+    Fish,
+    /// Atuin's SQLite database.
+    Atuin,
+}
+
+/// Values `--shell` and HISTORY_SHELL accept, for error messages.
+pub const HISTORY_SHELL_EXPECTED: &str = "zsh, bash, fish, atuin, or auto";
+
+impl HistoryShell {
+    /// Parses a shell name as accepted by `--shell` and HISTORY_SHELL.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "auto" => Some(HistoryShell::Auto),
+            "zsh" => Some(HistoryShell::Zsh),
+            "bash" => Some(HistoryShell::Bash),
+            "fish" => Some(HistoryShell::Fish),
+            "atuin" => Some(HistoryShell::Atuin),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HistoryShell::Auto => "auto",
+            HistoryShell::Zsh => "zsh",
+            HistoryShell::Bash => "bash",
+            HistoryShell::Fish => "fish",
+            HistoryShell::Atuin => "atuin",
+        }
+    }
+
+    /// Where the shell keeps its history by default. `Auto` has no file of its own.
+    fn default_path(self) -> Option<PathBuf> {
+        let home = PathBuf::from(env::var("HOME").unwrap_or_default());
+        let data_home = data_home().unwrap_or_else(|| home.join(".local").join("share"));
+        match self {
+            HistoryShell::Auto => None,
+            HistoryShell::Zsh => Some(home.join(".zsh_history")),
+            HistoryShell::Bash => Some(home.join(".bash_history")),
+            HistoryShell::Fish => Some(data_home.join("fish").join("fish_history")),
+            HistoryShell::Atuin => Some(
+                env::var("ATUIN_DB_PATH")
+                    .ok()
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| data_home.join("atuin").join("history.db")),
+            ),
+        }
+    }
+}
+
+/// The time zone history timestamps are shown in, as accepted by `--timezone` and TIMEZONE.
+#[derive(Clone, Copy, Debug)]
+pub enum HistoryTimeZone {
+    /// The system's local time zone, which honors the TZ environment variable.
+    Local,
+    /// An IANA time zone such as `Europe/Berlin`, or `UTC`.
+    Named(Tz),
+}
+
+/// Values `--timezone` and TIMEZONE accept, for error messages.
+pub const TIMEZONE_EXPECTED: &str = "local or an IANA time zone such as Europe/Berlin or UTC";
+
+impl HistoryTimeZone {
+    /// Parses a time zone as accepted by `--timezone` and TIMEZONE; an empty value is the local time zone.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "" => Some(HistoryTimeZone::Local),
+            name if name.eq_ignore_ascii_case("local") => Some(HistoryTimeZone::Local),
+            name if name.eq_ignore_ascii_case("utc") => Some(HistoryTimeZone::Named(Tz::UTC)),
+            name => name.parse::<Tz>().ok().map(HistoryTimeZone::Named),
+        }
+    }
+
+    /// The wall-clock time of a Unix timestamp in this time zone.
+    pub fn at(self, timestamp: i64) -> Option<DateTime<FixedOffset>> {
+        let time = Utc.timestamp_opt(timestamp, 0).single()?;
+        Some(match self {
+            HistoryTimeZone::Local => time.with_timezone(&chrono::Local).fixed_offset(),
+            HistoryTimeZone::Named(tz) => time.with_timezone(&tz).fixed_offset(),
+        })
+    }
+}
+
+/// A history file and the format it is in, which is never `Auto`.
+#[derive(Clone, Debug)]
+pub struct HistorySource {
+    pub path: PathBuf,
+    pub shell: HistoryShell,
+}
+
+/// The history files to read: the `--history` paths or HISTORY_FILES (separated like PATH), in the `--shell` or
+/// HISTORY_SHELL format or each file's detected one. Without files, the default history file of that shell, or
+/// with `auto` of the user's login shell (falling back to the first default file that exists, then zsh's).
+pub fn history_sources(files: &ConfigFiles, args: &HistoryArgs) -> Result<Vec<HistorySource>> {
+    let shell = match args.shell {
+        Some(shell) => shell,
+        None => parse_setting(files, "HISTORY_SHELL", HISTORY_SHELL_EXPECTED, HistoryShell::parse)?.unwrap_or(HistoryShell::Auto),
+    };
+    let paths: Vec<PathBuf> = if args.files.is_empty() {
+        files
+            .get("HISTORY_FILES")
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default()
+    } else {
+        args.files.clone()
+    };
+
+    if !paths.is_empty() {
+        return Ok(paths
+            .into_iter()
+            .map(|path| {
+                let shell = if shell == HistoryShell::Auto { detect_shell(&path) } else { shell };
+                HistorySource { path, shell }
+            })
+            .collect());
+    }
+    let shell = match shell {
+        HistoryShell::Auto => login_shell()
+            .filter(|shell| shell.default_path().is_some_and(|path| path.is_file()))
+            .or_else(|| {
+                [HistoryShell::Zsh, HistoryShell::Bash, HistoryShell::Fish, HistoryShell::Atuin]
+                    .into_iter()
+                    .find(|shell| shell.default_path().is_some_and(|path| path.is_file()))
+            })
+            .unwrap_or(HistoryShell::Zsh),
+        shell => shell,
+    };
+    Ok(shell.default_path().map(|path| HistorySource { path, shell }).into_iter().collect())
+}
+
+/// The time zone for history timestamps: `--timezone`, else TIMEZONE, else the local one.
+pub fn history_time_zone(files: &ConfigFiles, args: &HistoryArgs) -> Result<HistoryTimeZone> {
+    Ok(match args.timezone {
+        Some(time_zone) => time_zone,
+        None => parse_setting(files, "TIMEZONE", TIMEZONE_EXPECTED, HistoryTimeZone::parse)?.unwrap_or(HistoryTimeZone::Local),
+    })
+}
+
+/// The shell named by $SHELL, if its history format is supported.
+fn login_shell() -> Option<HistoryShell> {
+    let shell = env::var("SHELL").ok()?;
+    match HistoryShell::parse(Path::new(&shell).file_name()?.to_str()?)? {
+        HistoryShell::Auto | HistoryShell::Atuin => None,
+        shell => Some(shell),
+    }
+}
+
+/// Guesses the format of a history file from its first lines: an SQLite header is atuin, `- cmd:` is fish, and
+/// `: <timestamp>:` is zsh. Anything else, including a missing file, is read as bash.
+fn detect_shell(path: &Path) -> HistoryShell {
+    let Ok(mut file) = File::open(path) else {
+        return HistoryShell::Bash;
+    };
+    let mut header = [0u8; 16];
+    if file.read_exact(&mut header).is_ok() && header.starts_with(b"SQLite format 3") {
+        return HistoryShell::Atuin;
+    }
+    let Ok(file) = File::open(path) else {
+        return HistoryShell::Bash;
+    };
+    for line in BufReader::new(file).split(b'\n').take(20).flatten() {
+        let line = String::from_utf8_lossy(&line);
+        if line.starts_with("- cmd:") {
+            return HistoryShell::Fish;
+        }
+        if parse_zsh_history(&line).is_some() {
+            return HistoryShell::Zsh;
+        }
+    }
+    HistoryShell::Bash
+}
+
+/// Reads the entries newer than `cutoff_timestamp` from every source, newest first, as JSON values with the
+/// command, its time in `time_zone`, how long ago that was, which day it was, and its exit code (zsh's duration
+/// field, kept for compatibility; atuin's exit code; empty for bash and fish, which don't record one).
+pub fn read_history(sources: &[HistorySource], cutoff_timestamp: i64, time_zone: HistoryTimeZone) -> Result<Vec<HistoryEntry>> {
+    let mut entries = Vec::new();
+    for source in sources {
+        let read = match source.shell {
+            HistoryShell::Zsh | HistoryShell::Auto => read_zsh_history(&source.path, cutoff_timestamp),
+            HistoryShell::Bash => read_bash_history(&source.path, cutoff_timestamp),
+            HistoryShell::Fish => read_fish_history(&source.path, cutoff_timestamp),
+            HistoryShell::Atuin => read_atuin_history(&source.path, cutoff_timestamp),
+        };
+        entries.extend(read.with_context(|| {
+            Error::History(format!(
+                "Failed to read {} history from {}",
+                source.shell.name(),
+                source.path.display()
+            ))
+        })?);
+    }
+    // A stable sort keeps each file's own order for commands run in the same second.
+    entries.sort_by_key(|(timestamp, _, _)| std::cmp::Reverse(*timestamp));
+    Ok(entries
+        .into_iter()
+        .map(|(timestamp, exit_code, command)| history_entry(timestamp, exit_code, command, time_zone))
+        .collect())
+}
+
+/// A history entry as it is sent and shown. The weekday and `day` (today, yesterday, or how many days ago, by the
+/// calendar in `time_zone`) let the model tell an earlier setup session from today's work.
+fn history_entry(timestamp: i64, exit_code: String, command: String, time_zone: HistoryTimeZone) -> HistoryEntry {
+    let (command_time, day) = match time_zone.at(timestamp) {
+        Some(time) => (time.format("%a %Y-%m-%d %H:%M:%S %:z").to_string(), day_hint(time, time_zone)),
+        None => ("Invalid timestamp".to_string(), String::new()),
+    };
+
+    let elapsed_secs = Utc::now().timestamp() - timestamp;
+    let relative_duration = Duration::seconds(elapsed_secs);
+    let formatted_relative_time = humantime::format_duration(relative_duration.to_std().unwrap_or_default()).to_string();
+
+    HistoryEntry {
+        timestamp: command_time,
+        relative_time: formatted_relative_time,
+        day,
+        exit_code,
+        command,
+    }
+}
+
+/// Which day `time` was, relative to today in `time_zone`: `today`, `yesterday`, or e.g. `Monday, 3 days ago`.
+fn day_hint(time: DateTime<FixedOffset>, time_zone: HistoryTimeZone) -> String {
+    let Some(now) = time_zone.at(Utc::now().timestamp()) else {
+        return String::new();
+    };
+    match (now.date_naive() - time.date_naive()).num_days() {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        days => format!("{}, {} days ago", time.format("%A"), days),
+    }
+}
+
+/// An entry as read from a history file: timestamp, exit code, and command.
+type RawEntry = (i64, String, String);
+
+/// Reads a zsh history file from the end, stopping at the first entry older than the cutoff or not in extended
+/// format, so only the entries that are sent are ever held, however long the file is.
+fn read_zsh_history(path: &Path, cutoff_timestamp: i64) -> Result<Vec<RawEntry>> {
+    let mut command_history = Vec::new();
+    for entry in ZshEntries::new(File::open(path)?) {
+        match entry {
+            Some(entry) if entry.0 >= cutoff_timestamp => command_history.push(entry),
+            // Entries are newest first, so everything further back is older still.
+            _ => break,
+        }
+    }
+    Ok(command_history)
+}
+
+/// The entries of a zsh history file, newest first, read line by line from the end. zsh ends every line of a
+/// multi-line command but the last with a backslash, so lines are gathered until the one that starts the entry.
+/// Yields `None` for an entry that isn't in extended format.
+struct ZshEntries<R: Read + Seek> {
+    lines: RevLines<R>,
+    /// The lines of the entry being gathered, last line first.
+    pending: Vec<String>,
+}
+
+impl<R: Read + Seek> ZshEntries<R> {
+    fn new(reader: R) -> Self {
+        ZshEntries {
+            lines: RevLines::new(reader),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Parses the gathered lines as one entry, joining continued lines with newlines.
+    fn take_entry(&mut self) -> Option<RawEntry> {
+        let lines: Vec<String> = self.pending.drain(..).rev().collect();
+        let last = lines.len() - 1;
+        let text: Vec<&str> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                if index < last {
+                    line.strip_suffix('\\').unwrap_or(line)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        parse_zsh_history(&text.join("\n"))
+    }
+}
+
+impl<R: Read + Seek> Iterator for ZshEntries<R> {
+    type Item = Option<RawEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next() {
+                // A line that doesn't continue onto the next one ends the entry before it, so the gathered one is
+                // complete.
+                Some(Ok(line)) if !self.pending.is_empty() && !line.ends_with('\\') => {
+                    let entry = self.take_entry();
+                    self.pending.push(line);
+                    return Some(entry);
+                }
+                Some(Ok(line)) if self.pending.is_empty() && line.trim().is_empty() => {}
+                Some(Ok(line)) => self.pending.push(line),
+                Some(Err(_)) => status!("Skipping invalid UTF-8 sequence"),
+                None if self.pending.is_empty() => return None,
+                None => return Some(self.take_entry()),
+            }
+        }
+    }
+}
+
+/// Parses a line from the zsh history and returns a tuple of (timestamp, exit_code, command).
+fn parse_zsh_history(entry: &str) -> Option<RawEntry> {
+    if !entry.starts_with(':') {
+        return None;
+    }
+
+    let parts: Vec<&str> = entry.splitn(3, ':').collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let timestamp_str = parts[1].trim();
+    let command_part = parts[2];
+
+    let timestamp = match timestamp_str.parse::<i64>() {
+        Ok(t) => t,
+        Err(_) => {
+            status!("Failed to parse timestamp: {}", timestamp_str);
+            return None;
+        }
+    };
+
+    let command_parts: Vec<&str> = command_part.splitn(2, ';').collect();
+    if command_parts.len() < 2 {
+        return None;
+    }
+
+    let exit_code = command_parts[0].trim().to_string();
+    let command = command_parts[1].trim().to_string();
+
+    Some((timestamp, exit_code, command))
+}
+
+/// Reads a bash history file written with HISTTIMEFORMAT set, where each command follows a `#<timestamp>` line.
+/// Commands without a timestamp can't be placed in time and are skipped; lines up to the next timestamp belong
+/// to the same command.
+fn read_bash_history(path: &Path, cutoff_timestamp: i64) -> Result<Vec<RawEntry>> {
+    let content = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let mut entries: Vec<RawEntry> = Vec::new();
+    for line in content.lines() {
+        if let Some(timestamp) = bash_timestamp(line) {
+            entries.push((timestamp, String::new(), String::new()));
+        } else if let Some((_, _, command)) = entries.last_mut() {
+            if !command.is_empty() {
+                command.push('\n');
+            }
+            command.push_str(line);
+        }
+    }
+    entries.retain(|(timestamp, _, command)| *timestamp >= cutoff_timestamp && !command.trim().is_empty());
+    entries.reverse();
+    Ok(entries)
+}
+
+/// The timestamp of a bash `#<timestamp>` line.
+fn bash_timestamp(line: &str) -> Option<i64> {
+    line.strip_prefix('#')
+        .filter(|digits| !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()))?
+        .parse()
+        .ok()
+}
+
+/// Reads a fish history file: `- cmd: <command>` entries, each followed by `  when: <timestamp>`.
+fn read_fish_history(path: &Path, cutoff_timestamp: i64) -> Result<Vec<RawEntry>> {
+    let content = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let mut entries: Vec<RawEntry> = Vec::new();
+    let mut command = None;
+    for line in content.lines() {
+        if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            command = Some(unescape_fish(cmd));
+        } else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+            if let (Some(command), Ok(timestamp)) = (command.take(), when.trim().parse::<i64>()) {
+                if timestamp >= cutoff_timestamp {
+                    entries.push((timestamp, String::new(), command));
+                }
+            }
+        }
+    }
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Undoes fish's escaping of backslashes and newlines in history commands.
+fn unescape_fish(command: &str) -> String {
+    let mut unescaped = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Reads atuin's history database, leaving out entries deleted in atuin. Timestamps there are in nanoseconds.
+fn read_atuin_history(path: &Path, cutoff_timestamp: i64) -> Result<Vec<RawEntry>> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+    let mut statement = connection
+        .prepare("SELECT timestamp, exit, command FROM history WHERE deleted_at IS NULL AND timestamp >= ?1 ORDER BY timestamp DESC")?;
+    let rows = statement.query_map([cutoff_timestamp.saturating_mul(1_000_000_000)], |row| {
+        Ok((
+            row.get::<_, i64>(0)? / 1_000_000_000,
+            row.get::<_, i64>(1)?.to_string(),
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Why a history file can't be used as it is, with how to fix it, or `None` if its newest entries can be read.
+/// A missing file is not reported here.
+pub fn format_problem(source: &HistorySource) -> Option<String> {
+    let path = source.path.display();
+    match source.shell {
+        HistoryShell::Zsh | HistoryShell::Auto => (!newest_zsh_history_entry_parses(&source.path)).then(|| {
+            format!(
+                "{} is not in extended format; add `setopt EXTENDED_HISTORY` to ~/.zshrc (see the README)",
+                path
+            )
+        }),
+        HistoryShell::Bash => {
+            let content = fs::read(&source.path).unwrap_or_default();
+            let has_timestamps = String::from_utf8_lossy(&content).lines().any(|line| bash_timestamp(line).is_some());
+            (!has_timestamps).then(|| format!("{} has no timestamps; add `export HISTTIMEFORMAT='%F %T '` to ~/.bashrc", path))
+        }
+        HistoryShell::Fish => None,
+        HistoryShell::Atuin => read_atuin_history(&source.path, i64::MAX / 1_000_000_000)
+            .err()
+            .map(|error| format!("{} can't be read as an atuin database ({})", path, error)),
+    }
+}
+
+/// Whether the newest entry of the history file is in zsh's extended format (`: <timestamp>:<duration>;<command>`),
+/// which the history parser needs. Blank trailing lines are skipped.
+fn newest_zsh_history_entry_parses(history_path: &Path) -> bool {
+    let Ok(file) = File::open(history_path) else {
+        return false;
+    };
+    ZshEntries::new(file).next().flatten().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn zsh_entries(history: &str) -> Vec<Option<RawEntry>> {
+        ZshEntries::new(Cursor::new(history.as_bytes().to_vec())).collect()
+    }
+
+    fn entry(timestamp: i64, duration: &str, command: &str) -> Option<RawEntry> {
+        Some((timestamp, duration.to_string(), command.to_string()))
+    }
+
+    #[test]
+    fn parses_extended_history_metadata() {
+        assert_eq!(
+            parse_zsh_history(": 1700000000:12;cargo build"),
+            entry(1700000000, "12", "cargo build")
+        );
+        // Only the first `;` ends the metadata.
+        assert_eq!(parse_zsh_history(": 1700000000:0;cd src; ls"), entry(1700000000, "0", "cd src; ls"));
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert_eq!(parse_zsh_history("cargo build"), None);
+        assert_eq!(parse_zsh_history(": 1700000000"), None);
+        assert_eq!(parse_zsh_history(": soon:0;cargo build"), None);
+        assert_eq!(parse_zsh_history(": 1700000000:0"), None);
+    }
+
+    #[test]
+    fn reads_entries_newest_first() {
+        let history = ": 1700000000:0;cargo build\n: 1700000010:1;cargo test\n\n";
+        assert_eq!(
+            zsh_entries(history),
+            vec![entry(1700000010, "1", "cargo test"), entry(1700000000, "0", "cargo build")]
+        );
+    }
+
+    #[test]
+    fn joins_continued_lines() {
+        let history = ": 1700000000:0;docker run \\\n  --rm \\\n  alpine\n: 1700000010:0;ls\n";
+        assert_eq!(
+            zsh_entries(history),
+            vec![
+                entry(1700000010, "0", "ls"),
+                entry(1700000000, "0", "docker run \n  --rm \n  alpine")
+            ]
+        );
+    }
+
+    #[test]
+    fn yields_none_for_lines_not_in_extended_format() {
+        let history = ": 1700000000:0;cargo build\nplain command\n: 1700000010:0;ls\n";
+        assert_eq!(
+            zsh_entries(history),
+            vec![entry(1700000010, "0", "ls"), None, entry(1700000000, "0", "cargo build")]
+        );
+    }
+
+    #[test]
+    fn stops_at_the_cutoff_and_at_malformed_entries() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(
+            file.path(),
+            ": 1600000000:0;old\nplain command\n: 1700000000:0;cargo build\n: 1700000010:0;ls\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_zsh_history(file.path(), 1700000005).unwrap(),
+            vec![(1700000010, "0".to_string(), "ls".to_string())]
+        );
+        assert_eq!(read_zsh_history(file.path(), 0).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(feature = "hook-scripts")]
+use anyhow::{anyhow, Context};
+use anyhow::{bail, Result};
+#[cfg(feature = "hook-scripts")]
+use rhai::serde::{from_dynamic, to_dynamic};
+#[cfg(feature = "hook-scripts")]
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use std::path::Path;
+#[cfg(feature = "hook-scripts")]
+use std::path::PathBuf;
+
+#[cfg(feature = "hook-scripts")]
+use crate::console::status;
+use crate::doc_types::DocType;
+use crate::errors::Error;
+use crate::models::ContextBundle;
+
+/// Operations a hook may run per call before it is stopped, so a script stuck in a loop can't hang a run.
+#[cfg(feature = "hook-scripts")]
+const HOOK_MAX_OPERATIONS: u64 = 50_000_000;
+
+/// How deeply a hook's functions may call one another.
+#[cfg(feature = "hook-scripts")]
+const HOOK_MAX_CALL_LEVELS: usize = 64;
+
+/// The longest string a hook may build, in bytes, so it can't exhaust memory. Documents and file contents passed in
+/// are far smaller, since requests are capped by MAX_PAYLOAD_BYTES.
+#[cfg(feature = "hook-scripts")]
+const HOOK_MAX_STRING_BYTES: usize = 16 * 1024 * 1024;
+
+/// The most entries an array or object a hook builds may have.
+#[cfg(feature = "hook-scripts")]
+const HOOK_MAX_ENTRIES: usize = 1_000_000;
+
+/// A Rhai script from HOOK_SCRIPT, for rules the settings can't express. It may define either hook:
+///
+/// - `pre_send(context, doc_type)`, given each document's context after the built-in filters, scans, and masking,
+///   returns the context to build the request from (or nothing to keep it);
+/// - `post_generate(document, doc_type)`, given each generated document before it is formatted and written,
+///   returns the document to write.
+#[cfg(feature = "hook-scripts")]
+pub struct HookScript {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+}
+
+#[cfg(feature = "hook-scripts")]
+impl HookScript {
+    /// Compiles the script at `path`; one that can't be read or compiled is a configuration error.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(HOOK_MAX_OPERATIONS)
+            .set_max_call_levels(HOOK_MAX_CALL_LEVELS)
+            .set_max_string_size(HOOK_MAX_STRING_BYTES)
+            .set_max_array_size(HOOK_MAX_ENTRIES)
+            .set_max_map_size(HOOK_MAX_ENTRIES);
+        // `print` and `debug` are progress messages, so they stay off stdout when it is reserved.
+        engine.on_print(|text| status!("{}", text));
+        engine.on_debug(|text, _, _| status!("{}", text));
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|error| anyhow!("{}", error))
+            .with_context(|| Error::Config(format!("Failed to load the hook script {}", path.display())))?;
+        Ok(HookScript {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+        })
+    }
+
+    fn defines(&self, hook: &str, params: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == hook && function.params.len() == params)
+    }
+
+    /// Calls `hook`. A script that goes over the engine's limits is a configuration error, like one that can't
+    /// be compiled.
+    fn call(&self, hook: &str, args: (Dynamic, Dynamic)) -> Result<Dynamic> {
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, hook, args)
+            .map_err(|error| {
+                let over_limits = matches!(
+                    error.unwrap_inner(),
+                    EvalAltResult::ErrorTooManyOperations(_) | EvalAltResult::ErrorStackOverflow(_) | EvalAltResult::ErrorDataTooLarge(..)
+                );
+                let cause = anyhow!("{}", error);
+                if over_limits {
+                    cause.context(Error::Config(format!(
+                        "The hook script {} was stopped in `{}` for going over its limits, as a loop that never ends or data that keeps growing does",
+                        self.path.display(),
+                        hook
+                    )))
+                } else {
+                    cause.context(format!("The hook script {} failed in `{}`", self.path.display(), hook))
+                }
+            })
+    }
+
+    /// Runs `pre_send` over the context a document's request is built from.
+    pub fn pre_send(&self, doc_type: DocType, context: &mut ContextBundle) -> Result<()> {
+        if !self.defines("pre_send", 2) {
+            return Ok(());
+        }
+        let output = self.call("pre_send", (to_dynamic(&*context)?, doc_type.name().into()))?;
+        if !output.is_unit() {
+            *context = from_dynamic(&output).map_err(|error| anyhow!("{}", error)).with_context(|| {
+                format!(
+                    "The hook script {} returned a context from `pre_send` that isn't one",
+                    self.path.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Runs `post_generate` over a generated document.
+    pub fn post_generate(&self, doc_type: DocType, document: String) -> Result<String> {
+        if !self.defines("post_generate", 2) {
+            return Ok(document);
+        }
+        let output = self.call("post_generate", (document.into(), doc_type.name().into()))?;
+        let Ok(document) = output.into_string() else {
+            bail!(
+                "The hook script {} returned something other than a string from `post_generate`",
+                self.path.display()
+            );
+        };
+        Ok(document)
+    }
+}
+
+/// Without the `hook-scripts` feature no script can be loaded, so there is never one to run.
+#[cfg(not(feature = "hook-scripts"))]
+pub enum HookScript {}
+
+#[cfg(not(feature = "hook-scripts"))]
+impl HookScript {
+    /// Refuses the script at `path`: this build can't run hook scripts.
+    pub fn load(path: &Path) -> Result<Self> {
+        bail!(Error::Config(format!(
+            "Can't load the hook script {}: this build of magic_quickstart was made without the `hook-scripts` feature",
+            path.display()
+        )))
+    }
+
+    pub fn pre_send(&self, _doc_type: DocType, _context: &mut ContextBundle) -> Result<()> {
+        match *self {}
+    }
+
+    pub fn post_generate(&self, _doc_type: DocType, _document: String) -> Result<String> {
+        match *self {}
+    }
+}
@@ -0,0 +1,111 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::env;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// The compiled-in messages for each locale, as Fluent files under `locales/`. English is complete and is used for
+/// any message a translation leaves out. A translation is added by copying `locales/en-US/main.ftl` to
+/// `locales/<locale>/main.ftl`, translating it, and listing it here.
+const LOCALES: &[(&str, &str)] = &[("en-US", include_str!("../locales/en-US/main.ftl"))];
+
+/// The locale every message exists in.
+const FALLBACK_LOCALE: &str = "en-US";
+
+/// The bundle for the user's locale, if there is a translation for it, and the English one.
+struct Messages {
+    translated: Option<FluentBundle<FluentResource>>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+static MESSAGES: OnceLock<Messages> = OnceLock::new();
+
+fn bundle(locale: &str, source: &str) -> FluentBundle<FluentResource> {
+    let language: LanguageIdentifier = locale.parse().expect("locales in LOCALES are valid language identifiers");
+    let resource = FluentResource::try_new(source.to_string()).expect("bundled message files are valid Fluent");
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Unicode isolation marks around variables show up as stray characters in terminals and logs.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .expect("bundled message files don't define a message twice");
+    bundle
+}
+
+/// The user's language from LC_ALL, LC_MESSAGES, or LANG, the first one set, as other command line tools read it:
+/// `de_DE.UTF-8` is `de-DE`. `C` and `POSIX` aren't languages, so they mean the default.
+fn user_language() -> Option<LanguageIdentifier> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))?;
+    let name = value.split(['.', '@']).next()?.replace('_', "-");
+    name.parse().ok()
+}
+
+/// Picks the translation for the user's language: the exact locale if there is one, else another one for the same
+/// language, so `de-AT` uses `de-DE`.
+fn translation(language: &LanguageIdentifier) -> Option<&'static (&'static str, &'static str)> {
+    let parsed = || {
+        LOCALES
+            .iter()
+            .filter_map(|entry| Some((entry, entry.0.parse::<LanguageIdentifier>().ok()?)))
+    };
+    parsed()
+        .find(|(_, locale)| locale == language)
+        .or_else(|| parsed().find(|(_, locale)| locale.language == language.language))
+        .map(|(entry, _)| entry)
+        .filter(|(locale, _)| *locale != FALLBACK_LOCALE)
+}
+
+fn messages() -> &'static Messages {
+    MESSAGES.get_or_init(|| {
+        let (_, fallback) = LOCALES
+            .iter()
+            .find(|(locale, _)| *locale == FALLBACK_LOCALE)
+            .expect("the fallback locale is bundled");
+        Messages {
+            translated: user_language()
+                .as_ref()
+                .and_then(translation)
+                .map(|(locale, source)| bundle(locale, source)),
+            fallback: bundle(FALLBACK_LOCALE, fallback),
+        }
+    })
+}
+
+/// Formats the message `id` in the user's language, falling back to English, and to the id itself for a message
+/// that doesn't exist. Use the `t!` macro rather than calling this directly.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let messages = messages();
+    for bundle in messages.translated.iter().chain([&messages.fallback]) {
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = Vec::new();
+            return bundle.format_pattern(pattern, args, &mut errors).into_owned();
+        }
+    }
+    id.to_string()
+}
+
+/// Whether `answer` means yes to a confirmation question: `y` or `yes`, or a yes in the user's language.
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    ["y", "yes"].contains(&answer.as_str())
+        || message("confirm-answers", None)
+            .split_whitespace()
+            .any(|yes| yes.to_lowercase() == answer)
+}
+
+/// A user-facing message in the user's language: `t!("nothing-sent")`, or with variables,
+/// `t!("wrote", path = path.display().to_string())`.
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
+
+pub(crate) use t;
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde_json::json;
+
+use crate::console::stdout_reserved;
+use crate::errors::EXIT_INTERRUPTED;
+use crate::i18n::t;
+
+/// Files and directories to remove if the run is interrupted, because the code that would normally remove them
+/// when it finishes never gets to run.
+static CLEANUP: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// How many responses are being received, each counted by a `Receiving` guard.
+static RECEIVING: AtomicUsize = AtomicUsize::new(0);
+
+/// Set by the first Ctrl-C while responses are being received.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Handles Ctrl-C and SIGTERM. While responses are being received, the first one only sets `interrupted`, so the
+/// requests stop and what has arrived is written, marked as truncated. Otherwise, or on a second Ctrl-C, it removes
+/// everything registered with `remove_on_interrupt` and exits with `EXIT_INTERRUPTED`, dropping any request in
+/// flight. With `--json`, an `interrupted` record is printed first. Only `main` installs it: exiting would take down a
+/// program that embeds the library. Installing it again keeps the one installed.
+pub fn install_interrupt_handler() -> Result<()> {
+    let installed = ctrlc::set_handler(|| {
+        if RECEIVING.load(Ordering::SeqCst) > 0 && !INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("\n{}", t!("interrupt-keeping-partial"));
+            return;
+        }
+        eprintln!("\n{}", t!("interrupted"));
+        if stdout_reserved() {
+            println!("{}", json!({"outcome": "interrupted", "exit_code": EXIT_INTERRUPTED}));
+        }
+        let paths = CLEANUP.lock().map(|paths| paths.clone()).unwrap_or_default();
+        for path in paths.iter().rev() {
+            if path.is_dir() {
+                let _ = fs::remove_dir_all(path);
+            } else {
+                let _ = fs::remove_file(path);
+            }
+        }
+        std::process::exit(EXIT_INTERRUPTED);
+    });
+    match installed {
+        Err(ctrlc::Error::MultipleHandlers) => Ok(()),
+        installed => installed.context("Failed to install the Ctrl-C handler"),
+    }
+}
+
+/// Removes `path` (a file, or a directory with everything in it) if the run is interrupted.
+pub fn remove_on_interrupt(path: &Path) {
+    if let Ok(mut paths) = CLEANUP.lock() {
+        paths.push(path.to_path_buf());
+    }
+}
+
+/// Stops removing `path` on interrupt, once it has been cleaned up (or is meant to stay).
+pub fn forget_on_interrupt(path: &Path) {
+    if let Ok(mut paths) = CLEANUP.lock() {
+        paths.retain(|registered| registered != path);
+    }
+}
+
+/// Whether Ctrl-C stopped the responses being received.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Forgets an earlier Ctrl-C, so a run starts uninterrupted even after a previous one in the same process was stopped.
+pub fn reset_interrupted() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Marks a response as being received for as long as it lives, so Ctrl-C stops it instead of exiting.
+pub struct Receiving;
+
+impl Receiving {
+    pub fn start() -> Self {
+        RECEIVING.fetch_add(1, Ordering::SeqCst);
+        Receiving
+    }
+}
+
+impl Drop for Receiving {
+    fn drop(&mut self) {
+        RECEIVING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::assets::present_asset_directories;
+use crate::audit::default_audit_log_path;
+use crate::ci::ci_config_paths;
+use crate::endpoints::{parse_base_url, url_host, DEFAULT_BASE_URL};
+use crate::history::HistorySource;
+use crate::lockfiles::present_lockfiles;
+use crate::parsers::{find_project_files, ENV_FILES};
+use crate::policy::Policy;
+use crate::rules::ContextRules;
+use crate::settings::{data_home, parse_setting, ConfigFiles};
+use crate::state::state_path;
+use crate::toolchain::toolchain_sources;
+
+/// Whether a boolean setting is on, defaulting to off.
+fn enabled(files: &ConfigFiles, name: &str) -> bool {
+    files.get(name).unwrap_or_default().to_lowercase() == "true"
+}
+
+/// `(collected)` or `(not collected: NAME is off)`, for a category heading.
+fn collected(files: &ConfigFiles, name: &str) -> String {
+    if enabled(files, name) {
+        "(collected)".to_string()
+    } else {
+        format!("(not collected: {} is off)", name)
+    }
+}
+
+/// Prints `items` indented under a heading, or `none` if there are none.
+fn print_items(items: &[String]) {
+    if items.is_empty() {
+        println!("  none");
+    }
+    for item in items {
+        println!("  {}", item);
+    }
+}
+
+/// Prints every kind of data this tool would collect in `project_dir` and on this machine, where it would come from,
+/// and whether the current settings collect it, for privacy reviews. Only paths are looked at; no history, file
+/// contents, env values, or git metadata is read.
+pub fn print_inventory(project_dir: &Path, files: &ConfigFiles, sources: &[HistorySource]) -> Result<()> {
+    let rules = ContextRules::load(files, &Policy::load()?)?;
+    let hours = parse_setting(files, "HOURS_OF_SHELL_HISTORY", "a whole number", |hours| hours.parse::<i64>().ok())?.unwrap_or(5);
+    let max_files = parse_setting(files, "MAX_FILE_COUNT_FOR_CONTEXT", "a whole number", |count| count.parse().ok())?.unwrap_or(5);
+    let follow_symlinks = enabled(files, "FOLLOW_SYMLINKS");
+    println!("Data magic_quickstart would collect for {}", project_dir.display());
+    println!("(Nothing was read to make this list.)");
+
+    println!("\nShell history {}", collected(files, "INCLUDE_SHELL_HISTORY"));
+    println!("  Commands, exit codes, and times from the last {} hours, minus COMMAND_BLOCKLIST, with credentials typed on the command line removed, from:", hours);
+    print_items(
+        &sources
+            .iter()
+            .map(|source| {
+                let state = if source.path.is_file() { "" } else { " (missing)" };
+                format!("  {} ({}){}", source.path.display(), source.shell.name(), state)
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    println!("\nEnv file keys {}", collected(files, "INCLUDE_ENV_FILE_KEYS"));
+    println!("  Key names only, never values, filtered by ENV_KEY_ALLOWLIST and ENV_KEY_DENYLIST, from:");
+    print_items(
+        &ENV_FILES
+            .iter()
+            .filter(|file| project_dir.join(file).is_file())
+            .map(|file| format!("  {}", file))
+            .collect::<Vec<_>>(),
+    );
+
+    let project_files = find_project_files(project_dir, max_files, follow_symlinks);
+    let (ignored, included): (Vec<_>, Vec<_>) = project_files.iter().partition(|path| rules.ignores(path));
+    println!("\nProject files {}", collected(files, "INCLUDE_REPOSITORY_FILES"));
+    println!(
+        "  Paths and contents, up to MAX_FILE_COUNT_FOR_CONTEXT={}, unless excluded or credential stores:",
+        max_files
+    );
+    print_items(&included.iter().map(|path| format!("  {}", path.display())).collect::<Vec<_>>());
+    if !ignored.is_empty() {
+        println!("  Left out by IGNORE_GLOBS or the policy: {}", ignored.len());
+    }
+
+    println!("\nDependencies {}", collected(files, "INCLUDE_REPOSITORY_FILES"));
+    println!("  Names and locked versions of direct dependencies, from:");
+    print_items(
+        &present_lockfiles(project_dir)
+            .iter()
+            .map(|file| format!("  {}", file))
+            .collect::<Vec<_>>(),
+    );
+
+    println!("\nAsset and data directories {}", collected(files, "INCLUDE_REPOSITORY_FILES"));
+    println!("  File counts by extension, subdirectories, and a few file names, from:");
+    print_items(
+        &present_asset_directories(project_dir)
+            .iter()
+            .map(|dir| format!("  {}/", dir))
+            .collect::<Vec<_>>(),
+    );
+
+    println!("\nOnly for some document types:");
+    let ci_files = ci_config_paths(project_dir);
+    println!(
+        "  CI, lint, and formatter configuration (contributing, tasks, ci-workflow): {}",
+        if ci_files.is_empty() {
+            "none".to_string()
+        } else {
+            ci_files.join(", ")
+        }
+    );
+    let is_git = project_dir.join(".git").exists();
+    println!(
+        "  Git branch names and recent commit subjects (contributing, tasks, ci-workflow): {}",
+        if is_git {
+            "from the git repository"
+        } else {
+            "none (not a git repository)"
+        }
+    );
+    println!(
+        "  The origin remote's URL (onboarding, setup-script, devcontainer, ci-workflow): {}",
+        if is_git {
+            "from the git repository"
+        } else {
+            "none (not a git repository)"
+        }
+    );
+    println!("  Module structure and imports between modules (architecture): from the project's source files");
+    let (pin_files, tools) = toolchain_sources(project_dir);
+    println!(
+        "  Toolchain versions (onboarding, setup-script, devcontainer, ci-workflow): pinned in {}; installed versions of {}",
+        if pin_files.is_empty() {
+            "no files".to_string()
+        } else {
+            pin_files.join(", ")
+        },
+        if tools.is_empty() {
+            "no tools".to_string()
+        } else {
+            tools.join(", ")
+        }
+    );
+    match files.get("EXEMPLARS_DIR") {
+        Some(dir) => println!("  Example documents (EXEMPLARS_DIR): up to two documents from {}", dir),
+        None => println!("  Example documents: none (EXEMPLARS_DIR is not set)"),
+    }
+
+    let base_url = files
+        .get("OPENAI_BASE_URL")
+        .and_then(|url| parse_base_url(&url))
+        .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    println!("\nSent to");
+    if enabled(files, "ENABLE_OPENAI") {
+        println!("  {} (OpenAI-compatible API), after you confirm", url_host(&base_url));
+    } else {
+        println!("  nowhere: ENABLE_OPENAI is off");
+    }
+
+    println!("\nKept on this machine");
+    if enabled(files, "DEBUG_REQUEST") {
+        println!(
+            "  {}/.magic_quickstart/: intermediate artifacts, removed after each run unless kept",
+            project_dir.display()
+        );
+    }
+    let audit_log = match files.get("AUDIT_LOG") {
+        Some(path) => path,
+        None => default_audit_log_path()?.display().to_string(),
+    };
+    println!("  {}: every payload sent, with the response status", audit_log);
+    println!(
+        "  {}: every generated run, with its context manifest and output, and which kinds of data you agreed to \
+         send, per project",
+        state_path()?.display()
+    );
+    if let Some(dir) = data_home().map(|dir| dir.join("magic_quickstart")) {
+        if enabled(files, "PSEUDONYMIZE") {
+            println!("  {}: the key pseudonyms are derived from", dir.join("pseudonym.key").display());
+        }
+    }
+    if enabled(files, "ENCRYPT_ARTIFACTS") {
+        println!("  The system keyring: the key artifacts and runs are encrypted with");
+    }
+    Ok(())
+}
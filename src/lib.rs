@@ -0,0 +1,219 @@
+//! Generates project quickstart guides and other documents from shell history, repository files, and env keys.
+//!
+//! The `magic_quickstart` binary is a thin wrapper around [`run`]. Other tools, such as editor extensions and bots,
+//! embed generation with [`QuickstartBuilder`].
+
+use anyhow::{bail, Context, Result};
+use artifacts::validate_artifacts;
+pub use builder::{Provider, Quickstart, QuickstartBuilder, Source};
+use serde_json::json;
+use std::env;
+
+mod api_key;
+mod artifacts;
+mod assets;
+mod audit;
+mod budget;
+mod builder;
+mod ci;
+pub mod cli;
+mod clipboard;
+mod command_credentials;
+mod commands;
+mod config;
+mod consent;
+mod console;
+mod convert;
+mod credential_files;
+mod doc_types;
+mod doctor;
+mod documents;
+mod encryption;
+mod endpoints;
+mod env_usage;
+mod errors;
+mod excerpt;
+mod exemplars;
+mod external;
+mod file_lock;
+mod formats;
+mod front_matter;
+mod generate;
+mod git;
+mod history;
+mod hooks;
+mod i18n;
+mod injection;
+mod interrupt;
+mod inventory;
+mod lockfiles;
+mod manpage;
+mod markdown;
+mod models;
+mod offline;
+mod output;
+mod parsers;
+mod payload_size;
+mod pii;
+mod plugins;
+mod policy;
+mod preview;
+mod pricing;
+mod prompt;
+mod provenance;
+mod pseudonyms;
+mod redaction_report;
+mod remote;
+mod retention;
+mod review;
+mod rules;
+mod run_manifest;
+mod runs;
+mod safety;
+mod secrets;
+mod sections;
+mod send;
+mod settings;
+mod sinks;
+mod sources;
+mod state;
+mod streaming;
+mod structure;
+mod style;
+mod templates;
+mod tokens;
+mod toolchain;
+mod transforms;
+mod update;
+mod watch;
+use cli::{Command, ConfigAction, ConfigFileArgs, RunsAction};
+use commands::{config, print_env_usage, print_history};
+use console::stdout_reserved;
+pub use doc_types::DocType;
+use doctor::run_doctor;
+use endpoints::HostAllowlist;
+pub use errors::Error;
+use errors::{exit_code, EXIT_FAILURE, EXIT_INTERRUPTED};
+pub use formats::OutputFormat;
+pub use generate::Outcome;
+use generate::{generate, watch};
+use history::{history_sources, history_time_zone};
+use i18n::t;
+pub use interrupt::install_interrupt_handler;
+use interrupt::interrupted;
+use inventory::print_inventory;
+pub use models::{FileContext, HistoryEntry};
+use preview::{default_preview_file, serve_preview};
+use retention::{audit_log_path, enforce_retention, print_purged, purge, retention_days};
+use runs::{diff_runs, list_runs, restore_run, show_run};
+use settings::ConfigFiles;
+pub use sinks::{MemorySink, OutputSink};
+pub use sources::{ContextBlock, ContextData, ContextSource};
+use update::check_for_update;
+
+/// Prints an error from `run` with its causes and returns the exit code for its kind (see `errors`).
+pub fn report_error(error: &anyhow::Error) -> i32 {
+    eprintln!("{}", t!("error-prefix", error = format!("{:#}", error)));
+    // With --json, failures get a record too, so wrappers never have to parse stderr.
+    if stdout_reserved() {
+        println!(
+            "{}",
+            json!({"outcome": "error", "error": format!("{:#}", error), "exit_code": exit_code(error)})
+        );
+    }
+    exit_code(error)
+}
+
+/// Runs a command as parsed from the command line. Returns the exit code for how it ended, 0 unless it was
+/// interrupted, found drift, or a check failed; failures are errors, whose exit code `report_error` gives.
+pub fn run(command: Command) -> Result<i32> {
+    match command {
+        Command::Generate(args) if args.watch => {
+            watch(args)?;
+            if interrupted() {
+                return Ok(EXIT_INTERRUPTED);
+            }
+        }
+        // An external subcommand's exit code becomes ours, as if it had been run directly.
+        Command::Generate(args) => return Ok(generate(&args)?.exit_code()),
+        Command::Preview { file, port } => {
+            let current_dir = env::current_dir().context("Failed to get current working directory")?;
+            match file.or_else(|| default_preview_file(&current_dir)) {
+                Some(file) => serve_preview(&file, port)?,
+                None => println!("Nothing to preview: no README_GENERATED_*.md or README.md in the current directory."),
+            }
+        }
+        Command::Config { action, project } => config(action.unwrap_or(ConfigAction::List {
+            file: ConfigFileArgs::default(),
+            project,
+        }))?,
+        Command::Doctor { project, offline, history } => {
+            let project_dir = project.dir()?;
+            let files = ConfigFiles::load(&project_dir, project.profile.as_deref())?;
+            let sources = history_sources(&files, &history)?;
+            if !run_doctor(&project_dir, &files, &sources, offline) {
+                return Ok(EXIT_FAILURE);
+            }
+        }
+        Command::History { project, hours, history } => {
+            let project_dir = project.dir()?;
+            let files = ConfigFiles::load(&project_dir, project.profile.as_deref())?;
+            print_history(
+                &files,
+                &history_sources(&files, &history)?,
+                history_time_zone(&files, &history)?,
+                hours,
+            )?;
+        }
+        Command::Inventory { project, history } => {
+            let project_dir = project.dir()?;
+            let files = ConfigFiles::load(&project_dir, project.profile.as_deref())?;
+            print_inventory(&project_dir, &files, &history_sources(&files, &history)?)?;
+        }
+        Command::Usage(project) => {
+            let project_dir = project.dir()?;
+            print_env_usage(&project_dir, &ConfigFiles::load(&project_dir, project.profile.as_deref())?);
+        }
+        Command::Decrypt { file } => print!("{}", encryption::read_to_string(&file)?),
+        Command::ValidateArtifacts { files, project } => validate_artifacts(&project.dir()?, &files)?,
+        Command::Purge {
+            older_than,
+            dry_run,
+            project,
+        } => {
+            let project_dir = project.dir()?;
+            let files = ConfigFiles::load(&project_dir, project.profile.as_deref())?;
+            let days = match older_than {
+                Some(days) => Some(days),
+                None => retention_days(&files)?,
+            };
+            let Some(days) = days else {
+                bail!(Error::Config("Pass --older-than DAYS or set RETENTION_DAYS".to_string()));
+            };
+            let purged = purge(&project_dir, &audit_log_path(&files)?, days, dry_run)?;
+            print_purged(&purged, days, dry_run);
+        }
+        Command::Runs { action } => {
+            // Expired runs are deleted before looking back, with RETENTION_DAYS as set for the current directory.
+            let current_dir = env::current_dir()?;
+            let files = ConfigFiles::load(&current_dir, None)?;
+            if let Some(days) = retention_days(&files)? {
+                enforce_retention(&current_dir, &audit_log_path(&files)?, days)?;
+            }
+            match action {
+                RunsAction::List => list_runs()?,
+                RunsAction::Show { id } => show_run(&id)?,
+                RunsAction::Diff { a, b } => diff_runs(&a, &b)?,
+                RunsAction::Restore { id, yes } => restore_run(&id, yes)?,
+            }
+        }
+        Command::CheckUpdate => {
+            // ALLOWED_HOSTS applies here too, as set for the current directory.
+            let files = ConfigFiles::load(&env::current_dir()?, None)?;
+            check_for_update(&HostAllowlist::load(&files)?)?
+        }
+        // The command line turns `redact` into a dry run already; a library caller may not have.
+        Command::Redact { report, args } => return run(Command::Generate(args.redacting(report))),
+    }
+    Ok(0)
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::console::status;
+use crate::models::DependencySummary;
+
+/// Reads a lockfile in the project root and returns `name version` lines, or `None` if it can't be parsed.
+type LockfileSummarizer = fn(&Path) -> Option<Vec<String>>;
+
+/// The lockfiles that are summarized, each with its summarizer.
+const SUMMARIZERS: [(&str, LockfileSummarizer); 3] = [
+    ("Cargo.lock", summarize_cargo_lock),
+    ("package-lock.json", summarize_package_lock),
+    ("poetry.lock", summarize_poetry_lock),
+];
+
+/// The lockfiles in the project root that would be summarized, without reading them.
+pub fn present_lockfiles(root: &Path) -> Vec<&'static str> {
+    SUMMARIZERS
+        .iter()
+        .map(|(lockfile, _)| *lockfile)
+        .filter(|lockfile| root.join(lockfile).exists())
+        .collect()
+}
+
+/// Summarizes top-level dependencies (name and locked version) from Cargo.lock, package-lock.json, and poetry.lock,
+/// so the model knows the stack without being fed the whole lockfile.
+pub fn summarize_dependencies(root: &Path) -> Vec<DependencySummary> {
+    let mut summaries = Vec::new();
+
+    for (lockfile, summarize) in SUMMARIZERS {
+        if !root.join(lockfile).exists() {
+            continue;
+        }
+        match summarize(root) {
+            Some(dependencies) => summaries.push(DependencySummary {
+                lockfile: lockfile.to_string(),
+                dependencies,
+            }),
+            None => status!("Failed to parse {}, skipping dependency summary", lockfile),
+        }
+    }
+
+    summaries
+}
+
+/// Lists the direct dependencies of the workspace's local packages (those without a registry source).
+fn summarize_cargo_lock(root: &Path) -> Option<Vec<String>> {
+    let lock: toml::Table = fs::read_to_string(root.join("Cargo.lock")).ok()?.parse().ok()?;
+    let packages = lock.get("package")?.as_array()?;
+
+    let mut versions: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for package in packages {
+        if let (Some(name), Some(version)) = (
+            package.get("name").and_then(|v| v.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) {
+            versions.entry(name).or_default().push(version);
+        }
+    }
+
+    let local_packages: Vec<&toml::Value> = packages.iter().filter(|package| package.get("source").is_none()).collect();
+    let local_names: Vec<&str> = local_packages.iter().filter_map(|package| package.get("name")?.as_str()).collect();
+
+    let mut dependencies = Vec::new();
+    for package in local_packages {
+        let Some(entries) = package.get("dependencies").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        for entry in entries.iter().filter_map(|v| v.as_str()) {
+            // Entries are `name` or `name version [(source)]` when several versions are locked.
+            let mut parts = entry.split_whitespace();
+            let name = parts.next().unwrap_or_default();
+            if local_names.contains(&name) {
+                continue;
+            }
+            let version = parts
+                .next()
+                .or_else(|| versions.get(name).and_then(|found| found.first().copied()))
+                .unwrap_or("?");
+            let line = format!("{} {}", name, version);
+            if !dependencies.contains(&line) {
+                dependencies.push(line);
+            }
+        }
+    }
+
+    dependencies.sort();
+    Some(dependencies)
+}
+
+/// Lists the root package's dependencies and devDependencies with their installed versions.
+fn summarize_package_lock(root: &Path) -> Option<Vec<String>> {
+    let lock: serde_json::Value = serde_json::from_str(&fs::read_to_string(root.join("package-lock.json")).ok()?).ok()?;
+    let mut dependencies = Vec::new();
+
+    if let Some(packages) = lock["packages"].as_object() {
+        // lockfileVersion 2 and 3.
+        let root_package = &packages.get("")?;
+        for (section, suffix) in [("dependencies", ""), ("devDependencies", " (dev)")] {
+            for name in root_package[section].as_object().into_iter().flat_map(|deps| deps.keys()) {
+                let version = packages
+                    .get(&format!("node_modules/{}", name))
+                    .and_then(|package| package["version"].as_str())
+                    .unwrap_or("?");
+                dependencies.push(format!("{} {}{}", name, version, suffix));
+            }
+        }
+    } else if let Some(packages) = lock["dependencies"].as_object() {
+        // lockfileVersion 1 only lists top-level packages at this level, including transitive hoisted ones.
+        for (name, package) in packages {
+            let suffix = if package["dev"].as_bool() == Some(true) { " (dev)" } else { "" };
+            dependencies.push(format!("{} {}{}", name, package["version"].as_str().unwrap_or("?"), suffix));
+        }
+    }
+
+    dependencies.sort();
+    Some(dependencies)
+}
+
+/// Lists the dependencies declared in pyproject.toml with the versions locked in poetry.lock.
+fn summarize_poetry_lock(root: &Path) -> Option<Vec<String>> {
+    let lock: toml::Table = fs::read_to_string(root.join("poetry.lock")).ok()?.parse().ok()?;
+    let locked: BTreeMap<String, &str> = lock
+        .get("package")?
+        .as_array()?
+        .iter()
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            Some((normalize_python_name(name), package.get("version")?.as_str()?))
+        })
+        .collect();
+
+    let declared = fs::read_to_string(root.join("pyproject.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .map(|pyproject| declared_python_dependencies(&pyproject))
+        .unwrap_or_default();
+
+    let mut dependencies: Vec<String> = if declared.is_empty() {
+        // Without declarations every locked package is listed, which is still far smaller than the lockfile.
+        locked.iter().map(|(name, version)| format!("{} {}", name, version)).collect()
+    } else {
+        declared
+            .iter()
+            .map(|name| format!("{} {}", name, locked.get(&normalize_python_name(name)).copied().unwrap_or("?")))
+            .collect()
+    };
+
+    dependencies.sort();
+    Some(dependencies)
+}
+
+/// Reads dependency names from `[tool.poetry.dependencies]` and PEP 621 `[project].dependencies`.
+fn declared_python_dependencies(pyproject: &toml::Table) -> Vec<String> {
+    let mut names = Vec::new();
+
+    if let Some(poetry) = pyproject
+        .get("tool")
+        .and_then(|tool| tool.get("poetry"))
+        .and_then(|poetry| poetry.get("dependencies"))
+        .and_then(|deps| deps.as_table())
+    {
+        names.extend(poetry.keys().filter(|name| name.as_str() != "python").cloned());
+    }
+
+    if let Some(project) = pyproject
+        .get("project")
+        .and_then(|project| project.get("dependencies"))
+        .and_then(|deps| deps.as_array())
+    {
+        for requirement in project.iter().filter_map(|v| v.as_str()) {
+            let name: String = requirement
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                .collect();
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    names
+}
+
+/// Normalizes a Python distribution name as described in PEP 503.
+fn normalize_python_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
@@ -0,0 +1,150 @@
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+
+use crate::console::status;
+
+/// Man page sections built from the structured template slots, in order.
+const MAN_SECTIONS: [(&str, &str); 7] = [
+    ("prerequisites", "PREREQUISITES"),
+    ("installation", "INSTALLATION"),
+    ("configuration", "CONFIGURATION"),
+    ("usage", "USAGE"),
+    ("testing", "TESTING"),
+    ("deployment", "DEPLOYMENT"),
+    ("troubleshooting", "TROUBLESHOOTING"),
+];
+
+/// Renders the model's structured slots (a JSON object, see `templates::TEMPLATE_SLOTS`) as a section 1 roff man page.
+pub fn render_man_page(response: &str, project_name: &str) -> String {
+    let slots: serde_json::Value = serde_json::from_str(response.trim()).unwrap_or_else(|_| {
+        status!("Model response was not valid JSON; using it as the man page description.");
+        serde_json::json!({ "description": response })
+    });
+    let slot = |name: &str| slots[name].as_str().unwrap_or_default().trim().to_string();
+
+    let title = if slot("title").is_empty() {
+        project_name.to_string()
+    } else {
+        slot("title")
+    };
+    let description = slot("description");
+    let summary = description.split(". ").next().unwrap_or_default().trim_end_matches('.');
+
+    let mut page = format!(
+        ".TH {} 1 \"{}\" \"{}\" \"User Commands\"\n",
+        escape(&project_name.to_uppercase()),
+        chrono::Utc::now().format("%Y-%m-%d"),
+        escape(&title)
+    );
+    page.push_str(&format!(
+        ".SH NAME\n{} \\- {}\n",
+        escape(project_name),
+        escape(&plain_text(summary))
+    ));
+    if !description.is_empty() {
+        page.push_str(".SH DESCRIPTION\n");
+        page.push_str(&markdown_to_roff(&description));
+    }
+    for (name, heading) in MAN_SECTIONS {
+        let content = slot(name);
+        if content.is_empty() {
+            continue;
+        }
+        page.push_str(&format!(".SH {}\n", heading));
+        page.push_str(&markdown_to_roff(&content));
+    }
+
+    page
+}
+
+/// Converts a Markdown fragment into roff: headings become `.SS`, lists `.IP`, and code blocks no-fill blocks.
+pub fn markdown_to_roff(markdown: &str) -> String {
+    let mut roff = String::new();
+    let mut line = String::new();
+    let mut in_code_block = false;
+
+    let flush = |roff: &mut String, line: &mut String| {
+        let text = line.trim();
+        if !text.is_empty() {
+            roff.push_str(text);
+            roff.push('\n');
+        }
+        line.clear();
+    };
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                flush(&mut roff, &mut line);
+                line.push_str(if level == HeadingLevel::H1 { ".SH " } else { ".SS " });
+            }
+            Event::End(TagEnd::Heading(_)) => flush(&mut roff, &mut line),
+            Event::Start(Tag::Paragraph) => {
+                flush(&mut roff, &mut line);
+                if !roff.ends_with("\\(bu 2\n") {
+                    roff.push_str(".PP\n");
+                }
+            }
+            Event::End(TagEnd::Paragraph) => flush(&mut roff, &mut line),
+            Event::Start(Tag::Item) => {
+                flush(&mut roff, &mut line);
+                roff.push_str(".IP \\(bu 2\n");
+            }
+            Event::End(TagEnd::Item) => flush(&mut roff, &mut line),
+            Event::Start(Tag::CodeBlock(_)) => {
+                flush(&mut roff, &mut line);
+                roff.push_str(".PP\n.RS 4\n.nf\n");
+                in_code_block = true;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                roff.push_str(".fi\n.RE\n");
+                in_code_block = false;
+            }
+            Event::Start(Tag::Strong) => line.push_str("\\fB"),
+            Event::Start(Tag::Emphasis) => line.push_str("\\fI"),
+            Event::End(TagEnd::Strong) | Event::End(TagEnd::Emphasis) => line.push_str("\\fR"),
+            Event::Text(text) if in_code_block => {
+                for code_line in text.lines() {
+                    roff.push_str(&escape_line(code_line));
+                    roff.push('\n');
+                }
+            }
+            Event::Text(text) => line.push_str(&escape(&text)),
+            Event::Code(code) => line.push_str(&format!("\\fB{}\\fR", escape(&code))),
+            Event::SoftBreak => line.push(' '),
+            Event::HardBreak => {
+                flush(&mut roff, &mut line);
+                roff.push_str(".br\n");
+            }
+            _ => {}
+        }
+    }
+    flush(&mut roff, &mut line);
+
+    roff
+}
+
+/// Strips Markdown markup from a short inline fragment.
+fn plain_text(markdown: &str) -> String {
+    Parser::new(markdown)
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.to_string()),
+            Event::SoftBreak => Some(" ".to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Escapes backslashes and hyphens for roff text.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+/// Escapes a line so a leading `.` or `'` isn't read as a roff request.
+fn escape_line(line: &str) -> String {
+    let escaped = escape(line);
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Manifests that mark an ecosystem, and the code fence language used for its source snippets.
+/// `tsconfig.json` comes first so TypeScript projects aren't labeled as JavaScript.
+const ECOSYSTEM_LANGUAGES: [(&str, &str); 7] = [
+    ("tsconfig.json", "typescript"),
+    ("Cargo.toml", "rust"),
+    ("pyproject.toml", "python"),
+    ("requirements.txt", "python"),
+    ("setup.py", "python"),
+    ("package.json", "javascript"),
+    ("go.mod", "go"),
+];
+
+/// Words that suggest a snippet is written in a given fence language.
+const LANGUAGE_HINTS: [(&str, &[&str]); 5] = [
+    ("rust", &["fn ", "let ", "use ", "impl ", "pub "]),
+    ("python", &["def ", "import ", "from ", "print("]),
+    ("typescript", &["const ", "function ", "import ", "export ", "interface "]),
+    ("javascript", &["const ", "function ", "require(", "module.exports", "import "]),
+    ("go", &["func ", "package ", ":= "]),
+];
+
+/// Commands that mark a snippet as shell.
+const SHELL_COMMANDS: [&str; 30] = [
+    "cargo", "npm", "npx", "pnpm", "yarn", "node", "pip", "pip3", "python", "python3", "poetry", "uv", "go", "git", "cd", "make", "just",
+    "docker", "export", "source", "curl", "brew", "apt", "apt-get", "sudo", "mkdir", "cp", "mv", "rustup", "echo",
+];
+
+/// Documents with fewer second-level sections than this don't get a table of contents.
+const MIN_TOC_SECTIONS: usize = 3;
+
+/// Code fence languages for the ecosystems whose manifests are present under `root`, most specific first.
+pub fn detect_fence_languages(root: &Path) -> Vec<&'static str> {
+    let mut languages = Vec::new();
+    for (manifest, language) in ECOSYSTEM_LANGUAGES {
+        if root.join(manifest).is_file() && !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    languages
+}
+
+/// Tidies generated Markdown: gives unlabeled code fences a language (shell, JSON, TOML, or one of
+/// `fence_languages`), normalizes headings to a single H1 without skipped levels, and optionally inserts a
+/// table of contents before the first section.
+pub fn postprocess_markdown(markdown: &str, fence_languages: &[&str], insert_toc: bool) -> String {
+    let mut lines: Vec<String> = markdown.lines().map(str::to_string).collect();
+    label_code_fences(&mut lines, fence_languages);
+    normalize_headings(&mut lines);
+    if insert_toc {
+        insert_table_of_contents(&mut lines);
+    }
+
+    let mut result = lines.join("\n");
+    if markdown.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Returns the fence marker (``` or ~~~) if the line opens or closes a code block.
+pub fn fence_marker(line: &str) -> Option<&'static str> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("```") {
+        Some("```")
+    } else if trimmed.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// Ends a document that was cut off partway with `note` as a paragraph of its own, closing the code block it stopped
+/// in first, if any, so the note isn't taken for code.
+pub fn append_truncation_note(markdown: &str, note: &str) -> String {
+    let mut open_fence: Option<&str> = None;
+    for line in markdown.lines() {
+        match (open_fence, fence_marker(line)) {
+            (None, Some(marker)) => open_fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => open_fence = None,
+            _ => {}
+        }
+    }
+    let mut result = markdown.trim_end().to_string();
+    if let Some(marker) = open_fence {
+        result.push('\n');
+        result.push_str(marker);
+    }
+    result.push_str(&format!("\n\n> **{}**\n", note));
+    result
+}
+
+/// Calls `visit` with the index of every line outside code blocks.
+fn for_each_prose_line(lines: &[String], mut visit: impl FnMut(usize)) {
+    let mut open_fence: Option<&str> = None;
+    for (index, line) in lines.iter().enumerate() {
+        match (open_fence, fence_marker(line)) {
+            (None, Some(marker)) => open_fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => open_fence = None,
+            (None, None) => visit(index),
+            _ => {}
+        }
+    }
+}
+
+fn label_code_fences(lines: &mut [String], fence_languages: &[&str]) {
+    let mut index = 0;
+    while index < lines.len() {
+        let Some(marker) = fence_marker(&lines[index]) else {
+            index += 1;
+            continue;
+        };
+        let close = (index + 1..lines.len())
+            .find(|&i| fence_marker(&lines[i]) == Some(marker))
+            .unwrap_or(lines.len());
+        if lines[index].trim() == marker {
+            let language = infer_fence_language(&lines[index + 1..close], fence_languages);
+            lines[index].push_str(language);
+        }
+        index = close + 1;
+    }
+}
+
+/// Guesses the language of an unlabeled code block.
+fn infer_fence_language(body: &[String], fence_languages: &[&str]) -> &'static str {
+    let text = body.join("\n");
+    let first_line = body
+        .iter()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let first_word = first_line.trim_start_matches("$ ").split_whitespace().next().unwrap_or_default();
+
+    if first_line.starts_with("$ ") || first_line.starts_with("./") || SHELL_COMMANDS.contains(&first_word) {
+        return "bash";
+    }
+    if (first_line.starts_with('{') || first_line.starts_with('[')) && serde_json::from_str::<serde_json::Value>(&text).is_ok() {
+        return "json";
+    }
+    if first_line.starts_with('[') && text.lines().any(|line| line.contains(" = ")) {
+        return "toml";
+    }
+    for (language, hints) in LANGUAGE_HINTS {
+        if fence_languages.contains(&language) && hints.iter().any(|hint| text.contains(hint)) {
+            return language;
+        }
+    }
+    "text"
+}
+
+/// Parses an ATX heading into its level and text.
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Makes the first heading the only H1 and removes skipped levels, keeping the relative nesting of sections.
+fn normalize_headings(lines: &mut [String]) {
+    let mut headings = Vec::new();
+    for_each_prose_line(lines, |index| {
+        if let Some((level, _)) = parse_heading(&lines[index]) {
+            headings.push((index, level));
+        }
+    });
+
+    // Each stack entry maps an original heading level to its normalized level.
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    for (index, level) in headings {
+        let normalized = if stack.is_empty() {
+            1
+        } else {
+            while stack.len() > 1 && stack.last().is_some_and(|&(original, _)| original >= level) {
+                stack.pop();
+            }
+            stack.last().map(|&(_, parent)| parent + 1).unwrap_or(1).min(6)
+        };
+        stack.push((level, normalized));
+
+        let text = parse_heading(&lines[index]).map(|(_, text)| text.to_string()).unwrap_or_default();
+        lines[index] = format!("{} {}", "#".repeat(normalized), text);
+    }
+}
+
+/// GitHub-style anchor for a heading: lowercase, punctuation removed, spaces replaced by hyphens.
+pub fn heading_anchor(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// Inserts a table of contents listing second- and third-level headings before the first section.
+fn insert_table_of_contents(lines: &mut Vec<String>) {
+    let mut headings = Vec::new();
+    for_each_prose_line(lines, |index| {
+        if let Some((level, text)) = parse_heading(&lines[index]) {
+            headings.push((index, level, text.to_string()));
+        }
+    });
+    let already_present = headings
+        .iter()
+        .any(|(_, _, text)| ["table of contents", "contents"].contains(&text.to_lowercase().as_str()));
+    let sections = headings.iter().filter(|(_, level, _)| *level == 2).count();
+    if already_present || sections < MIN_TOC_SECTIONS {
+        return;
+    }
+
+    // Repeated headings get -1, -2, ... suffixes, as on GitHub.
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut toc = vec!["## Table of Contents".to_string(), String::new()];
+    for (_, level, text) in &headings {
+        let base = heading_anchor(text);
+        let count = seen.entry(base.clone()).or_insert(0);
+        let anchor = if *count == 0 { base } else { format!("{}-{}", base, count) };
+        *count += 1;
+        if (2..=3).contains(level) {
+            toc.push(format!("{}- [{}](#{})", "  ".repeat(level - 2), text, anchor));
+        }
+    }
+    toc.push(String::new());
+
+    let first_section = headings.iter().find(|(_, level, _)| *level == 2).map(|(index, ..)| *index).unwrap();
+    lines.splice(first_section..first_section, toc);
+}
+
+/// Splits a Markdown document at its second-level headings into the introduction before the first section and
+/// `(heading, body)` pairs. Headings inside code blocks don't split.
+pub fn split_sections(markdown: &str) -> (String, Vec<(String, String)>) {
+    let lines: Vec<String> = markdown.lines().map(str::to_string).collect();
+    let mut section_starts = Vec::new();
+    for_each_prose_line(&lines, |index| {
+        if let Some((2, _)) = parse_heading(&lines[index]) {
+            section_starts.push(index);
+        }
+    });
+
+    let intro_end = section_starts.first().copied().unwrap_or(lines.len());
+    let intro = lines[..intro_end].join("\n");
+    let sections = section_starts
+        .iter()
+        .enumerate()
+        .map(|(position, &start)| {
+            let end = section_starts.get(position + 1).copied().unwrap_or(lines.len());
+            let heading = parse_heading(&lines[start]).map(|(_, text)| text.to_string()).unwrap_or_default();
+            (heading, lines[start + 1..end].join("\n"))
+        })
+        .collect();
+    (intro, sections)
+}
+
+/// Raises every heading outside code blocks by one level, for a section that becomes its own document.
+pub fn promote_headings(markdown: &str) -> String {
+    let mut lines: Vec<String> = markdown.lines().map(str::to_string).collect();
+    let mut headings = Vec::new();
+    for_each_prose_line(&lines, |index| {
+        if let Some((level, _)) = parse_heading(&lines[index]) {
+            if level > 1 {
+                headings.push(index);
+            }
+        }
+    });
+    for index in headings {
+        lines[index].remove(0);
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A shell history entry as it is sent and shown.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the command ran, in the history time zone, or `Invalid timestamp`.
+    pub timestamp: String,
+    /// How long ago that was, e.g. `2h 5m 3s`.
+    pub relative_time: String,
+    /// `today`, `yesterday`, or e.g. `Monday, 3 days ago`.
+    pub day: String,
+    /// zsh's duration field, atuin's exit code, or empty for shells that don't record one.
+    pub exit_code: String,
+    pub command: String,
+}
+
+impl HistoryEntry {
+    /// The entry as one line of JSON, the way the model sees it.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// A project file's path (relative to the project) and contents.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileContext {
+    pub file_path: String,
+    pub content: String,
+}
+
+/// How the project's code reads an env key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvKeyUsage {
+    /// Read without a fallback.
+    Required,
+    /// Read with a default.
+    Optional,
+    /// Never read, or not looked for yet.
+    #[default]
+    Unused,
+}
+
+impl EnvKeyUsage {
+    /// The usage as it is serialized, e.g. `required`.
+    pub fn name(self) -> &'static str {
+        match self {
+            EnvKeyUsage::Required => "required",
+            EnvKeyUsage::Optional => "optional",
+            EnvKeyUsage::Unused => "unused",
+        }
+    }
+}
+
+/// A key from one of the project's env files. Values are never read.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvKey {
+    pub key: String,
+    /// The env file it was found in, e.g. `.env.local`.
+    pub file: String,
+    #[serde(default)]
+    pub usage: EnvKeyUsage,
+    /// Source files that read it, relative to the project.
+    #[serde(default)]
+    pub referenced_in: Vec<String>,
+}
+
+impl EnvKey {
+    /// The entry as one line of JSON, the way the model sees it.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// The top-level dependencies locked in one lockfile, as `name version` lines.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DependencySummary {
+    /// The lockfile's name, e.g. `Cargo.lock`.
+    pub lockfile: String,
+    pub dependencies: Vec<String>,
+}
+
+impl DependencySummary {
+    /// The summary as one line of JSON, the way the model sees it.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// What an asset or data directory holds, without its contents.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssetDirectory {
+    /// The directory, relative to the project, e.g. `db/migrations`.
+    pub directory: String,
+    pub file_count: usize,
+    /// Whether the directory had more files than were counted.
+    pub truncated: bool,
+    /// File counts by lowercase extension, with `(none)` for files without one.
+    pub counts_by_extension: BTreeMap<String, usize>,
+    pub subdirectories: Vec<String>,
+    /// The first and last few file names, which for migrations are the oldest and newest.
+    pub notable_files: Vec<String>,
+}
+
+/// An example document whose style the output should match.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exemplar {
+    /// The document's file name.
+    pub file: String,
+    pub content: String,
+}
+
+/// Context gathered from the project and shell history that is sent to the model.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ContextBundle {
+    pub command_history: Vec<HistoryEntry>,
+    pub project_files: Vec<PathBuf>,
+    pub project_files_content: Vec<FileContext>,
+    pub dependency_summary: Vec<DependencySummary>,
+    pub asset_inventory: Vec<AssetDirectory>,
+    pub env_file_keys: Vec<EnvKey>,
+    /// Additional labeled context specific to the document type, e.g. CI configuration. The data is any JSON, since
+    /// plugins and the embedding tool's context sources supply it too.
+    pub extra_context: Vec<(String, serde_json::Value)>,
+    pub exemplars: Vec<Exemplar>,
+}
+
+/// Who a chat message is from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+}
+
+impl Role {
+    /// The role as the API names it, e.g. `system`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+        }
+    }
+}
+
+/// One chat message of a request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn system(content: impl Into<String>) -> Self {
+        Message {
+            role: Role::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Message {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+}
+
+/// A response format the API is asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Any JSON object, for devcontainer.json and template slots.
+    JsonObject,
+}
+
+/// The chat completion request for one document, serialized as the OpenAI-compatible API expects it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// SEED, which asks the model to sample the same way for the same request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+}
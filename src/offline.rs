@@ -0,0 +1,374 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::git::origin_url;
+use crate::models::{EnvKey, EnvKeyUsage, HistoryEntry};
+
+/// The layout local quickstarts are rendered with when OUTPUT_TEMPLATE isn't set.
+pub const LOCAL_TEMPLATE: &str = include_str!("../templates/quickstart.md.tera");
+
+/// Stands in for the model name in provenance footers and templates when nothing was generated by a model.
+pub const LOCAL_GENERATOR: &str = "local heuristics";
+
+/// What a shell history command does, as far as a quickstart is concerned.
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    Install,
+    Run,
+    Test,
+}
+
+/// Command prefixes from shell history worth repeating in a quickstart, by the section they belong in.
+const HISTORY_STEPS: [(&str, Step); 38] = [
+    ("npm install", Step::Install),
+    ("npm ci", Step::Install),
+    ("yarn install", Step::Install),
+    ("pnpm install", Step::Install),
+    ("pip install", Step::Install),
+    ("python -m pip install", Step::Install),
+    ("poetry install", Step::Install),
+    ("uv sync", Step::Install),
+    ("go mod download", Step::Install),
+    ("bundle install", Step::Install),
+    ("cargo build", Step::Install),
+    ("npm run build", Step::Install),
+    ("go build", Step::Install),
+    ("docker compose build", Step::Install),
+    ("cargo run", Step::Run),
+    ("npm start", Step::Run),
+    ("npm run dev", Step::Run),
+    ("yarn dev", Step::Run),
+    ("pnpm dev", Step::Run),
+    ("go run", Step::Run),
+    ("python manage.py runserver", Step::Run),
+    ("flask run", Step::Run),
+    ("uvicorn", Step::Run),
+    ("poetry run", Step::Run),
+    ("uv run", Step::Run),
+    ("docker compose up", Step::Run),
+    ("cargo test", Step::Test),
+    ("cargo clippy", Step::Test),
+    ("cargo fmt", Step::Test),
+    ("pytest", Step::Test),
+    ("python -m pytest", Step::Test),
+    ("ruff", Step::Test),
+    ("npm test", Step::Test),
+    ("npm run lint", Step::Test),
+    ("npx eslint", Step::Test),
+    ("go test", Step::Test),
+    ("go vet", Step::Test),
+    ("golangci-lint", Step::Test),
+];
+
+/// Makefile and justfile targets worth listing, by the section they belong in.
+const TASK_STEPS: [(&str, Step); 10] = [
+    ("install", Step::Install),
+    ("setup", Step::Install),
+    ("build", Step::Install),
+    ("run", Step::Run),
+    ("start", Step::Run),
+    ("serve", Step::Run),
+    ("dev", Step::Run),
+    ("test", Step::Test),
+    ("lint", Step::Test),
+    ("check", Step::Test),
+];
+
+/// Commands found for each section, in the order they should be run, without duplicates.
+#[derive(Default)]
+struct Steps {
+    install: Vec<String>,
+    run: Vec<String>,
+    test: Vec<String>,
+}
+
+impl Steps {
+    fn add(&mut self, step: Step, command: impl Into<String>) {
+        let command = command.into();
+        let commands = match step {
+            Step::Install => &mut self.install,
+            Step::Run => &mut self.run,
+            Step::Test => &mut self.test,
+        };
+        if !commands.contains(&command) {
+            commands.push(command);
+        }
+    }
+}
+
+/// The project's manifests, each parsed if it exists.
+struct Manifests {
+    cargo: Option<toml::Table>,
+    package: Option<Value>,
+    pyproject: Option<toml::Table>,
+    go_module: Option<String>,
+}
+
+impl Manifests {
+    fn read(root: &Path) -> Self {
+        let read = |name: &str| fs::read_to_string(root.join(name)).ok();
+        Manifests {
+            cargo: read("Cargo.toml").and_then(|content| content.parse().ok()),
+            package: read("package.json").and_then(|content| serde_json::from_str(&content).ok()),
+            pyproject: read("pyproject.toml").and_then(|content| content.parse().ok()),
+            go_module: read("go.mod").and_then(|go_mod| {
+                go_mod
+                    .lines()
+                    .find_map(|line| line.trim().strip_prefix("module ").map(|module| module.trim().to_string()))
+            }),
+        }
+    }
+
+    /// A string from the first manifest that has it: `[package]` in Cargo.toml, package.json, then `[project]` or
+    /// `[tool.poetry]` in pyproject.toml.
+    fn field(&self, key: &str) -> Option<String> {
+        let pyproject = self.pyproject.as_ref().and_then(|table| {
+            table
+                .get("project")
+                .and_then(|project| project.get(key))
+                .or_else(|| table.get("tool")?.get("poetry")?.get(key))
+        });
+        self.cargo
+            .as_ref()
+            .and_then(|table| table.get("package")?.get(key)?.as_str().map(str::to_string))
+            .or_else(|| self.package.as_ref().and_then(|package| package[key].as_str().map(str::to_string)))
+            .or_else(|| pyproject.and_then(|value| value.as_str().map(str::to_string)))
+            .filter(|value| !value.trim().is_empty())
+    }
+}
+
+/// The recipe names in a Makefile or justfile: lines like `test:` or `run *args:` at the start of a line.
+fn task_names(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with([' ', '\t', '#', '.']))
+        .filter_map(|line| {
+            let (head, rest) = line.split_once(':')?;
+            // `:=` is an assignment, not a recipe.
+            if rest.starts_with('=') {
+                return None;
+            }
+            head.split_whitespace().next().map(str::to_string)
+        })
+        .collect()
+}
+
+/// Builds the quickstart's template slots (see `TEMPLATE_SLOTS`) from the project's manifests, the scripts and
+/// recipes it defines, the successful commands in `command_history`, and the classified `env_file_keys`, without a
+/// model. Sections nothing was found for are left empty, so the template leaves them out.
+pub fn local_quickstart_slots(root: &Path, command_history: &[HistoryEntry], env_file_keys: &[EnvKey]) -> Value {
+    let manifests = Manifests::read(root);
+    let exists = |name: &str| root.join(name).exists();
+    let mut prerequisites: Vec<String> = Vec::new();
+    let mut steps = Steps::default();
+
+    if let Some(cargo) = &manifests.cargo {
+        let version = cargo.get("package").and_then(|package| {
+            package
+                .get("rust-version")?
+                .as_str()
+                .map(|version| format!(" {} or newer", version))
+        });
+        prerequisites.push(format!("Rust{} with Cargo", version.unwrap_or_default()));
+        steps.add(Step::Install, "cargo build");
+        if exists("src/main.rs") || cargo.contains_key("bin") {
+            steps.add(Step::Run, "cargo run");
+        }
+        steps.add(Step::Test, "cargo test");
+    }
+
+    if let Some(package) = &manifests.package {
+        let (manager, install) = if exists("pnpm-lock.yaml") {
+            ("pnpm", "pnpm install")
+        } else if exists("yarn.lock") {
+            ("yarn", "yarn install")
+        } else if exists("package-lock.json") {
+            ("npm", "npm ci")
+        } else {
+            ("npm", "npm install")
+        };
+        let version = package["engines"]["node"].as_str().map(|version| format!(" {}", version));
+        prerequisites.push(format!("Node.js{} with {}", version.unwrap_or_default(), manager));
+        steps.add(Step::Install, install);
+        let run_script = |script: &str| match (manager, script) {
+            ("npm", "start" | "test") => format!("npm {}", script),
+            ("npm", _) => format!("npm run {}", script),
+            _ => format!("{} {}", manager, script),
+        };
+        if let Some(scripts) = package["scripts"].as_object() {
+            for (script, step) in [
+                ("build", Step::Install),
+                ("dev", Step::Run),
+                ("start", Step::Run),
+                ("test", Step::Test),
+                ("lint", Step::Test),
+            ] {
+                if scripts.contains_key(script) {
+                    steps.add(step, run_script(script));
+                }
+            }
+        }
+    }
+
+    if let Some(pyproject) = &manifests.pyproject {
+        let version = pyproject
+            .get("project")
+            .and_then(|project| project.get("requires-python")?.as_str().map(|version| format!(" {}", version)));
+        prerequisites.push(format!("Python{}", version.unwrap_or_default()));
+        let runner = if exists("uv.lock") {
+            steps.add(Step::Install, "uv sync");
+            "uv run "
+        } else if exists("poetry.lock") || pyproject.get("tool").and_then(|tool| tool.get("poetry")).is_some() {
+            steps.add(Step::Install, "poetry install");
+            "poetry run "
+        } else {
+            steps.add(Step::Install, "pip install -e .");
+            ""
+        };
+        let scripts = pyproject
+            .get("project")
+            .and_then(|project| project.get("scripts"))
+            .or_else(|| pyproject.get("tool")?.get("poetry")?.get("scripts"))
+            .and_then(|scripts| scripts.as_table());
+        for script in scripts.into_iter().flat_map(|scripts| scripts.keys()) {
+            steps.add(Step::Run, format!("{}{}", runner, script));
+        }
+        if exists("tests") || exists("pytest.ini") {
+            steps.add(Step::Test, format!("{}pytest", runner));
+        }
+    } else if exists("requirements.txt") {
+        prerequisites.push("Python".to_string());
+        steps.add(Step::Install, "pip install -r requirements.txt");
+    }
+
+    if manifests.go_module.is_some() {
+        let version = fs::read_to_string(root.join("go.mod")).ok().and_then(|go_mod| {
+            go_mod.lines().find_map(|line| {
+                line.trim()
+                    .strip_prefix("go ")
+                    .map(|version| format!(" {} or newer", version.trim()))
+            })
+        });
+        prerequisites.push(format!("Go{}", version.unwrap_or_default()));
+        steps.add(Step::Install, "go mod download");
+        if exists("main.go") {
+            steps.add(Step::Run, "go run .");
+        }
+        steps.add(Step::Test, "go test ./...");
+    }
+
+    if exists("docker-compose.yml") || exists("compose.yaml") || exists("docker-compose.yaml") || exists("compose.yml") {
+        prerequisites.push("Docker with Compose".to_string());
+        steps.add(Step::Run, "docker compose up");
+    }
+
+    // Recipes the project already defines are the commands its maintainers expect people to run.
+    for (file, runner) in [("justfile", "just"), ("Makefile", "make")] {
+        let Ok(content) = fs::read_to_string(root.join(file)) else {
+            continue;
+        };
+        let names = task_names(&content);
+        let mut used = false;
+        for (target, step) in TASK_STEPS {
+            if names.iter().any(|name| name == target) {
+                steps.add(step, format!("{} {}", runner, target));
+                used = true;
+            }
+        }
+        if used && runner == "just" {
+            prerequisites.push("[just](https://github.com/casey/just)".to_string());
+        }
+    }
+
+    // Commands from shell history that succeeded, oldest first, as that is the order they were needed in.
+    for entry in command_history.iter().rev() {
+        if !entry.exit_code.is_empty() && entry.exit_code != "0" {
+            continue;
+        }
+        let command = entry.command.trim();
+        if let Some((_, step)) = HISTORY_STEPS
+            .iter()
+            .find(|(prefix, _)| command == *prefix || command.starts_with(&format!("{} ", prefix)))
+        {
+            steps.add(*step, command);
+        }
+    }
+
+    let title = manifests
+        .field("name")
+        .or_else(|| {
+            manifests
+                .go_module
+                .as_ref()
+                .and_then(|module| module.rsplit('/').next().map(str::to_string))
+        })
+        .unwrap_or_default();
+    // A remote with credentials in it is left out rather than written into a document.
+    let mut installation: Vec<String> = Vec::new();
+    if let Some(url) = origin_url(root).filter(|url| url.starts_with("git@") || !url.contains('@')) {
+        let directory = url
+            .trim_end_matches(".git")
+            .rsplit(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        installation.push(format!("git clone {}", url));
+        installation.push(format!("cd {}", directory));
+    }
+    installation.extend(steps.install);
+    let block = |commands: &[String]| {
+        if commands.is_empty() {
+            String::new()
+        } else {
+            command_block(commands)
+        }
+    };
+
+    json!({
+        "title": title,
+        "description": manifests.field("description").unwrap_or_default(),
+        "prerequisites": prerequisites.iter().map(|tool| format!("- {}", tool)).collect::<Vec<_>>().join("\n"),
+        "installation": block(&installation),
+        "configuration": configuration(root, env_file_keys),
+        "usage": block(&steps.run),
+        "testing": block(&steps.test),
+        "deployment": "",
+        "troubleshooting": "",
+    })
+}
+
+/// The configuration section: where to put settings, then the keys the code requires and the ones it reads
+/// optionally. Keys the code never reads are left out.
+fn configuration(root: &Path, env_file_keys: &[EnvKey]) -> String {
+    let keys = |usage: EnvKeyUsage| -> Vec<String> {
+        // The same key is often in several env files.
+        let mut keys: Vec<String> = Vec::new();
+        for entry in env_file_keys.iter().filter(|entry| entry.usage == usage) {
+            let key = format!("- `{}`", entry.key);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    };
+    let (required, optional) = (keys(EnvKeyUsage::Required), keys(EnvKeyUsage::Optional));
+    if required.is_empty() && optional.is_empty() {
+        return String::new();
+    }
+    let mut configuration = if root.join(".env.example").exists() {
+        String::from("Copy `.env.example` to `.env` and fill in the values.")
+    } else {
+        String::from("Set these in the environment or in a `.env` file.")
+    };
+    for (heading, keys) in [("Required:", required), ("Optional:", optional)] {
+        if !keys.is_empty() {
+            configuration.push_str(&format!("\n\n{}\n\n{}", heading, keys.join("\n")));
+        }
+    }
+    configuration
+}
+
+fn command_block(commands: &[String]) -> String {
+    format!("```sh\n{}\n```", commands.join("\n"))
+}
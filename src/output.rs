@@ -0,0 +1,239 @@
+use anyhow::{bail, Context, Result};
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::console::{console, paint, status};
+use crate::errors::Error;
+use crate::i18n::{is_yes, t};
+use crate::markdown::fence_marker;
+
+/// Marks the start of the generated section in a hand-maintained README.
+pub const START_MARKER: &str = "<!-- magic-quickstart:start -->";
+
+/// Marks the end of the generated section in a hand-maintained README.
+pub const END_MARKER: &str = "<!-- magic-quickstart:end -->";
+
+/// Places the generated content between the section markers of an existing document (such as README.md),
+/// preserving everything around them. Documents without markers get the section appended; a missing document
+/// becomes just the section. Regenerating with the same content yields the same file. Fails if only one marker
+/// is present or they are out of order.
+pub fn merge_generated_section(existing: Option<&str>, generated: &str) -> Result<String> {
+    let section = format!("{}\n{}\n{}", START_MARKER, generated.trim(), END_MARKER);
+
+    let Some(existing) = existing else {
+        return Ok(format!("{}\n", section));
+    };
+
+    match (find_marker_line(existing, START_MARKER), find_marker_line(existing, END_MARKER)) {
+        (Some(start), Some(end)) if start < end => Ok(format!("{}{}{}", &existing[..start], section, &existing[end + END_MARKER.len()..])),
+        (None, None) => Ok(format!("{}\n\n{}\n", existing.trim_end(), section)),
+        _ => bail!("Mismatched magic-quickstart markers; fix them before merging"),
+    }
+}
+
+/// Returns the generated content between the section markers of an existing document, if it has them.
+pub fn generated_section(existing: &str) -> Option<&str> {
+    let start = find_marker_line(existing, START_MARKER)? + START_MARKER.len();
+    let end = find_marker_line(existing, END_MARKER)?;
+    (start <= end).then(|| existing[start..end].trim())
+}
+
+/// Returns the byte offset of the first line outside code blocks consisting solely of `marker`, so markers quoted
+/// inline or shown in an example block (for example in documentation about the markers) are not mistaken for the
+/// real ones.
+fn find_marker_line(content: &str, marker: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut open_fence: Option<&str> = None;
+    for line in content.split_inclusive('\n') {
+        match (open_fence, fence_marker(line)) {
+            (None, Some(fence)) => open_fence = Some(fence),
+            (Some(open), Some(fence)) if open == fence => open_fence = None,
+            (None, None) if line.trim() == marker => return Some(offset + line.find(marker).unwrap_or(0)),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Writes the output file. If it already exists with different content, a colored diff is shown and the user must
+/// confirm (unless `assume_yes`), and the previous version is saved next to it with a `.bak` suffix.
+/// Returns whether the file was written.
+pub fn write_output(path: &Path, content: &str, assume_yes: bool) -> Result<bool> {
+    if let Ok(existing) = fs::read_to_string(path) {
+        if existing == content {
+            status!("{}", t!("up-to-date", path = path.display().to_string()));
+            return Ok(false);
+        }
+        print_diff(&existing, content);
+        if !assume_yes && !confirm(&t!("confirm-overwrite", path = path.display().to_string())) {
+            status!("{}", t!("left-unchanged", path = path.display().to_string()));
+            return Ok(false);
+        }
+        let backup = path.with_file_name(format!("{}.bak", path.file_name().unwrap_or_default().to_string_lossy()));
+        fs::copy(path, &backup).with_context(|| Error::Output(format!("Failed to back up {} to {}", path.display(), backup.display())))?;
+        status!("{}", t!("backed-up", path = backup.display().to_string()));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| Error::Output(format!("Failed to create {}", parent.display())))?;
+    }
+    fs::write(path, content).with_context(|| Error::Output(format!("Failed to write to {}", path.display())))?;
+    Ok(true)
+}
+
+/// Prints a unified, colored line diff between the current and new content.
+pub fn print_diff(current: &str, new: &str) {
+    let diff = TextDiff::from_lines(current, new);
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        status!("{}", paint("36", &hunk.header().to_string()));
+        for change in hunk.iter_changes() {
+            let line = match change.tag() {
+                ChangeTag::Delete => paint("31", &format!("-{}", change)),
+                ChangeTag::Insert => paint("32", &format!("+{}", change)),
+                ChangeTag::Equal => format!(" {}", change),
+            };
+            let _ = write!(console(), "{}", line);
+            if change.missing_newline() {
+                status!();
+            }
+        }
+    }
+}
+
+/// Asks a yes/no question on stdin; anything but `y`/`yes` (including EOF) counts as no.
+pub fn confirm(question: &str) -> bool {
+    let mut console = console();
+    let _ = write!(console, "{} [y/N] ", question);
+    let _ = console.flush();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).unwrap_or(0);
+    is_yes(&answer)
+}
+
+/// Removes a Markdown code fence wrapped around the whole response, which models sometimes add to scripts.
+pub fn strip_code_fences(content: &str) -> String {
+    let trimmed = content.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return format!("{}\n", trimmed);
+    };
+    let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or_default();
+    format!("{}\n", body.trim_end().trim_end_matches("```").trim_end())
+}
+
+/// Marks a file as executable by its owner, group, and others (no-op on non-Unix platforms).
+pub fn make_executable(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs::metadata(path) {
+            let mut permissions = metadata.permissions();
+            permissions.set_mode(permissions.mode() | 0o111);
+            let _ = fs::set_permissions(path, permissions);
+        }
+    }
+}
+
+/// Opens `content` in `$VISUAL` or `$EDITOR` (falling back to `vi`) for review, using a temporary file with the
+/// target's extension so the editor picks the right syntax. After the editor exits, asks whether to finalize the
+/// edited version to `target` (unless `assume_yes`). Returns the edited content, or `None` if the user declines.
+pub fn review_in_editor(content: &str, target: &Path, assume_yes: bool) -> Result<Option<String>> {
+    let suffix = target
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut draft = tempfile::Builder::new()
+        .prefix("magic_quickstart_")
+        .suffix(&suffix)
+        .tempfile()
+        .context("Failed to create a temporary file for editing")?;
+    draft
+        .write_all(content.as_bytes())
+        .context("Failed to write the draft for editing")?;
+
+    // The editor variable may include arguments, e.g. `code --wait`.
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(parts)
+        .arg(draft.path())
+        .status()
+        .with_context(|| format!("Failed to start editor `{}`", editor))?;
+    if !status.success() {
+        status!("{}", t!("editor-failed", status = status.to_string()));
+        return Ok(None);
+    }
+
+    let edited = fs::read_to_string(draft.path()).context("Failed to read the edited draft")?;
+    if !assume_yes && !confirm(&t!("confirm-finalize", path = target.display().to_string())) {
+        status!("{}", t!("draft-discarded", path = target.display().to_string()));
+        return Ok(None);
+    }
+    Ok(Some(edited))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_creates_a_missing_document() {
+        assert_eq!(
+            merge_generated_section(None, "# Quickstart\n").unwrap(),
+            format!("{}\n# Quickstart\n{}\n", START_MARKER, END_MARKER)
+        );
+    }
+
+    #[test]
+    fn merge_appends_to_a_document_without_markers() {
+        assert_eq!(
+            merge_generated_section(Some("# Project\n\nIntro.\n"), "Run it.").unwrap(),
+            format!("# Project\n\nIntro.\n\n{}\nRun it.\n{}\n", START_MARKER, END_MARKER)
+        );
+    }
+
+    #[test]
+    fn merge_replaces_the_section_and_keeps_the_rest() {
+        let existing = format!("# Project\n\n{}\nOld.\n{}\n\n## License\n", START_MARKER, END_MARKER);
+        let merged = merge_generated_section(Some(&existing), "New.").unwrap();
+        assert_eq!(
+            merged,
+            format!("# Project\n\n{}\nNew.\n{}\n\n## License\n", START_MARKER, END_MARKER)
+        );
+        assert_eq!(merge_generated_section(Some(&merged), "New.").unwrap(), merged);
+        assert_eq!(generated_section(&merged), Some("New."));
+    }
+
+    #[test]
+    fn merge_fails_on_mismatched_markers() {
+        assert!(merge_generated_section(Some(&format!("{}\nOld.\n", START_MARKER)), "New.").is_err());
+        assert!(merge_generated_section(Some(&format!("{}\nOld.\n{}\n", END_MARKER, START_MARKER)), "New.").is_err());
+    }
+
+    #[test]
+    fn merge_skips_markers_inside_code_blocks() {
+        let example = format!(
+            "Wrap the section like this:\n\n```markdown\n{}\n...\n{}\n```\n",
+            START_MARKER, END_MARKER
+        );
+        let existing = format!("# Project\n\n{}\n{}\nOld.\n{}\n", example, START_MARKER, END_MARKER);
+        assert_eq!(
+            merge_generated_section(Some(&existing), "New.").unwrap(),
+            format!("# Project\n\n{}\n{}\nNew.\n{}\n", example, START_MARKER, END_MARKER)
+        );
+        assert_eq!(generated_section(&existing), Some("Old."));
+
+        // Markers that only appear in an example mean the document has no section yet.
+        let merged = merge_generated_section(Some(&example), "New.").unwrap();
+        assert_eq!(
+            merged,
+            format!("{}\n\n{}\nNew.\n{}\n", example.trim_end(), START_MARKER, END_MARKER)
+        );
+    }
+}
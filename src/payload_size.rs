@@ -0,0 +1,73 @@
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::cmp::Reverse;
+
+use crate::doc_types::DocType;
+use crate::errors::Error;
+use crate::models::{ContextBundle, GenerationRequest, Message};
+
+/// The cap on a request's serialized size unless MAX_PAYLOAD_BYTES says otherwise: 1 MiB, several times what the
+/// largest context windows take, so only a misconfigured budget reaches it.
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+/// How many of the largest messages and files the error lists.
+const LARGEST_SHOWN: usize = 5;
+
+/// Size of `value` as JSON, the way it is sent.
+fn serialized_len(value: &impl Serialize) -> usize {
+    serde_json::to_vec(value).map(|bytes| bytes.len()).unwrap_or_default()
+}
+
+/// What a message is, for the breakdown: its role and the text before its first colon, e.g. `user: File contents`.
+fn message_label(message: &Message) -> String {
+    let label: String = message
+        .content
+        .split([':', '\n'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(60)
+        .collect();
+    format!("{}: {}", message.role.name(), label.trim())
+}
+
+/// Checks a request against MAX_PAYLOAD_BYTES just before it is sent, whatever the token estimates say (0 turns the
+/// check off). Over the cap, the run stops with the largest messages and project files, so the culprit can be
+/// excluded or the budget fixed.
+pub fn check_payload_size(doc_type: DocType, request: &GenerationRequest, context: &ContextBundle, max_bytes: usize) -> Result<()> {
+    let size = serialized_len(request);
+    if max_bytes == 0 || size <= max_bytes {
+        return Ok(());
+    }
+    let mut messages: Vec<(String, usize)> = request
+        .messages
+        .iter()
+        .map(|message| (message_label(message), serialized_len(message)))
+        .collect();
+    messages.sort_by_key(|(_, size)| Reverse(*size));
+    let mut files: Vec<(String, usize)> = context
+        .project_files_content
+        .iter()
+        .map(|file| (file.file_path.clone(), serialized_len(&file.content)))
+        .collect();
+    files.sort_by_key(|(_, size)| Reverse(*size));
+
+    let mut breakdown = String::from("Largest messages:");
+    for (label, size) in messages.iter().take(LARGEST_SHOWN) {
+        breakdown.push_str(&format!("\n  {:>10} bytes  {}", size, label));
+    }
+    if !files.is_empty() {
+        breakdown.push_str("\nLargest project files:");
+        for (path, size) in files.iter().take(LARGEST_SHOWN) {
+            breakdown.push_str(&format!("\n  {:>10} bytes  {}", size, path));
+        }
+    }
+    bail!(Error::Config(format!(
+        "The {} request is {} bytes, over the MAX_PAYLOAD_BYTES cap of {}; lower TOKEN_BUDGET or \
+         MAX_FILE_COUNT_FOR_CONTEXT, leave large files out with IGNORE_GLOBS, or raise MAX_PAYLOAD_BYTES. Nothing was sent.\n{}",
+        doc_type.name(),
+        size,
+        max_bytes,
+        breakdown
+    )))
+}
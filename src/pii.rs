@@ -0,0 +1,137 @@
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::i18n::t;
+use crate::pseudonyms::is_pseudonymous;
+
+/// Domain suffixes that are only used inside private networks.
+const INTERNAL_SUFFIXES: &str = r"internal|local|localdomain|corp|lan|intranet|home\.arpa";
+
+/// Prefixes of standards and algorithms that look like ticket IDs (`UTF-8`, `SHA-256`) but aren't.
+const NOT_TICKETS: [&str; 14] = [
+    "UTF", "UCS", "SHA", "MD", "ISO", "RFC", "AES", "TLS", "SSL", "HTTP", "PEP", "ES", "ECMA", "CVE",
+];
+
+/// How many of each kind of detail a scrub masked.
+#[derive(Clone, Copy, Default)]
+pub struct PiiCounts {
+    pub emails: usize,
+    pub ip_addresses: usize,
+    pub hostnames: usize,
+    pub ticket_ids: usize,
+}
+
+impl PiiCounts {
+    pub fn total(&self) -> usize {
+        self.emails + self.ip_addresses + self.hostnames + self.ticket_ids
+    }
+
+    /// What was masked, e.g. `2 email addresses, 1 IP address`, leaving out kinds that weren't found.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [
+            ("pii-emails", self.emails),
+            ("pii-ip-addresses", self.ip_addresses),
+            ("pii-hostnames", self.hostnames),
+            ("pii-ticket-ids", self.ticket_ids),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(id, count)| t!(id, count = count))
+        .collect();
+        parts.join(", ")
+    }
+}
+
+/// Masks email addresses, IP addresses, internal hostnames, and ticket IDs (SCRUB_PII), so history and files can be
+/// sent without naming people, machines, or internal work. Each is replaced with a marker such as `<email>`, which
+/// still tells the model what kind of value was there.
+pub struct PiiScrubber {
+    /// Hostnames under the private suffixes or INTERNAL_DOMAINS.
+    hostname: Regex,
+}
+
+fn email() -> &'static Regex {
+    static EMAIL: OnceLock<Regex> = OnceLock::new();
+    EMAIL.get_or_init(|| Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b(:?)").unwrap())
+}
+
+fn ip_address() -> &'static Regex {
+    static IP_ADDRESS: OnceLock<Regex> = OnceLock::new();
+    IP_ADDRESS.get_or_init(|| {
+        Regex::new(r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b|\b(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\b|\b(?:[0-9a-fA-F]{1,4}:){1,6}:(?:[0-9a-fA-F]{1,4}:){0,5}[0-9a-fA-F]{1,4}\b")
+            .unwrap()
+    })
+}
+
+fn ticket_id() -> &'static Regex {
+    static TICKET_ID: OnceLock<Regex> = OnceLock::new();
+    TICKET_ID.get_or_init(|| Regex::new(r"\b([A-Z][A-Z0-9]{1,9})-[1-9][0-9]{0,6}\b").unwrap())
+}
+
+/// Matches hostnames under the private suffixes or `internal_domains` (e.g. `corp.example.com`).
+pub fn internal_hostname(internal_domains: &[String]) -> Regex {
+    let domains: Vec<String> = internal_domains
+        .iter()
+        .map(|domain| regex::escape(domain.trim().trim_start_matches('.')))
+        .chain([format!("(?:{})", INTERNAL_SUFFIXES)])
+        .collect();
+    let hostname = format!(r"(?i)\b(?:[a-z0-9-]+\.)+(?:{})\b", domains.join("|"));
+    Regex::new(&hostname).expect("escaped domains make a valid regex")
+}
+
+impl PiiScrubber {
+    /// A scrubber that also treats hostnames under `internal_domains` (e.g. `corp.example.com`) as internal.
+    pub fn new(internal_domains: &[String]) -> Self {
+        PiiScrubber {
+            hostname: internal_hostname(internal_domains),
+        }
+    }
+
+    /// Masks every detail in `text`, adding what was masked to `counts`.
+    pub fn scrub(&self, text: &str, counts: &mut PiiCounts) -> String {
+        // SCP-style git remotes (`git@github.com:org/repo`) look like email addresses but aren't anyone's, and
+        // neither are PSEUDONYMIZE's placeholders for `user@host`.
+        let text = email().replace_all(text, |captures: &regex::Captures| {
+            if &captures[1] == ":" || is_pseudonymous(&captures[0]) {
+                captures[0].to_string()
+            } else {
+                counts.emails += 1;
+                "<email>".to_string()
+            }
+        });
+        // Loopback and unspecified addresses are part of setup instructions rather than anyone's network.
+        let text = ip_address().replace_all(&text, |captures: &regex::Captures| {
+            let address = &captures[0];
+            if address.starts_with("127.") || address == "0.0.0.0" {
+                address.to_string()
+            } else {
+                counts.ip_addresses += 1;
+                "<ip>".to_string()
+            }
+        });
+        let text = self.hostname.replace_all(&text, |_: &regex::Captures| {
+            counts.hostnames += 1;
+            "<host>"
+        });
+        let text = ticket_id().replace_all(&text, |captures: &regex::Captures| {
+            if NOT_TICKETS.contains(&&captures[1]) {
+                captures[0].to_string()
+            } else {
+                counts.ticket_ids += 1;
+                "<ticket>".to_string()
+            }
+        });
+        text.into_owned()
+    }
+
+    /// Masks every detail in every string in `value`, adding what was masked to `counts`.
+    pub fn scrub_json(&self, value: &mut Value, counts: &mut PiiCounts) {
+        match value {
+            Value::String(text) => *text = self.scrub(text, counts),
+            Value::Array(values) => values.iter_mut().for_each(|value| self.scrub_json(value, counts)),
+            Value::Object(map) => map.values_mut().for_each(|value| self.scrub_json(value, counts)),
+            _ => {}
+        }
+    }
+}
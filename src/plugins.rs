@@ -0,0 +1,244 @@
+#[cfg(feature = "wasm-plugins")]
+use anyhow::{anyhow, Context};
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+#[cfg(feature = "wasm-plugins")]
+use wasmtime::{Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::doc_types::DocType;
+use crate::errors::Error;
+use crate::formats::OutputFormat;
+use crate::models::FileContext;
+use crate::parsers::{read_project_files_content, walk_source_files};
+use crate::sources::{ContextBlock, ContextData, ContextSource};
+
+/// Instructions a plugin may run per call before it is stopped, so a plugin stuck in a loop can't hang a run.
+#[cfg(feature = "wasm-plugins")]
+const PLUGIN_FUEL: u64 = 2_000_000_000;
+
+/// The most memory a plugin instance may grow to.
+#[cfg(feature = "wasm-plugins")]
+const PLUGIN_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+
+/// How many project paths a detector is shown.
+const DETECTOR_PATHS: usize = 2000;
+
+/// A WebAssembly plugin from WASM_PLUGINS. Plugins import nothing, so they can't touch files, the network, or the
+/// environment: they only see the JSON each hook is given. A plugin exports `memory`, `alloc(len: i32) -> i32`, and
+/// any of the hooks `detect`, `context`, and `postprocess`, each taking a pointer and length of UTF-8 JSON and
+/// returning `(pointer << 32) | length` of the JSON it answers with.
+#[cfg(feature = "wasm-plugins")]
+pub struct WasmPlugin {
+    path: PathBuf,
+    engine: Engine,
+    module: Module,
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl WasmPlugin {
+    /// Compiles the plugin at `path`; one that can't be read or compiled is a configuration error.
+    pub fn load(engine: &Engine, path: &Path) -> Result<Self> {
+        let module = Module::from_file(engine, path)
+            .map_err(|error| anyhow!("{:#}", error))
+            .with_context(|| Error::Config(format!("Failed to load the WASM plugin {}", path.display())))?;
+        if module.imports().next().is_some() {
+            bail!(Error::Config(format!(
+                "The WASM plugin {} imports functions, but plugins are given none",
+                path.display()
+            )));
+        }
+        Ok(WasmPlugin {
+            path: path.to_path_buf(),
+            engine: engine.clone(),
+            module,
+        })
+    }
+
+    /// The plugin's file name, for messages.
+    pub fn name(&self) -> String {
+        self.path.file_name().unwrap_or_default().to_string_lossy().to_string()
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn exports(&self, hook: &str) -> bool {
+        self.module.get_export(hook).is_some()
+    }
+
+    /// Calls `hook` with `input` in a fresh instance, so no state carries over between calls.
+    fn call(&self, hook: &str, input: &Value) -> Result<Value> {
+        self.call_instance(hook, input)
+            .map_err(|error| anyhow!("{:#}", error))
+            .with_context(|| format!("The WASM plugin {} failed in `{}`", self.path.display(), hook))
+    }
+
+    fn call_instance(&self, hook: &str, input: &Value) -> wasmtime::Result<Value> {
+        let limits = StoreLimitsBuilder::new().memory_size(PLUGIN_MEMORY_BYTES).build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(PLUGIN_FUEL)?;
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("it doesn't export `memory`"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let function = instance.get_typed_func::<(i32, i32), i64>(&mut store, hook)?;
+
+        let input = serde_json::to_vec(input)?;
+        let length = i32::try_from(input.len())?;
+        let pointer = alloc.call(&mut store, length)?;
+        memory.write(&mut store, pointer as u32 as usize, &input)?;
+        let packed = function.call(&mut store, (pointer, length))? as u64;
+        let (pointer, length) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let output = memory
+            .data(&store)
+            .get(pointer..pointer + length)
+            .ok_or_else(|| anyhow!("it answered with memory it doesn't have"))?;
+        serde_json::from_slice(output).map_err(|error| anyhow!("it answered with invalid JSON: {}", error))
+    }
+}
+
+/// Without the `wasm-plugins` feature no plugin can be loaded, so there is never one to call.
+#[cfg(not(feature = "wasm-plugins"))]
+pub enum WasmPlugin {}
+
+#[cfg(not(feature = "wasm-plugins"))]
+impl WasmPlugin {
+    pub fn name(&self) -> String {
+        match *self {}
+    }
+
+    fn path(&self) -> &Path {
+        match *self {}
+    }
+
+    fn exports(&self, _hook: &str) -> bool {
+        match *self {}
+    }
+
+    fn call(&self, _hook: &str, _input: &Value) -> Result<Value> {
+        match *self {}
+    }
+}
+
+/// The plugins from WASM_PLUGINS and `--plugin`, in the order they were listed, which is the order their hooks run.
+#[derive(Default)]
+pub struct Plugins {
+    plugins: Vec<WasmPlugin>,
+}
+
+impl Plugins {
+    /// Compiles the plugin at `path` and adds it after the others.
+    #[cfg(feature = "wasm-plugins")]
+    pub fn add(&mut self, path: &Path) -> Result<()> {
+        // Fuel is only counted if the engine is set up for it, so every plugin shares one that is.
+        let engine = match self.plugins.first() {
+            Some(plugin) => plugin.engine.clone(),
+            None => {
+                let mut config = wasmtime::Config::new();
+                config.consume_fuel(true);
+                Engine::new(&config).map_err(|error| anyhow!("{:#}", error))?
+            }
+        };
+        self.plugins.push(WasmPlugin::load(&engine, path)?);
+        Ok(())
+    }
+
+    /// Refuses the plugin at `path`: this build can't run plugins.
+    #[cfg(not(feature = "wasm-plugins"))]
+    pub fn add(&mut self, path: &Path) -> Result<()> {
+        bail!(Error::Config(format!(
+            "Can't load the WASM plugin {}: this build of magic_quickstart was made without the `wasm-plugins` feature",
+            path.display()
+        )))
+    }
+
+    /// Project files the `detect` hooks pick, given every path in the project (up to a limit) relative to
+    /// `project_dir`. Paths that leave the project or aren't files are dropped.
+    pub fn detect(&self, project_dir: &Path, follow_symlinks: bool) -> Result<Vec<PathBuf>> {
+        let detectors: Vec<&WasmPlugin> = self.plugins.iter().filter(|plugin| plugin.exports("detect")).collect();
+        if detectors.is_empty() {
+            return Ok(vec![]);
+        }
+        let files: Vec<PathBuf> = walk_source_files(project_dir, &[], DETECTOR_PATHS, follow_symlinks)
+            .files
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(project_dir).ok().map(Path::to_path_buf))
+            .collect();
+        let mut picked = Vec::new();
+        for plugin in detectors {
+            let output = plugin.call("detect", &json!({ "files": files }))?;
+            for path in output["files"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                let path = PathBuf::from(path);
+                let inside = path.components().all(|component| matches!(component, Component::Normal(_)));
+                if inside && project_dir.join(&path).is_file() && !picked.contains(&path) {
+                    picked.push(path);
+                }
+            }
+        }
+        Ok(picked)
+    }
+
+    /// A context source for each plugin with a `context` hook, given the contents of `files`.
+    pub fn context_sources<'a>(&'a self, files: &'a [PathBuf]) -> Vec<WasmPluginSource<'a>> {
+        self.plugins
+            .iter()
+            .filter(|plugin| plugin.exports("context"))
+            .map(|plugin| WasmPluginSource {
+                name: plugin.name(),
+                plugin,
+                files,
+            })
+            .collect()
+    }
+
+    /// Runs the `postprocess` hooks over a document in turn, each getting what the one before returned.
+    pub fn postprocess(&self, doc_type: DocType, format: OutputFormat, content: String) -> Result<String> {
+        let mut content = content;
+        for plugin in self.plugins.iter().filter(|plugin| plugin.exports("postprocess")) {
+            let output = plugin.call(
+                "postprocess",
+                &json!({"doc_type": doc_type.name(), "extension": format.extension(), "content": content}),
+            )?;
+            let Some(processed) = output["content"].as_str() else {
+                bail!(
+                    "The WASM plugin {} answered `postprocess` without `content`",
+                    plugin.path().display()
+                );
+            };
+            content = processed.to_string();
+        }
+        Ok(content)
+    }
+}
+
+/// Context from a plugin's `context` hook, which is given the project files the detectors picked and answers with
+/// labeled blocks: `{"blocks": [{"label": "...", "data": ...}]}`.
+pub struct WasmPluginSource<'a> {
+    name: String,
+    plugin: &'a WasmPlugin,
+    files: &'a [PathBuf],
+}
+
+impl ContextSource for WasmPluginSource<'_> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn collect(&self, project_dir: &Path) -> Result<Vec<ContextBlock>> {
+        let files: Vec<FileContext> = read_project_files_content(project_dir, self.files);
+        let output = self.plugin.call("context", &json!({ "files": files }))?;
+        Ok(output["blocks"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|block| {
+                let label = block["label"].as_str()?;
+                Some(ContextBlock::new(label, ContextData::Labeled(block["data"].clone())))
+            })
+            .collect())
+    }
+}
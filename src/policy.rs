@@ -0,0 +1,196 @@
+use anyhow::{bail, Context, Result};
+use globset::Glob;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::parse_cost;
+use crate::errors::Error;
+
+/// Where an organization installs its data-handling policy.
+#[cfg(not(windows))]
+pub const SYSTEM_POLICY_PATH: &str = "/etc/magic_quickstart/policy.toml";
+#[cfg(windows)]
+pub const SYSTEM_POLICY_PATH: &str = r"C:\ProgramData\magic_quickstart\policy.toml";
+
+/// The keys a policy file may set.
+const POLICY_KEYS: [&str; 4] = ["allowed_providers", "forbidden_globs", "redact_patterns", "max_cost"];
+
+/// Rules an organization enforces on every run, read from `SYSTEM_POLICY_PATH` and the file MAGIC_QUICKSTART_POLICY
+/// names, if either exists. Unlike settings, nothing in the configuration files, the environment, or flags can relax
+/// them: globs and patterns are added to the configured ones, and the cost cap is the lowest of all that are set.
+/// When both files exist, both apply.
+#[derive(Default)]
+pub struct Policy {
+    /// The policy files that were read.
+    pub sources: Vec<PathBuf>,
+    /// Providers requests may be sent to; `None` allows every provider.
+    pub allowed_providers: Option<Vec<String>>,
+    /// Globs of project files that are never sent, each with the file that set it.
+    pub forbidden_globs: Vec<(String, String)>,
+    /// Regular expressions that are always redacted, each with the file that set it.
+    pub redact_patterns: Vec<(String, String)>,
+    /// The highest estimated cost in US dollars a run may send, with the file that set it.
+    pub max_cost: Option<(f64, String)>,
+}
+
+impl Policy {
+    /// Reads the policy files. A policy file that can't be read or parsed, or sets an unknown key or a value of the
+    /// wrong type, is a configuration error, since running without the rules it was meant to enforce isn't safe.
+    pub fn load() -> Result<Self> {
+        let mut policy = Policy::default();
+        let system = Path::new(SYSTEM_POLICY_PATH);
+        if system.exists() {
+            policy.read(system)?;
+        }
+        if let Some(path) = env::var_os("MAGIC_QUICKSTART_POLICY").filter(|path| !path.is_empty()) {
+            policy.read(Path::new(&path))?;
+        }
+        Ok(policy)
+    }
+
+    fn read(&mut self, path: &Path) -> Result<()> {
+        let source = path.display().to_string();
+        let content = fs::read_to_string(path).with_context(|| Error::Config(format!("Failed to read the policy file {}", source)))?;
+        let table: toml::Table = content
+            .parse()
+            .with_context(|| Error::Config(format!("Failed to parse the policy file {}", source)))?;
+        let invalid =
+            |key: &str, expected: &str| Error::Config(format!("Invalid {} in the policy file {} (expected {})", key, source, expected));
+
+        for (key, value) in &table {
+            if !POLICY_KEYS.contains(&key.as_str()) {
+                bail!(Error::Config(format!(
+                    "Unknown key {} in the policy file {} (expected one of {})",
+                    key,
+                    source,
+                    POLICY_KEYS.join(", ")
+                )));
+            }
+            if key == "max_cost" {
+                let cost = match value {
+                    toml::Value::Float(cost) => Some(*cost).filter(|cost| *cost >= 0.0),
+                    toml::Value::Integer(cost) => u32::try_from(*cost).ok().map(f64::from),
+                    toml::Value::String(cost) => parse_cost(cost),
+                    _ => None,
+                };
+                let Some(cost) = cost else {
+                    bail!(invalid(key, "an amount in US dollars, e.g. 0.50"));
+                };
+                if self.max_cost.as_ref().is_none_or(|(max_cost, _)| cost < *max_cost) {
+                    self.max_cost = Some((cost, source.clone()));
+                }
+                continue;
+            }
+            let Some(entries) = value.as_array().and_then(|values| {
+                values
+                    .iter()
+                    .map(|value| value.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+            }) else {
+                bail!(invalid(key, "an array of strings"));
+            };
+            let broken = match key.as_str() {
+                "forbidden_globs" => entries
+                    .iter()
+                    .find(|glob| Glob::new(glob).is_err())
+                    .map(|glob| (glob, "a glob such as *.pem or secrets/**")),
+                "redact_patterns" => entries
+                    .iter()
+                    .find(|pattern| Regex::new(pattern).is_err())
+                    .map(|pattern| (pattern, "a regular expression")),
+                _ => None,
+            };
+            if let Some((entry, expected)) = broken {
+                bail!(invalid(&format!("{} entry \"{}\"", key, entry), expected));
+            }
+            match key.as_str() {
+                // Each file narrows the providers further, so a second policy can't allow what the first forbids.
+                "allowed_providers" => {
+                    let entries: Vec<String> = entries.iter().map(|provider| provider.trim().to_lowercase()).collect();
+                    self.allowed_providers = Some(match self.allowed_providers.take() {
+                        Some(allowed) => allowed.into_iter().filter(|provider| entries.contains(provider)).collect(),
+                        None => entries,
+                    });
+                }
+                "forbidden_globs" => self.forbidden_globs.extend(entries.into_iter().map(|glob| (glob, source.clone()))),
+                _ => self
+                    .redact_patterns
+                    .extend(entries.into_iter().map(|pattern| (pattern, source.clone()))),
+            }
+        }
+        self.sources.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Fails with a configuration error if the policy doesn't allow sending to `provider`.
+    pub fn check_provider(&self, provider: &str) -> Result<()> {
+        match &self.allowed_providers {
+            Some(allowed) if !allowed.iter().any(|allowed| allowed == provider) => bail!(Error::Config(format!(
+                "The policy in {} doesn't allow sending to {} (allowed providers: {})",
+                self.source_list(),
+                provider,
+                if allowed.is_empty() {
+                    "none".to_string()
+                } else {
+                    allowed.join(", ")
+                }
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Lowers a configured cost cap to the policy's, or sets it if none was configured.
+    pub fn cap_cost(&self, max_cost: Option<f64>) -> Option<f64> {
+        match (&self.max_cost, max_cost) {
+            (Some((policy_cost, _)), Some(cost)) => Some(cost.min(*policy_cost)),
+            (Some((policy_cost, _)), None) => Some(*policy_cost),
+            (None, cost) => cost,
+        }
+    }
+
+    /// The policy file that set `max_cost`, if it is the policy's cap.
+    pub fn cost_source(&self, max_cost: f64) -> Option<&str> {
+        self.max_cost
+            .as_ref()
+            .filter(|(policy_cost, _)| *policy_cost == max_cost)
+            .map(|(_, source)| source.as_str())
+    }
+
+    fn source_list(&self) -> String {
+        let sources: Vec<String> = self.sources.iter().map(|path| path.display().to_string()).collect();
+        sources.join(" and ")
+    }
+
+    /// Prints what the policy enforces as commented TOML, for `config effective`.
+    pub fn print(&self) {
+        if self.sources.is_empty() {
+            return;
+        }
+        println!();
+        println!("# Enforced by the policy in {}; settings can't override it.", self.source_list());
+        if let Some(allowed) = &self.allowed_providers {
+            let allowed: Vec<String> = allowed
+                .iter()
+                .map(|provider| toml::Value::String(provider.clone()).to_string())
+                .collect();
+            println!("# allowed_providers = [{}]", allowed.join(", "));
+        }
+        for (key, entries) in [
+            ("forbidden_globs", &self.forbidden_globs),
+            ("redact_patterns", &self.redact_patterns),
+        ] {
+            if !entries.is_empty() {
+                println!("# {} = [", key);
+                for (entry, source) in entries {
+                    println!("#     {}, # {}", toml::Value::String(entry.clone()), source);
+                }
+                println!("# ]");
+            }
+        }
+        if let Some((max_cost, source)) = &self.max_cost {
+            println!("# max_cost = {} # {}", toml::Value::Float(*max_cost), source);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Result};
+use pulldown_cmark::{html, Options, Parser};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tiny_http::{Header, Response, Server};
+
+/// Polls the server for changes and reloads the page when the previewed file is modified.
+const RELOAD_SCRIPT: &str = r#"<script>
+let version = null;
+setInterval(async () => {
+  try {
+    const current = await (await fetch('/version')).text();
+    if (version !== null && current !== version) location.reload();
+    version = current;
+  } catch (e) {}
+}, 1000);
+</script>"#;
+
+/// Minimal styling so the preview resembles a rendered README.
+const STYLE: &str = "<style>body{max-width:860px;margin:2rem auto;padding:0 1rem;font-family:-apple-system,Segoe UI,Helvetica,Arial,sans-serif;line-height:1.6}pre{background:#f6f8fa;padding:1rem;overflow:auto}code{background:#f6f8fa;padding:.1rem .3rem}table{border-collapse:collapse}td,th{border:1px solid #ddd;padding:.3rem .6rem}</style>";
+
+/// Finds the file to preview: the newest `README_GENERATED_*.md` in `directory`, else its README.md.
+pub fn default_preview_file(directory: &Path) -> Option<PathBuf> {
+    let newest_generated = fs::read_dir(directory)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.starts_with("README_GENERATED_") && name.ends_with(".md")
+        })
+        .max();
+    let readme = directory.join("README.md");
+    newest_generated.or_else(|| readme.exists().then_some(readme))
+}
+
+/// Renders Markdown to a standalone HTML page with the auto-reload script.
+fn render_page(path: &Path) -> String {
+    let markdown = fs::read_to_string(path).unwrap_or_else(|_| format!("Failed to read `{}`", path.display()));
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(&markdown, options));
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title>{}</head><body>{}{}</body></html>",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        STYLE,
+        body,
+        RELOAD_SCRIPT
+    )
+}
+
+/// Identifies the current version of the file by its modification time.
+fn file_version(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_millis().to_string())
+        .unwrap_or_default()
+}
+
+/// Serves the rendered Markdown file on localhost until interrupted, reloading the page whenever the file changes.
+pub fn serve_preview(path: &Path, port: u16) -> Result<()> {
+    let address = format!("127.0.0.1:{}", port);
+    let server = Server::http(&address).map_err(|error| anyhow!("Failed to start preview server on {}: {}", address, error))?;
+    println!("Previewing {} at http://{} (Ctrl-C to stop)", path.display(), address);
+
+    let html_header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+    for request in server.incoming_requests() {
+        let response = match request.url() {
+            "/version" => Response::from_string(file_version(path)),
+            "/" | "/index.html" => Response::from_string(render_page(path)).with_header(html_header.clone()),
+            _ => Response::from_string("Not found").with_status_code(404),
+        };
+        if let Err(error) = request.respond(response) {
+            println!("Failed to respond to preview request: {}", error);
+        }
+    }
+    Ok(())
+}
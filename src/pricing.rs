@@ -0,0 +1,41 @@
+use crate::models::GenerationRequest;
+use crate::tokens::estimate_tokens;
+
+/// USD prices per million input and output tokens, by model name prefix. More specific prefixes come first.
+const MODEL_PRICES: [(&str, f64, f64); 9] = [
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o3-mini", 1.10, 4.40),
+    ("o4-mini", 1.10, 4.40),
+];
+
+/// Typical length of a generated document, used to estimate the output cost before anything is sent.
+pub const EXPECTED_OUTPUT_TOKENS: usize = 1_500;
+
+/// Tokens a chat message costs on top of its content.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Estimates the input tokens of a chat completion request from its message contents.
+pub fn request_tokens(request: &GenerationRequest) -> usize {
+    request
+        .messages
+        .iter()
+        .map(|message| estimate_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS)
+        .sum()
+}
+
+/// Estimates the cost in USD of a request to `model`, or `None` for models without a known price.
+pub fn estimate_cost(model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+    let (_, input_price, output_price) = MODEL_PRICES.iter().find(|(prefix, ..)| model.starts_with(prefix))?;
+    Some((input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0)
+}
+
+/// Formats a cost estimate, e.g. `$0.0123`, or `unknown` for models without a known price.
+pub fn format_cost(cost: Option<f64>) -> String {
+    cost.map(|cost| format!("${:.4}", cost)).unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+
+use crate::doc_types::{DocType, ScriptShell, TaskRunner};
+use crate::formats::OutputFormat;
+use crate::injection::{untrusted_block, UNTRUSTED_DATA_INSTRUCTIONS};
+use crate::models::{ContextBundle, FileContext, GenerationRequest, HistoryEntry, Message, ResponseFormat};
+use crate::sections::SectionPresets;
+use crate::style::{Audience, Tone};
+use crate::templates::{render_prompt_template, slot_instructions};
+
+/// Options that shape the prompt independently of the gathered context.
+#[derive(Clone)]
+pub struct PromptOptions {
+    pub model: String,
+    pub seed: Option<u32>,
+    pub time_back_hours: i64,
+    pub format: OutputFormat,
+    pub doc_type: DocType,
+    pub script_shell: ScriptShell,
+    pub task_runner: TaskRunner,
+    /// Ask for a Dockerfile alongside devcontainer.json.
+    pub devcontainer_dockerfile: bool,
+    /// Natural language to write the document in, e.g. `ja` or `German` (English if unset).
+    pub language: Option<String>,
+    pub audience: Option<Audience>,
+    pub tone: Option<Tone>,
+    /// Heading of the single section to regenerate with `--section`.
+    pub section: Option<String>,
+    /// Sections a quickstart must include or leave out, from `[sections]`.
+    pub sections: Option<SectionPresets>,
+    /// Tera template replacing the default system prompt and user-message scaffolding.
+    pub prompt_template: Option<PathBuf>,
+    /// Ask the model for JSON template slots instead of a finished document.
+    pub structured_output: bool,
+}
+
+/// Builds the system prompt for the document type and output format (or script language for scripts).
+fn system_prompt(options: &PromptOptions) -> String {
+    let mut prompt = if options.doc_type.writes_project_file() {
+        let language = match options.doc_type {
+            DocType::Tasks => options.task_runner.display_name(),
+            DocType::Devcontainer => "JSON",
+            DocType::CiWorkflow => "YAML",
+            _ => options.script_shell.display_name(),
+        };
+        let mut prompt = options.doc_type.system_prompt(language);
+        if options.doc_type == DocType::Devcontainer {
+            prompt.push_str(if options.devcontainer_dockerfile {
+                "  Write a Dockerfile that installs the toolchain and reference it from devcontainer.json with a `build` section."
+            } else {
+                "  Prefer a prebuilt image with Dev Container Features and leave `dockerfile` empty unless the toolchain cannot be set up without one."
+            });
+        }
+        prompt
+    } else {
+        let mut prompt = options.doc_type.system_prompt(options.format.display_name());
+        if let Some(instructions) = options.format.instructions() {
+            prompt.push_str("  ");
+            prompt.push_str(instructions);
+        }
+        prompt
+    };
+
+    if let Some(language) = &options.language {
+        if options.doc_type.writes_project_file() {
+            prompt.push_str(&format!("  Write all comments in {}.", language));
+        } else {
+            prompt.push_str(&format!(
+                "  Write the document in {}, keeping commands, code, file names, and identifiers unchanged.",
+                language
+            ));
+        }
+    }
+    if let Some(heading) = &options.section {
+        prompt.push_str(&format!("  Regenerate only the \"{heading}\" section of the current document provided below, using the fresh project data. Output just that section, starting with its `## {heading}` heading, and keep it consistent in style with the rest of the document."));
+    }
+    if let Some(sections) = options.sections.as_ref().filter(|_| options.doc_type == DocType::Quickstart) {
+        prompt.push_str("  ");
+        prompt.push_str(&sections.instructions());
+    }
+    for instructions in [options.audience.map(Audience::instructions), options.tone.map(Tone::instructions)]
+        .into_iter()
+        .flatten()
+    {
+        prompt.push_str("  ");
+        prompt.push_str(instructions);
+    }
+    prompt.push_str("  ");
+    prompt.push_str(UNTRUSTED_DATA_INSTRUCTIONS);
+    prompt
+}
+
+/// The shell history as one untrusted block, an entry per line.
+fn history_block(history: &[HistoryEntry]) -> String {
+    let entries: Vec<String> = history.iter().map(HistoryEntry::to_json).collect();
+    untrusted_block("shell history", &entries.join("\n"))
+}
+
+/// Each file's contents in its own untrusted block, named by its path.
+fn file_blocks(files: &[FileContext]) -> String {
+    let blocks: Vec<String> = files.iter().map(|file| untrusted_block(&file.file_path, &file.content)).collect();
+    blocks.join("\n")
+}
+
+/// Context entries as a JSON array.
+fn json_list<T: Serialize>(entries: &[T]) -> String {
+    serde_json::to_string(entries).unwrap_or_default()
+}
+
+/// Extra context as an untrusted block; strings are sent as they are, anything else as JSON.
+fn extra_block(label: &str, value: &serde_json::Value) -> String {
+    match value.as_str() {
+        Some(text) => untrusted_block(label, text),
+        None => untrusted_block(label, &value.to_string()),
+    }
+}
+
+/// Constructs the request for the OpenAI API.
+/// Fails if the prompt template can't be rendered.
+pub fn build_request_payload(context: &ContextBundle, options: &PromptOptions) -> Result<GenerationRequest> {
+    let mut messages = vec![
+        Message::system(system_prompt(options)),
+        Message::user(options.doc_type.user_request()),
+        Message::user(format!(
+            "Shell history (last {} hours):\n{}",
+            options.time_back_hours,
+            history_block(&context.command_history)
+        )),
+        Message::user(format!("Project files: {:?}", context.project_files)),
+        Message::user(format!("File contents:\n{}", file_blocks(&context.project_files_content))),
+        Message::user(format!(
+            "Top-level dependencies from lockfiles (if any): {}",
+            json_list(&context.dependency_summary)
+        )),
+        Message::user(format!(
+            "Asset and data directory inventory (if any): {}",
+            json_list(&context.asset_inventory)
+        )),
+        Message::user(format!(
+            "Environment file keys with their source file and usage in code (required, optional, or unused) (if any): {}",
+            json_list(&context.env_file_keys)
+        )),
+    ];
+
+    for (label, value) in &context.extra_context {
+        messages.push(Message::user(format!("{}:\n{}", label, extra_block(label, value))));
+    }
+
+    // Few-shot exemplars follow the system prompt, so the style guidance comes before the project context.
+    if !context.exemplars.is_empty() && !options.doc_type.writes_project_file() {
+        let mut position = 1;
+        messages.insert(position, Message::system("The following are example documents from my organization. Match their structure, heading style, and tone, but take all facts from this project's data only."));
+        for exemplar in &context.exemplars {
+            position += 1;
+            messages.insert(
+                position,
+                Message::user(format!("Example document {}:\n{}", exemplar.file, exemplar.content)),
+            );
+        }
+    }
+
+    if let Some(template_path) = &options.prompt_template {
+        messages = render_prompt_template(template_path, &prompt_template_context(context, options))?;
+    }
+
+    let mut response_format = None;
+    if options.doc_type == DocType::Devcontainer {
+        response_format = Some(ResponseFormat::JsonObject);
+    } else if options.structured_output {
+        messages.insert(1, Message::system(slot_instructions()));
+        response_format = Some(ResponseFormat::JsonObject);
+    }
+
+    Ok(GenerationRequest {
+        model: options.model.clone(),
+        messages,
+        response_format,
+        seed: options.seed,
+    })
+}
+
+/// Variables available to a PROMPT_TEMPLATE: the default `system_prompt` and `user_request`, the gathered context,
+/// and the document settings.
+fn prompt_template_context(context: &ContextBundle, options: &PromptOptions) -> tera::Context {
+    let extra_context: Vec<serde_json::Value> = context
+        .extra_context
+        .iter()
+        .map(|(label, value)| json!({"label": label, "value": value}))
+        .collect();
+
+    let mut template_context = tera::Context::new();
+    template_context.insert("system_prompt", &system_prompt(options));
+    template_context.insert("user_request", options.doc_type.user_request());
+    template_context.insert("hours", &options.time_back_hours);
+    template_context.insert("history", &context.command_history);
+    template_context.insert("files", &context.project_files);
+    template_context.insert("file_contents", &context.project_files_content);
+    template_context.insert("dependencies", &context.dependency_summary);
+    template_context.insert("assets", &context.asset_inventory);
+    template_context.insert("env_keys", &context.env_file_keys);
+    template_context.insert("extra_context", &extra_context);
+    template_context.insert("exemplars", &context.exemplars);
+    template_context.insert("doc_type", options.doc_type.file_stem());
+    template_context.insert("format", options.format.display_name());
+    template_context.insert("language", &options.language);
+    template_context
+}
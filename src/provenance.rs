@@ -0,0 +1,28 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of the request payload, identifying exactly which inputs a document was generated from.
+pub fn context_hash(request_body: &impl Serialize) -> String {
+    format!("{:x}", Sha256::digest(serde_json::to_vec(request_body).unwrap_or_default()))
+}
+
+/// One-line provenance record: tool version, model, generation time, and context hash.
+pub fn provenance_line(model: &str, context_hash: &str) -> String {
+    format!(
+        "Generated by magic_quickstart {} with {} on {} from context sha256:{}",
+        env!("CARGO_PKG_VERSION"),
+        model,
+        chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        context_hash
+    )
+}
+
+/// Appends a comment line to `content`, separated by a newline if needed.
+pub fn append_footer(mut content: String, comment: &str) -> String {
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(comment);
+    content.push('\n');
+    content
+}
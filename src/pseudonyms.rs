@@ -0,0 +1,217 @@
+use anyhow::{bail, Context, Result};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::errors::Error;
+use crate::file_lock::FileLock;
+use crate::i18n::t;
+use crate::pii::internal_hostname;
+use crate::settings::data_home;
+
+/// A kind of identifier that gets a pseudonym.
+#[derive(Clone, Copy, PartialEq)]
+enum Identifier {
+    Hostname,
+    Bucket,
+    Username,
+}
+
+/// Home directory names that don't belong to anyone, and the account every SSH git remote uses.
+const SHARED_USERNAMES: [&str; 5] = ["shared", "public", "default", "all users", "git"];
+
+/// Bucket names in storage URLs, virtual-hosted and path-style S3 endpoints, and `--bucket` arguments. Each
+/// alternative captures the name in its own group.
+fn bucket() -> &'static Regex {
+    static BUCKET: OnceLock<Regex> = OnceLock::new();
+    BUCKET.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)\b(?:s3a?|gs|gcs|r2|az|wasbs?|abfss?)://([a-z0-9][a-z0-9._-]{1,61}[a-z0-9])",
+            r"|\b([a-z0-9][a-z0-9.-]{1,61}[a-z0-9])\.s3(?:[.-][a-z0-9-]+)?\.amazonaws\.com",
+            r"|\bs3(?:[.-][a-z0-9-]+)?\.amazonaws\.com/([a-z0-9][a-z0-9._-]{1,61}[a-z0-9])",
+            r"|\bstorage\.googleapis\.com/([a-z0-9][a-z0-9._-]{1,61}[a-z0-9])",
+            r#"|--bucket(?:-name)?[=\s]+["']?([a-z0-9][a-z0-9._-]{1,61}[a-z0-9])"#,
+        ))
+        .unwrap()
+    })
+}
+
+/// Usernames in home directory paths and in the `user@host` of SSH-style commands, each captured in its own group.
+fn username() -> &'static Regex {
+    static USERNAME: OnceLock<Regex> = OnceLock::new();
+    USERNAME.get_or_init(|| {
+        Regex::new(concat!(
+            r"/home/([a-z_][a-z0-9_.-]{0,31})",
+            r#"|/Users/([^/\s"'`]+)"#,
+            r#"|\b[A-Za-z]:\\Users\\([^\\\s"'`]+)"#,
+            r"|\b(?:ssh|scp|sftp|mosh|rsync)\b[^\n|;&]*?\s([a-z_][a-z0-9_.-]{0,31})@[A-Za-z0-9]",
+        ))
+        .unwrap()
+    })
+}
+
+/// Whether an email-like `address` is made of placeholders, e.g. `user-c19e5f30@host-3f2a9c1e.example` from
+/// `ssh deploy@bastion.corp`, which is already anonymous and has to survive SCRUB_PII to be mapped back.
+pub fn is_pseudonymous(address: &str) -> bool {
+    static PSEUDONYMOUS: OnceLock<Regex> = OnceLock::new();
+    PSEUDONYMOUS
+        .get_or_init(|| Regex::new(r"^user-[0-9a-f]{8}@host-[0-9a-f]{8}\.example$").unwrap())
+        .is_match(address)
+}
+
+/// Where the key pseudonyms are derived from is kept: `$XDG_DATA_HOME/magic_quickstart/pseudonym.key`, defaulting
+/// to `~/.local/share/magic_quickstart/pseudonym.key`.
+fn key_path() -> Result<PathBuf> {
+    match data_home() {
+        Some(dir) => Ok(dir.join("magic_quickstart").join("pseudonym.key")),
+        None => bail!(Error::Config(
+            "Can't find the directory for the pseudonym key; set HOME or XDG_DATA_HOME".to_string()
+        )),
+    }
+}
+
+/// Reads the pseudonym key, creating a random one the first time. `RandomState` is seeded from the operating
+/// system's random source, so no one else can derive the same pseudonyms. On Unix the key is only readable by the
+/// user. The key file is locked while it is read, so instances starting at the same time agree on one key.
+fn load_or_create_key() -> Result<String> {
+    let path = key_path()?;
+    if let Ok(key) = fs::read_to_string(&path) {
+        if !key.trim().is_empty() {
+            return Ok(key.trim().to_string());
+        }
+    }
+    let error = || Error::Config(format!("Can't write the pseudonym key {}; fix its permissions", path.display()));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(error)?;
+    }
+    let mut lock = FileLock::open(&path).with_context(error)?;
+    // Another instance may have created the key while this one waited.
+    let existing = lock.read_to_string().with_context(error)?;
+    if !existing.trim().is_empty() {
+        return Ok(existing.trim().to_string());
+    }
+    let key: String = (0..4u8).map(|part| format!("{:016x}", RandomState::new().hash_one(part))).collect();
+    lock.replace(&format!("{}\n", key)).with_context(error)?;
+    Ok(key)
+}
+
+/// Replaces internal hostnames, bucket names, and usernames with placeholders such as `host-3f2a9c1e.example`,
+/// `bucket-8d04b7a2`, and `user-c19e5f30` (PSEUDONYMIZE). Unlike SCRUB_PII's markers, each identifier always gets
+/// the same placeholder, derived from a key kept on this machine, so the model can still tell that two commands
+/// talk to the same server while never learning its name. The placeholders are mapped back in the response.
+pub struct Pseudonymizer {
+    key: String,
+    /// Hostnames under the private suffixes or INTERNAL_DOMAINS.
+    hostname: Regex,
+    /// Each placeholder handed out, with the kind and the identifier it stands for.
+    originals: BTreeMap<String, (Identifier, String)>,
+}
+
+/// The placeholder for an identifier: a keyed hash of it, so it is the same in every run and project.
+fn placeholder(key: &str, originals: &mut BTreeMap<String, (Identifier, String)>, kind: Identifier, identifier: &str) -> String {
+    // Hostnames and bucket names ignore case; usernames don't.
+    let normalized = match kind {
+        Identifier::Username => identifier.to_string(),
+        _ => identifier.to_lowercase(),
+    };
+    let (prefix, suffix) = match kind {
+        Identifier::Hostname => ("host", ".example"),
+        Identifier::Bucket => ("bucket", ""),
+        Identifier::Username => ("user", ""),
+    };
+    let digest = Sha256::digest(format!("{}\0{}\0{}", key, prefix, normalized));
+    let hash: String = digest[..4].iter().map(|byte| format!("{:02x}", byte)).collect();
+    let placeholder = format!("{}-{}{}", prefix, hash, suffix);
+    originals.insert(placeholder.clone(), (kind, identifier.to_string()));
+    placeholder
+}
+
+/// Replaces the first capture group that took part in the match with `replacement`, keeping the rest of the match.
+pub fn replace_group(captures: &Captures, replacement: impl FnOnce(&str) -> String) -> String {
+    let whole = captures.get(0).expect("group 0 is the whole match");
+    match captures.iter().skip(1).flatten().next() {
+        Some(group) => format!(
+            "{}{}{}",
+            &whole.as_str()[..group.start() - whole.start()],
+            replacement(group.as_str()),
+            &whole.as_str()[group.end() - whole.start()..]
+        ),
+        None => whole.as_str().to_string(),
+    }
+}
+
+impl Pseudonymizer {
+    /// A pseudonymizer that also treats hostnames under `internal_domains` as internal. Fails with a configuration
+    /// error if the key can't be read or created.
+    pub fn new(internal_domains: &[String]) -> Result<Self> {
+        Ok(Pseudonymizer {
+            key: load_or_create_key()?,
+            hostname: internal_hostname(internal_domains),
+            originals: BTreeMap::new(),
+        })
+    }
+
+    /// Replaces every identifier in `text` with its placeholder.
+    pub fn pseudonymize(&mut self, text: &str) -> String {
+        let (key, originals) = (&self.key, &mut self.originals);
+        let text = bucket().replace_all(text, |captures: &Captures| {
+            replace_group(captures, |name| placeholder(key, originals, Identifier::Bucket, name))
+        });
+        let text = username().replace_all(&text, |captures: &Captures| {
+            replace_group(captures, |name| {
+                if SHARED_USERNAMES.contains(&name.to_lowercase().as_str()) {
+                    name.to_string()
+                } else {
+                    placeholder(key, originals, Identifier::Username, name)
+                }
+            })
+        });
+        let text = self.hostname.replace_all(&text, |captures: &Captures| {
+            placeholder(key, originals, Identifier::Hostname, &captures[0])
+        });
+        text.into_owned()
+    }
+
+    /// Replaces every identifier in every string in `value`.
+    pub fn pseudonymize_json(&mut self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.pseudonymize(text),
+            Value::Array(values) => values.iter_mut().for_each(|value| self.pseudonymize_json(value)),
+            Value::Object(map) => map.values_mut().for_each(|value| self.pseudonymize_json(value)),
+            _ => {}
+        }
+    }
+
+    /// Puts the real identifiers back in place of the placeholders in a response.
+    pub fn restore(&self, text: &str) -> String {
+        self.originals.iter().fold(text.to_string(), |text, (placeholder, (_, original))| {
+            text.replace(placeholder, original)
+        })
+    }
+
+    /// How many different identifiers were replaced.
+    pub fn count(&self) -> usize {
+        self.originals.len()
+    }
+
+    /// What was replaced, e.g. `2 internal hostnames, 1 bucket`, leaving out kinds that weren't found.
+    pub fn summary(&self) -> String {
+        let count = |kind: Identifier| self.originals.values().filter(|(found, _)| *found == kind).count();
+        let parts: Vec<String> = [
+            ("pii-hostnames", count(Identifier::Hostname)),
+            ("pseudonym-buckets", count(Identifier::Bucket)),
+            ("pseudonym-usernames", count(Identifier::Username)),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(id, count)| t!(id, count = count))
+        .collect();
+        parts.join(", ")
+    }
+}
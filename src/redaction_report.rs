@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use crate::doc_types::DocType;
+use crate::i18n::t;
+use crate::injection::InjectionScan;
+use crate::pii::PiiCounts;
+use crate::secrets::{Finding, SecretScan};
+
+/// What each rule that keeps something out of the requests found, for `redact --report`. Like the secret scanner's
+/// findings, it says where something was found and which rule found it, never the text itself.
+#[derive(Default)]
+pub struct RedactionReport {
+    /// Project and CI files left out by IGNORE_GLOBS or the policy's forbidden globs.
+    pub ignored_files: Vec<String>,
+    /// Project and CI files left out because a comment at their top has the exclude marker.
+    pub excluded_files: Vec<String>,
+    /// Project and CI files left out because they look like credential stores, with the reason.
+    pub credential_files: Vec<(String, &'static str)>,
+    /// How many history entries each COMMAND_BLOCKLIST entry dropped.
+    pub blocked_commands: BTreeMap<String, usize>,
+    /// How many credentials typed on the command line each rule removed from history entries.
+    pub history_credentials: BTreeMap<&'static str, usize>,
+    /// Env keys left out by ENV_KEY_ALLOWLIST or ENV_KEY_DENYLIST.
+    pub filtered_env_keys: Vec<String>,
+    /// Likely secrets in file contents, whatever SECRET_SCAN does with them.
+    pub secrets: Vec<Finding>,
+    /// Instruction-like text in file contents, whatever INJECTION_SCAN does with it.
+    pub injections: Vec<Finding>,
+    /// Personal details SCRUB_PII masks, or would mask if it were set.
+    pub pii: PiiCounts,
+    /// What PSEUDONYMIZE replaced, or `None` if it is off.
+    pub pseudonyms: Option<String>,
+    /// How many times each redaction pattern matched in each document's request.
+    pub redactions: Vec<(DocType, Vec<(String, usize)>)>,
+}
+
+impl RedactionReport {
+    /// Prints the findings of every rule, including the rules that found nothing, so patterns that never match
+    /// stand out as well as ones that match too much.
+    pub fn print(&self, secret_scan: SecretScan, injection_scan: InjectionScan, scrub_pii: bool) {
+        println!("{}", t!("report-title"));
+
+        println!("{}", t!("report-ignored-files", count = self.ignored_files.len()));
+        for path in &self.ignored_files {
+            println!("  {}", path);
+        }
+
+        println!("{}", t!("report-excluded-files", count = self.excluded_files.len()));
+        for path in &self.excluded_files {
+            println!("  {}", path);
+        }
+
+        println!("{}", t!("report-credential-files", count = self.credential_files.len()));
+        for (path, reason) in &self.credential_files {
+            println!("  {}: {}", path, reason);
+        }
+
+        let blocked: usize = self.blocked_commands.values().sum();
+        println!("{}", t!("report-blocked-commands", count = blocked));
+        for (command, count) in &self.blocked_commands {
+            println!("  {}: {}", command, count);
+        }
+
+        let stripped: usize = self.history_credentials.values().sum();
+        println!("{}", t!("report-history-credentials", count = stripped));
+        for (rule, count) in &self.history_credentials {
+            println!("  {}: {}", rule, count);
+        }
+
+        println!("{}", t!("report-env-keys", count = self.filtered_env_keys.len()));
+        for key in &self.filtered_env_keys {
+            println!("  {}", key);
+        }
+
+        let secrets_id = match secret_scan {
+            SecretScan::Redact => "report-secrets-redact",
+            SecretScan::Abort => "report-secrets-abort",
+            SecretScan::Off => "report-secrets-off",
+        };
+        println!("{}", t!(secrets_id, count = self.secrets.len()));
+        for finding in &self.secrets {
+            println!("  {}", finding);
+        }
+
+        let injections_id = match injection_scan {
+            InjectionScan::Strip => "report-injections-strip",
+            InjectionScan::Flag => "report-injections-flag",
+            InjectionScan::Off => "report-injections-off",
+        };
+        println!("{}", t!(injections_id, count = self.injections.len()));
+        for finding in &self.injections {
+            println!("  {}", finding);
+        }
+
+        if self.pii.total() == 0 {
+            println!("{}", t!("report-pii-none"));
+        } else if scrub_pii {
+            println!("{}", t!("report-pii-masked", summary = self.pii.summary()));
+        } else {
+            println!("{}", t!("report-pii-unmasked", summary = self.pii.summary()));
+        }
+
+        match &self.pseudonyms {
+            None => println!("{}", t!("report-pseudonyms-off")),
+            Some(summary) if summary.is_empty() => println!("{}", t!("report-pseudonyms-none")),
+            Some(summary) => println!("{}", t!("report-pseudonyms", summary = summary.as_str())),
+        }
+
+        for (doc_type, patterns) in &self.redactions {
+            if patterns.is_empty() {
+                println!("{}", t!("report-no-patterns", doc_type = doc_type.name()));
+                continue;
+            }
+            println!("{}", t!("report-patterns", doc_type = doc_type.name()));
+            for (pattern, count) in patterns {
+                println!("  {}", t!("report-pattern-matches", pattern = pattern.as_str(), count = *count));
+            }
+        }
+        println!("{}", t!("report-nothing-sent"));
+    }
+}
@@ -0,0 +1,36 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::console::status;
+use crate::errors::Error;
+use crate::interrupt::remove_on_interrupt;
+
+/// Returns true if the argument looks like a git remote rather than a local path.
+pub fn is_git_url(target: &str) -> bool {
+    ["https://", "http://", "ssh://", "git://", "git@"]
+        .iter()
+        .any(|prefix| target.starts_with(prefix))
+}
+
+/// Shallow-clones a git repository into a temporary directory that is removed when dropped, or on interrupt.
+/// A failed clone is reported as a network error, since an unreachable or inaccessible remote is the usual cause.
+pub fn clone_repository(url: &str) -> Result<TempDir> {
+    let checkout = tempfile::Builder::new()
+        .prefix("magic_quickstart_")
+        .tempdir()
+        .context("Failed to create temporary directory")?;
+    remove_on_interrupt(checkout.path());
+    status!("Cloning {} into {}", url, checkout.path().display());
+
+    let status = Command::new("git")
+        .args(["clone", "--depth", "1", "--quiet", url])
+        .arg(checkout.path())
+        .status()
+        .context("Failed to run git. Is it installed?")?;
+    if !status.success() {
+        return Err(Error::Network(format!("git clone of {} failed", url)).into());
+    }
+
+    Ok(checkout)
+}
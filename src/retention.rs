@@ -0,0 +1,168 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::artifacts::ARTIFACTS_DIR;
+use crate::audit::default_audit_log_path;
+use crate::console::status;
+use crate::file_lock::FileLock;
+use crate::i18n::t;
+use crate::settings::{parse_setting, ConfigFiles};
+use crate::state::StateStore;
+
+/// What a purge removed, or would remove with `dry_run`.
+#[derive(Default)]
+pub struct Purged {
+    /// Ids of saved runs.
+    pub runs: Vec<String>,
+    pub artifacts: Vec<PathBuf>,
+    pub audit_entries: usize,
+}
+
+impl Purged {
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty() && self.artifacts.is_empty() && self.audit_entries == 0
+    }
+}
+
+/// RETENTION_DAYS, if it is set. 0 is refused: as a setting, it would delete every saved run and audit log entry on
+/// each start, so deleting everything takes `--retention-days 0` or `purge --older-than 0` on the command line.
+pub fn retention_days(files: &ConfigFiles) -> Result<Option<u64>> {
+    parse_setting(files, "RETENTION_DAYS", "a whole number of days, at least 1", |days| {
+        days.parse().ok().filter(|&days| days > 0)
+    })
+}
+
+/// The audit log AUDIT_LOG names, or the default one.
+pub fn audit_log_path(files: &ConfigFiles) -> Result<PathBuf> {
+    match files.get("AUDIT_LOG").filter(|path| !path.trim().is_empty()) {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => default_audit_log_path(),
+    }
+}
+
+/// The local time `days` days ago; anything older is purged. `None` if that is before any time chrono can represent.
+fn cutoff(days: u64) -> Option<DateTime<Local>> {
+    Local::now().checked_sub_signed(Duration::try_days(i64::try_from(days).ok()?)?)
+}
+
+/// Kept artifacts in `project_dir/.magic_quickstart` last written before `cutoff`. The directory's `.gitignore` is
+/// left alone.
+fn expired_artifacts(project_dir: &Path, cutoff: &DateTime<Local>) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(project_dir.join(ARTIFACTS_DIR)) else {
+        return vec![];
+    };
+    let cutoff = SystemTime::from(*cutoff);
+    let mut artifacts: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name() != ".gitignore")
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified < cutoff)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    artifacts.sort();
+    artifacts
+}
+
+/// Removes the audit log's entries from before `cutoff`, keeping the rest in order. Lines without a readable time
+/// are kept. Returns how many were (or, with `dry_run`, would be) removed.
+fn purge_audit_log(path: &Path, cutoff: &DateTime<Local>, dry_run: bool) -> Result<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    // Runs appending to the log at the same time wait until it has been replaced, then append to the new one.
+    let _lock = FileLock::beside(path)?;
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(0);
+    };
+    let (expired, kept): (Vec<&str>, Vec<&str>) = content.lines().partition(|line| {
+        serde_json::from_str::<Value>(line)
+            .ok()
+            .and_then(|record| DateTime::parse_from_rfc3339(record["timestamp"].as_str()?).ok())
+            .is_some_and(|time| time < *cutoff)
+    });
+    if expired.is_empty() || dry_run {
+        return Ok(expired.len());
+    }
+    // Written beside the log and renamed over it, so an interrupted purge leaves the old log whole.
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".purge");
+    let temporary = PathBuf::from(temporary);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&temporary)
+        .with_context(|| format!("Failed to write {}", temporary.display()))?;
+    for line in &kept {
+        writeln!(file, "{}", line).with_context(|| format!("Failed to write {}", temporary.display()))?;
+    }
+    drop(file);
+    fs::rename(&temporary, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(expired.len())
+}
+
+/// Deletes saved runs, kept artifacts of `project_dir`, and audit log entries older than `days` days. With
+/// `dry_run`, only finds them. There is no response cache, so nothing else is kept that could expire.
+pub fn purge(project_dir: &Path, audit_log: &Path, days: u64, dry_run: bool) -> Result<Purged> {
+    let Some(cutoff) = cutoff(days) else {
+        return Ok(Purged::default());
+    };
+    let purged = Purged {
+        runs: StateStore::open()?.expire_runs(&cutoff, dry_run)?,
+        artifacts: expired_artifacts(project_dir, &cutoff),
+        audit_entries: purge_audit_log(audit_log, &cutoff, dry_run)?,
+    };
+    if !dry_run {
+        for path in &purged.artifacts {
+            fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+    }
+    Ok(purged)
+}
+
+/// Enforces RETENTION_DAYS at startup: deletes what has expired and says so in one status line.
+pub fn enforce_retention(project_dir: &Path, audit_log: &Path, days: u64) -> Result<()> {
+    let purged = purge(project_dir, audit_log, days, false)?;
+    if !purged.is_empty() {
+        status!(
+            "{}",
+            t!(
+                "retention-purged",
+                days = days,
+                runs = purged.runs.len(),
+                artifacts = purged.artifacts.len(),
+                entries = purged.audit_entries
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Prints what a `purge` removed, or would remove with `dry_run`.
+pub fn print_purged(purged: &Purged, days: u64, dry_run: bool) {
+    if purged.is_empty() {
+        println!("Nothing is older than {} days.", days);
+        return;
+    }
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    for id in &purged.runs {
+        println!("{} run {}", verb, id);
+    }
+    for path in &purged.artifacts {
+        println!("{} {}", verb, path.display());
+    }
+    if purged.audit_entries > 0 {
+        let verb = if dry_run { "Would remove" } else { "Removed" };
+        println!("{} {} audit log entries", verb, purged.audit_entries);
+    }
+}
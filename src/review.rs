@@ -0,0 +1,145 @@
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::console::{console, status};
+use crate::i18n::t;
+use crate::models::{EnvKey, FileContext, HistoryEntry};
+use crate::tokens::estimate_tokens;
+
+/// A single piece of context the user can include or exclude.
+struct ReviewItem {
+    kind: ItemKind,
+    label: String,
+    tokens: usize,
+    included: bool,
+}
+
+#[derive(Clone, Copy)]
+enum ItemKind {
+    File(usize),
+    History(usize),
+    EnvKey(usize),
+}
+
+/// Lists every file, history entry, and env key about to be sent with token estimates,
+/// and lets the user toggle items on stdin until they press Enter on an empty line.
+/// Excluded items are removed from the given collections.
+pub fn review_context(
+    command_history: &mut Vec<HistoryEntry>,
+    project_files: &mut Vec<PathBuf>,
+    project_files_content: &mut Vec<FileContext>,
+    env_file_keys: &mut Vec<EnvKey>,
+) {
+    let mut items = Vec::new();
+    for (index, file) in project_files.iter().enumerate() {
+        let content = project_files_content
+            .iter()
+            .find(|entry| entry.file_path == file.display().to_string())
+            .map_or("", |entry| entry.content.as_str());
+        items.push(ReviewItem {
+            kind: ItemKind::File(index),
+            label: format!("file     {}", file.display()),
+            tokens: estimate_tokens(content),
+            included: true,
+        });
+    }
+    for (index, entry) in command_history.iter().enumerate() {
+        items.push(ReviewItem {
+            kind: ItemKind::History(index),
+            label: format!("history  {}", entry.command),
+            tokens: estimate_tokens(&entry.to_json()),
+            included: true,
+        });
+    }
+    for (index, entry) in env_file_keys.iter().enumerate() {
+        items.push(ReviewItem {
+            kind: ItemKind::EnvKey(index),
+            label: format!("env key  {} ({})", entry.key, entry.file),
+            tokens: estimate_tokens(&entry.to_json()),
+            included: true,
+        });
+    }
+
+    if items.is_empty() {
+        status!("{}", t!("review-empty"));
+        return;
+    }
+
+    let stdin = io::stdin();
+    loop {
+        print_items(&items);
+        let mut console = console();
+        let _ = write!(console, "{} ", t!("review-prompt"));
+        let _ = console.flush();
+
+        let mut input = String::new();
+        if stdin.lock().read_line(&mut input).unwrap_or(0) == 0 || input.trim().is_empty() {
+            break;
+        }
+        for selection in parse_selection(input.trim(), items.len()) {
+            items[selection].included = !items[selection].included;
+        }
+    }
+
+    let excluded = |kind: fn(ItemKind) -> Option<usize>| -> Vec<usize> {
+        items
+            .iter()
+            .filter(|item| !item.included)
+            .filter_map(|item| kind(item.kind))
+            .collect()
+    };
+    let excluded_files = excluded(|kind| if let ItemKind::File(i) = kind { Some(i) } else { None });
+    let excluded_history = excluded(|kind| if let ItemKind::History(i) = kind { Some(i) } else { None });
+    let excluded_env_keys = excluded(|kind| if let ItemKind::EnvKey(i) = kind { Some(i) } else { None });
+
+    let excluded_paths: Vec<String> = excluded_files.iter().map(|&i| project_files[i].display().to_string()).collect();
+    project_files_content.retain(|entry| !excluded_paths.contains(&entry.file_path));
+    retain_by_index(project_files, &excluded_files);
+    retain_by_index(command_history, &excluded_history);
+    retain_by_index(env_file_keys, &excluded_env_keys);
+}
+
+fn print_items(items: &[ReviewItem]) {
+    status!();
+    for (number, item) in items.iter().enumerate() {
+        let marker = if item.included { "x" } else { " " };
+        status!(
+            "{:>4} [{}] {:>13}  {}",
+            number + 1,
+            marker,
+            t!("review-item-tokens", tokens = item.tokens),
+            item.label
+        );
+    }
+    let total: usize = items.iter().filter(|item| item.included).map(|item| item.tokens).sum();
+    status!("{}", t!("review-total", tokens = total));
+}
+
+/// Parses a selection such as `1 3 5-7` or `a` into zero-based indices, ignoring out of range entries.
+fn parse_selection(input: &str, count: usize) -> Vec<usize> {
+    if input.eq_ignore_ascii_case("a") {
+        return (0..count).collect();
+    }
+
+    let mut selected = Vec::new();
+    for part in input.split([' ', ',']).filter(|part| !part.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.parse::<usize>(), end.parse::<usize>()),
+            None => (part.parse::<usize>(), part.parse::<usize>()),
+        };
+        match (start, end) {
+            (Ok(start), Ok(end)) if start >= 1 && start <= end => selected.extend((start..=end.min(count)).map(|n| n - 1)),
+            _ => status!("{}", t!("review-invalid-selection", selection = part)),
+        }
+    }
+    selected
+}
+
+fn retain_by_index<T>(items: &mut Vec<T>, excluded: &[usize]) {
+    let mut index = 0;
+    items.retain(|_| {
+        let keep = !excluded.contains(&index);
+        index += 1;
+        keep
+    });
+}
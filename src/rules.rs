@@ -0,0 +1,197 @@
+use anyhow::{bail, Result};
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::errors::Error;
+use crate::models::Message;
+use crate::policy::Policy;
+use crate::settings::ConfigFiles;
+
+/// What each match of a redaction pattern is replaced with.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Rules that keep things out of what is sent, usually set once in the user's configuration file and extended by
+/// each project's (see `ConfigFiles::list_entries`), the environment, and flags.
+#[derive(Clone, Default)]
+pub struct ContextRules {
+    /// REDACT_PATTERNS: matches are replaced with `REDACTED` in the prompt messages.
+    redact_patterns: Vec<Regex>,
+    /// IGNORE_GLOBS: project files whose path (relative to the project) matches are never read or listed.
+    ignore_globs: Vec<GlobMatcher>,
+    /// COMMAND_BLOCKLIST: history entries running one of these commands are dropped.
+    blocked_commands: Vec<String>,
+    /// ENV_KEY_ALLOWLIST: if any are set, only env keys matching one of these globs are sent.
+    allowed_env_keys: Vec<GlobMatcher>,
+    /// ENV_KEY_DENYLIST: env keys matching one of these globs are never sent.
+    denied_env_keys: Vec<GlobMatcher>,
+}
+
+impl ContextRules {
+    /// Reads the merged REDACT_PATTERNS, IGNORE_GLOBS, COMMAND_BLOCKLIST, ENV_KEY_ALLOWLIST, and ENV_KEY_DENYLIST,
+    /// with the policy's redaction patterns and forbidden globs added. Fails with a configuration error naming where
+    /// an invalid pattern or glob was set.
+    pub fn load(files: &ConfigFiles, policy: &Policy) -> Result<Self> {
+        let mut rules = ContextRules::default();
+        for (pattern, source) in files
+            .list_entries("REDACT_PATTERNS")
+            .into_iter()
+            .chain(policy.redact_patterns.clone())
+        {
+            rules.redact(&pattern, &source)?;
+        }
+        for (glob, source) in files.list_entries("IGNORE_GLOBS").into_iter().chain(policy.forbidden_globs.clone()) {
+            rules.ignore(&glob, &source)?;
+        }
+        for (command, _) in files.list_entries("COMMAND_BLOCKLIST") {
+            rules.block_command(&command);
+        }
+        for (pattern, source) in files.list_entries("ENV_KEY_ALLOWLIST") {
+            rules.allow_env_key(&pattern, &source)?;
+        }
+        for (pattern, source) in files.list_entries("ENV_KEY_DENYLIST") {
+            rules.deny_env_key(&pattern, &source)?;
+        }
+        Ok(rules)
+    }
+
+    /// Adds a redaction pattern; `source` says where it was set, for the error if it isn't a valid regex.
+    pub fn redact(&mut self, pattern: &str, source: &str) -> Result<()> {
+        match Regex::new(pattern) {
+            Ok(regex) => self.redact_patterns.push(regex),
+            Err(_) => bail!(Error::Config(format!(
+                "Invalid REDACT_PATTERNS entry \"{}\" in {} (expected a regular expression)",
+                pattern, source
+            ))),
+        }
+        Ok(())
+    }
+
+    /// Adds an ignore glob; `source` says where it was set, for the error if it isn't a valid glob.
+    pub fn ignore(&mut self, glob: &str, source: &str) -> Result<()> {
+        match Glob::new(glob) {
+            Ok(glob) => self.ignore_globs.push(glob.compile_matcher()),
+            Err(_) => bail!(Error::Config(format!(
+                "Invalid IGNORE_GLOBS entry \"{}\" in {} (expected a glob such as *.pem or secrets/**)",
+                glob, source
+            ))),
+        }
+        Ok(())
+    }
+
+    /// Adds a command to the blocklist, e.g. `vault` or `aws configure`.
+    pub fn block_command(&mut self, command: &str) {
+        self.blocked_commands.push(command.trim().to_string());
+    }
+
+    /// Adds an env key glob such as `APP_*` to the allowlist; `source` says where it was set, for the error if it
+    /// isn't a valid glob.
+    pub fn allow_env_key(&mut self, pattern: &str, source: &str) -> Result<()> {
+        self.allowed_env_keys.push(env_key_glob("ENV_KEY_ALLOWLIST", pattern, source)?);
+        Ok(())
+    }
+
+    /// Adds an env key glob such as `*SECRET*` to the denylist; `source` says where it was set, for the error if it
+    /// isn't a valid glob.
+    pub fn deny_env_key(&mut self, pattern: &str, source: &str) -> Result<()> {
+        self.denied_env_keys.push(env_key_glob("ENV_KEY_DENYLIST", pattern, source)?);
+        Ok(())
+    }
+
+    /// Whether an env key may be sent: it matches no denied glob, and an allowed one if there are any.
+    pub fn includes_env_key(&self, key: &str) -> bool {
+        (self.allowed_env_keys.is_empty() || self.allowed_env_keys.iter().any(|glob| glob.is_match(key)))
+            && !self.denied_env_keys.iter().any(|glob| glob.is_match(key))
+    }
+
+    /// Whether a project file, relative to the project directory, matches an ignore glob.
+    pub fn ignores(&self, path: &Path) -> bool {
+        self.ignore_globs.iter().any(|glob| glob.is_match(path))
+    }
+
+    /// Whether a history command runs a blocked command: it is the blocked command, or starts with it followed by
+    /// arguments, so `vault` blocks `vault read secret/db` but not `vaultwarden`.
+    pub fn blocks(&self, command: &str) -> bool {
+        self.blocked_by(command).is_some()
+    }
+
+    /// The blocklist entry a history command runs, if any.
+    pub fn blocked_by(&self, command: &str) -> Option<&str> {
+        let command = command.trim();
+        self.blocked_commands
+            .iter()
+            .find(|blocked| {
+                command
+                    .strip_prefix(blocked.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            })
+            .map(String::as_str)
+    }
+
+    /// Replaces every match of a redaction pattern in `text`, returning the new text and how many matches there were.
+    pub fn redact_text(&self, text: &str) -> (String, usize) {
+        let mut text = text.to_string();
+        let mut count = 0;
+        for pattern in &self.redact_patterns {
+            let matches = pattern.find_iter(&text).count();
+            if matches > 0 {
+                count += matches;
+                text = pattern.replace_all(&text, REDACTED).into_owned();
+            }
+        }
+        (text, count)
+    }
+
+    /// How many times each redaction pattern matches a request's messages, before any is replaced, including
+    /// patterns that don't match at all.
+    pub fn pattern_matches(&self, messages: &[Message]) -> Vec<(&str, usize)> {
+        self.redact_patterns
+            .iter()
+            .map(|pattern| {
+                let count = messages.iter().map(|message| pattern.find_iter(&message.content).count()).sum();
+                (pattern.as_str(), count)
+            })
+            .collect()
+    }
+
+    /// Redacts a request's messages in place, returning how many matches were replaced.
+    pub fn redact_messages(&self, messages: &mut [Message]) -> usize {
+        if self.redact_patterns.is_empty() {
+            return 0;
+        }
+        messages
+            .iter_mut()
+            .map(|message| {
+                let (redacted, count) = self.redact_text(&message.content);
+                message.content = redacted;
+                count
+            })
+            .sum()
+    }
+
+    /// Redacts every string in `value` in place, returning how many matches were replaced.
+    pub fn redact_json(&self, value: &mut Value) -> usize {
+        match value {
+            Value::String(text) => {
+                let (redacted, count) = self.redact_text(text);
+                *text = redacted;
+                count
+            }
+            Value::Array(values) => values.iter_mut().map(|value| self.redact_json(value)).sum(),
+            Value::Object(map) => map.values_mut().map(|value| self.redact_json(value)).sum(),
+            _ => 0,
+        }
+    }
+}
+
+/// Compiles an env key glob, ignoring case as env key conventions vary.
+fn env_key_glob(setting: &str, pattern: &str, source: &str) -> Result<GlobMatcher> {
+    match GlobBuilder::new(pattern.trim()).case_insensitive(true).build() {
+        Ok(glob) => Ok(glob.compile_matcher()),
+        Err(_) => bail!(Error::Config(format!(
+            "Invalid {} entry \"{}\" in {} (expected a glob such as *SECRET* or APP_*)",
+            setting, pattern, source
+        ))),
+    }
+}
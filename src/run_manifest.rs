@@ -0,0 +1,160 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::doc_types::DocType;
+use crate::errors::Error;
+use crate::file_lock::FileLock;
+use crate::models::GenerationRequest;
+use crate::provenance::context_hash;
+
+/// The manifest's name in the output directory.
+pub const RUN_MANIFEST_FILE: &str = "quickstart.lock";
+
+/// Version of the manifest's layout, raised when a field is removed, renamed, or changes meaning.
+const MANIFEST_VERSION: u32 = 1;
+
+/// What every generated document in an output directory was generated from, so a later run can tell whether the
+/// inputs changed without sending anything. Kept as `quickstart.lock` next to the documents and meant to be
+/// committed with them.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub version: u32,
+    #[serde(default, rename = "document")]
+    pub documents: Vec<ManifestEntry>,
+}
+
+/// One document in the manifest, from the run that last generated it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub doc_type: String,
+    pub tool_version: String,
+    pub model: String,
+    pub seed: Option<u32>,
+    /// SHA-256 of the request the document was generated from, as in `--json` records and provenance footers.
+    pub context_hash: String,
+    /// The files written, relative to the output directory.
+    pub output_paths: Vec<String>,
+}
+
+impl ManifestEntry {
+    /// The entry for a document generated from `request_body` into `output_paths`.
+    pub fn new(doc_type: DocType, request_body: &GenerationRequest, output_dir: &Path, output_paths: &[PathBuf]) -> Self {
+        ManifestEntry {
+            doc_type: doc_type.name().to_string(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            model: request_body.model.clone(),
+            seed: request_body.seed,
+            context_hash: context_hash(request_body),
+            output_paths: output_paths
+                .iter()
+                .map(|path| path.strip_prefix(output_dir).unwrap_or(path).display().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Why a document's inputs no longer match the manifest, from the most to the least specific.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Drift {
+    /// The manifest has no entry for the document.
+    Missing,
+    /// It was generated with another model.
+    Model,
+    /// It was generated with another SEED.
+    Seed,
+    /// It was generated by another version of the tool, whose prompts may differ.
+    ToolVersion,
+    /// The gathered context differs.
+    Context,
+}
+
+impl Drift {
+    /// The drift as the `--json` record names it, e.g. `context`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Drift::Missing => "missing",
+            Drift::Model => "model",
+            Drift::Seed => "seed",
+            Drift::ToolVersion => "tool_version",
+            Drift::Context => "context",
+        }
+    }
+}
+
+impl RunManifest {
+    /// Reads the manifest in `output_dir`, or an empty one if there is none yet. A manifest that can't be parsed, or
+    /// was written by a newer version, is a configuration error.
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(RUN_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(RunManifest::default());
+        }
+        let content = FileLock::open(&path)?.read_to_string()?;
+        Self::parse(&content, &path)
+    }
+
+    /// Parses the manifest read from `path`; an empty file is an empty manifest.
+    fn parse(content: &str, path: &Path) -> Result<Self> {
+        if content.trim().is_empty() {
+            return Ok(RunManifest::default());
+        }
+        let manifest: RunManifest =
+            toml::from_str(content).map_err(|err| Error::Config(format!("{} is not a valid run manifest: {}", path.display(), err)))?;
+        if manifest.version > MANIFEST_VERSION {
+            bail!(Error::Config(format!(
+                "{} was written by a newer version of magic_quickstart; upgrade to read it",
+                path.display()
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// The entry for `doc_type`, if the manifest has one.
+    pub fn entry(&self, doc_type: DocType) -> Option<&ManifestEntry> {
+        self.documents.iter().find(|entry| entry.doc_type == doc_type.name())
+    }
+
+    /// Records `entries` in the manifest in `output_dir`, replacing those for the same document types, so documents
+    /// generated in separate runs share one manifest. Documents are kept in a stable order so the file only changes
+    /// when they do. The manifest stays locked from reading it to writing it back, so runs writing to the same
+    /// directory at the same time both get their entries in.
+    pub fn update(output_dir: &Path, entries: Vec<ManifestEntry>) -> Result<PathBuf> {
+        let path = output_dir.join(RUN_MANIFEST_FILE);
+        let error = || Error::Output(format!("Failed to write {}", path.display()));
+        let mut lock = FileLock::open(&path).with_context(error)?;
+        let mut manifest = Self::parse(&lock.read_to_string()?, &path)?;
+        for entry in entries {
+            match manifest.documents.iter_mut().find(|existing| existing.doc_type == entry.doc_type) {
+                Some(existing) => *existing = entry,
+                None => manifest.documents.push(entry),
+            }
+        }
+        manifest.version = MANIFEST_VERSION;
+        manifest.documents.sort_by(|a, b| a.doc_type.cmp(&b.doc_type));
+        let content = format!(
+            "# Written by magic_quickstart; `magic_quickstart --verify-manifest` checks it against the current inputs.\n{}",
+            toml::to_string(&manifest)?
+        );
+        lock.replace(&content).with_context(error)?;
+        Ok(path)
+    }
+
+    /// Whether the document `request_body` would generate still matches the manifest, and if not, why.
+    pub fn drift(&self, doc_type: DocType, request_body: &GenerationRequest) -> Option<Drift> {
+        let Some(entry) = self.entry(doc_type) else {
+            return Some(Drift::Missing);
+        };
+        if entry.context_hash == context_hash(request_body) {
+            None
+        } else if entry.model != request_body.model {
+            Some(Drift::Model)
+        } else if entry.seed != request_body.seed {
+            Some(Drift::Seed)
+        } else if entry.tool_version != env!("CARGO_PKG_VERSION") {
+            Some(Drift::ToolVersion)
+        } else {
+            Some(Drift::Context)
+        }
+    }
+}
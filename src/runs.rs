@@ -0,0 +1,223 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::console::status;
+use crate::encryption::{decrypt_to_string, Sealer};
+use crate::errors::Error;
+use crate::output::{make_executable, print_diff, write_output};
+use crate::pricing::format_cost;
+use crate::state::{state_path, StateStore, StoredRun};
+
+/// Saves a generated run: its `--json` record plus the time, the project (a directory or git URL), and the content
+/// of every file it wrote, so it can be compared with later runs. The id is the local start time as
+/// `YYYYMMDD-HHMMSS`, with a suffix when runs start in the same second, as with `--watch` or several instances at
+/// once. With `encrypt` (ENCRYPT_ARTIFACTS), the record is saved encrypted.
+pub fn save_run(mut record: Value, project: &str, written: &[PathBuf], encrypt: bool) -> Result<String> {
+    let store = StateStore::open()?;
+    let now = Local::now();
+
+    // Paths are stored absolute so a run can be found and restored from any directory.
+    let outputs: Vec<Value> = written
+        .iter()
+        .filter_map(|path| {
+            let content = fs::read_to_string(path).ok()?;
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            Some(json!({"path": path, "content": content}))
+        })
+        .collect();
+    record["created_at"] = json!(now.to_rfc3339());
+    record["project"] = json!(project);
+    record["outputs"] = json!(outputs);
+
+    let content = serde_json::to_string_pretty(&record)?.into_bytes();
+    let content = if encrypt { Sealer::get()?.seal(&content) } else { content };
+    store.insert_run(&now.format("%Y%m%d-%H%M%S").to_string(), &now, &content, encrypt)
+}
+
+/// A stored run's record, decrypted if need be, with its id.
+fn decode_run(run: StoredRun) -> Result<Value> {
+    let source = format!("Run {}", run.id);
+    let mut record: Value =
+        serde_json::from_str(&decrypt_to_string(run.record, &source)?).with_context(|| format!("{} is damaged", source))?;
+    record["id"] = json!(run.id);
+    Ok(record)
+}
+
+/// Reads a saved run; an unknown id is a configuration error.
+pub fn load_run(id: &str) -> Result<Value> {
+    let Some(run) = StateStore::open()?.run(id)? else {
+        bail!(Error::Config(format!(
+            "No saved run {}; `magic_quickstart runs list` shows them",
+            id
+        )));
+    };
+    decode_run(run)
+}
+
+/// Every saved run, oldest first. Runs that can't be read (or decrypted) are skipped.
+fn saved_runs() -> Result<Vec<Value>> {
+    Ok(StateStore::open()?
+        .runs()?
+        .into_iter()
+        .filter_map(|run| decode_run(run).ok())
+        .collect())
+}
+
+/// The document types a run generated, e.g. `quickstart, setup-script`.
+fn doc_types(run: &Value) -> String {
+    let doc_types: Vec<&str> = run["documents"]
+        .as_array()
+        .map(|documents| documents.iter().filter_map(|document| document["doc_type"].as_str()).collect())
+        .unwrap_or_default();
+    doc_types.join(", ")
+}
+
+/// Prints one line per saved run, oldest first: id, project, documents, model, and cost.
+pub fn list_runs() -> Result<()> {
+    let runs = saved_runs()?;
+    if runs.is_empty() {
+        println!("No saved runs in {}", state_path()?.display());
+        return Ok(());
+    }
+    for run in &runs {
+        println!(
+            "{:20} {:40} {:24} {:12} {}",
+            run["id"].as_str().unwrap_or_default(),
+            run["project"].as_str().unwrap_or_default(),
+            doc_types(run),
+            run["model"].as_str().unwrap_or_default(),
+            format_cost(run["cost"].as_f64())
+        );
+    }
+    Ok(())
+}
+
+/// Prints a saved run: when and for what it ran, the model and cost, the context it sent, and every file it wrote.
+pub fn show_run(id: &str) -> Result<()> {
+    let run = load_run(id)?;
+    println!("Run:       {}", id);
+    println!("Created:   {}", run["created_at"].as_str().unwrap_or_default());
+    println!("Project:   {}", run["project"].as_str().unwrap_or_default());
+    println!("Documents: {}", doc_types(&run));
+    println!("Model:     {}", run["model"].as_str().unwrap_or_default());
+    println!("Cost:      {}", format_cost(run["cost"].as_f64()));
+    println!("Context:   {}", serde_json::to_string_pretty(&run["context"])?);
+    for output in run["outputs"].as_array().into_iter().flatten() {
+        println!("\n--- {} ---", output["path"].as_str().unwrap_or_default());
+        print!("{}", output["content"].as_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Compares two saved runs: the model, cost, and context that changed, then a diff of each file they wrote.
+pub fn diff_runs(a: &str, b: &str) -> Result<()> {
+    let (run_a, run_b) = (load_run(a)?, load_run(b)?);
+    println!("--- {} ({})", a, run_a["created_at"].as_str().unwrap_or_default());
+    println!("+++ {} ({})", b, run_b["created_at"].as_str().unwrap_or_default());
+
+    let mut changes = Vec::new();
+    for key in ["project", "model"] {
+        if run_a[key] != run_b[key] {
+            changes.push((key.to_string(), run_a[key].to_string(), run_b[key].to_string()));
+        }
+    }
+    if run_a["cost"] != run_b["cost"] {
+        changes.push((
+            "cost".to_string(),
+            format_cost(run_a["cost"].as_f64()),
+            format_cost(run_b["cost"].as_f64()),
+        ));
+    }
+    let empty = serde_json::Map::new();
+    let context_a = run_a["context"].as_object().unwrap_or(&empty);
+    let context_b = run_b["context"].as_object().unwrap_or(&empty);
+    let mut keys: Vec<&String> = context_a.keys().chain(context_b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let (value_a, value_b) = (
+            context_a.get(key).unwrap_or(&Value::Null),
+            context_b.get(key).unwrap_or(&Value::Null),
+        );
+        if value_a != value_b {
+            changes.push((format!("context.{}", key), value_a.to_string(), value_b.to_string()));
+        }
+    }
+    for (name, value_a, value_b) in &changes {
+        println!("{}: {} -> {}", name, value_a, value_b);
+    }
+
+    let outputs = |run: &Value| -> Vec<(String, String)> {
+        run["outputs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|output| {
+                (
+                    output["path"].as_str().unwrap_or_default().to_string(),
+                    output["content"].as_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect()
+    };
+    let (outputs_a, outputs_b) = (outputs(&run_a), outputs(&run_b));
+    // Outputs with new file names (timestamped documents) are compared in order when the paths don't match.
+    let mut unmatched_b: Vec<&(String, String)> = outputs_b
+        .iter()
+        .filter(|(path, _)| !outputs_a.iter().any(|(a, _)| a == path))
+        .collect();
+    for (path_a, content_a) in &outputs_a {
+        let counterpart = match outputs_b.iter().find(|(path_b, _)| path_b == path_a) {
+            Some(output) => Some(output),
+            None if !unmatched_b.is_empty() => Some(unmatched_b.remove(0)),
+            None => None,
+        };
+        match counterpart {
+            Some((path_b, content_b)) => {
+                let label = if path_a == path_b {
+                    path_a.clone()
+                } else {
+                    format!("{} -> {}", path_a, path_b)
+                };
+                if content_a == content_b {
+                    println!("\n{}: unchanged", label);
+                } else {
+                    println!("\n{}:", label);
+                    print_diff(content_a, content_b);
+                }
+            }
+            None => println!("\n{}: only in {}", path_a, a),
+        }
+    }
+    for (path_b, _) in unmatched_b {
+        println!("\n{}: only in {}", path_b, b);
+    }
+    Ok(())
+}
+
+/// Writes the files of a saved run back, undoing a later regeneration. Each file that changed since is shown as a
+/// diff and must be confirmed (unless `assume_yes`), and its current version is kept with a `.bak` suffix.
+pub fn restore_run(id: &str, assume_yes: bool) -> Result<()> {
+    let run = load_run(id)?;
+    let outputs = run["outputs"].as_array().cloned().unwrap_or_default();
+    if outputs.is_empty() {
+        bail!(Error::Config(format!("Run {} has no saved output to restore", id)));
+    }
+    for output in &outputs {
+        let (Some(path), Some(content)) = (output["path"].as_str(), output["content"].as_str()) else {
+            continue;
+        };
+        let path = Path::new(path);
+        if write_output(path, content, assume_yes)? {
+            // Setup scripts are saved by content only, so a script that was deleted since gets its mode back here.
+            if content.starts_with("#!") {
+                make_executable(path);
+            }
+            status!("Restored {} from run {}", path.display(), id);
+        }
+    }
+    Ok(())
+}
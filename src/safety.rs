@@ -0,0 +1,221 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// What to do about risky commands in a generated document (COMMAND_SAFETY).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommandSafety {
+    /// Add a warning below each code block with a risky command, and list them.
+    Annotate,
+    /// Only list them.
+    Warn,
+    Off,
+}
+
+impl CommandSafety {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "annotate" => Some(CommandSafety::Annotate),
+            "warn" => Some(CommandSafety::Warn),
+            "off" => Some(CommandSafety::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Describes a valid COMMAND_SAFETY in configuration errors.
+pub const COMMAND_SAFETY_EXPECTED: &str = "annotate, warn, or off";
+
+/// A kind of risky command and why it is risky, phrased to follow the command in a warning.
+struct SafetyRule {
+    pattern: &'static str,
+    reason: &'static str,
+}
+
+const SAFETY_RULES: &[SafetyRule] = &[
+    SafetyRule {
+        pattern: r"\b(?:curl|wget|iwr|Invoke-WebRequest)\b[^|\n]*\|\s*(?:sudo\s+)?(?:sh|bash|zsh|dash|python3?|perl|ruby|node|iex)\b",
+        reason: "runs a downloaded script without a chance to read it first",
+    },
+    SafetyRule {
+        pattern: r"\brm\s+(?:-[a-zA-Z]*r[a-zA-Z]*f[a-zA-Z]*|-[a-zA-Z]*f[a-zA-Z]*r[a-zA-Z]*|-r\s+-f|-f\s+-r|--recursive\s+--force|--force\s+--recursive)\b",
+        reason: "deletes files recursively without asking; check the path",
+    },
+    SafetyRule {
+        pattern: r"\b(?:dd\b[^\n]*\bof=/dev/|mkfs(?:\.\w+)?\s)|>\s*/dev/(?:sd|nvme|disk|hd)",
+        reason: "writes to a disk device",
+    },
+    SafetyRule {
+        pattern: r"\bchmod\s+(?:-R\s+)?(?:0?777|a\+rwx)\b",
+        reason: "makes files writable by every user",
+    },
+    SafetyRule {
+        pattern: r"\bgit\s+push\b[^\n]*\s(?:--force|-f)\b",
+        reason: "overwrites history on the remote",
+    },
+    SafetyRule {
+        pattern: r"(?i)\b(?:echo|printf|cat)\b[^|\n]*(?:\$\{?\w*(?:token|secret|passw(?:or)?d|api_?key|private_key)\w*\}?|\.env\b|id_rsa|\.pem\b)[^|\n]*\|\s*(?:curl|wget|nc|ncat|netcat|telnet|ssh)\b",
+        reason: "sends a secret over the network",
+    },
+    SafetyRule {
+        pattern: r#"(?i)--(?:password|passwd|token|api-key|secret)[=\s]["']?[A-Za-z0-9!@#%^&*_+-]{6,}"#,
+        reason: "puts a secret on the command line, where it ends up in shell history",
+    },
+];
+
+/// `sudo` that a quickstart plausibly needs: installing system packages.
+const EXPECTED_SUDO: &str = r"\bsudo\s+(?:-\S+\s+)*(?:apt(?:-get)?\s+(?:install|update)|dnf\s+install|yum\s+install|pacman\s+-S|zypper\s+install|apk\s+add|snap\s+install|port\s+install)\b";
+
+fn compiled_rules() -> &'static [(Regex, &'static str)] {
+    static COMPILED: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        SAFETY_RULES
+            .iter()
+            .map(|rule| (Regex::new(rule.pattern).expect("safety patterns are valid regexes"), rule.reason))
+            .collect()
+    })
+}
+
+fn sudo() -> &'static (Regex, Regex) {
+    static SUDO: OnceLock<(Regex, Regex)> = OnceLock::new();
+    SUDO.get_or_init(|| (Regex::new(r"\bsudo\b").unwrap(), Regex::new(EXPECTED_SUDO).unwrap()))
+}
+
+/// A risky command in a generated document.
+pub struct RiskyCommand {
+    pub command: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for RiskyCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` {}", self.command, self.reason)
+    }
+}
+
+/// Why a command is risky, if it is. `explained` says whether a comment line right above it gives a reason, which is
+/// the context `sudo` needs unless it installs system packages.
+fn risk(command: &str, explained: bool) -> Option<&'static str> {
+    if let Some((_, reason)) = compiled_rules().iter().find(|(regex, _)| regex.is_match(command)) {
+        return Some(reason);
+    }
+    let (sudo, expected) = sudo();
+    (sudo.is_match(command) && !expected.is_match(command) && !explained).then_some("runs as root without saying why")
+}
+
+/// Whether `line` closes a code block opened with `marker`: only fence characters of the same kind, at least as many.
+fn closes_fence(line: &str, marker: &str) -> bool {
+    let line = line.trim();
+    let fence_char = marker.chars().next();
+    line.len() >= marker.len() && line.chars().all(|c| Some(c) == fence_char)
+}
+
+/// Checks every command in the fenced code blocks of a Markdown document. Commands continued over several lines
+/// with `\` are checked as one. With `annotate`, a warning listing the risky commands is added below their block.
+/// Returns the (possibly annotated) document and what was found.
+pub fn review_commands(markdown: &str, annotate: bool) -> (String, Vec<RiskyCommand>) {
+    let mut output: Vec<String> = Vec::new();
+    let mut findings = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut block_findings: Vec<usize> = Vec::new();
+    // The command being read if it continues on the next line, and whether the line above it was a comment.
+    let mut command: Option<String> = None;
+    let mut explained = false;
+
+    for line in markdown.lines() {
+        output.push(line.to_string());
+        let trimmed = line.trim_start();
+        match &fence {
+            None => {
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    fence = Some(trimmed.chars().take_while(|c| *c == '`' || *c == '~').collect());
+                    explained = false;
+                }
+            }
+            Some(marker) if closes_fence(line, marker) => {
+                fence = None;
+                if annotate && !block_findings.is_empty() {
+                    output.push(String::new());
+                    output.push("> **Warning:** check these commands before running them.".to_string());
+                    output.push(">".to_string());
+                    for &finding in &block_findings {
+                        output.push(format!("> - {}.", findings[finding]));
+                    }
+                }
+                block_findings.clear();
+            }
+            Some(_) => {
+                let text = trimmed.trim_start_matches(['$', '>']).trim_start();
+                let mut joined = command.take().unwrap_or_default();
+                if let Some(continued) = text.strip_suffix('\\') {
+                    joined.push_str(continued);
+                    command = Some(joined);
+                    continue;
+                }
+                joined.push_str(text);
+                let joined = joined.trim().to_string();
+                if joined.starts_with('#') {
+                    explained = true;
+                    continue;
+                }
+                if let Some(reason) = risk(&joined, explained) {
+                    block_findings.push(findings.len());
+                    findings.push(RiskyCommand { command: joined, reason });
+                }
+                explained = false;
+            }
+        }
+    }
+    let mut reviewed = output.join("\n");
+    if markdown.ends_with('\n') {
+        reviewed.push('\n');
+    }
+    (reviewed, findings)
+}
+
+/// Checks every command in a script, task file, Dockerfile, or CI workflow, where each line that isn't a comment is
+/// one (or a YAML key, recipe name, or instruction holding one). Commands continued over several lines with `\` (or
+/// `` ` `` in PowerShell) are checked as one. With `annotate`, a `#` comment saying why is added above each risky
+/// command, which shell, PowerShell, YAML, make, just, and Docker all read as a comment. Returns the (possibly annotated) script and what was found.
+pub fn review_script_commands(script: &str, annotate: bool) -> (String, Vec<RiskyCommand>) {
+    let mut output: Vec<String> = Vec::new();
+    let mut findings = Vec::new();
+    // The lines of the command being read if it continues on the next line, and whether the line above it was a
+    // comment.
+    let mut command: Option<(String, Vec<&str>)> = None;
+    let mut explained = false;
+
+    for line in script.lines() {
+        let text = line.trim();
+        let (mut joined, mut lines) = command.take().unwrap_or_default();
+        lines.push(line);
+        if let Some(continued) = text.strip_suffix('\\').or_else(|| text.strip_suffix('`')) {
+            joined.push_str(continued);
+            command = Some((joined, lines));
+            continue;
+        }
+        joined.push_str(text);
+        let joined = joined.trim().to_string();
+        if joined.starts_with('#') {
+            explained = true;
+        } else {
+            if let Some(reason) = risk(&joined, explained) {
+                if annotate {
+                    let indent: String = lines[0].chars().take_while(|c| c.is_whitespace()).collect();
+                    output.push(format!("{}# Warning: the next command {}.", indent, reason));
+                }
+                findings.push(RiskyCommand { command: joined, reason });
+            }
+            explained = false;
+        }
+        output.extend(lines.iter().map(|line| line.to_string()));
+    }
+    if let Some((_, lines)) = command {
+        output.extend(lines.iter().map(|line| line.to_string()));
+    }
+    let mut reviewed = output.join("\n");
+    if script.ends_with('\n') {
+        reviewed.push('\n');
+    }
+    (reviewed, findings)
+}
@@ -0,0 +1,246 @@
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::rules::REDACTED;
+
+/// What to do when file contents about to be sent look like they contain a credential (SECRET_SCAN).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SecretScan {
+    /// Replace each secret with `[REDACTED]` and list where they were.
+    Redact,
+    /// Send nothing and list where the secrets are.
+    Abort,
+    Off,
+}
+
+impl SecretScan {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "redact" => Some(SecretScan::Redact),
+            "abort" => Some(SecretScan::Abort),
+            "off" => Some(SecretScan::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Describes a valid SECRET_SCAN in configuration errors.
+pub const SECRET_SCAN_EXPECTED: &str = "redact, abort, or off";
+
+/// A kind of credential and how to find it.
+struct SecretRule {
+    name: &'static str,
+    pattern: &'static str,
+    /// Minimum Shannon entropy of the secret in bits per character, to tell random keys from ordinary words.
+    min_entropy: f64,
+    /// Whether values that are obviously placeholders, such as `changeme` or `${DB_PASSWORD}`, are skipped.
+    skip_placeholders: bool,
+}
+
+/// Credentials recognized in file contents, in the style of gitleaks. Each pattern's first capture group (or the
+/// whole match if it has none) is the secret that gets redacted.
+const SECRET_RULES: &[SecretRule] = &[
+    SecretRule {
+        name: "private key",
+        pattern: r"-----BEGIN [A-Z ]*PRIVATE KEY(?: BLOCK)?-----[\s\S]*?(?:-----END [A-Z ]*PRIVATE KEY(?: BLOCK)?-----|\z)",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "AWS access key ID",
+        pattern: r"\b((?:AKIA|ASIA|ABIA|ACCA)[0-9A-Z]{16})\b",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "GitHub token",
+        pattern: r"\b((?:ghp|gho|ghu|ghs|ghr)_[A-Za-z0-9]{36}|github_pat_[A-Za-z0-9_]{82})\b",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "GitLab token",
+        pattern: r"\b(glpat-[A-Za-z0-9_-]{20})\b",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "Slack token",
+        pattern: r"\b(xox[baprs]-[A-Za-z0-9-]{10,})\b",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "Stripe key",
+        pattern: r"\b((?:sk|rk)_live_[A-Za-z0-9]{24,})\b",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "Google API key",
+        pattern: r"\b(AIza[0-9A-Za-z_-]{35})\b",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "OpenAI API key",
+        pattern: r"\b(sk-(?:proj-)?[A-Za-z0-9_-]{32,})\b",
+        min_entropy: 3.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "JSON web token",
+        pattern: r"\b(eyJ[A-Za-z0-9_-]{10,}\.eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,})",
+        min_entropy: 0.0,
+        skip_placeholders: false,
+    },
+    SecretRule {
+        name: "password in URL",
+        pattern: r"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^\s:/@]+:([^\s:/@]+)@",
+        min_entropy: 1.5,
+        skip_placeholders: true,
+    },
+    SecretRule {
+        name: "hardcoded password or key",
+        pattern: r#"(?i)[\w.-]*(?:password|passwd|pwd|secret|token|api[_-]?key|access[_-]?key)(?:[_-]?key)?["']?\s*(?::=|=>|[:=])\s*[rbf]?["']([^"'\s]{6,})["']"#,
+        min_entropy: 2.0,
+        skip_placeholders: true,
+    },
+    SecretRule {
+        name: "high-entropy string",
+        pattern: r#"["']([A-Za-z0-9+/=_-]{32,})["']"#,
+        min_entropy: 4.5,
+        skip_placeholders: true,
+    },
+];
+
+/// Values that stand in for a secret rather than being one.
+const PLACEHOLDERS: [&str; 14] = [
+    "changeme",
+    "change_me",
+    "password",
+    "passwd",
+    "secret",
+    "example",
+    "placeholder",
+    "dummy",
+    "redacted",
+    "notasecret",
+    "your_password",
+    "your_api_key",
+    "your_token",
+    "xxxxxx",
+];
+
+fn compiled_rules() -> &'static [(Regex, &'static SecretRule)] {
+    static COMPILED: OnceLock<Vec<(Regex, &'static SecretRule)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        SECRET_RULES
+            .iter()
+            .map(|rule| (Regex::new(rule.pattern).expect("secret patterns are valid regexes"), rule))
+            .collect()
+    })
+}
+
+/// A likely secret in content about to be sent. The secret itself is never kept, so it can't end up in a report.
+pub struct Finding {
+    /// The file (or kind of context) it was found in.
+    pub location: String,
+    pub line: usize,
+    pub rule: &'static str,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.location, self.line, self.rule)
+    }
+}
+
+/// Shannon entropy in bits per character.
+pub fn entropy(text: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in text.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let length = text.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Whether a value is obviously not a real secret: a well-known placeholder, a template or environment reference
+/// such as `${DB_PASSWORD}` or `{{ token }}`, or a run of one character such as `********`.
+fn is_placeholder(value: &str) -> bool {
+    let lower = value.to_lowercase();
+    PLACEHOLDERS.contains(&lower.as_str())
+        || lower.starts_with("your")
+        || lower.starts_with('$')
+        || lower.starts_with('<')
+        || lower.contains("{{")
+        || lower.contains("${")
+        || lower.contains("example")
+        || lower.chars().all(|c| c == lower.chars().next().unwrap_or_default())
+}
+
+/// The byte ranges of likely secrets in `text`, each with the name of the rule that found it, in order.
+fn find_secrets(text: &str) -> Vec<(std::ops::Range<usize>, &'static str)> {
+    let mut found: Vec<(std::ops::Range<usize>, &'static str)> = Vec::new();
+    for (regex, rule) in compiled_rules() {
+        for captures in regex.captures_iter(text) {
+            let Some(secret) = captures.get(1).or_else(|| captures.get(0)) else {
+                continue;
+            };
+            if entropy(secret.as_str()) < rule.min_entropy || (rule.skip_placeholders && is_placeholder(secret.as_str())) {
+                continue;
+            }
+            // A secret already found by a more specific rule isn't reported again.
+            if found
+                .iter()
+                .any(|(range, _)| range.start < secret.end() && secret.start() < range.end)
+            {
+                continue;
+            }
+            found.push((secret.range(), rule.name));
+        }
+    }
+    found.sort_by_key(|(range, _)| range.start);
+    found
+}
+
+/// Scans `text` for likely secrets, replacing each with `[REDACTED]` when `redact` is set. Findings are reported under
+/// `location` with their line number.
+pub fn scan_text_secrets(text: &mut String, location: &str, redact: bool) -> Vec<Finding> {
+    let found = find_secrets(text);
+    let findings = found
+        .iter()
+        .map(|(range, rule)| Finding {
+            location: location.to_string(),
+            line: text[..range.start].matches('\n').count() + 1,
+            rule,
+        })
+        .collect();
+    if redact {
+        for (range, _) in found.iter().rev() {
+            text.replace_range(range.clone(), REDACTED);
+        }
+    }
+    findings
+}
+
+/// Scans every string in `value` for likely secrets, replacing each with `[REDACTED]` when `redact` is set.
+/// Findings are reported under `location` with their line number in the string they were found in.
+pub fn scan_secrets(value: &mut Value, location: &str, redact: bool) -> Vec<Finding> {
+    match value {
+        Value::String(text) => scan_text_secrets(text, location, redact),
+        Value::Array(values) => values.iter_mut().flat_map(|value| scan_secrets(value, location, redact)).collect(),
+        Value::Object(map) => map.values_mut().flat_map(|value| scan_secrets(value, location, redact)).collect(),
+        _ => vec![],
+    }
+}
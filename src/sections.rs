@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::console::status;
+use crate::errors::Error;
+
+/// Project-local configuration file holding the `[sections]` presets.
+pub const PROJECT_CONFIG_FILE: &str = ".magic_quickstart.toml";
+
+/// Quickstart sections that can be switched on or off: the preset key, the heading titles that count as that
+/// section, and the template slot that holds it (if any).
+const SECTIONS: [(&str, &[&str], Option<&str>); 7] = [
+    ("prerequisites", &["prerequisites", "requirements"], Some("prerequisites")),
+    (
+        "install",
+        &["install", "installation", "setup", "build and install"],
+        Some("installation"),
+    ),
+    ("run", &["run", "running", "usage"], Some("usage")),
+    ("test", &["test", "tests", "testing"], Some("testing")),
+    ("docker", &["docker", "running with docker", "container"], None),
+    ("deployment", &["deployment", "deploy", "release"], Some("deployment")),
+    (
+        "troubleshooting",
+        &["troubleshooting", "common issues", "faq"],
+        Some("troubleshooting"),
+    ),
+];
+
+/// Which quickstart sections a project wants, from the `[sections]` table, e.g. `docker = false`.
+/// Sections that are not listed are left to the model.
+#[derive(Clone, Debug, Default)]
+pub struct SectionPresets(Vec<(&'static str, bool)>);
+
+impl SectionPresets {
+    fn enabled(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name)
+    }
+
+    fn disabled(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.iter().filter(|(_, enabled)| !*enabled).map(|(name, _)| *name)
+    }
+
+    /// Instruction appended to the system prompt listing the sections to include and to leave out.
+    pub fn instructions(&self) -> String {
+        let enabled: Vec<_> = self.enabled().collect();
+        let disabled: Vec<_> = self.disabled().collect();
+        let mut instructions = String::new();
+        if !enabled.is_empty() {
+            instructions.push_str(&format!(
+                "Include a section for each of: {}, even if it is short.",
+                enabled.join(", ")
+            ));
+        }
+        if !disabled.is_empty() {
+            if !instructions.is_empty() {
+                instructions.push(' ');
+            }
+            instructions.push_str(&format!("Do not include sections for: {}.", disabled.join(", ")));
+        }
+        instructions
+    }
+
+    /// Whether a heading belongs to a disabled section.
+    fn is_disabled_heading(&self, heading: &str) -> bool {
+        let heading = heading.trim().to_lowercase();
+        self.disabled().any(|name| {
+            SECTIONS
+                .iter()
+                .find(|(key, ..)| *key == name)
+                .is_some_and(|(_, titles, _)| titles.contains(&heading.as_str()))
+        })
+    }
+
+    /// Template slots holding disabled sections.
+    pub fn disabled_slots(&self) -> Vec<&'static str> {
+        self.disabled()
+            .filter_map(|name| SECTIONS.iter().find(|(key, ..)| *key == name).and_then(|(_, _, slot)| *slot))
+            .collect()
+    }
+}
+
+/// Reads the `[sections]` presets from the project's `.magic_quickstart.toml`, if it has any.
+pub fn load_section_presets(project_dir: &Path) -> Result<Option<SectionPresets>> {
+    let path = project_dir.join(PROJECT_CONFIG_FILE);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let config: toml::Table = content
+        .parse()
+        .with_context(|| Error::Config(format!("Failed to parse {}", path.display())))?;
+    let Some(table) = config.get("sections").and_then(|sections| sections.as_table()) else {
+        return Ok(None);
+    };
+
+    let mut presets = Vec::new();
+    for (name, value) in table {
+        match (SECTIONS.iter().find(|(key, ..)| key == name), value.as_bool()) {
+            (Some((key, ..)), Some(enabled)) => presets.push((*key, enabled)),
+            _ => status!("Ignoring invalid [sections] entry in {}: {} = {}", path.display(), name, value),
+        }
+    }
+    Ok(Some(SectionPresets(presets)))
+}
+
+/// Removes Markdown sections whose heading belongs to a disabled section, up to the next heading of the same
+/// or a higher level. Headings inside code blocks are ignored.
+pub fn filter_markdown_sections(markdown: &str, presets: &SectionPresets) -> String {
+    let mut kept = Vec::new();
+    let mut skipping_level: Option<usize> = None;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        let heading_level = line.chars().take_while(|&c| c == '#').count();
+        let is_heading = !in_code_block && (1..=6).contains(&heading_level) && line[heading_level..].starts_with(' ');
+
+        if is_heading {
+            if skipping_level.is_some_and(|level| heading_level <= level) {
+                skipping_level = None;
+            }
+            if skipping_level.is_none() && heading_level > 1 && presets.is_disabled_heading(&line[heading_level..]) {
+                skipping_level = Some(heading_level);
+            }
+        }
+        if skipping_level.is_none() {
+            kept.push(line);
+        }
+    }
+
+    let mut result = kept.join("\n");
+    if markdown.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Blanks the template slots of disabled sections in the model's JSON slot response.
+pub fn clear_disabled_slots(response: &str, presets: &SectionPresets) -> String {
+    let Ok(mut slots) = serde_json::from_str::<serde_json::Value>(response.trim()) else {
+        return response.to_string();
+    };
+    for slot in presets.disabled_slots() {
+        if slots.get(slot).is_some() {
+            slots[slot] = serde_json::json!("");
+        }
+    }
+    slots.to_string()
+}
+
+/// Finds the heading of the section `name` refers to in a Markdown document: either the heading text itself
+/// (case-insensitive) or a preset key such as `install` matching one of its usual titles.
+pub fn find_section_heading(markdown: &str, name: &str) -> Option<String> {
+    let name = name.trim().to_lowercase();
+    let titles: Vec<&str> = SECTIONS
+        .iter()
+        .find(|(key, titles, _)| *key == name || titles.contains(&name.as_str()))
+        .map(|(_, titles, _)| titles.to_vec())
+        .unwrap_or_default();
+
+    section_headings(markdown).into_iter().find_map(|(_, text)| {
+        let lower = text.to_lowercase();
+        (lower == name || titles.contains(&lower.as_str())).then_some(text)
+    })
+}
+
+/// Replaces the section titled `heading` (the heading line and everything up to the next heading of the same or
+/// a higher level) with `replacement`.
+pub fn replace_markdown_section(markdown: &str, heading: &str, replacement: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let headings = section_headings(markdown);
+    let Some(position) = headings.iter().position(|(_, text)| text == heading) else {
+        return markdown.to_string();
+    };
+    let (start, level) = headings[position].0;
+    let end = headings[position + 1..]
+        .iter()
+        .find(|((_, other_level), _)| *other_level <= level)
+        .map(|((index, _), _)| *index)
+        .unwrap_or(lines.len());
+
+    let mut result: Vec<&str> = lines[..start].to_vec();
+    result.extend(replacement.trim().lines());
+    if end < lines.len() {
+        result.push("");
+        result.extend(&lines[end..]);
+    }
+    result.join("\n")
+}
+
+/// Headings outside code blocks as `((line index, level), text)`.
+fn section_headings(markdown: &str) -> Vec<((usize, usize), String)> {
+    let mut headings = Vec::new();
+    let mut in_code_block = false;
+    for (index, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if !in_code_block && (1..=6).contains(&level) && line[level..].starts_with(' ') {
+            headings.push(((index, level), line[level..].trim().trim_end_matches('#').trim_end().to_string()));
+        }
+    }
+    headings
+}
@@ -0,0 +1,582 @@
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use crate::api_key::ApiKey;
+use crate::audit::{default_audit_log_path, AuditLog};
+use crate::cli::{Args, Target};
+use crate::config::Config;
+use crate::consent::{data_categories, Consent};
+use crate::console::status;
+use crate::doc_types::DocType;
+use crate::documents::{write_document, GeneratedDocument};
+use crate::endpoints::{endpoint_provider, url_host, HostAllowlist};
+use crate::errors::Error;
+use crate::generate::Outcome;
+use crate::i18n::t;
+use crate::interrupt::interrupted;
+use crate::markdown::detect_fence_languages;
+use crate::models::{ContextBundle, GenerationRequest};
+use crate::output::confirm;
+use crate::payload_size::check_payload_size;
+use crate::policy::Policy;
+use crate::pricing::{estimate_cost, format_cost, request_tokens, EXPECTED_OUTPUT_TOKENS};
+use crate::provenance::context_hash;
+use crate::pseudonyms::Pseudonymizer;
+use crate::run_manifest::{Drift, ManifestEntry, RunManifest, RUN_MANIFEST_FILE};
+use crate::runs::save_run;
+use crate::sinks::OutputSink;
+use crate::streaming::{event_stream_content, receive, Cut, RequestTimeouts, Unanswered};
+
+/// Requests ready to be sent, with what they were built from.
+pub struct PreparedRequests {
+    pub context: ContextBundle,
+    pub requests: Vec<(DocType, GenerationRequest)>,
+    /// Labels of the extra context, which are kinds of data the user agrees to send, like the context itself.
+    pub extra_labels: Vec<String>,
+    /// Maps PSEUDONYMIZE placeholders in the responses back.
+    pub pseudonymizer: Option<Pseudonymizer>,
+}
+
+/// Sends the requests once the user agrees, and writes the documents and the record of the run. A dry run prints
+/// the payloads instead, and with ENABLE_OPENAI off nothing is sent.
+pub fn send_requests(
+    args: &Args,
+    config: &mut Config,
+    policy: &Policy,
+    project_dir: &Path,
+    output_dir: &Path,
+    sink: &dyn OutputSink,
+    prepared: PreparedRequests,
+) -> Result<Outcome> {
+    let PreparedRequests {
+        context,
+        requests,
+        extra_labels,
+        pseudonymizer,
+    } = prepared;
+    // With --json, a record of the run is printed however it ends; sent documents get their results added.
+    let model = config.openai_model.as_str();
+    let mut documents: Vec<serde_json::Value> = requests
+        .iter()
+        .map(|(doc_type, request_body)| document_record(*doc_type, request_body, model))
+        .collect();
+
+    // Verifying compares the requests with the ones the documents were generated from instead of sending them.
+    if args.verify_manifest {
+        let outcome = verify_manifest(output_dir, &requests, &mut documents)?;
+        if args.json {
+            print_run_record(&outcome, model, &context, &documents);
+        }
+        return Ok(outcome);
+    }
+
+    // A dry run prints the payloads and their estimated cost instead of sending them, whatever ENABLE_OPENAI says.
+    if args.dry_run {
+        print_dry_run(&requests, model)?;
+        if args.json {
+            print_run_record(&Outcome::DryRun, model, &context, &documents);
+        }
+        return Ok(Outcome::DryRun);
+    }
+
+    // Only send the request if ENABLE_OPENAI is set to true.
+    if !config.enable_openai {
+        status!("{}", t!("openai-disabled"));
+        if args.json {
+            print_run_record(&Outcome::Disabled, model, &context, &documents);
+        }
+        return Ok(Outcome::Disabled);
+    }
+    // The provider is whoever runs the endpoint, which OPENAI_BASE_URL may point anywhere.
+    let endpoint = format!("{}/chat/completions", config.openai_base_url);
+    policy.check_provider(&endpoint_provider(&endpoint))?;
+    // A last check on what actually leaves the machine, in case the budgets above were misconfigured.
+    for (doc_type, request_body) in &requests {
+        check_payload_size(*doc_type, request_body, &context, config.max_payload_bytes)?;
+    }
+    config.allowed_hosts.check(&endpoint)?;
+    // The API key is only needed once a request is actually sent, so it is only read from stdin or a descriptor now.
+    let api_key = match (args.api_key_stdin, args.api_key_fd) {
+        (true, _) => Some(ApiKey::from_stdin()?),
+        (false, Some(fd)) => Some(ApiKey::from_fd(fd)?),
+        (false, None) => args.api_key.clone().or_else(|| config.openai_api_key.take()),
+    };
+    let Some(api_key) = api_key else {
+        bail!(Error::Config(
+            "OPENAI_API_KEY is not set; add it to .env, the environment, or ~/.config/magic_quickstart/config.toml".to_string()
+        ));
+    };
+
+    // A cost cap is checked against the estimate, which assumes a typical response length.
+    if let Some(max_cost) = config.max_cost {
+        let estimated_cost: Option<f64> = documents.iter().map(|record| record["estimated_cost"].as_f64()).sum();
+        match estimated_cost {
+            Some(cost) if cost > max_cost => match policy.cost_source(max_cost) {
+                Some(source) => bail!(Error::Config(format!(
+                    "The estimated cost {} exceeds the cap of {} set by the policy in {}; lower TOKEN_BUDGET",
+                    format_cost(Some(cost)),
+                    format_cost(Some(max_cost)),
+                    source
+                ))),
+                None => bail!(Error::Config(format!(
+                    "The estimated cost {} exceeds the cap of {}; raise MAX_COST or lower TOKEN_BUDGET",
+                    format_cost(Some(cost)),
+                    format_cost(Some(max_cost))
+                ))),
+            },
+            Some(_) => {}
+            None => bail!(Error::Config(format!(
+                "{} has no known price, so the cost cap of {} can't be checked; use a known model or unset MAX_COST",
+                model,
+                format_cost(Some(max_cost))
+            ))),
+        }
+    }
+
+    // Nothing leaves the machine until the user has seen what is sent and agreed to it. With REMEMBER_CONSENT, the
+    // answer is kept per project and host, and the user is only asked again when kinds of data they haven't agreed
+    // to would be added.
+    let project = match args.target()? {
+        Target::Local(dir) => fs::canonicalize(&dir).unwrap_or(dir).display().to_string(),
+        Target::Remote(url) => url,
+    };
+    if !args.yes {
+        let categories = data_categories(&context, &extra_labels);
+        let mut consent = if config.remember_consent {
+            Some(Consent::load(&project, &url_host(&endpoint))?)
+        } else {
+            None
+        };
+        let new_categories = match &consent {
+            Some(consent) => consent.new_categories(&categories),
+            None => categories.iter().cloned().collect(),
+        };
+        if consent.as_ref().is_some_and(Consent::is_recorded) && new_categories.is_empty() {
+            status!("{}", t!("consent-remembered"));
+        } else {
+            print_send_summary(&context, &requests, &endpoint, model);
+            if consent.as_ref().is_some_and(Consent::is_recorded) {
+                status!("{}", t!("consent-new-categories", categories = new_categories.join(", ")));
+            }
+            if !confirm(&t!("confirm-send")) {
+                status!("{}", t!("nothing-sent"));
+                if args.json {
+                    print_run_record(&Outcome::Declined, model, &context, &documents);
+                }
+                return Ok(Outcome::Declined);
+            }
+            // Failing to remember the answer only means asking again next time.
+            if let Some(consent) = &mut consent {
+                if let Err(err) = consent.record(&categories) {
+                    status!("{}", t!("consent-not-saved", error = format!("{:#}", err)));
+                }
+            }
+        }
+    }
+
+    // Every call is recorded in the audit log, which is opened first so a call never goes unrecorded. An in-memory
+    // run only keeps one if AUDIT_LOG names it.
+    let audit_log = match &config.audit_log {
+        Some(path) => AuditLog::open(path)?,
+        None if args.in_memory => AuditLog::disabled(),
+        None => AuditLog::open(&default_audit_log_path()?)?,
+    };
+
+    // Send the API requests in parallel, then write the results one at a time so confirmation prompts don't interleave.
+    let project_name = project_dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let fence_languages = detect_fence_languages(project_dir);
+    let responses: Vec<Completion> = thread::scope(|scope| {
+        let handles: Vec<_> = requests
+            .iter()
+            .map(|(_, request_body)| {
+                scope.spawn(|| {
+                    send_openai_request(
+                        &endpoint,
+                        &config.allowed_hosts,
+                        &config.request_timeouts,
+                        &api_key,
+                        request_body,
+                        &audit_log,
+                    )
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(Error::Network("A request stopped before it was answered".to_string()).into()))
+            })
+            .collect::<Result<_>>()
+    })?;
+    // Every request has been sent, so the key is wiped now rather than kept for the rest of the run.
+    drop(api_key);
+    let mut written = Vec::new();
+    // Documents written whole are recorded in quickstart.lock, unless they only went to the embedding tool's sink or
+    // another that doesn't write to the output directory. A regenerated section was built from the current document
+    // as well, so it can't be checked later.
+    let record_manifest =
+        config.output_sink.writes_files() && args.output_sink.is_none() && !args.in_memory && config.regenerate_section.is_none();
+    let mut manifest_entries = Vec::new();
+    for (((doc_type, request_body), completion), record) in requests.into_iter().zip(responses).zip(&mut documents) {
+        let Completion { content, usage, cut } = completion;
+        record["truncated"] = json!(cut.map(Cut::name));
+        if let Some(cut) = cut {
+            // Ctrl-C may come before anything arrived, and JSON that stops partway can't be read at all.
+            if content.is_empty() || request_body.response_format.is_some() {
+                status!("{}", t!("response-cut-unusable", doc_type = doc_type.name(), reason = cut.reason()));
+                continue;
+            }
+            status!("{}", t!("response-cut", doc_type = doc_type.name(), reason = cut.reason()));
+        }
+        let document = GeneratedDocument {
+            doc_type,
+            response: match &pseudonymizer {
+                Some(pseudonymizer) => pseudonymizer.restore(&content),
+                None => content,
+            },
+            context_hash: context_hash(&request_body),
+            cut,
+        };
+        record["context_hash"] = json!(document.context_hash);
+        let output_paths = write_document(config, args, output_dir, &project_name, &fence_languages, document, sink)?;
+        if record_manifest && cut.is_none() && !output_paths.is_empty() {
+            manifest_entries.push(ManifestEntry::new(doc_type, &request_body, output_dir, &output_paths));
+        }
+        record["output_paths"] = json!(output_paths);
+        record["cost"] = json!(usage_cost(model, &usage));
+        record["usage"] = usage;
+        written.extend(output_paths);
+    }
+    if !manifest_entries.is_empty() {
+        let path = RunManifest::update(output_dir, manifest_entries)?;
+        status!("{}", t!("manifest-saved", path = path.display().to_string()));
+    }
+    let outcome = if interrupted() {
+        Outcome::Interrupted(written.clone())
+    } else {
+        Outcome::Generated(written.clone())
+    };
+    let record = run_record(&outcome, model, &context, &documents);
+    if args.json {
+        println!("{}", record);
+    }
+    // Saving is a convenience for `runs`; the documents are already written, so a failure only warns.
+    if args.in_memory {
+        return Ok(outcome);
+    }
+    match save_run(record, &project, &written, config.encrypt_artifacts) {
+        Ok(id) => status!("{}", t!("run-saved", id = id)),
+        Err(err) => status!("{}", t!("run-not-saved", error = format!("{:#}", err))),
+    }
+    Ok(outcome)
+}
+
+/// Checks each request against the entry quickstart.lock has for its document, printing which documents are up to
+/// date and why the others aren't, and adding `context_hash` and `drift` to their records. Fails with a
+/// configuration error if there is no manifest to check against.
+fn verify_manifest(output_dir: &Path, requests: &[(DocType, GenerationRequest)], documents: &mut [serde_json::Value]) -> Result<Outcome> {
+    let manifest = RunManifest::load(output_dir)?;
+    if manifest.documents.is_empty() {
+        bail!(Error::Config(format!(
+            "No {} in {} to verify against; generate the documents first, then commit it with them",
+            RUN_MANIFEST_FILE,
+            output_dir.display()
+        )));
+    }
+    let mut drifted = Vec::new();
+    for ((doc_type, request_body), record) in requests.iter().zip(documents) {
+        let drift = manifest.drift(*doc_type, request_body);
+        record["context_hash"] = json!(context_hash(request_body));
+        record["drift"] = json!(drift.map(Drift::name));
+        let doc_type_name = doc_type.name();
+        let Some(drift) = drift else {
+            status!("{}", t!("manifest-current", doc_type = doc_type_name));
+            continue;
+        };
+        let entry = manifest.entry(*doc_type);
+        let message = match (drift, entry) {
+            (Drift::Model, Some(entry)) => t!(
+                "manifest-drift-model",
+                doc_type = doc_type_name,
+                was = entry.model.as_str(),
+                now = request_body.model.as_str()
+            ),
+            (Drift::Seed, Some(entry)) => t!(
+                "manifest-drift-seed",
+                doc_type = doc_type_name,
+                was = entry.seed.map_or("none".to_string(), |seed| seed.to_string()),
+                now = request_body.seed.map_or("none".to_string(), |seed| seed.to_string())
+            ),
+            (Drift::ToolVersion, Some(entry)) => t!(
+                "manifest-drift-tool-version",
+                doc_type = doc_type_name,
+                was = entry.tool_version.as_str(),
+                now = env!("CARGO_PKG_VERSION")
+            ),
+            (Drift::Context, _) => t!("manifest-drift-context", doc_type = doc_type_name),
+            _ => t!("manifest-drift-missing", doc_type = doc_type_name, path = RUN_MANIFEST_FILE),
+        };
+        status!("{}", message);
+        drifted.push(*doc_type);
+    }
+    if drifted.is_empty() {
+        Ok(Outcome::Verified)
+    } else {
+        Ok(Outcome::Drifted(drifted))
+    }
+}
+
+/// The `--json` record of one document before it is sent: its estimated input tokens and cost.
+fn document_record(doc_type: DocType, request_body: &GenerationRequest, model: &str) -> serde_json::Value {
+    let input_tokens = request_tokens(request_body);
+    json!({
+        "doc_type": doc_type.name(),
+        "estimated_input_tokens": input_tokens,
+        "estimated_cost": estimate_cost(model, input_tokens, EXPECTED_OUTPUT_TOKENS),
+    })
+}
+
+/// The cost of a request from the token usage the API reported, or `None` for models without a known price.
+fn usage_cost(model: &str, usage: &serde_json::Value) -> Option<f64> {
+    let input_tokens = usage["prompt_tokens"].as_u64()? as usize;
+    let output_tokens = usage["completion_tokens"].as_u64()? as usize;
+    estimate_cost(model, input_tokens, output_tokens)
+}
+
+/// Prints the `--json` record to stdout.
+fn print_run_record(outcome: &Outcome, model: &str, context: &ContextBundle, documents: &[serde_json::Value]) {
+    println!("{}", run_record(outcome, model, context, documents));
+}
+
+/// The record of a run, printed with `--json` and saved for `runs`: how the run ended (`generated`, `interrupted`,
+/// `dry_run`, `disabled`, or `declined`), the model, each document's paths, usage, cost, and why it was cut off, and a manifest of the context that
+/// was (or would have been) sent. Totals are `null` if any document's price is unknown.
+fn run_record(outcome: &Outcome, model: &str, context: &ContextBundle, documents: &[serde_json::Value]) -> serde_json::Value {
+    let total = |key: &str| -> Option<f64> { documents.iter().map(|document| document[key].as_f64()).sum() };
+    let env_file_keys: Vec<&str> = context.env_file_keys.iter().map(|entry| entry.key.as_str()).collect();
+    let exemplars: Vec<&str> = context.exemplars.iter().map(|exemplar| exemplar.file.as_str()).collect();
+    json!({
+        "outcome": outcome.name(),
+        "provider": "openai",
+        "model": model,
+        "documents": documents,
+        "estimated_cost": total("estimated_cost"),
+        "cost": if matches!(outcome, Outcome::Generated(_) | Outcome::Interrupted(_)) { total("cost") } else { None },
+        "context": {
+            "shell_history_entries": context.command_history.len(),
+            "project_files": context.project_files,
+            "project_files_with_contents": context.project_files_content.len(),
+            "dependencies": context.dependency_summary.len(),
+            "asset_directories": context.asset_inventory.len(),
+            "env_file_keys": env_file_keys,
+            "exemplars": exemplars,
+        },
+    })
+}
+
+/// Prints each request payload followed by its estimated input tokens and cost.
+fn print_dry_run(requests: &[(DocType, GenerationRequest)], model: &str) -> Result<()> {
+    for (doc_type, request_body) in requests {
+        status!("{}", t!("dry-run-request", doc_type = doc_type.name()));
+        status!("{}", serde_json::to_string_pretty(request_body)?);
+    }
+
+    status!("{}", t!("dry-run-estimate", model = model));
+    let mut total_tokens = 0;
+    let mut total_cost = Some(0.0);
+    for (doc_type, request_body) in requests {
+        let input_tokens = request_tokens(request_body);
+        let cost = estimate_cost(model, input_tokens, EXPECTED_OUTPUT_TOKENS);
+        status!(
+            "{:14} {}",
+            doc_type.name(),
+            t!(
+                "dry-run-document",
+                input = input_tokens,
+                output = EXPECTED_OUTPUT_TOKENS,
+                cost = format_cost(cost)
+            )
+        );
+        total_tokens += input_tokens;
+        total_cost = total_cost.zip(cost).map(|(total, cost)| total + cost);
+    }
+    if requests.len() > 1 {
+        status!(
+            "{:14} {}",
+            t!("dry-run-total-label"),
+            t!("dry-run-total", input = total_tokens, cost = format_cost(total_cost))
+        );
+    }
+    status!("{}", t!("dry-run-nothing-sent"));
+    Ok(())
+}
+
+/// Prints a one-screen summary of what is about to be sent: who receives it, how much of each kind of context,
+/// and the estimated tokens and cost of all requests together.
+fn print_send_summary(context: &ContextBundle, requests: &[(DocType, GenerationRequest)], endpoint: &str, model: &str) {
+    let documents: Vec<&str> = requests.iter().map(|(doc_type, _)| doc_type.name()).collect();
+    let input_tokens: usize = requests.iter().map(|(_, request_body)| request_tokens(request_body)).sum();
+    let output_tokens = EXPECTED_OUTPUT_TOKENS * requests.len();
+    status!("{}", t!("summary-title"));
+    status!("{}", t!("summary-endpoint", host = url_host(endpoint), model = model));
+    status!("{}", t!("summary-documents", documents = documents.join(", ")));
+    status!("{}", t!("summary-history", count = context.command_history.len()));
+    status!(
+        "{}",
+        t!(
+            "summary-files",
+            count = context.project_files.len(),
+            with_contents = context.project_files_content.len()
+        )
+    );
+    status!("{}", t!("summary-dependencies", count = context.dependency_summary.len()));
+    status!("{}", t!("summary-env-keys", count = context.env_file_keys.len()));
+    if !context.exemplars.is_empty() {
+        status!("{}", t!("summary-exemplars", count = context.exemplars.len()));
+    }
+    status!(
+        "{}",
+        t!(
+            "summary-estimate",
+            input = input_tokens,
+            output = output_tokens,
+            cost = format_cost(estimate_cost(model, input_tokens, output_tokens))
+        )
+    );
+}
+
+/// A generated document as the API returned it: the Markdown content, the token usage the API reported (`null` if
+/// it reported none), and why the content stops early, if it was cut off.
+struct Completion {
+    content: String,
+    usage: serde_json::Value,
+    cut: Option<Cut>,
+}
+
+impl Completion {
+    /// Ctrl-C stopped the request before any content arrived.
+    fn interrupted() -> Self {
+        Completion {
+            content: String::new(),
+            usage: serde_json::Value::Null,
+            cut: Some(Cut::Interrupted),
+        }
+    }
+}
+
+/// Sends the request to the OpenAI-compatible `url`, recording the call in the audit log, and returns the generated
+/// content. The answer is streamed, so an answer cut off by Ctrl-C or one of `timeouts` still returns what arrived.
+/// Redirects are only followed to hosts on `allowed_hosts`.
+/// Fails with a network error if the API can't be reached, doesn't start answering in time, or the connection breaks
+/// before any content arrives, a configuration error if it rejects the key, and a provider error if it answers with
+/// any other error or without content.
+fn send_openai_request(
+    url: &str,
+    allowed_hosts: &HostAllowlist,
+    timeouts: &RequestTimeouts,
+    api_key: &ApiKey,
+    request_body: &GenerationRequest,
+    audit_log: &AuditLog,
+) -> Result<Completion> {
+    // The client's own timeout only ends a read the answer has stopped waiting for, so it is a little longer.
+    let client = Client::builder()
+        .redirect(allowed_hosts.redirect_policy())
+        .connect_timeout(timeouts.connect)
+        .timeout(timeouts.total + std::time::Duration::from_secs(5))
+        .build()
+        .context("Failed to build HTTP client")?;
+    let host = url_host(url);
+    // An API that doesn't stream answers with plain JSON, which is read the same as before streaming was asked for.
+    let mut payload = serde_json::to_value(request_body)?;
+    payload["stream"] = json!(true);
+    payload["stream_options"] = json!({"include_usage": true});
+
+    let request = client
+        .post(url)
+        .bearer_auth(api_key.expose())
+        .header("Content-Type", "application/json")
+        .json(&payload);
+    let received = match receive(request, timeouts) {
+        Ok(received) => received,
+        Err(unanswered) => {
+            audit_log.record(&endpoint_provider(url), url, &payload, None)?;
+            match unanswered {
+                Unanswered::Failed(error) => {
+                    return Err(error).context(Error::Network(format!(
+                        "Can't reach {}; check your network connection or HTTPS_PROXY",
+                        host
+                    )))
+                }
+                Unanswered::TimedOut(seconds) => bail!(Error::Network(format!(
+                    "{} didn't start answering within {} seconds; raise `response` in REQUEST_TIMEOUTS if the model \
+                     needs longer",
+                    host, seconds
+                ))),
+                Unanswered::Interrupted => return Ok(Completion::interrupted()),
+                Unanswered::Lost => bail!(Error::Network(format!("The request to {} stopped before it was answered", host))),
+            }
+        }
+    };
+
+    let status = received.status;
+    let body = received.body;
+    audit_log.record(&endpoint_provider(url), url, &payload, Some((status.as_u16(), &body)))?;
+    if received.event_stream && status.is_success() {
+        let (content, usage) = event_stream_content(&body)?;
+        return match received.cut {
+            Some(cut) if content.is_empty() && cut != Cut::Interrupted => bail!(Error::Network(format!(
+                "The answer from {} was cut off before any content arrived because {}",
+                host,
+                cut.reason()
+            ))),
+            None if content.is_empty() => bail!(Error::provider("The API response had no message content")),
+            cut => Ok(Completion { content, usage, cut }),
+        };
+    }
+    // A JSON answer is only of use whole.
+    match received.cut {
+        Some(Cut::Interrupted) => return Ok(Completion::interrupted()),
+        Some(cut) => bail!(Error::Network(format!(
+            "The connection to {} ended while reading its {} response because {}",
+            host,
+            status,
+            cut.reason()
+        ))),
+        None => {}
+    }
+    let response_json: serde_json::Value = serde_json::from_slice(&body).context(Error::Provider {
+        message: format!("The API answered {} with a response that is not JSON", status),
+        status: Some(status.as_u16()),
+        body: Some(String::from_utf8_lossy(&body).to_string()),
+    })?;
+    // Error responses explain themselves in `error.message`.
+    let message = response_json["error"]["message"].as_str().unwrap_or("no details given");
+    if status.as_u16() == 401 {
+        bail!(Error::Config(format!(
+            "OPENAI_API_KEY was rejected ({}); create a new key at https://platform.openai.com/api-keys",
+            message
+        )));
+    }
+    if !status.is_success() {
+        bail!(Error::Provider {
+            message: format!("The API answered {}: {}", status, message),
+            status: Some(status.as_u16()),
+            body: Some(String::from_utf8_lossy(&body).to_string()),
+        });
+    }
+    match response_json["choices"][0]["message"]["content"].as_str() {
+        Some(content) => Ok(Completion {
+            content: content.to_string(),
+            usage: response_json["usage"].clone(),
+            cut: None,
+        }),
+        None => bail!(Error::provider("The API response had no message content")),
+    }
+}
@@ -0,0 +1,666 @@
+use anyhow::{bail, Context, Result};
+use globset::Glob;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::parse_cost;
+use crate::console::status;
+use crate::doc_types::{DocType, ScriptShell, TaskRunner};
+use crate::endpoints::parse_base_url;
+use crate::errors::Error;
+use crate::formats::OutputFormat;
+use crate::front_matter::FrontMatter;
+use crate::history::{HistoryShell, HistoryTimeZone};
+use crate::injection::InjectionScan;
+use crate::safety::CommandSafety;
+use crate::secrets::SecretScan;
+use crate::sections::PROJECT_CONFIG_FILE;
+use crate::sinks::SinkKind;
+use crate::style::{Audience, Tone};
+
+/// A setting read from the environment (or `.env`) or a configuration file, with its default and the flag that overrides it.
+pub struct Setting {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub flag: Option<&'static str>,
+    /// Secret values are masked when shown.
+    pub secret: bool,
+    /// Trusted settings choose where data is sent or recorded, what code runs, or which local records are deleted, so
+    /// a project's file, which comes with the repository, can't set them; only the environment, the user's file, and flags can.
+    pub trusted: bool,
+}
+
+const fn setting(name: &'static str, default: &'static str, flag: Option<&'static str>) -> Setting {
+    Setting {
+        name,
+        default,
+        flag,
+        secret: false,
+        trusted: false,
+    }
+}
+
+const fn trusted_setting(name: &'static str, default: &'static str, flag: Option<&'static str>) -> Setting {
+    Setting {
+        trusted: true,
+        ..setting(name, default, flag)
+    }
+}
+
+/// Every setting the tool reads. The API key has no flag, so it never ends up in shell history.
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        name: "OPENAI_API_KEY",
+        default: "",
+        flag: None,
+        secret: true,
+        trusted: true,
+    },
+    setting("OPENAI_MODEL", "gpt-4o", Some("--model")),
+    setting("SEED", "", Some("--seed")),
+    trusted_setting("OPENAI_BASE_URL", "https://api.openai.com/v1", Some("--base-url")),
+    setting("ENABLE_OPENAI", "false", Some("--enable-openai")),
+    setting("HOURS_OF_SHELL_HISTORY", "5", Some("--hours")),
+    setting("HISTORY_FILES", "", Some("--history")),
+    setting("HISTORY_SHELL", "auto", Some("--shell")),
+    setting("TIMEZONE", "local", Some("--timezone")),
+    setting("MAX_FILE_COUNT_FOR_CONTEXT", "5", Some("--max-files")),
+    setting("DEBUG_REQUEST", "false", Some("--debug-request")),
+    setting("INCLUDE_SHELL_HISTORY", "false", Some("--include-shell-history")),
+    setting("INCLUDE_REPOSITORY_FILES", "false", Some("--include-repository-files")),
+    setting("INCLUDE_ENV_FILE_KEYS", "false", Some("--include-env-file-keys")),
+    setting("FOLLOW_SYMLINKS", "false", Some("--follow-symlinks")),
+    setting("CONTENT_TRANSFORMS", "", Some("--content-transforms")),
+    setting("REDACT_PATTERNS", "", Some("--redact")),
+    setting("IGNORE_GLOBS", "", Some("--ignore")),
+    setting("COMMAND_BLOCKLIST", "", Some("--block-command")),
+    setting("ENV_KEY_ALLOWLIST", "", Some("--allow-env-key")),
+    setting("ENV_KEY_DENYLIST", "", Some("--deny-env-key")),
+    trusted_setting("SECRET_SCAN", "redact", Some("--secret-scan")),
+    setting("INJECTION_SCAN", "strip", Some("--injection-scan")),
+    setting("SCRUB_PII", "false", Some("--scrub-pii")),
+    setting("PSEUDONYMIZE", "false", Some("--pseudonymize")),
+    setting("INTERNAL_DOMAINS", "", Some("--internal-domain")),
+    trusted_setting("ALLOWED_HOSTS", "", Some("--allow-host")),
+    setting(
+        "REQUEST_TIMEOUTS",
+        "connect:10,response:120,idle:60,total:600",
+        Some("--request-timeouts"),
+    ),
+    trusted_setting("WASM_PLUGINS", "", Some("--plugin")),
+    setting("REMEMBER_CONSENT", "true", Some("--remember-consent")),
+    trusted_setting("AUDIT_LOG", "", Some("--audit-log")),
+    trusted_setting("RETENTION_DAYS", "", Some("--retention-days")),
+    setting("ENCRYPT_ARTIFACTS", "false", Some("--encrypt-artifacts")),
+    setting("LOCAL_ONLY", "false", Some("--local-only")),
+    setting("COMMAND_SAFETY", "annotate", Some("--command-safety")),
+    setting("TOKEN_BUDGET", "", Some("--token-budget")),
+    setting("MAX_COST", "", Some("--max-cost")),
+    setting("MAX_PAYLOAD_BYTES", "1048576", Some("--max-payload-bytes")),
+    setting("BUDGET_SPLIT", "", Some("--budget-split")),
+    setting("MERGE_INTO_README", "false", Some("--merge")),
+    trusted_setting("OUTPUT_SINK", "file", Some("--sink")),
+    trusted_setting("OUTPUT_SINK_URL", "", Some("--sink-url")),
+    trusted_setting("OUTPUT_TEMPLATE", "", Some("--output-template")),
+    setting("OUTPUT_FORMAT", "markdown", Some("--format")),
+    setting("DOC_TYPE", "quickstart", Some("--doc-type")),
+    setting("SETUP_SCRIPT_SHELL", "sh", Some("--script-shell")),
+    setting("TASK_RUNNER", "just", Some("--task-runner")),
+    setting("DEVCONTAINER_DOCKERFILE", "false", Some("--dockerfile")),
+    setting("OUTPUT_LANGUAGE", "", Some("--language")),
+    setting("AUDIENCE", "", Some("--audience")),
+    setting("TONE", "", Some("--tone")),
+    trusted_setting("PROMPT_TEMPLATE", "", Some("--prompt-template")),
+    trusted_setting("HOOK_SCRIPT", "", Some("--hook-script")),
+    trusted_setting("EXEMPLARS_DIR", "", Some("--exemplars")),
+    setting("INSERT_TOC", "true", Some("--no-toc")),
+    setting("PROVENANCE_FOOTER", "false", Some("--provenance")),
+    setting("FRONT_MATTER", "", Some("--front-matter")),
+    setting("MAGIC_QUICKSTART_PROFILE", "", Some("--profile")),
+];
+
+/// Settings that are `true` or `false`; anything else is silently treated as false (or true for INSERT_TOC).
+const BOOLEAN_SETTINGS: [&str; 15] = [
+    "ENABLE_OPENAI",
+    "DEBUG_REQUEST",
+    "INCLUDE_SHELL_HISTORY",
+    "INCLUDE_REPOSITORY_FILES",
+    "INCLUDE_ENV_FILE_KEYS",
+    "FOLLOW_SYMLINKS",
+    "SCRUB_PII",
+    "PSEUDONYMIZE",
+    "REMEMBER_CONSENT",
+    "ENCRYPT_ARTIFACTS",
+    "LOCAL_ONLY",
+    "MERGE_INTO_README",
+    "DEVCONTAINER_DOCKERFILE",
+    "INSERT_TOC",
+    "PROVENANCE_FOOTER",
+];
+
+/// Whether a value is one a setting accepts.
+type Validator = fn(&str) -> bool;
+
+/// Settings that name one of a fixed set of values, with the parser that accepts them.
+const CHOICE_SETTINGS: [(&str, Validator); 13] = [
+    ("OUTPUT_FORMAT", |value| OutputFormat::parse(value).is_some()),
+    ("DOC_TYPE", |value| DocType::parse(value).is_some()),
+    ("SETUP_SCRIPT_SHELL", |value| ScriptShell::parse(value).is_some()),
+    ("TASK_RUNNER", |value| TaskRunner::parse(value).is_some()),
+    ("AUDIENCE", |value| Audience::parse(value).is_some()),
+    ("TONE", |value| Tone::parse(value).is_some()),
+    ("FRONT_MATTER", |value| FrontMatter::parse(value).is_some()),
+    ("HISTORY_SHELL", |value| HistoryShell::parse(value).is_some()),
+    ("TIMEZONE", |value| HistoryTimeZone::parse(value).is_some()),
+    ("SECRET_SCAN", |value| SecretScan::parse(value).is_some()),
+    ("INJECTION_SCAN", |value| InjectionScan::parse(value).is_some()),
+    ("COMMAND_SAFETY", |value| CommandSafety::parse(value).is_some()),
+    ("OUTPUT_SINK", |value| SinkKind::parse(value).is_some()),
+];
+
+/// Settings that hold a list. Each configuration file extends the list instead of replacing it, so rules set once in
+/// the user file apply to every project. Files set them as arrays; the environment separates entries with newlines.
+pub const LIST_SETTINGS: [&str; 8] = [
+    "REDACT_PATTERNS",
+    "IGNORE_GLOBS",
+    "COMMAND_BLOCKLIST",
+    "ENV_KEY_ALLOWLIST",
+    "ENV_KEY_DENYLIST",
+    "INTERNAL_DOMAINS",
+    "ALLOWED_HOSTS",
+    "WASM_PLUGINS",
+];
+
+/// Settings that must be non-negative whole numbers.
+const NUMBER_SETTINGS: [&str; 6] = [
+    "SEED",
+    "MAX_FILE_COUNT_FOR_CONTEXT",
+    "HOURS_OF_SHELL_HISTORY",
+    "TOKEN_BUDGET",
+    "MAX_PAYLOAD_BYTES",
+    "RETENTION_DAYS",
+];
+
+/// Finds a setting by name, in upper or lower case (`OPENAI_MODEL` or `openai_model`).
+pub fn find_setting(name: &str) -> Result<&'static Setting> {
+    match SETTINGS.iter().find(|setting| setting.name.eq_ignore_ascii_case(name)) {
+        Some(setting) => Ok(setting),
+        None => bail!(Error::Config(format!(
+            "Unknown setting {}; `magic_quickstart config` lists them all",
+            name
+        ))),
+    }
+}
+
+/// What's wrong with a value for a setting, as a hint on how to fix it, or `None` if the setting accepts it.
+pub fn value_problem(name: &str, value: &str) -> Option<&'static str> {
+    if BOOLEAN_SETTINGS.contains(&name) && !matches!(value.to_lowercase().as_str(), "true" | "false") {
+        return Some("use true or false");
+    }
+    if CHOICE_SETTINGS.iter().any(|(choice, accepts)| *choice == name && !accepts(value)) {
+        return Some("see `magic_quickstart --help` for valid values");
+    }
+    if NUMBER_SETTINGS.contains(&name) && value.parse::<usize>().is_err() {
+        return Some("use a whole number");
+    }
+    if name == "RETENTION_DAYS" && value.parse::<usize>() == Ok(0) {
+        return Some("use 1 or more; to delete everything once, run `magic_quickstart purge --older-than 0`");
+    }
+    if name == "MAX_COST" && parse_cost(value).is_none() {
+        return Some("use an amount in US dollars, e.g. 0.50");
+    }
+    if name == "OPENAI_BASE_URL" && parse_base_url(value).is_none() {
+        return Some("use an http:// or https:// URL such as https://api.openai.com/v1");
+    }
+    if name == "OUTPUT_SINK_URL" && parse_base_url(value).is_none() {
+        return Some("use an http:// or https:// URL");
+    }
+    if name == "REDACT_PATTERNS" && value.lines().any(|pattern| Regex::new(pattern).is_err()) {
+        return Some("use regular expressions, one per entry");
+    }
+    if matches!(name, "IGNORE_GLOBS" | "ENV_KEY_ALLOWLIST" | "ENV_KEY_DENYLIST") && value.lines().any(|glob| Glob::new(glob).is_err()) {
+        return Some("use globs such as *.pem or secrets/**, one per entry");
+    }
+    if name == "ALLOWED_HOSTS" && value.lines().any(|host| Glob::new(host).is_err()) {
+        return Some("use hostnames such as api.openai.com or *.example.com, one per entry");
+    }
+    None
+}
+
+/// One of the configuration files, for `config get`, `set`, and `list`.
+#[derive(Clone, Copy)]
+pub enum ConfigFile {
+    /// `~/.config/magic_quickstart/config.toml`.
+    User,
+    /// The project's `.magic_quickstart.toml`.
+    Project,
+}
+
+impl ConfigFile {
+    pub fn path(self, project_dir: &Path) -> Result<PathBuf> {
+        match self {
+            ConfigFile::User => user_config_path()
+                .ok_or_else(|| Error::Config("Can't find the user configuration directory; set HOME or XDG_CONFIG_HOME".into()).into()),
+            ConfigFile::Project => Ok(project_dir.join(PROJECT_CONFIG_FILE)),
+        }
+    }
+}
+
+/// The configuration files settings are read from, below the environment in precedence:
+/// the project's `.magic_quickstart.toml` overrides the user's `~/.config/magic_quickstart/config.toml`.
+/// Keys are the setting names in lowercase, e.g. `openai_model = "gpt-4o"` or `include_shell_history = true`.
+/// A selected profile's `[profiles.<name>]` table overrides the rest of the file it is in.
+pub struct ConfigFiles {
+    /// Settings tables in order of precedence, each labeled with where it came from.
+    layers: Vec<(String, toml::Table)>,
+    /// The `.env` file and the variables it sets, which the process environment overrides. It is read into this
+    /// run's settings rather than the process environment, so one project's `.env` can't leak into another's run.
+    dotenv: Option<(PathBuf, Vec<(String, String)>)>,
+}
+
+impl ConfigFiles {
+    /// Reads the user and project configuration files; missing files are skipped. The profile is taken from
+    /// `profile` (the `--profile` flag), else from MAGIC_QUICKSTART_PROFILE in the environment or the files.
+    /// Fails if a file is not valid TOML or the selected profile is defined in neither file.
+    pub fn load(project_dir: &Path, profile: Option<&str>) -> Result<Self> {
+        Self::load_files(Some(project_dir.join(PROJECT_CONFIG_FILE)), read_dotenv(project_dir), profile)
+    }
+
+    /// Reads only the user's configuration file, for a cloned repository, whose project file is someone else's.
+    /// The `.env` is the current directory's, not the clone's.
+    pub fn load_user(profile: Option<&str>) -> Result<Self> {
+        let dotenv = env::current_dir().ok().and_then(|dir| read_dotenv(&dir));
+        Self::load_files(None, dotenv, profile)
+    }
+
+    fn load_files(project_file: Option<PathBuf>, dotenv: Option<(PathBuf, Vec<(String, String)>)>, profile: Option<&str>) -> Result<Self> {
+        let mut files: Vec<(PathBuf, toml::Table, bool)> = Vec::new();
+        for (path, user_file) in [(project_file, false), (user_config_path(), true)] {
+            if let Some(path) = path {
+                if let Some(table) = read_config_file(&path)? {
+                    files.push((path, table, user_file));
+                }
+            }
+        }
+
+        let base_layers: Vec<(String, toml::Table)> = files
+            .iter()
+            .map(|(path, table, user_file)| (path.display().to_string(), settings_table(path, table, *user_file)))
+            .collect();
+        let base = ConfigFiles {
+            layers: base_layers,
+            dotenv,
+        };
+        let Some(profile) = profile.map(str::to_string).or_else(|| base.get("MAGIC_QUICKSTART_PROFILE")) else {
+            return Ok(base);
+        };
+
+        let mut layers = Vec::new();
+        for ((path, table, user_file), base_layer) in files.iter().zip(base.layers) {
+            let profile_table = table
+                .get("profiles")
+                .and_then(|profiles| profiles.get(&profile))
+                .and_then(|profile| profile.as_table());
+            if let Some(profile_table) = profile_table {
+                let label = format!("{} [profile {}]", path.display(), profile);
+                layers.push((label, settings_table(path, profile_table, *user_file)));
+            }
+            layers.push(base_layer);
+        }
+        if layers.len() == files.len() {
+            return Err(Error::Config(format!(
+                "Profile \"{}\" not found; define it as [profiles.{}] in {} or {}",
+                profile,
+                profile,
+                PROJECT_CONFIG_FILE,
+                user_config_path().map(|path| path.display().to_string()).unwrap_or_default()
+            ))
+            .into());
+        }
+        Ok(ConfigFiles {
+            layers,
+            dotenv: base.dotenv,
+        })
+    }
+
+    /// The `.env` file settings were read from, if one was found.
+    pub fn dotenv_path(&self) -> Option<&Path> {
+        self.dotenv.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// A variable from the process environment, else from the `.env` file, with where it came from.
+    fn environment(&self, name: &str) -> Option<(String, String)> {
+        if let Ok(value) = env::var(name) {
+            return Some((value, "environment".to_string()));
+        }
+        let (path, variables) = self.dotenv.as_ref()?;
+        let value = variables.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone())?;
+        Some((value, path.display().to_string()))
+    }
+
+    /// Returns the value of a setting and where it came from: the environment first, then `.env`,
+    /// then the project file, then the user file. A list setting's entries are merged from all of them, one per line.
+    pub fn lookup(&self, name: &str) -> Option<(String, String)> {
+        if LIST_SETTINGS.contains(&name) {
+            let entries = self.list_entries(name);
+            let mut sources: Vec<&str> = Vec::new();
+            for (_, source) in &entries {
+                if !sources.contains(&source.as_str()) {
+                    sources.push(source);
+                }
+            }
+            let values: Vec<&str> = entries.iter().map(|(value, _)| value.as_str()).collect();
+            return (!entries.is_empty()).then(|| (values.join("\n"), sources.join(", ")));
+        }
+        if let Some(found) = self.environment(name) {
+            return Some(found);
+        }
+        let key = name.to_lowercase();
+        self.layers
+            .iter()
+            .find_map(|(label, table)| Some((display_value(table.get(&key)?), label.clone())))
+    }
+
+    /// Returns the value of a setting from the environment or the configuration files.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.lookup(name).map(|(value, _)| value)
+    }
+
+    /// Every entry of a list setting with where it came from, broadest first: the user file, then the project file
+    /// (each followed by the selected profile's table), then the environment or `.env`. Empty entries are skipped.
+    pub fn list_entries(&self, name: &str) -> Vec<(String, String)> {
+        let key = name.to_lowercase();
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for (label, table) in self.layers.iter().rev() {
+            if let Some(value) = table.get(&key) {
+                entries.extend(display_value(value).lines().map(|entry| (entry.to_string(), label.clone())));
+            }
+        }
+        if let Some((value, source)) = self.environment(name) {
+            entries.extend(value.lines().map(|entry| (entry.to_string(), source.clone())));
+        }
+        entries.retain(|(entry, _)| !entry.trim().is_empty());
+        entries
+    }
+}
+
+/// `$XDG_CONFIG_HOME/magic_quickstart/config.toml`, defaulting to `~/.config/magic_quickstart/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var("HOME").ok().map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("magic_quickstart").join("config.toml"))
+}
+
+/// `$XDG_DATA_HOME`, defaulting to `~/.local/share`, where shells keep history and this tool keeps its runs.
+pub fn data_home() -> Option<PathBuf> {
+    env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var("HOME").ok().map(|home| Path::new(&home).join(".local").join("share")))
+}
+
+/// Parses a setting with `parse`, or returns `None` if it isn't set. An invalid value is a configuration error that
+/// says where it was set and what was `expected`.
+pub fn parse_setting<T>(files: &ConfigFiles, name: &str, expected: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>> {
+    let Some((value, source)) = files.lookup(name) else {
+        return Ok(None);
+    };
+    match parse(&value) {
+        Some(parsed) => Ok(Some(parsed)),
+        None => bail!(Error::Config(format!(
+            "Invalid {} \"{}\" in {} (expected {})",
+            name, value, source, expected
+        ))),
+    }
+}
+
+/// A setting's value from a configuration file as it would be read from the environment: strings unquoted, and
+/// arrays one entry per line.
+fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(value) => value.clone(),
+        toml::Value::Array(values) => values.iter().map(display_value).collect::<Vec<_>>().join("\n"),
+        value => value.to_string(),
+    }
+}
+
+/// The settings one configuration file sets, or its `[profiles.<profile>]` table sets, by setting name.
+/// A missing file sets none.
+pub fn file_settings(path: &Path, profile: Option<&str>) -> Result<Vec<(&'static Setting, String)>> {
+    let table = read_config_file(path)?.unwrap_or_default();
+    let table = match profile {
+        Some(profile) => match table.get("profiles").and_then(|profiles| profiles.get(profile)) {
+            Some(toml::Value::Table(profile_table)) => profile_table.clone(),
+            _ => toml::Table::new(),
+        },
+        None => table,
+    };
+    Ok(SETTINGS
+        .iter()
+        .filter_map(|setting| Some((setting, display_value(table.get(&setting.name.to_lowercase())?))))
+        .collect())
+}
+
+/// Sets a setting in a configuration file, or in its `[profiles.<profile>]` table, creating the file if needed and
+/// keeping its comments and layout. Booleans and numbers are written as TOML booleans and integers. Fails if the
+/// setting doesn't accept the value, or if it is a secret and the file is the project's, which is usually committed,
+/// or it is trusted and the file is the project's, which it would be ignored in.
+pub fn set_setting(file: ConfigFile, project_dir: &Path, profile: Option<&str>, setting: &Setting, value: &str) -> Result<PathBuf> {
+    if setting.secret && matches!(file, ConfigFile::Project) {
+        bail!(Error::Config(format!(
+            "{} can't be kept in {}, which is usually committed; pass --user to set it in the user file",
+            setting.name, PROJECT_CONFIG_FILE
+        )));
+    }
+    if setting.trusted && matches!(file, ConfigFile::Project) {
+        bail!(Error::Config(format!(
+            "{} is only read from the environment or the user file, never from {}; pass --user to set it in the user file",
+            setting.name, PROJECT_CONFIG_FILE
+        )));
+    }
+    if let Some(hint) = value_problem(setting.name, value) {
+        bail!(Error::Config(format!("\"{}\" is not a valid {}; {}", value, setting.name, hint)));
+    }
+
+    let path = file.path(project_dir)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut document: toml_edit::DocumentMut = content
+        .parse()
+        .with_context(|| Error::Config(format!("Failed to parse {}", path.display())))?;
+    let mut table = document.as_table_mut();
+    if let Some(profile) = profile {
+        let profiles = table.entry("profiles").or_insert_with(|| {
+            let mut profiles = toml_edit::Table::new();
+            profiles.set_implicit(true);
+            toml_edit::Item::Table(profiles)
+        });
+        table = profiles
+            .as_table_mut()
+            .and_then(|profiles| profiles.entry(profile).or_insert(toml_edit::table()).as_table_mut())
+            .with_context(|| Error::Config(format!("profiles.{} in {} is not a table", profile, path.display())))?;
+    }
+    let key = setting.name.to_lowercase();
+    table[&key] = match value.parse::<i64>() {
+        Ok(number) if NUMBER_SETTINGS.contains(&setting.name) => toml_edit::value(number),
+        _ if setting.name == "MAX_COST" => toml_edit::value(parse_cost(value).unwrap_or_default()),
+        _ if BOOLEAN_SETTINGS.contains(&setting.name) => toml_edit::value(value.eq_ignore_ascii_case("true")),
+        _ if LIST_SETTINGS.contains(&setting.name) => toml_edit::value(value.lines().collect::<toml_edit::Array>()),
+        _ => toml_edit::value(value),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, document.to_string()).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Reads a configuration file, or `None` if it doesn't exist.
+fn read_config_file(path: &Path) -> Result<Option<toml::Table>> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let table = content
+        .parse()
+        .with_context(|| Error::Config(format!("Failed to parse {}", path.display())))?;
+    Ok(Some(table))
+}
+
+/// Picks the settings out of a configuration file's table (or one of its profiles), warning about unknown keys.
+/// Tables such as `[sections]` and `[profiles]` are handled elsewhere. Secrets and trusted settings are only accepted
+/// from the user's file: project files are usually committed, and may come from someone else.
+fn settings_table(path: &Path, table: &toml::Table, user_file: bool) -> toml::Table {
+    let mut settings = toml::Table::new();
+    for (key, value) in table {
+        if value.is_table() {
+            continue;
+        }
+        match SETTINGS.iter().find(|setting| setting.name.to_lowercase() == *key) {
+            Some(setting) if setting.secret && !user_file => status!(
+                "Ignoring {} in {}: keep secrets in .env or {}",
+                key,
+                path.display(),
+                user_config_path().map(|path| path.display().to_string()).unwrap_or_default()
+            ),
+            Some(setting) if setting.trusted && !user_file => status!(
+                "Ignoring {} in {}: a repository's files can't set it; use the environment or {}",
+                key,
+                path.display(),
+                user_config_path().map(|path| path.display().to_string()).unwrap_or_default()
+            ),
+            Some(_) => {
+                settings.insert(key.clone(), value.clone());
+            }
+            None => status!("Ignoring unknown setting in {}: {}", path.display(), key),
+        }
+    }
+    settings
+}
+
+/// Prints every setting with its effective value and where it came from: the environment, a configuration file,
+/// or the default.
+pub fn print_settings(files: &ConfigFiles) {
+    let width = SETTINGS.iter().map(|setting| setting.name.len()).max().unwrap_or(0);
+    for setting in SETTINGS {
+        let (value, source) = match files.lookup(setting.name) {
+            Some((value, source)) if setting.secret => (mask(&value), source),
+            Some(found) => found,
+            None => (setting.default.to_string(), "default".to_string()),
+        };
+        let flag = setting.flag.map(|flag| format!(", flag {}", flag)).unwrap_or_default();
+        let value = value.replace('\n', ", ");
+        println!("{:width$} = {:30} ({}{})", setting.name, value, source, flag, width = width);
+    }
+}
+
+/// Prints the effective configuration as TOML, with where each value came from as a comment: every setting that is
+/// set, and the merged entries of list settings. Secrets are masked.
+pub fn print_effective_settings(files: &ConfigFiles) {
+    for setting in SETTINGS {
+        if LIST_SETTINGS.contains(&setting.name) {
+            let entries = files.list_entries(setting.name);
+            if entries.is_empty() {
+                continue;
+            }
+            println!("{} = [", setting.name.to_lowercase());
+            for (entry, source) in entries {
+                println!("    {}, # {}", toml::Value::String(entry), source);
+            }
+            println!("]");
+            continue;
+        }
+        let Some((value, source)) = files.lookup(setting.name) else {
+            continue;
+        };
+        let value = match value.parse::<i64>() {
+            _ if setting.secret => toml::Value::String(mask(&value)),
+            Ok(number) if NUMBER_SETTINGS.contains(&setting.name) => toml::Value::Integer(number),
+            _ if BOOLEAN_SETTINGS.contains(&setting.name) => toml::Value::Boolean(value.eq_ignore_ascii_case("true")),
+            _ => toml::Value::String(value),
+        };
+        println!("{} = {} # {}", setting.name.to_lowercase(), value, source);
+    }
+}
+
+/// Prints the settings one configuration file (or one of its profiles) sets, in the same layout as `print_settings`.
+pub fn print_file_settings(settings: &[(&Setting, String)]) {
+    let width = settings.iter().map(|(setting, _)| setting.name.len()).max().unwrap_or(0);
+    for (setting, value) in settings {
+        let value = if setting.secret { mask(value) } else { value.replace('\n', ", ") };
+        println!("{:width$} = {}", setting.name, value, width = width);
+    }
+}
+
+/// Shows only the last four characters of a secret, and nothing of short ones.
+pub fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+/// Reads the variables in a directory's .env file, or `None` if it has none; parent directories are not searched.
+/// Reading stops at the first line that can't be parsed. A `.env` comes with the repository like the project file,
+/// so it can't set trusted settings or the editor `--edit` runs either; those are reported and dropped. It can set
+/// the API key, which is where it is usually kept.
+fn read_dotenv(dir: &Path) -> Option<(PathBuf, Vec<(String, String)>)> {
+    let path = dir.join(".env");
+    let variables = dotenvy::from_path_iter(&path)
+        .ok()?
+        .map_while(Result::ok)
+        .filter(|(key, _)| match SETTINGS.iter().find(|setting| setting.name == key) {
+            Some(setting) if setting.trusted && !setting.secret => {
+                status!(
+                    "Ignoring {} in {}: a repository's files can't set it; use the environment or {}",
+                    key,
+                    path.display(),
+                    user_config_path().map(|path| path.display().to_string()).unwrap_or_default()
+                );
+                false
+            }
+            _ if matches!(key.as_str(), "EDITOR" | "VISUAL") => {
+                status!(
+                    "Ignoring {} in {}: the editor is only taken from the environment",
+                    key,
+                    path.display()
+                );
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    Some((path, variables))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_dotenv_cannot_set_base_url() {
+        let project = tempfile::tempdir().unwrap();
+        let dotenv = project.path().join(".env");
+        fs::write(
+            &dotenv,
+            "OPENAI_BASE_URL=https://attacker.example/v1\nOPENAI_MODEL=gpt-test\nEDITOR=/tmp/payload\n",
+        )
+        .unwrap();
+
+        let files = ConfigFiles::load(project.path(), None).unwrap();
+        let (_, variables) = files.dotenv.as_ref().unwrap();
+        assert_eq!(variables, &[("OPENAI_MODEL".to_string(), "gpt-test".to_string())]);
+        let from_dotenv = matches!(files.lookup("OPENAI_BASE_URL"), Some((_, source)) if source == dotenv.display().to_string());
+        assert!(!from_dotenv);
+    }
+}
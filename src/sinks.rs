@@ -0,0 +1,234 @@
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use crate::clipboard::copy_to_clipboard;
+use crate::console::status;
+use crate::endpoints::{url_host, HostAllowlist};
+use crate::errors::Error;
+use crate::i18n::t;
+use crate::output::write_output;
+
+/// Where generated files go (OUTPUT_SINK).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SinkKind {
+    /// Written to the output directory, with a diff and a confirmation before a changed file is overwritten.
+    File,
+    /// Printed to stdout.
+    Stdout,
+    /// Copied to the clipboard.
+    Clipboard,
+    /// Posted as JSON to OUTPUT_SINK_URL.
+    Http,
+    /// Written like `file`, then committed on its own.
+    Git,
+}
+
+impl SinkKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "file" => Some(SinkKind::File),
+            "stdout" => Some(SinkKind::Stdout),
+            "clipboard" => Some(SinkKind::Clipboard),
+            "http" => Some(SinkKind::Http),
+            "git" => Some(SinkKind::Git),
+            _ => None,
+        }
+    }
+
+    /// Whether files end up in the output directory, where merging, splitting, and `runs` can find them.
+    pub fn writes_files(self) -> bool {
+        matches!(self, SinkKind::File | SinkKind::Git)
+    }
+}
+
+/// Describes a valid OUTPUT_SINK in configuration errors.
+pub const OUTPUT_SINK_EXPECTED: &str = "file, stdout, clipboard, http, or git";
+
+/// Somewhere generated files are delivered. The generation core decides what each file is called and holds; the
+/// sink decides what happens to it.
+pub trait OutputSink: Send + Sync {
+    /// Delivers `content`, the file meant for `path`. Returns whether it was written to `path`, which is what a
+    /// run records as the files it wrote.
+    fn write(&self, path: &Path, content: &str) -> Result<bool>;
+}
+
+impl<T: OutputSink + ?Sized> OutputSink for Arc<T> {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        (**self).write(path, content)
+    }
+}
+
+/// Keeps each file in memory instead of delivering it, for servers and bots that handle the documents themselves.
+/// Pass it to `output_sink` in an `Arc`, and read `files` once the run is done.
+#[derive(Default)]
+pub struct MemorySink {
+    files: Mutex<Vec<(PathBuf, String)>>,
+}
+
+impl MemorySink {
+    /// The files delivered so far, in order, each with the path it was meant for.
+    pub fn files(&self) -> Vec<(PathBuf, String)> {
+        self.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push((path.to_path_buf(), content.to_string()));
+        Ok(false)
+    }
+}
+
+/// Writes to the output directory. A changed file is shown as a diff and only overwritten once confirmed (unless
+/// `assume_yes`), keeping the previous version as a `.bak`.
+pub struct FileSink {
+    pub assume_yes: bool,
+}
+
+impl OutputSink for FileSink {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        write_output(path, content, self.assume_yes)
+    }
+}
+
+/// Prints each file to stdout, with its name on stderr, for piping into other tools.
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        status!("{}", t!("sink-stdout", path = path.display().to_string()));
+        print!("{}", content);
+        Ok(false)
+    }
+}
+
+/// Copies each file to the clipboard.
+pub struct ClipboardSink;
+
+impl OutputSink for ClipboardSink {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        if copy_to_clipboard(content) {
+            status!(
+                "{}",
+                t!("copied", name = path.file_name().unwrap_or_default().to_string_lossy().to_string())
+            );
+        }
+        Ok(false)
+    }
+}
+
+/// Posts each file to a URL as `{"path": ..., "content": ...}`, with the path relative to the output directory,
+/// e.g. for a docs service or a chat webhook. Like API requests, it only goes to hosts on ALLOWED_HOSTS.
+pub struct HttpSink {
+    pub url: String,
+    pub output_dir: PathBuf,
+    pub allowed_hosts: HostAllowlist,
+}
+
+impl OutputSink for HttpSink {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        self.allowed_hosts.check(&self.url)?;
+        let client = Client::builder()
+            .redirect(self.allowed_hosts.redirect_policy())
+            .build()
+            .context("Failed to build HTTP client")?;
+        let relative = path.strip_prefix(&self.output_dir).unwrap_or(path);
+        let host = url_host(&self.url);
+        let response = client
+            .post(&self.url)
+            .json(&json!({"path": relative, "content": content}))
+            .send()
+            .context(Error::Network(format!(
+                "Can't reach {}; check your network connection or HTTPS_PROXY",
+                host
+            )))?;
+        if !response.status().is_success() {
+            bail!(Error::Network(format!(
+                "{} answered {} when {} was posted to it",
+                host,
+                response.status(),
+                relative.display()
+            )));
+        }
+        status!("{}", t!("sink-posted", path = relative.display().to_string(), host = host.as_str()));
+        Ok(false)
+    }
+}
+
+/// Writes like [`FileSink`], then commits each written file on its own, leaving anything else staged alone.
+pub struct GitSink {
+    pub assume_yes: bool,
+}
+
+impl OutputSink for GitSink {
+    fn write(&self, path: &Path, content: &str) -> Result<bool> {
+        if !write_output(path, content, self.assume_yes)? {
+            return Ok(false);
+        }
+        let directory = path.parent().unwrap_or(Path::new("."));
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let message = format!("Update {} with magic_quickstart", name);
+        for args in [
+            vec!["add", "--", name.as_str()],
+            vec!["commit", "-m", &message, "--", name.as_str()],
+        ] {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(directory)
+                .args(&args)
+                .output()
+                .context(Error::Output("Failed to run git".to_string()))?;
+            if !output.status.success() {
+                bail!(Error::Output(format!(
+                    "Wrote {}, but `git {}` failed: {}",
+                    path.display(),
+                    args[0],
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+        }
+        status!("{}", t!("sink-committed", path = path.display().to_string()));
+        Ok(true)
+    }
+}
+
+/// The sink OUTPUT_SINK names. `http` needs OUTPUT_SINK_URL, whose host must be on ALLOWED_HOSTS, which must be set:
+/// documents built from shell history aren't posted to a host nobody listed.
+pub fn configured_sink(
+    kind: SinkKind,
+    url: Option<&str>,
+    allowed_hosts: &HostAllowlist,
+    output_dir: &Path,
+    assume_yes: bool,
+) -> Result<Arc<dyn OutputSink>> {
+    Ok(match kind {
+        SinkKind::File => Arc::new(FileSink { assume_yes }),
+        SinkKind::Stdout => Arc::new(StdoutSink),
+        SinkKind::Clipboard => Arc::new(ClipboardSink),
+        SinkKind::Http => {
+            let Some(url) = url else {
+                bail!(Error::Config("OUTPUT_SINK=http needs OUTPUT_SINK_URL".to_string()));
+            };
+            if allowed_hosts.is_empty() {
+                bail!(Error::Config(format!(
+                    "OUTPUT_SINK=http needs ALLOWED_HOSTS to list {}, so generated documents only go to a host you chose",
+                    url_host(url)
+                )));
+            }
+            allowed_hosts.check(url)?;
+            Arc::new(HttpSink {
+                url: url.to_string(),
+                output_dir: output_dir.to_path_buf(),
+                allowed_hosts: allowed_hosts.clone(),
+            })
+        }
+        SinkKind::Git => Arc::new(GitSink { assume_yes }),
+    })
+}
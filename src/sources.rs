@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::assets::summarize_asset_directories;
+use crate::console::status;
+use crate::env_usage::classify_env_keys;
+use crate::errors::Error;
+use crate::git::git_metadata;
+use crate::history::{read_history, HistorySource, HistoryTimeZone};
+use crate::i18n::t;
+use crate::lockfiles::summarize_dependencies;
+use crate::models::{AssetDirectory, DependencySummary, EnvKey, FileContext, HistoryEntry};
+use crate::parsers::{collect_env_file_keys, read_project_files_content, ENV_FILES};
+use crate::tokens::estimate_tokens;
+use crate::transforms::{apply_content_transforms, TransformConfig};
+
+/// What a block holds, which decides where in the requests it goes.
+pub enum ContextData {
+    /// Shell history entries, newest first.
+    History(Vec<HistoryEntry>),
+    /// Project file contents.
+    Files(Vec<FileContext>),
+    /// Direct dependencies from lockfiles.
+    Dependencies(Vec<DependencySummary>),
+    /// Asset and data directory summaries.
+    Assets(Vec<AssetDirectory>),
+    /// Env file keys, classified by how the code reads them.
+    EnvKeys(Vec<EnvKey>),
+    /// Branch names and recent commit subjects, for the documents that describe contributing.
+    GitMetadata(Value),
+    /// Anything else, sent to every document as its own message under the block's label.
+    Labeled(Value),
+}
+
+/// A labeled piece of context from one source, with an estimate of the tokens it takes.
+pub struct ContextBlock {
+    pub label: String,
+    pub data: ContextData,
+    pub tokens: usize,
+}
+
+impl ContextBlock {
+    pub fn new(label: impl Into<String>, data: ContextData) -> Self {
+        let json = match &data {
+            ContextData::History(entries) => serde_json::to_string(entries),
+            ContextData::Files(files) => serde_json::to_string(files),
+            ContextData::Dependencies(summaries) => serde_json::to_string(summaries),
+            ContextData::Assets(directories) => serde_json::to_string(directories),
+            ContextData::EnvKeys(keys) => serde_json::to_string(keys),
+            ContextData::GitMetadata(value) | ContextData::Labeled(value) => serde_json::to_string(value),
+        };
+        ContextBlock {
+            label: label.into(),
+            tokens: estimate_tokens(&json.unwrap_or_default()),
+            data,
+        }
+    }
+}
+
+/// Somewhere context is gathered from. Each source is collected on its own thread, and everything it returns is checked,
+/// redacted, budgeted, and agreed to like the built-in context before it is sent.
+pub trait ContextSource: Send + Sync {
+    /// A short name for progress messages, e.g. `history`.
+    fn name(&self) -> &str;
+
+    /// Gathers this source's context for the project in `project_dir`.
+    fn collect(&self, project_dir: &Path) -> Result<Vec<ContextBlock>>;
+}
+
+/// Recent commands from the shell history files.
+pub struct ShellHistorySource<'a> {
+    pub sources: &'a [HistorySource],
+    pub cutoff_timestamp: i64,
+    pub time_zone: HistoryTimeZone,
+}
+
+impl ContextSource for ShellHistorySource<'_> {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn collect(&self, _project_dir: &Path) -> Result<Vec<ContextBlock>> {
+        let history = read_history(self.sources, self.cutoff_timestamp, self.time_zone).context(Error::History(
+            "INCLUDE_SHELL_HISTORY is true, but the shell history can't be read".to_string(),
+        ))?;
+        Ok(vec![ContextBlock::new("Shell history", ContextData::History(history))])
+    }
+}
+
+/// The contents of the project files the detectors picked, with CONTENT_TRANSFORMS applied.
+pub struct ProjectFileSource<'a> {
+    pub files: &'a [PathBuf],
+    pub transforms: &'a TransformConfig,
+}
+
+impl ContextSource for ProjectFileSource<'_> {
+    fn name(&self) -> &str {
+        "files"
+    }
+
+    fn collect(&self, project_dir: &Path) -> Result<Vec<ContextBlock>> {
+        let mut content = read_project_files_content(project_dir, self.files);
+        apply_content_transforms(&mut content, self.transforms);
+        Ok(vec![ContextBlock::new("File contents", ContextData::Files(content))])
+    }
+}
+
+/// Key names from the project's env files, never their values.
+pub struct EnvKeySource {
+    pub follow_symlinks: bool,
+}
+
+impl ContextSource for EnvKeySource {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn collect(&self, project_dir: &Path) -> Result<Vec<ContextBlock>> {
+        let keys = collect_env_file_keys(project_dir, &ENV_FILES);
+        let keys = classify_env_keys(project_dir, &keys, self.follow_symlinks);
+        Ok(vec![ContextBlock::new("Environment file keys", ContextData::EnvKeys(keys))])
+    }
+}
+
+/// Branch names and recent commit subjects, outside a git repository nothing.
+pub struct GitSource;
+
+impl ContextSource for GitSource {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn collect(&self, project_dir: &Path) -> Result<Vec<ContextBlock>> {
+        Ok(git_metadata(project_dir)
+            .map(|metadata| ContextBlock::new("Git branches and recent commit subjects", ContextData::GitMetadata(metadata)))
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Direct dependencies from lockfiles, and what the asset and data directories hold.
+pub struct ManifestSource {
+    pub follow_symlinks: bool,
+}
+
+impl ContextSource for ManifestSource {
+    fn name(&self) -> &str {
+        "manifests"
+    }
+
+    fn collect(&self, project_dir: &Path) -> Result<Vec<ContextBlock>> {
+        Ok(vec![
+            ContextBlock::new("Dependencies", ContextData::Dependencies(summarize_dependencies(project_dir))),
+            ContextBlock::new(
+                "Asset and data directories",
+                ContextData::Assets(summarize_asset_directories(project_dir, self.follow_symlinks)),
+            ),
+        ])
+    }
+}
+
+/// Everything the sources gathered, sorted by where it goes.
+#[derive(Default)]
+pub struct Collected {
+    pub command_history: Vec<HistoryEntry>,
+    pub project_files_content: Vec<FileContext>,
+    pub dependency_summary: Vec<DependencySummary>,
+    pub asset_inventory: Vec<AssetDirectory>,
+    pub env_file_keys: Vec<EnvKey>,
+    pub git_metadata: Option<Value>,
+    /// Labeled blocks, which every document gets as extra context.
+    pub labeled: Vec<(String, Value)>,
+}
+
+impl Collected {
+    fn add(&mut self, block: ContextBlock) {
+        match block.data {
+            ContextData::History(entries) => self.command_history.extend(entries),
+            ContextData::Files(files) => self.project_files_content.extend(files),
+            ContextData::Dependencies(summaries) => self.dependency_summary.extend(summaries),
+            ContextData::Assets(directories) => self.asset_inventory.extend(directories),
+            ContextData::EnvKeys(keys) => self.env_file_keys.extend(keys),
+            ContextData::GitMetadata(value) => self.git_metadata = Some(value),
+            ContextData::Labeled(value) => self.labeled.push((block.label, value)),
+        }
+    }
+}
+
+/// Runs the sources concurrently, each on its own thread, since reading a long history, many files, and git all
+/// take a while on large projects. Their blocks are then taken in the order of `sources`, saying what each holds and
+/// about how many tokens it takes, so the context is the same however the threads finish. Fails with the first
+/// source (in that order) that failed or panicked, naming it.
+pub fn collect_context(sources: &[&dyn ContextSource], project_dir: &Path) -> Result<Collected> {
+    let results: Vec<Result<Vec<ContextBlock>>> = thread::scope(|scope| {
+        let handles: Vec<_> = sources
+            .iter()
+            .map(|source| scope.spawn(move || source.collect(project_dir)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err(anyhow!("it panicked"))))
+            .collect()
+    });
+
+    let mut collected = Collected::default();
+    for (source, blocks) in sources.iter().zip(results) {
+        let blocks = blocks.with_context(|| format!("The {} context source failed", source.name()))?;
+        for block in blocks {
+            status!(
+                "{}",
+                t!(
+                    "context-block",
+                    source = source.name(),
+                    label = block.label.as_str(),
+                    tokens = block.tokens
+                )
+            );
+            collected.add(block);
+        }
+    }
+    Ok(collected)
+}
@@ -0,0 +1,221 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::encryption::is_sealed;
+use crate::errors::Error;
+use crate::settings::data_home;
+
+/// How long a write waits for another instance's to finish before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The tables, created on first use. `user_version` tells later versions which schema they found.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        record BLOB NOT NULL,
+        encrypted INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS runs_created_at ON runs (created_at);
+    CREATE TABLE IF NOT EXISTS consent (
+        project TEXT PRIMARY KEY,
+        host TEXT NOT NULL,
+        categories TEXT NOT NULL,
+        agreed_at TEXT NOT NULL
+    );
+    PRAGMA user_version = 1;
+";
+
+/// Where saved runs and consent decisions are kept: `$XDG_DATA_HOME/magic_quickstart/state.db`, defaulting to
+/// `~/.local/share/magic_quickstart/state.db`.
+pub fn state_path() -> Result<PathBuf> {
+    match data_home() {
+        Some(dir) => Ok(dir.join("magic_quickstart").join("state.db")),
+        None => bail!(Error::Config(
+            "Can't find the directory for saved runs and consent decisions; set HOME or XDG_DATA_HOME".to_string()
+        )),
+    }
+}
+
+/// A saved run as stored: its record as JSON, sealed if it was saved with ENCRYPT_ARTIFACTS.
+pub struct StoredRun {
+    pub id: String,
+    pub record: Vec<u8>,
+}
+
+/// The SQLite database holding what the tool keeps between runs. Every write is a transaction, and instances
+/// running at the same time wait their turn, so none of them sees another's half-written state. The audit log and
+/// the pseudonym key stay files of their own: the log is for compliance tools to read, and the key is a secret.
+pub struct StateStore {
+    connection: Connection,
+}
+
+impl StateStore {
+    /// Opens the database, creating it readable only by the user (it holds file contents), and moves runs and
+    /// consent decisions kept as JSON files by earlier versions into it.
+    pub fn open() -> Result<Self> {
+        let path = state_path()?;
+        let error = || Error::Config(format!("Can't open {}; fix its permissions or set XDG_DATA_HOME", path.display()));
+        let dir = path.parent().expect("the state path is in a directory");
+        fs::create_dir_all(dir).with_context(error)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .mode(0o600)
+                .open(&path)
+                .with_context(error)?;
+        }
+        let connection = Connection::open(&path).with_context(error)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        // Readers don't block the writer, so `runs list` works while a run is being saved.
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        connection.execute_batch(SCHEMA).with_context(error)?;
+        let mut store = StateStore { connection };
+        store.import_files(dir)?;
+        Ok(store)
+    }
+
+    /// Moves `runs/*.json[.enc]` and `consent.json` from `dir` into the database, then deletes them. Files that
+    /// can't be read are left where they are.
+    fn import_files(&mut self, dir: &Path) -> Result<()> {
+        let runs_dir = dir.join("runs");
+        let consent_path = dir.join("consent.json");
+        if !runs_dir.is_dir() && !consent_path.is_file() {
+            return Ok(());
+        }
+        let mut imported = Vec::new();
+        let transaction = self.connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for path in fs::read_dir(&runs_dir).into_iter().flatten().flatten().map(|entry| entry.path()) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let Some(id) = name.strip_suffix(".json").or_else(|| name.strip_suffix(".json.enc")) else {
+                continue;
+            };
+            let Ok(record) = fs::read(&path) else {
+                continue;
+            };
+            let encrypted = is_sealed(&record);
+            // Runs are named by the local time they were saved.
+            let created_at = NaiveDateTime::parse_from_str(id.get(..15).unwrap_or_default(), "%Y%m%d-%H%M%S")
+                .ok()
+                .and_then(|time| Local.from_local_datetime(&time).earliest())
+                .map_or(0, |time| time.timestamp());
+            transaction.execute(
+                "INSERT INTO runs (id, created_at, record, encrypted) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (id) DO NOTHING",
+                params![id, created_at, record, encrypted],
+            )?;
+            imported.push(path);
+        }
+        if let Some(decisions) = fs::read_to_string(&consent_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        {
+            for (project, decision) in decisions.as_object().into_iter().flatten() {
+                transaction.execute(
+                    "INSERT INTO consent (project, host, categories, agreed_at) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (project) DO NOTHING",
+                    params![
+                        project,
+                        decision["host"].as_str().unwrap_or_default(),
+                        decision["categories"].to_string(),
+                        decision["agreed_at"].as_str().unwrap_or_default(),
+                    ],
+                )?;
+            }
+            imported.push(consent_path);
+        }
+        transaction.commit()?;
+        // Once the rows are committed the files are only copies; another instance may have removed them first.
+        for path in imported {
+            let _ = fs::remove_file(path);
+        }
+        let _ = fs::remove_dir(runs_dir);
+        Ok(())
+    }
+
+    /// Saves a run under `base_id`, or `base_id-2`, `-3`, ... if that is taken, and returns the id it got.
+    pub fn insert_run(&self, base_id: &str, created_at: &DateTime<Local>, record: &[u8], encrypted: bool) -> Result<String> {
+        let mut id = base_id.to_string();
+        for suffix in 2.. {
+            let inserted = self.connection.execute(
+                "INSERT INTO runs (id, created_at, record, encrypted) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (id) DO NOTHING",
+                params![id, created_at.timestamp(), record, encrypted],
+            )?;
+            if inserted == 1 {
+                break;
+            }
+            id = format!("{}-{}", base_id, suffix);
+        }
+        Ok(id)
+    }
+
+    /// The run saved as `id`, if there is one.
+    pub fn run(&self, id: &str) -> Result<Option<StoredRun>> {
+        Ok(self
+            .connection
+            .query_row("SELECT id, record FROM runs WHERE id = ?1", [id], |row| {
+                Ok(StoredRun {
+                    id: row.get(0)?,
+                    record: row.get(1)?,
+                })
+            })
+            .optional()?)
+    }
+
+    /// Every saved run, oldest first.
+    pub fn runs(&self) -> Result<Vec<StoredRun>> {
+        let mut statement = self.connection.prepare("SELECT id, record FROM runs ORDER BY created_at, id")?;
+        let runs = statement.query_map([], |row| {
+            Ok(StoredRun {
+                id: row.get(0)?,
+                record: row.get(1)?,
+            })
+        })?;
+        Ok(runs.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The ids of runs saved before `cutoff`, oldest first, deleting them unless `dry_run`.
+    pub fn expire_runs(&self, cutoff: &DateTime<Local>, dry_run: bool) -> Result<Vec<String>> {
+        let sql = if dry_run {
+            "SELECT id FROM runs WHERE created_at < ?1"
+        } else {
+            "DELETE FROM runs WHERE created_at < ?1 RETURNING id"
+        };
+        let mut statement = self.connection.prepare(sql)?;
+        let mut ids = statement
+            .query_map([cutoff.timestamp()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// The host `project` last agreed to send to, and the kinds of data it agreed to.
+    pub fn consent(&self, project: &str) -> Result<Option<(String, BTreeSet<String>)>> {
+        let decision = self
+            .connection
+            .query_row("SELECT host, categories FROM consent WHERE project = ?1", [project], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .optional()?;
+        Ok(decision.map(|(host, categories)| (host, serde_json::from_str(&categories).unwrap_or_default())))
+    }
+
+    /// Records that `project` agreed to send `categories` to `host`, replacing what it agreed to before.
+    pub fn record_consent(&self, project: &str, host: &str, categories: &BTreeSet<String>) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO consent (project, host, categories, agreed_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (project) DO UPDATE SET host = excluded.host, categories = excluded.categories,
+             agreed_at = excluded.agreed_at",
+            params![project, host, serde_json::to_string(categories)?, Local::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+}
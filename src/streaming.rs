@@ -0,0 +1,247 @@
+use anyhow::{bail, Result};
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::io::{ErrorKind, Read};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::Error;
+use crate::i18n::t;
+use crate::interrupt::{interrupted, Receiving};
+
+/// How often a request waiting on the API checks for Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Describes a valid REQUEST_TIMEOUTS in configuration errors.
+pub const REQUEST_TIMEOUTS_EXPECTED: &str = "seconds per phase such as connect:10,response:120,idle:60,total:600, each above zero";
+
+/// How long each phase of a request may take (REQUEST_TIMEOUTS): connecting to the API, waiting for it to start
+/// answering, waiting for the next part of the answer, and the request as a whole.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestTimeouts {
+    pub connect: Duration,
+    pub response: Duration,
+    pub idle: Duration,
+    pub total: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        RequestTimeouts {
+            connect: Duration::from_secs(10),
+            response: Duration::from_secs(120),
+            idle: Duration::from_secs(60),
+            total: Duration::from_secs(600),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Parses timeouts such as `response:300,total:900`. Phases that are not listed keep their default. Returns
+    /// `None` for an unknown phase or a duration that isn't a whole number of seconds above zero.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut timeouts = RequestTimeouts::default();
+        for part in spec.split(',').filter(|part| !part.trim().is_empty()) {
+            let (phase, seconds) = part.split_once(':')?;
+            let seconds = seconds.trim().parse::<u64>().ok().filter(|&seconds| seconds > 0)?;
+            let timeout = match phase.trim() {
+                "connect" => &mut timeouts.connect,
+                "response" => &mut timeouts.response,
+                "idle" => &mut timeouts.idle,
+                "total" => &mut timeouts.total,
+                _ => return None,
+            };
+            *timeout = Duration::from_secs(seconds);
+        }
+        Some(timeouts)
+    }
+}
+
+/// Why an answer stopped before the API finished it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cut {
+    /// Ctrl-C stopped it.
+    Interrupted,
+    /// The API sent nothing for this many seconds.
+    Idle(u64),
+    /// The request took longer than this many seconds.
+    Deadline(u64),
+    /// The connection broke.
+    Broken,
+}
+
+impl Cut {
+    /// The cut as the `--json` record names it, e.g. `idle`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Cut::Interrupted => "interrupted",
+            Cut::Idle(_) => "idle",
+            Cut::Deadline(_) => "deadline",
+            Cut::Broken => "broken",
+        }
+    }
+
+    /// Why the answer stopped, to finish a sentence such as "The response was cut off because ...".
+    pub fn reason(self) -> String {
+        match self {
+            Cut::Interrupted => t!("cut-interrupted"),
+            Cut::Idle(seconds) => t!("cut-idle", seconds = seconds),
+            Cut::Deadline(seconds) => t!("cut-deadline", seconds = seconds),
+            Cut::Broken => t!("cut-broken"),
+        }
+    }
+}
+
+/// An answer as it arrived: its status, its raw body, whether the body is a stream of server-sent events, and why it
+/// stopped early, if it did.
+pub struct Received {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+    pub event_stream: bool,
+    pub cut: Option<Cut>,
+}
+
+/// Why a request got no answer at all.
+pub enum Unanswered {
+    /// The request couldn't be sent, e.g. because the API can't be reached.
+    Failed(reqwest::Error),
+    /// The API didn't start answering within this many seconds.
+    TimedOut(u64),
+    /// Ctrl-C stopped it.
+    Interrupted,
+    /// The thread sending it stopped before the API answered.
+    Lost,
+}
+
+/// What the reading thread hands to the one waiting for it.
+enum Event {
+    Answered(StatusCode, bool),
+    Data(Vec<u8>),
+    Finished,
+    Failed(reqwest::Error),
+    Broken,
+}
+
+/// Sends `request` and receives the answer as it arrives, within `timeouts` and until Ctrl-C. The request is sent
+/// and read on a thread of its own, so waiting can stop at any moment; the thread is left to finish on its own.
+pub fn receive(request: RequestBuilder, timeouts: &RequestTimeouts) -> Result<Received, Unanswered> {
+    let _receiving = Receiving::start();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut response = match request.send() {
+            Ok(response) => response,
+            Err(error) => {
+                let _ = sender.send(Event::Failed(error));
+                return;
+            }
+        };
+        let event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        if sender.send(Event::Answered(response.status(), event_stream)).is_err() {
+            return;
+        }
+        let mut buffer = [0; 8192];
+        loop {
+            let event = match response.read(&mut buffer) {
+                Ok(0) => Event::Finished,
+                Ok(length) => Event::Data(buffer[..length].to_vec()),
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => Event::Broken,
+            };
+            let last = !matches!(event, Event::Data(_));
+            if sender.send(event).is_err() || last {
+                return;
+            }
+        }
+    });
+
+    let started = Instant::now();
+    let deadline = started + timeouts.total;
+    let mut answer: Option<(StatusCode, bool)> = None;
+    let mut body = Vec::new();
+    let mut last_heard = started;
+    let cut = loop {
+        if interrupted() {
+            match answer {
+                Some(_) => break Some(Cut::Interrupted),
+                None => return Err(Unanswered::Interrupted),
+            }
+        }
+        let limit = match answer {
+            None => started + timeouts.response.min(timeouts.total),
+            Some(_) => (last_heard + timeouts.idle).min(deadline),
+        };
+        let now = Instant::now();
+        if now >= limit {
+            match answer {
+                None => return Err(Unanswered::TimedOut(limit.duration_since(started).as_secs())),
+                Some(_) if limit < deadline => break Some(Cut::Idle(timeouts.idle.as_secs())),
+                Some(_) => break Some(Cut::Deadline(timeouts.total.as_secs())),
+            }
+        }
+        match receiver.recv_timeout(POLL_INTERVAL.min(limit - now)) {
+            Ok(Event::Answered(status, event_stream)) => {
+                answer = Some((status, event_stream));
+                last_heard = Instant::now();
+            }
+            Ok(Event::Data(data)) => {
+                body.extend(data);
+                last_heard = Instant::now();
+            }
+            Ok(Event::Finished) => break None,
+            Ok(Event::Failed(error)) => return Err(Unanswered::Failed(error)),
+            Err(RecvTimeoutError::Disconnected) if answer.is_none() => return Err(Unanswered::Lost),
+            Ok(Event::Broken) | Err(RecvTimeoutError::Disconnected) => break Some(Cut::Broken),
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    };
+    let (status, event_stream) = answer.expect("the loop only ends without an answer by returning");
+    Ok(Received {
+        status,
+        body,
+        event_stream,
+        cut,
+    })
+}
+
+/// The content and token usage in a stream of chat completion chunks, as far as it goes. An event cut off partway
+/// is left out. Fails with a provider error if the API reported an error in the stream.
+pub fn event_stream_content(body: &[u8]) -> Result<(String, Value)> {
+    let text = String::from_utf8_lossy(body);
+    let complete = text.rfind('\n').map_or("", |end| &text[..end]);
+    let mut content = String::new();
+    let mut usage = Value::Null;
+    for line in complete.lines() {
+        // Other fields, such as `event:` and `:` comments, carry nothing the tool uses.
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            continue;
+        };
+        if data == "[DONE]" {
+            break;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            continue;
+        };
+        if let Some(message) = chunk["error"]["message"].as_str() {
+            bail!(Error::Provider {
+                message: format!("The API stopped with an error partway through: {}", message),
+                status: None,
+                body: Some(data.to_string()),
+            });
+        }
+        if let Some(delta) = chunk["choices"][0]["delta"]["content"].as_str() {
+            content.push_str(delta);
+        }
+        // Usage comes in a last chunk of its own.
+        if !chunk["usage"].is_null() {
+            usage = chunk["usage"].clone();
+        }
+    }
+    Ok((content, usage))
+}
@@ -0,0 +1,182 @@
+use regex::Regex;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::excerpt::read_text;
+use crate::parsers::{is_excluded, walk_source_files};
+
+/// Source extensions included in the module structure.
+const STRUCTURE_EXTENSIONS: [&str; 7] = ["rs", "py", "js", "jsx", "ts", "tsx", "go"];
+
+/// Upper bound on files examined, to keep monorepos manageable.
+const MAX_STRUCTURE_FILES: usize = 400;
+
+/// Upper bound on dependency edges reported.
+const MAX_EDGES: usize = 600;
+
+/// Maps the project's source files and the local imports between them, so the model can infer components
+/// and their relationships. Returns `{modules: [...], dependencies: ["a -> b", ...]}` with extension-less paths.
+pub fn module_structure(root: &Path, follow_symlinks: bool) -> serde_json::Value {
+    let mut files = walk_source_files(root, &STRUCTURE_EXTENSIONS, MAX_STRUCTURE_FILES, follow_symlinks).files;
+    files.retain(|file| !is_excluded(file));
+    files.sort();
+    let relative: Vec<PathBuf> = files
+        .iter()
+        .map(|file| file.strip_prefix(root).unwrap_or(file).to_path_buf())
+        .collect();
+    let modules: BTreeSet<String> = relative.iter().map(|file| module_id(file)).collect();
+    let go_module = go_module_path(root);
+
+    let rust_mod = Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;").unwrap();
+    let rust_use = Regex::new(r"(?m)^\s*(?:pub\s+)?use\s+crate::(\w+)").unwrap();
+    let python_import = Regex::new(r"(?m)^\s*(?:from\s+([\w.]+)\s+import|import\s+([\w.]+))").unwrap();
+    let js_import = Regex::new(r#"(?:from\s+|require\(\s*|import\(\s*)['"](\.{1,2}/[^'"]+)['"]"#).unwrap();
+    let go_import = Regex::new(r#""([\w./-]+)""#).unwrap();
+
+    let mut dependencies = BTreeSet::new();
+    for (file, relative_file) in files.iter().zip(&relative) {
+        let Some(content) = read_text(file) else {
+            continue;
+        };
+        let from = module_id(relative_file);
+        let directory = relative_file.parent().unwrap_or(Path::new(""));
+        let extension = relative_file.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+
+        let targets: Vec<String> = match extension {
+            "rs" => {
+                let crate_root = rust_crate_root(relative_file);
+                let module_dir = rust_module_dir(relative_file);
+                rust_mod
+                    .captures_iter(&content)
+                    .map(|captures| module_dir.join(&captures[1]))
+                    .chain(rust_use.captures_iter(&content).map(|captures| crate_root.join(&captures[1])))
+                    .map(|path| normalize(&path))
+                    .map(|id| {
+                        if modules.contains(&format!("{}/mod", id)) {
+                            format!("{}/mod", id)
+                        } else {
+                            id
+                        }
+                    })
+                    .collect()
+            }
+            "py" => python_import
+                .captures_iter(&content)
+                .filter_map(|captures| captures.get(1).or(captures.get(2)))
+                .flat_map(|name| python_candidates(name.as_str(), directory))
+                .collect(),
+            "js" | "jsx" | "ts" | "tsx" => js_import
+                .captures_iter(&content)
+                .flat_map(|captures| {
+                    let target = normalize(&directory.join(&captures[1]));
+                    let without_extension = target.rsplit_once('.').map(|(stem, _)| stem.to_string());
+                    [format!("{}/index", target), target.clone()].into_iter().chain(without_extension)
+                })
+                .collect(),
+            "go" => match &go_module {
+                Some(module_path) => go_import
+                    .captures_iter(&content)
+                    .filter_map(|captures| {
+                        captures[1]
+                            .strip_prefix(module_path)
+                            .map(|rest| rest.trim_start_matches('/').to_string())
+                    })
+                    .flat_map(|package| go_package_modules(&modules, &package))
+                    .collect(),
+                None => vec![],
+            },
+            _ => vec![],
+        };
+
+        for target in targets {
+            if target != from && modules.contains(&target) && dependencies.len() < MAX_EDGES {
+                dependencies.insert(format!("{} -> {}", from, target));
+            }
+        }
+    }
+
+    json!({
+        "modules": modules,
+        "dependencies": dependencies
+    })
+}
+
+/// Identifies a module by its relative path without extension, using `/` separators.
+fn module_id(relative_file: &Path) -> String {
+    normalize(&relative_file.with_extension(""))
+}
+
+/// Resolves `.` and `..` components and joins with `/`.
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// Directory containing the crate root for a Rust file (the nearest `src` ancestor).
+fn rust_crate_root(relative_file: &Path) -> PathBuf {
+    relative_file
+        .ancestors()
+        .find(|ancestor| ancestor.ends_with("src"))
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Directory where a Rust file's `mod x;` children live: its own directory for main.rs, lib.rs, and mod.rs,
+/// otherwise a directory named after the file.
+fn rust_module_dir(relative_file: &Path) -> PathBuf {
+    let directory = relative_file.parent().unwrap_or(Path::new("")).to_path_buf();
+    match relative_file.file_stem().and_then(|stem| stem.to_str()) {
+        Some("main" | "lib" | "mod") | None => directory,
+        Some(stem) => directory.join(stem),
+    }
+}
+
+/// Candidate module ids for a Python import, absolute from the root, from `src/`, or relative to the file.
+fn python_candidates(name: &str, directory: &Path) -> Vec<String> {
+    let relative_dots = name.chars().take_while(|&c| c == '.').count();
+    let dotted = &name[relative_dots..];
+    let as_path = dotted.replace('.', "/");
+
+    if relative_dots > 0 {
+        let mut base = directory.to_path_buf();
+        for _ in 1..relative_dots {
+            base.pop();
+        }
+        let target = normalize(&base.join(&as_path));
+        return vec![target.clone(), format!("{}/__init__", target)];
+    }
+
+    ["", "src/"]
+        .iter()
+        .flat_map(|prefix| [format!("{}{}", prefix, as_path), format!("{}{}/__init__", prefix, as_path)])
+        .collect()
+}
+
+/// Reads the module path declared in go.mod.
+fn go_module_path(root: &Path) -> Option<String> {
+    let go_mod = fs::read_to_string(root.join("go.mod")).ok()?;
+    go_mod
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().to_string())
+}
+
+/// Go imports name packages (directories), so every file in the package directory is a target.
+fn go_package_modules(modules: &BTreeSet<String>, package: &str) -> Vec<String> {
+    modules
+        .iter()
+        .filter(|module| Path::new(module.as_str()).parent().map(normalize).unwrap_or_default() == package)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,51 @@
+/// Who the generated document is written for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Audience {
+    Beginner,
+    Expert,
+}
+
+impl Audience {
+    /// Parses an audience as accepted by `--audience` and `AUDIENCE`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "beginner" | "new-hire" | "newcomer" => Some(Audience::Beginner),
+            "expert" | "senior" => Some(Audience::Expert),
+            _ => None,
+        }
+    }
+
+    /// Instruction appended to the system prompt.
+    pub fn instructions(self) -> &'static str {
+        match self {
+            Audience::Beginner => "The reader is new to the project and possibly to its ecosystem: explain what each step does and why, spell out prerequisites, and say what success looks like.",
+            Audience::Expert => "The reader is an experienced developer: skip explanations of standard tooling and ecosystem basics and focus on what is specific to this project.",
+        }
+    }
+}
+
+/// How the generated document reads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tone {
+    Terse,
+    Friendly,
+}
+
+impl Tone {
+    /// Parses a tone as accepted by `--tone` and `TONE`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "terse" | "concise" => Some(Tone::Terse),
+            "friendly" | "conversational" => Some(Tone::Friendly),
+            _ => None,
+        }
+    }
+
+    /// Instruction appended to the system prompt.
+    pub fn instructions(self) -> &'static str {
+        match self {
+            Tone::Terse => "Keep the wording terse: short imperative sentences, no filler or pleasantries.",
+            Tone::Friendly => "Use a friendly, welcoming tone addressing the reader directly, while keeping every step precise.",
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context as _, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tera::{Context, Tera};
+
+use crate::console::status;
+use crate::errors::Error;
+use crate::models::{Message, Role};
+
+/// Marker lines in a prompt template that start a new message with the given role.
+const PROMPT_ROLE_MARKERS: [(&str, Role); 2] = [("--- system ---", Role::System), ("--- user ---", Role::User)];
+
+/// Structured slots the model fills when an output template is used, with instructions for each.
+pub const TEMPLATE_SLOTS: [(&str, &str); 9] = [
+    ("title", "the project name as it should appear in the main heading (plain text)"),
+    ("description", "one short paragraph describing the project"),
+    (
+        "prerequisites",
+        "a Markdown bullet list of required tools with versions where known",
+    ),
+    (
+        "installation",
+        "Markdown with the commands needed to install dependencies and build",
+    ),
+    (
+        "configuration",
+        "Markdown describing required and optional environment variables or config files",
+    ),
+    ("usage", "Markdown with the commands to run the project"),
+    ("testing", "Markdown with the commands to run tests and linters"),
+    ("deployment", "Markdown with deployment or release steps"),
+    ("troubleshooting", "Markdown with common problems and fixes"),
+];
+
+/// Renders a template with a Tera instance whose `get_env` fails, so a template can't read OPENAI_API_KEY or any other
+/// environment variable into the request or the document.
+fn render_sandboxed(template: &str, context: &Context) -> tera::Result<String> {
+    let mut tera = Tera::default();
+    tera.register_function("get_env", |_: &HashMap<String, tera::Value>| {
+        Err(tera::Error::msg("get_env is not available in templates"))
+    });
+    tera.render_str(template, context)
+}
+
+/// Instruction appended to the system prompt so the model answers with the template slots as JSON.
+pub fn slot_instructions() -> String {
+    let slots: Vec<String> = TEMPLATE_SLOTS
+        .iter()
+        .map(|(name, description)| format!("\"{}\": {}", name, description))
+        .collect();
+    format!(
+        "Respond with a single JSON object and nothing else. It must have exactly these string keys: {}. Use an empty string for any slot that does not apply to this project. Do not include headings for the slots themselves; the document layout is applied separately.",
+        slots.join("; ")
+    )
+}
+
+/// Renders the Tera template at `template_path` with the slots from the model's JSON response, plus
+/// `project_name`, `model`, and `generated_at`. Falls back to the raw response if it isn't valid JSON.
+pub fn render_output_template(template_path: &Path, response: &str, project_name: &str, model: &str) -> Result<String> {
+    let template = fs::read_to_string(template_path)
+        .with_context(|| Error::Config(format!("Failed to read output template {}", template_path.display())))?;
+
+    let slots: serde_json::Value = match serde_json::from_str(response.trim()) {
+        Ok(slots) => slots,
+        Err(_) => {
+            status!("Model response was not valid JSON; writing it without applying the output template.");
+            return Ok(response.to_string());
+        }
+    };
+    render_slots(&template, &slots, project_name, model)
+        .with_context(|| Error::Config(format!("Failed to render output template {}", template_path.display())))
+}
+
+/// Renders `template` with the template slots, plus `project_name`, `model`, and `generated_at`.
+pub fn render_slots(template: &str, slots: &serde_json::Value, project_name: &str, model: &str) -> tera::Result<String> {
+    let mut context = Context::new();
+    for (name, _) in TEMPLATE_SLOTS {
+        context.insert(name, slots[name].as_str().unwrap_or_default().trim());
+    }
+    context.insert("project_name", project_name);
+    context.insert("model", model);
+    context.insert("generated_at", &chrono::Utc::now().format("%Y-%m-%d").to_string());
+    context.insert("slots", &json!(slots));
+    render_sandboxed(template, &context)
+}
+
+/// Renders the prompt template at `template_path` into chat messages. A line reading `--- system ---` or
+/// `--- user ---` starts a new message with that role; text before the first marker is a system message.
+/// Messages that render empty are dropped.
+pub fn render_prompt_template(template_path: &Path, context: &Context) -> Result<Vec<Message>> {
+    let template = fs::read_to_string(template_path)
+        .with_context(|| Error::Config(format!("Failed to read prompt template {}", template_path.display())))?;
+    let rendered = render_sandboxed(&template, context)
+        .with_context(|| Error::Config(format!("Failed to render prompt template {}", template_path.display())))?;
+
+    let mut messages: Vec<(Role, String)> = vec![(Role::System, String::new())];
+    for line in rendered.lines() {
+        match PROMPT_ROLE_MARKERS.iter().find(|(marker, _)| line.trim() == *marker) {
+            Some((_, role)) => messages.push((*role, String::new())),
+            None => {
+                let (_, content) = messages.last_mut().unwrap();
+                content.push_str(line);
+                content.push('\n');
+            }
+        }
+    }
+
+    Ok(messages
+        .into_iter()
+        .filter(|(_, content)| !content.trim().is_empty())
+        .map(|(role, content)| Message {
+            role,
+            content: content.trim().to_string(),
+        })
+        .collect())
+}
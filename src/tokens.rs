@@ -0,0 +1,4 @@
+/// Roughly estimates the number of model tokens in a piece of text (about four characters per token).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
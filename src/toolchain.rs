@@ -0,0 +1,101 @@
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Ecosystems: the manifest that marks them and the tools whose installed versions are reported.
+const ECOSYSTEMS: [(&str, &[&str]); 4] = [
+    ("Cargo.toml", &["rustc", "cargo"]),
+    ("pyproject.toml", &["python3", "pip3", "poetry", "uv"]),
+    ("package.json", &["node", "npm", "pnpm", "yarn"]),
+    ("go.mod", &["go"]),
+];
+
+/// Files that pin toolchain versions for a project.
+const VERSION_PIN_FILES: [&str; 7] = [
+    "rust-toolchain.toml",
+    "rust-toolchain",
+    ".python-version",
+    ".nvmrc",
+    ".node-version",
+    ".tool-versions",
+    "runtime.txt",
+];
+
+/// Reports toolchain versions relevant to the detected ecosystems: versions pinned by the project
+/// (toolchain files, `go` directive, `engines`, `requires-python`, `rust-version`) and versions installed locally.
+pub fn detect_toolchain_versions(root: &Path) -> serde_json::Value {
+    let mut pinned = serde_json::Map::new();
+    for file in VERSION_PIN_FILES {
+        if let Ok(content) = fs::read_to_string(root.join(file)) {
+            pinned.insert(file.to_string(), json!(content.trim()));
+        }
+    }
+    if let Some(go_version) = fs::read_to_string(root.join("go.mod"))
+        .ok()
+        .and_then(|go_mod| go_mod.lines().find_map(|line| line.trim().strip_prefix("go ").map(str::to_string)))
+    {
+        pinned.insert("go.mod go directive".to_string(), json!(go_version.trim()));
+    }
+    if let Some(engines) = fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .map(|package| package["engines"].clone())
+        .filter(|engines| !engines.is_null())
+    {
+        pinned.insert("package.json engines".to_string(), engines);
+    }
+    for (manifest, key_path) in [
+        ("Cargo.toml", ["package", "rust-version"]),
+        ("pyproject.toml", ["project", "requires-python"]),
+    ] {
+        if let Some(version) = fs::read_to_string(root.join(manifest))
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok())
+            .and_then(|table| table.get(key_path[0])?.get(key_path[1])?.as_str().map(str::to_string))
+        {
+            pinned.insert(format!("{} {}", manifest, key_path[1]), json!(version));
+        }
+    }
+
+    let mut installed = serde_json::Map::new();
+    for (manifest, tools) in ECOSYSTEMS {
+        if !root.join(manifest).exists() {
+            continue;
+        }
+        for tool in tools {
+            if let Some(version) = tool_version(tool) {
+                installed.insert(tool.to_string(), json!(version));
+            }
+        }
+    }
+
+    json!({
+        "pinned": pinned,
+        "installed": installed
+    })
+}
+
+/// Where toolchain versions would be read from, without reading them: the version pin files in the project, and the
+/// tools whose installed versions are asked for.
+pub fn toolchain_sources(root: &Path) -> (Vec<&'static str>, Vec<&'static str>) {
+    let pin_files = VERSION_PIN_FILES.into_iter().filter(|file| root.join(file).is_file()).collect();
+    let tools = ECOSYSTEMS
+        .iter()
+        .filter(|(manifest, _)| root.join(manifest).exists())
+        .flat_map(|(_, tools)| tools.iter().copied())
+        .collect();
+    (pin_files, tools)
+}
+
+/// Runs `<tool> --version` and returns the first line of output, or `None` if the tool isn't installed.
+/// (`go` uses `go version`.)
+fn tool_version(tool: &str) -> Option<String> {
+    let arg = if tool == "go" { "version" } else { "--version" };
+    let output = Command::new(tool).arg(arg).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(|line| line.trim().to_string())
+}
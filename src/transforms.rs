@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::console::status;
+use crate::models::FileContext;
+
+/// A preprocessing step applied to file contents before they are sent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    /// Removes line and block comments.
+    StripComments,
+    /// Trims trailing whitespace and collapses runs of blank lines into one.
+    CollapseWhitespace,
+    /// Removes a leading comment block that mentions a license or copyright.
+    DropLicenseHeader,
+}
+
+impl Transform {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim() {
+            "strip-comments" => Some(Transform::StripComments),
+            "collapse-whitespace" => Some(Transform::CollapseWhitespace),
+            "drop-license-header" => Some(Transform::DropLicenseHeader),
+            _ => None,
+        }
+    }
+}
+
+/// Transforms to apply per file extension. The `*` key applies to every file.
+pub type TransformConfig = HashMap<String, Vec<Transform>>;
+
+/// Parses a transform configuration such as `rs,go:strip-comments,collapse-whitespace;*:drop-license-header`.
+/// Unknown transform names are reported and ignored.
+pub fn parse_transform_config(spec: &str) -> TransformConfig {
+    let mut config = TransformConfig::new();
+
+    for rule in spec.split(';').filter(|rule| !rule.trim().is_empty()) {
+        let Some((extensions, transforms)) = rule.split_once(':') else {
+            status!("Ignoring invalid CONTENT_TRANSFORMS rule: {}", rule);
+            continue;
+        };
+        let transforms: Vec<Transform> = transforms
+            .split(',')
+            .filter_map(|name| {
+                let transform = Transform::from_name(name);
+                if transform.is_none() {
+                    status!("Ignoring unknown content transform: {}", name.trim());
+                }
+                transform
+            })
+            .collect();
+        for extension in extensions.split(',') {
+            config.entry(extension.trim().to_string()).or_default().extend(&transforms);
+        }
+    }
+
+    config
+}
+
+/// Runs the configured transforms over every file content entry in place.
+pub fn apply_content_transforms(project_files_content: &mut [FileContext], config: &TransformConfig) {
+    if config.is_empty() {
+        return;
+    }
+
+    for entry in project_files_content.iter_mut() {
+        entry.content = transform_content(Path::new(&entry.file_path), &entry.content, config);
+    }
+}
+
+/// Applies the transforms configured for the file's extension (and `*`) to its content.
+pub fn transform_content(path: &Path, content: &str, config: &TransformConfig) -> String {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    let syntax = CommentSyntax::for_extension(extension);
+    let mut transforms: Vec<Transform> = config.get("*").cloned().unwrap_or_default();
+    transforms.extend(config.get(extension).cloned().unwrap_or_default());
+
+    // License headers are detected from comments, so they are dropped before comments are stripped.
+    let mut content = content.to_string();
+    for transform in [
+        Transform::DropLicenseHeader,
+        Transform::StripComments,
+        Transform::CollapseWhitespace,
+    ] {
+        if !transforms.contains(&transform) {
+            continue;
+        }
+        content = match transform {
+            Transform::DropLicenseHeader => drop_license_header(&content, &syntax),
+            Transform::StripComments => strip_comments(&content, &syntax),
+            Transform::CollapseWhitespace => collapse_whitespace(&content),
+        };
+    }
+    content
+}
+
+/// Comment and string delimiters for a language family.
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+impl CommentSyntax {
+    fn for_extension(extension: &str) -> Self {
+        match extension {
+            // Rust uses `'` for lifetimes, so only double quotes delimit strings.
+            "rs" => CommentSyntax {
+                line: &["//"],
+                block: Some(("/*", "*/")),
+                quotes: &['"'],
+            },
+            "js" | "jsx" | "ts" | "tsx" | "go" | "c" | "h" | "cpp" | "java" | "kt" | "swift" | "cs" => CommentSyntax {
+                line: &["//"],
+                block: Some(("/*", "*/")),
+                quotes: &['"', '\'', '`'],
+            },
+            "py" | "rb" | "sh" | "bash" | "zsh" | "toml" | "yaml" | "yml" | "r" | "pl" => CommentSyntax {
+                line: &["#"],
+                block: None,
+                quotes: &['"', '\''],
+            },
+            "sql" | "lua" | "hs" => CommentSyntax {
+                line: &["--"],
+                block: None,
+                quotes: &['\''],
+            },
+            _ => CommentSyntax {
+                line: &[],
+                block: None,
+                quotes: &[],
+            },
+        }
+    }
+
+    fn is_comment_line(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        self.line.iter().any(|prefix| line.starts_with(prefix))
+            || self
+                .block
+                .is_some_and(|(open, close)| line.starts_with(open) || line.starts_with('*') || line.starts_with(close))
+    }
+}
+
+/// Removes line and block comments, leaving string literals intact. Lines that only held a comment are dropped.
+fn strip_comments(content: &str, syntax: &CommentSyntax) -> String {
+    if syntax.line.is_empty() && syntax.block.is_none() {
+        return content.to_string();
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut in_string: Option<char> = None;
+    let mut in_block = false;
+    let mut rest = content;
+
+    while let Some(c) = rest.chars().next() {
+        if in_block {
+            let (_, close) = syntax.block.unwrap();
+            match rest.find(close) {
+                Some(end) => {
+                    // Keep line structure so line-based collapsing still works afterwards.
+                    output.extend(rest[..end].chars().filter(|&c| c == '\n'));
+                    rest = &rest[end + close.len()..];
+                    in_block = false;
+                }
+                None => rest = "",
+            }
+            continue;
+        }
+        if let Some(quote) = in_string {
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+            if c == '\\' {
+                if let Some(escaped) = rest.chars().next() {
+                    output.push(escaped);
+                    rest = &rest[escaped.len_utf8()..];
+                }
+            } else if c == quote {
+                in_string = None;
+            }
+            continue;
+        }
+        if syntax.quotes.contains(&c) {
+            in_string = Some(c);
+        } else if syntax.line.iter().any(|prefix| rest.starts_with(prefix)) {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+            continue;
+        } else if let Some((open, _)) = syntax.block.filter(|(open, _)| rest.starts_with(open)) {
+            rest = &rest[open.len()..];
+            in_block = true;
+            continue;
+        }
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    // Drop lines that became empty only because their comment was removed.
+    output
+        .lines()
+        .zip(content.lines().chain(std::iter::repeat("")))
+        .filter(|(stripped, original)| !stripped.trim().is_empty() || original.trim().is_empty())
+        .map(|(stripped, _)| stripped.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Trims trailing whitespace and collapses consecutive blank lines into a single one.
+fn collapse_whitespace(content: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in content.lines().map(str::trim_end) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Drops the leading comment block if it mentions a license, copyright, or SPDX identifier.
+fn drop_license_header(content: &str, syntax: &CommentSyntax) -> String {
+    let header_len = content
+        .lines()
+        .take_while(|line| line.trim().is_empty() || syntax.is_comment_line(line))
+        .count();
+    let header = content.lines().take(header_len).collect::<Vec<_>>().join("\n").to_lowercase();
+
+    if ["license", "copyright", "spdx-license-identifier"]
+        .iter()
+        .any(|marker| header.contains(marker))
+    {
+        content.lines().skip(header_len).collect::<Vec<_>>().join("\n")
+    } else {
+        content.to_string()
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::Client;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::endpoints::HostAllowlist;
+use crate::errors::Error;
+
+/// Where releases are published; the newest one is compared against the running version.
+const REPOSITORY_URL: &str = "https://github.com/hortonew/magic_quickstart";
+
+/// GitHub's API for the newest release.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/hortonew/magic_quickstart/releases/latest";
+
+/// How long the update check waits for GitHub.
+const UPDATE_TIMEOUT_SECS: u64 = 10;
+
+/// The version this binary was built as.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The newest published release.
+pub struct Release {
+    /// Version without the leading `v` of the tag, e.g. `0.2.0`.
+    pub version: String,
+    pub tag: String,
+}
+
+impl Release {
+    /// Whether this release is newer than the running binary.
+    pub fn is_newer(&self) -> bool {
+        version_numbers(&self.version) > version_numbers(CURRENT_VERSION)
+    }
+
+    /// The command that installs this release.
+    pub fn upgrade_command(&self) -> String {
+        format!("cargo install --git {} --tag {} --locked", REPOSITORY_URL, self.tag)
+    }
+}
+
+/// Asks GitHub for the newest release. An unreachable API is a network error; an error answer, such as when no
+/// release has been published yet, is a provider error.
+pub fn latest_release(allowed_hosts: &HostAllowlist) -> Result<Release> {
+    allowed_hosts.check(LATEST_RELEASE_URL)?;
+    let client = Client::builder()
+        .redirect(allowed_hosts.redirect_policy())
+        .timeout(Duration::from_secs(UPDATE_TIMEOUT_SECS))
+        .user_agent(concat!("magic_quickstart/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to build HTTP client")?;
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .context(Error::Network(
+            "Can't reach api.github.com to check for updates; check your network connection or HTTPS_PROXY".into(),
+        ))?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!(Error::Provider {
+            message: format!("GitHub answered {} when asked for the latest release of {}", status, REPOSITORY_URL),
+            status: Some(status.as_u16()),
+            body: response.text().ok(),
+        });
+    }
+    let release: Value = response
+        .json()
+        .context(Error::provider("GitHub's release information couldn't be read"))?;
+    let Some(tag) = release["tag_name"].as_str() else {
+        bail!(Error::provider("GitHub's release information has no tag"));
+    };
+    Ok(Release {
+        version: tag.trim_start_matches('v').to_string(),
+        tag: tag.to_string(),
+    })
+}
+
+/// Prints whether a newer release is available and how to install it (`--check-update`).
+pub fn check_for_update(allowed_hosts: &HostAllowlist) -> Result<()> {
+    let release = latest_release(allowed_hosts)?;
+    if release.is_newer() {
+        println!(
+            "magic_quickstart {} is available (you have {}). Upgrade with:\n\n    {}",
+            release.version,
+            CURRENT_VERSION,
+            release.upgrade_command()
+        );
+    } else {
+        println!("magic_quickstart {} is the latest release", CURRENT_VERSION);
+    }
+    Ok(())
+}
+
+/// The numeric parts of a version, ignoring any pre-release or build suffix, so `0.10.0` sorts after `0.9.1`.
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
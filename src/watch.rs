@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use crate::artifacts::ARTIFACTS_DIR;
+use crate::console::status;
+use crate::i18n::t;
+use crate::parsers::IGNORED_DIRS;
+use crate::run_manifest::RUN_MANIFEST_FILE;
+
+/// How long changes must settle before regenerating, so saving several files or a burst of commands triggers one run.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Runs `regenerate` once, then again whenever a file under `project_dir` or a shell history file changes, after
+/// changes have settled. `regenerate` returns the files it wrote, whose changes are ignored along with generated,
+/// backup, hidden, and `IGNORED_DIRS` files, or `None` to stop watching. A failed run is reported and watching
+/// continues, since the next change may fix it. Runs until interrupted.
+pub fn watch_and_regenerate(
+    project_dir: &Path,
+    history_paths: &[PathBuf],
+    mut regenerate: impl FnMut() -> Result<Option<Vec<PathBuf>>>,
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("Failed to start watching for changes")?;
+    watcher
+        .watch(project_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", project_dir.display()))?;
+    let history_paths: Vec<&PathBuf> = history_paths.iter().filter(|path| path.is_file()).collect();
+    for history_path in &history_paths {
+        watcher
+            .watch(history_path, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", history_path.display()))?;
+    }
+
+    let mut run = || match regenerate() {
+        Ok(written) => written,
+        Err(error) => {
+            eprintln!("{}", t!("error-prefix", error = format!("{:#}", error)));
+            Some(vec![])
+        }
+    };
+    let Some(mut written) = run() else {
+        return Ok(());
+    };
+    let watched: Vec<String> = [project_dir]
+        .into_iter()
+        .chain(history_paths.iter().map(|path| path.as_path()))
+        .map(|path| path.display().to_string())
+        .collect();
+    status!("{}", t!("watching", paths = watched.join(", ")));
+
+    loop {
+        let relevant = |path: &Path| {
+            history_paths.iter().any(|history_path| *history_path == path)
+                || (!written.iter().any(|file| file == path) && !is_ignored(project_dir, path))
+        };
+        let changed = next_changes(&receiver, relevant)?;
+        let path = changed[0].strip_prefix(project_dir).unwrap_or(&changed[0]).display().to_string();
+        if changed.len() > 1 {
+            status!("\n{}", t!("watch-changed-more", path = path, count = changed.len() - 1));
+        } else {
+            status!("\n{}", t!("watch-changed", path = path));
+        }
+        match run() {
+            Some(files) => written = files,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Waits for a change to a `relevant` path, then collects further changes until none arrive for `DEBOUNCE`.
+/// Reads and metadata changes don't count, since gathering context reads every file it sends.
+fn next_changes(receiver: &Receiver<notify::Result<Event>>, relevant: impl Fn(&Path) -> bool) -> Result<Vec<PathBuf>> {
+    let collect = |event: notify::Result<Event>, changed: &mut Vec<PathBuf>| -> Result<()> {
+        let event = event.context("Watching for changes failed")?;
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_))
+            || matches!(event.kind, EventKind::Modify(kind) if !matches!(kind, ModifyKind::Metadata(_)))
+        {
+            for path in event.paths {
+                if relevant(&path) && !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+        Ok(())
+    };
+
+    let mut changed = Vec::new();
+    while changed.is_empty() {
+        collect(receiver.recv().context("Stopped watching for changes")?, &mut changed)?;
+    }
+    while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+        collect(event, &mut changed)?;
+    }
+    Ok(changed)
+}
+
+/// Whether a change to `path` should not trigger regeneration: it is inside a hidden or ignored directory, is an
+/// ignored directory itself, or is a generated document (`*_GENERATED_*`), a backup (`*.bak`), or the run manifest left
+/// by an earlier run.
+fn is_ignored(project_dir: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(project_dir).unwrap_or(path);
+    let mut components = relative.components().peekable();
+    while let Some(Component::Normal(name)) = components.next() {
+        let name = name.to_string_lossy();
+        if IGNORED_DIRS.contains(&name.as_ref()) || name == ARTIFACTS_DIR {
+            return true;
+        }
+        if components.peek().is_none() {
+            return name.contains("_GENERATED_") || name.ends_with(".bak") || name == RUN_MANIFEST_FILE;
+        }
+        if name.starts_with('.') {
+            return true;
+        }
+    }
+    false
+}